@@ -19,7 +19,7 @@
 //!   advanced_usage folder ./game_files/
 //!   advanced_usage batch file1.pak file2.utoc file3.zip
 
-use rust_ue_tools::{Unpacker, PakUnpackOptions, UtocListOptions, AssetPath, ProgressInfo};
+use rust_ue_tools::{Unpacker, PakUnpackOptions, ScanOptions, UtocListOptions, AssetPath};
 use std::path::{Path, PathBuf};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
@@ -87,8 +87,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 fn run_archive_example(archive_path: &str, aes_key: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
     println!("\n1. Extracting asset paths from archive (replaces Python function)...");
     
-    let unpacker = Unpacker::new();
-    match extract_uasset_paths_from_archive(&unpacker, archive_path, aes_key, false) {
+    let mut unpacker = Unpacker::new();
+    match extract_uasset_paths_from_archive(&mut unpacker, archive_path, aes_key, false) {
         Ok(asset_paths) => {
             println!("Found {} asset paths:", asset_paths.len());
             for asset in &asset_paths {
@@ -106,8 +106,8 @@ fn run_archive_example(archive_path: &str, aes_key: Option<&str>) -> Result<(),
 fn run_folder_example(folder_path: &str, aes_key: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
     println!("\n2. Extracting pak asset map from folder (replaces Python function)...");
     
-    let unpacker = Unpacker::new();
-    match extract_pak_asset_map_from_folder(&unpacker, folder_path, aes_key) {
+    let mut unpacker = Unpacker::new();
+    match extract_pak_asset_map_from_folder(&mut unpacker, folder_path, aes_key) {
         Ok(asset_map) => {
             println!("Asset map for {} pak files:", asset_map.len());
             for (pak_name, assets) in &asset_map {
@@ -131,8 +131,7 @@ fn run_folder_example(folder_path: &str, aes_key: Option<&str>) -> Result<(), Bo
 fn run_batch_example(file_paths: &[&str], aes_key: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
     println!("\n3. Processing multiple files in batch...");
     
-    let unpacker = Unpacker::new();
-    match process_multiple_files(&unpacker, file_paths, aes_key) {
+    match process_multiple_files(file_paths, aes_key) {
         Ok(results) => {
             println!("Successfully processed {} files:", results.len());
             for (file_name, assets) in &results {
@@ -149,7 +148,7 @@ fn run_batch_example(file_paths: &[&str], aes_key: Option<&str>) -> Result<(), B
 /// Rust equivalent of Python's extract_uasset_paths_from_zip function
 /// Updated to use the correct function name: extract_asset_paths_from_archive
 pub fn extract_uasset_paths_from_archive(
-    unpacker: &Unpacker,
+    unpacker: &mut Unpacker,
     archive_path: &str,
     aes_key: Option<&str>,
     keep_temp: bool,
@@ -170,79 +169,35 @@ pub fn extract_uasset_paths_from_archive(
 
 /// Rust equivalent of Python's extract_pak_asset_map_from_folder function
 pub fn extract_pak_asset_map_from_folder(
-    unpacker: &Unpacker,
+    unpacker: &mut Unpacker,
     folder_path: &str,
     aes_key: Option<&str>,
 ) -> Result<HashMap<String, Vec<AssetPath>>, Box<dyn std::error::Error>> {
     println!("Extracting pak asset map from folder: {}", folder_path);
 
-    let base = Path::new(folder_path);
-    if !base.exists() || !base.is_dir() {
-        return Err(format!("Folder not found: {}", folder_path).into());
+    let mut options = ScanOptions::new();
+    if let Some(key) = aes_key {
+        options = options.with_aes_key(key);
     }
 
-    let mut result: HashMap<String, Vec<AssetPath>> = HashMap::new();
-
-    // Process classic .pak files
-    println!("Processing classic .pak files...");
-    let pak_files: Vec<PathBuf> = walkdir::WalkDir::new(base)
-        .into_iter()
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| entry.file_type().is_file())
-        .filter(|entry| entry.path().extension().map_or(false, |ext| ext == "pak"))
-        .map(|entry| entry.path().to_path_buf())
-        .collect();
-
-    for pak in &pak_files {
-        let output_dir = pak.with_suffix("");
-        let options = PakUnpackOptions::new()
-            .with_aes_key(aes_key.unwrap_or_default())
-            .with_strip_prefix("../../../")
-            .with_force(true)
-            .with_quiet(true);
-
-        match unpacker.unpack_pak(pak, &output_dir, &options) {
-            Ok(assets) => {
-                result.insert(pak.file_stem().unwrap().to_string_lossy().to_string(), assets);
-            }
-            Err(e) => {
-                println!("Warning: Failed to unpack {}: {}", pak.display(), e);
-            }
-        }
+    // Solo paks and .utoc/.ucas bundles are paired and listed by the library
+    let containers = unpacker.scan_folder(folder_path, &options)?;
+    for container in containers.errors() {
+        println!("Warning: Failed to list {}: {}", container.name, container.error.as_deref().unwrap_or_default());
     }
 
-    // Process IoStore (.utoc + .ucas) files
-    println!("Processing IoStore (.utoc) files...");
-    let utoc_files: Vec<PathBuf> = walkdir::WalkDir::new(base)
+    Ok(containers
+        .containers
         .into_iter()
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| entry.file_type().is_file())
-        .filter(|entry| entry.path().extension().map_or(false, |ext| ext == "utoc"))
-        .map(|entry| entry.path().to_path_buf())
-        .collect();
-
-    for utoc in &utoc_files {
-        let pak_name = utoc.file_stem().unwrap().to_string_lossy().to_string();
-        let options = UtocListOptions::new()
-            .with_aes_key(aes_key.unwrap_or_default())
-            .with_json_format(false);
-
-        match unpacker.list_utoc(utoc, &options) {
-            Ok(assets) => {
-                result.insert(pak_name, assets);
-            }
-            Err(e) => {
-                println!("Warning: Failed to list {}: {}", utoc.display(), e);
-            }
-        }
-    }
-
-    Ok(result)
+        .filter(|(_, c)| c.is_ok())
+        .map(|(key, c)| (key, c.assets))
+        .collect())
 }
 
 /// Advanced example showing batch processing with progress tracking
+///
+/// Each thread uses its own `Unpacker`, since operations need `&mut self`.
 pub fn process_multiple_files(
-    unpacker: &Unpacker,
     file_paths: &[&str],
     aes_key: Option<&str>,
 ) -> Result<HashMap<String, Vec<AssetPath>>, Box<dyn std::error::Error>> {
@@ -255,8 +210,7 @@ pub fn process_multiple_files(
     // Process files in parallel
     std::thread::scope(|s| {
         let handles: Vec<_> = file_paths.iter().map(|file_path| {
-            let unpacker = unpacker;
-            let aes_key = aes_key;
+            let mut unpacker = Unpacker::new();
             let progress = Arc::clone(&progress);
             let results = Arc::clone(&results);
             
@@ -266,14 +220,14 @@ pub fn process_multiple_files(
                 
                 match ext {
                     "pak" => {
-                        let output_dir = path.with_suffix("");
+                        let output_dir = path.with_extension("");
                         let options = PakUnpackOptions::new()
                             .with_aes_key(aes_key.unwrap_or_default())
                             .with_strip_prefix("../../../")
                             .with_force(true)
                             .with_quiet(true);
 
-                        match unpacker.unpack_pak(path, &output_dir, &options) {
+                        match unpacker.unpack_pak(path, output_dir.as_path(), &options) {
                             Ok(assets) => {
                                 let mut results = results.lock().unwrap();
                                 results.insert(path.file_stem().unwrap().to_string_lossy().to_string(), assets);
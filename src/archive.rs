@@ -0,0 +1,325 @@
+//! Extraction of ZIP, RAR and 7z archives containing pak/utoc files
+//!
+//! Mods are usually distributed as archives. ZIP and 7z are extracted in
+//! process; RAR needs an external `rar`/`unrar` tool.
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::cache::{self, ListingKind};
+use crate::cancel::{self, PartialResult};
+use crate::error::{Result, UeToolError};
+use crate::extract::join_entry_path;
+use crate::progress::{self, Phase};
+use crate::{AssetPath, ContainerMap, ScanOptions, Unpacker};
+
+impl Unpacker {
+    /// Extract asset paths from an archive file (ZIP, RAR or 7z) containing pak/utoc files
+    ///
+    /// This function will:
+    /// 1. Detect archive type (ZIP, RAR or 7z)
+    /// 2. Extract the archive to a temporary directory
+    /// 3. Scan the extracted files with [`Unpacker::scan_folder`], reading
+    ///    only the pak and utoc indexes
+    /// 4. Return all discovered asset paths
+    ///
+    /// With a listing cache set, an archive that is unchanged since its last
+    /// complete listing is not extracted again.
+    ///
+    /// The cancellation token, if set, is checked between archive entries and
+    /// containers; assets found before cancellation are returned inside the
+    /// [`Interrupted`](crate::Interrupted) error.
+    pub fn extract_asset_paths_from_archive<P: AsRef<Path>>(
+        &mut self,
+        archive_path: P,
+        aes_key: Option<&str>,
+        keep_temp: bool,
+    ) -> PartialResult<Vec<AssetPath>> {
+        use tempfile::TempDir;
+
+        let archive_path = archive_path.as_ref();
+        let _span = tracing::info_span!("extract_archive", archive = %archive_path.display()).entered();
+
+        // An unchanged archive lists the same assets; skip extracting it again
+        let digest = cache::options_digest(&[aes_key]);
+        if let Some(assets) = self.cache.get(ListingKind::Archive, &digest, &[archive_path]) {
+            tracing::debug!(assets = assets.len(), "Using cached archive listing");
+            return Ok(assets);
+        }
+
+        // Create temporary directory
+        let temp_dir = TempDir::new()
+            .map_err(|e| UeToolError::IoError(format!("Failed to create temp directory: {}", e)))?;
+        let temp_path = temp_dir.path().to_path_buf();
+
+        // Detect archive type and extract
+        let archive_type = Self::detect_archive_type(archive_path)?;
+
+        match archive_type {
+            ArchiveType::Zip => {
+                self.extract_zip_archive(archive_path, &temp_path)?;
+            }
+            ArchiveType::Rar => {
+                self.extract_rar_archive(archive_path, &temp_path)?;
+            }
+            ArchiveType::SevenZip => {
+                self.extract_7z_archive(archive_path, &temp_path)?;
+            }
+        }
+
+        // Pair and list the extracted containers from their indexes
+        let mut options = ScanOptions::new();
+        if let Some(key) = aes_key {
+            options = options.with_aes_key(key);
+        }
+        let containers = self
+            .scan_folder(&temp_path, &options)
+            .map_err(|interrupted| interrupted.map(ContainerMap::into_assets))?;
+        tracing::info!(containers = containers.len(), assets = containers.asset_count(), "Scanned extracted containers");
+        let complete = containers.errors().next().is_none();
+        let all_assets = containers.into_assets();
+
+        // Only cache complete listings so a failed container is retried next time
+        if complete {
+            self.cache.insert(ListingKind::Archive, &digest, &[archive_path], &all_assets);
+        }
+
+        if keep_temp {
+            // Return temp dir so caller can access it
+            drop(temp_dir);
+        }
+
+        Ok(all_assets)
+    }
+
+    /// Detect the type of archive file
+    fn detect_archive_type(archive_path: &Path) -> Result<ArchiveType> {
+        let extension = archive_path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+
+        match extension.as_str() {
+            "zip" => Ok(ArchiveType::Zip),
+            "rar" => Ok(ArchiveType::Rar),
+            "7z" => Ok(ArchiveType::SevenZip),
+            _ => Err(UeToolError::InvalidArgument(format!(
+                "Unsupported archive type: {}", extension
+            ))),
+        }
+    }
+
+    /// Extract a ZIP archive to the specified directory
+    fn extract_zip_archive(&self, archive_path: &Path, dest_dir: &Path) -> Result<()> {
+        let zip_file = File::open(archive_path)
+            .map_err(|e| UeToolError::IoError(format!("Failed to open zip file: {}", e)))?;
+
+        let mut zip_archive = zip::ZipArchive::new(zip_file)
+            .map_err(|e| UeToolError::IoError(format!("Failed to open zip archive: {}", e)))?;
+
+        let total_bytes = (0..zip_archive.len())
+            .filter_map(|i| zip_archive.by_index_raw(i).ok().map(|f| f.size()))
+            .sum();
+        let mut progress = self.progress.phase(Phase::ExtractArchive, zip_archive.len() as u64, total_bytes);
+
+        for i in 0..zip_archive.len() {
+            cancel::check(self.cancellation.as_ref())?;
+
+            let mut file = zip_archive.by_index(i)
+                .map_err(|e| UeToolError::IoError(format!("Failed to read zip entry {}: {}", i, e)))?;
+            let name = file.name().to_string();
+            progress.entry_started(&name, file.size());
+
+            let out_path = match file.enclosed_name() {
+                Some(relative) => dest_dir.join(relative),
+                None => {
+                    return Err(UeToolError::InvalidFormat(format!(
+                        "Zip entry {} would be extracted outside {}",
+                        name,
+                        dest_dir.display()
+                    )))
+                }
+            };
+            if file.is_dir() {
+                fs::create_dir_all(&out_path)
+                    .map_err(|e| UeToolError::IoError(format!("Failed to create directory {}: {}", out_path.display(), e)))?;
+            } else {
+                if let Some(parent) = out_path.parent() {
+                    fs::create_dir_all(parent)
+                        .map_err(|e| UeToolError::IoError(format!("Failed to create parent directory {}: {}", parent.display(), e)))?;
+                }
+                let mut out_file = File::create(&out_path)
+                    .map_err(|e| UeToolError::IoError(format!("Failed to create file {}: {}", out_path.display(), e)))?;
+                progress::copy_with_progress(&mut file, &mut out_file, &mut progress)
+                    .map_err(|e| UeToolError::IoError(format!("Failed to copy file data: {}", e)))?;
+            }
+            tracing::trace!(entry = %name, bytes = file.size(), "Extracted archive entry");
+            progress.entry_finished(&name, file.size());
+        }
+
+        progress.finish();
+        Ok(())
+    }
+
+    /// Extract a 7z archive to the specified directory
+    fn extract_7z_archive(&self, archive_path: &Path, dest_dir: &Path) -> Result<()> {
+        let mut seven = sevenz_rust::SevenZReader::open(archive_path, sevenz_rust::Password::empty())
+            .map_err(|e| UeToolError::IoError(format!("Failed to open 7z archive: {}", e)))?;
+
+        let files = &seven.archive().files;
+        let total_bytes = files.iter().map(|f| f.size()).sum();
+        let mut progress = self.progress.phase(Phase::ExtractArchive, files.len() as u64, total_bytes);
+
+        // The extract callback can only stop iteration, so the reason is kept here
+        let mut stopped: Option<UeToolError> = None;
+        seven
+            .for_each_entries(|entry, reader| {
+                if let Err(e) = cancel::check(self.cancellation.as_ref()) {
+                    stopped = Some(e);
+                    return Ok(false);
+                }
+
+                let out_path = match join_entry_path(dest_dir, entry.name()) {
+                    Ok(out_path) => out_path,
+                    Err(e) => {
+                        stopped = Some(e);
+                        return Ok(false);
+                    }
+                };
+                if entry.is_directory() {
+                    fs::create_dir_all(&out_path)?;
+                } else {
+                    if let Some(parent) = out_path.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    progress.entry_started(entry.name(), entry.size());
+                    let mut out_file = File::create(&out_path)?;
+                    progress::copy_with_progress(reader, &mut out_file, &mut progress)?;
+                }
+
+                tracing::trace!(entry = %entry.name(), bytes = entry.size(), "Extracted archive entry");
+                progress.entry_finished(entry.name(), entry.size());
+                Ok(true)
+            })
+            .map_err(|e| UeToolError::IoError(format!("Failed to extract 7z archive: {}", e)))?;
+
+        match stopped {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Extract a RAR archive to the specified directory using external tools
+    ///
+    /// The tool is killed if it outlives the archive tool timeout or the
+    /// cancellation token fires.
+    fn extract_rar_archive(&self, archive_path: &Path, dest_dir: &Path) -> Result<()> {
+        // Try to find RAR tool (similar to Python implementation)
+        let rar_tool = Self::find_rar_tool()?;
+        tracing::debug!(tool = %rar_tool, "Running RAR tool");
+
+        // The external tool gives no progress, so the phase only marks start and end
+        let _progress = self.progress.phase(Phase::ExtractArchive, 0, 0);
+
+        // Run the RAR extraction command
+        let mut child = Command::new(&rar_tool)
+            .args(["x", "-y"]) // x = extract, -y = assume yes to all prompts
+            .arg(archive_path)
+            .arg(dest_dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| UeToolError::IoError(format!("Failed to run RAR tool: {}", e)))?;
+
+        // Drain stderr on a separate thread so a chatty tool cannot block on a full pipe
+        let stderr_reader = child.stderr.take().map(|mut stderr| {
+            std::thread::spawn(move || {
+                let mut buf = String::new();
+                let _ = stderr.read_to_string(&mut buf);
+                buf
+            })
+        });
+
+        let deadline = std::time::Instant::now() + self.archive_tool_timeout;
+        let status = loop {
+            if let Some(status) = child.try_wait()
+                .map_err(|e| UeToolError::ExternalTool(format!("Failed to wait for RAR tool: {}", e)))?
+            {
+                break status;
+            }
+
+            let stop = match cancel::check(self.cancellation.as_ref()) {
+                Err(e) => Some(e),
+                Ok(()) if std::time::Instant::now() >= deadline => Some(UeToolError::Timeout),
+                Ok(()) => None,
+            };
+            if let Some(e) = stop {
+                tracing::warn!(error = %e, "Stopping RAR tool");
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(e);
+            }
+
+            std::thread::sleep(Duration::from_millis(50));
+        };
+
+        if !status.success() {
+            let stderr = stderr_reader
+                .and_then(|handle| handle.join().ok())
+                .unwrap_or_default();
+            return Err(UeToolError::ExternalTool(format!(
+                "RAR extraction failed: {}", stderr
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Find the RAR tool executable (similar to Python implementation)
+    fn find_rar_tool() -> Result<String> {
+        // Check environment variable first
+        if let Ok(env_tool) = std::env::var("RAR_TOOL_PATH") {
+            if Path::new(&env_tool).exists() {
+                return Ok(env_tool);
+            }
+        }
+
+        // Check common WinRAR locations
+        let winrar_paths = [
+            r"C:\Program Files\WinRAR\rar.exe",
+            r"C:\Program Files (x86)\WinRAR\rar.exe",
+            r"C:\WinRAR\rar.exe",
+        ];
+
+        for path in &winrar_paths {
+            if Path::new(path).exists() {
+                return Ok(path.to_string());
+            }
+        }
+
+        // Check if rar.exe is in PATH
+        if let Ok(output) = Command::new("where").arg("rar.exe").output() {
+            if output.status.success() {
+                let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+                if !path.is_empty() {
+                    return Ok(path);
+                }
+            }
+        }
+
+        Err(UeToolError::IoError("No RAR tool found. Please install WinRAR or ensure rar.exe is in PATH".to_string()))
+    }
+}
+
+
+/// Archive type enumeration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveType {
+    Zip,
+    Rar,
+    SevenZip,
+}
@@ -0,0 +1,1230 @@
+//! Parsing of cooked package summaries
+//!
+//! Every cooked package starts with a summary locating its name map, import
+//! table and export table. Paks hold the legacy `FPackageFileSummary` at the
+//! start of each `.uasset`; IoStore containers hold an `FZenPackageSummary`
+//! at the start of each package chunk. [`PackageSummary::parse`] reads either
+//! into the same structs.
+//!
+//! Games usually cook their packages unversioned, so the layout depends on the
+//! engine the game was built with and an [`EngineVersion`] must be supplied
+//! for legacy packages. Zen summaries of UE 4.26/4.27 IoStore containers use
+//! an older layout and are not supported.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::binary::ReadExt;
+use crate::cancel;
+use crate::error::{Result, UeToolError};
+use crate::package::{ContainerKind, Package};
+use crate::progress::Phase;
+use crate::types::{ExtractOptions, InspectOptions};
+use crate::vfs::ContainerReader;
+use crate::Unpacker;
+
+/// Tag at the start of every legacy package summary
+pub const PACKAGE_FILE_TAG: u32 = 0x9E2A_83C1;
+
+/// Package flag set on cooked packages with editor-only data stripped
+const PKG_FILTER_EDITOR_ONLY: u32 = 0x8000_0000;
+
+// Object versions (EUnrealEngineObjectUE4Version) that changed the summary or table layout
+const VER_UE4_WORLD_LEVEL_INFO: i32 = 224;
+const VER_UE4_ADDED_CHUNKID_TO_ASSETDATA_AND_UPACKAGE: i32 = 278;
+const VER_UE4_CHANGED_CHUNKID_TO_BE_AN_ARRAY_OF_CHUNKIDS: i32 = 326;
+const VER_UE4_ENGINE_VERSION_OBJECT: i32 = 336;
+const VER_UE4_LOAD_FOR_EDITOR_GAME: i32 = 365;
+const VER_UE4_ADD_STRING_ASSET_REFERENCES_MAP: i32 = 384;
+const VER_UE4_PACKAGE_SUMMARY_HAS_COMPATIBLE_ENGINE_VERSION: i32 = 444;
+const VER_UE4_SERIALIZE_TEXT_IN_PACKAGES: i32 = 459;
+const VER_UE4_COOKED_ASSETS_IN_EDITOR_SUPPORT: i32 = 485;
+const VER_UE4_NAME_HASHES_SERIALIZED: i32 = 504;
+const VER_UE4_PRELOAD_DEPENDENCIES_IN_COOKED_EXPORTS: i32 = 507;
+const VER_UE4_TEMPLATE_INDEX_IN_COOKED_EXPORTS: i32 = 508;
+const VER_UE4_ADDED_SEARCHABLE_NAMES: i32 = 510;
+const VER_UE4_64BIT_EXPORTMAP_SERIALSIZES: i32 = 511;
+const VER_UE4_ADDED_SOFT_OBJECT_PATH: i32 = 514;
+const VER_UE4_ADDED_PACKAGE_SUMMARY_LOCALIZATION_ID: i32 = 516;
+const VER_UE4_ADDED_PACKAGE_OWNER: i32 = 518;
+const VER_UE4_NON_OUTER_PACKAGE_IMPORT: i32 = 520;
+
+// EUnrealEngineObjectUE5Version
+const VER_UE5_NAMES_REFERENCED_FROM_EXPORT_DATA: i32 = 1001;
+const VER_UE5_PAYLOAD_TOC: i32 = 1002;
+const VER_UE5_OPTIONAL_RESOURCES: i32 = 1003;
+const VER_UE5_REMOVE_OBJECT_EXPORT_PACKAGE_GUID: i32 = 1005;
+const VER_UE5_TRACK_OBJECT_EXPORT_IS_INHERITED: i32 = 1006;
+const VER_UE5_ADD_SOFTOBJECTPATH_LIST: i32 = 1008;
+pub(crate) const VER_UE5_DATA_RESOURCES: i32 = 1009;
+const VER_UE5_SCRIPT_SERIALIZATION_OFFSET: i32 = 1010;
+const VER_UE5_METADATA_SERIALIZATION_OFFSET: i32 = 1014;
+const VER_UE5_VERSE_CELLS: i32 = 1015;
+const VER_UE5_PACKAGE_SAVED_HASH: i32 = 1016;
+
+/// Size of a zen `FExportMapEntry`
+const ZEN_EXPORT_ENTRY_SIZE: usize = 72;
+
+/// Size of a zen `FBulkDataMapEntry`
+const ZEN_BULK_DATA_ENTRY_SIZE: u64 = 32;
+
+/// Engine release a package was cooked with, needed for unversioned packages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum EngineVersion {
+    #[serde(rename = "4.25")]
+    UE4_25,
+    #[serde(rename = "4.26")]
+    UE4_26,
+    #[serde(rename = "4.27")]
+    UE4_27,
+    #[serde(rename = "5.0")]
+    UE5_0,
+    #[serde(rename = "5.1")]
+    UE5_1,
+    #[serde(rename = "5.2")]
+    UE5_2,
+    #[serde(rename = "5.3")]
+    UE5_3,
+    #[serde(rename = "5.4")]
+    UE5_4,
+}
+
+impl EngineVersion {
+    /// Every supported release, oldest first
+    pub const ALL: [EngineVersion; 8] = [
+        EngineVersion::UE4_25,
+        EngineVersion::UE4_26,
+        EngineVersion::UE4_27,
+        EngineVersion::UE5_0,
+        EngineVersion::UE5_1,
+        EngineVersion::UE5_2,
+        EngineVersion::UE5_3,
+        EngineVersion::UE5_4,
+    ];
+
+    /// The (UE4, UE5) object versions packages of this release are saved with
+    pub fn object_versions(self) -> (i32, i32) {
+        match self {
+            EngineVersion::UE4_25 => (518, 0),
+            EngineVersion::UE4_26 => (519, 0),
+            EngineVersion::UE4_27 => (522, 0),
+            EngineVersion::UE5_0 => (522, 1004),
+            EngineVersion::UE5_1 => (522, 1008),
+            EngineVersion::UE5_2 | EngineVersion::UE5_3 => (522, 1009),
+            EngineVersion::UE5_4 => (522, 1012),
+        }
+    }
+}
+
+impl FromStr for EngineVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let version = s.trim().to_lowercase().replace('_', ".");
+        let version = version.strip_prefix("ue").unwrap_or(&version);
+        EngineVersion::ALL
+            .into_iter()
+            .find(|v| v.to_string() == version)
+            .ok_or_else(|| format!("Unsupported engine version: {} (expected 4.25 to 5.4)", s))
+    }
+}
+
+impl fmt::Display for EngineVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (major, minor) = match self {
+            EngineVersion::UE4_25 => (4, 25),
+            EngineVersion::UE4_26 => (4, 26),
+            EngineVersion::UE4_27 => (4, 27),
+            EngineVersion::UE5_0 => (5, 0),
+            EngineVersion::UE5_1 => (5, 1),
+            EngineVersion::UE5_2 => (5, 2),
+            EngineVersion::UE5_3 => (5, 3),
+            EngineVersion::UE5_4 => (5, 4),
+        };
+        write!(f, "{}.{}", major, minor)
+    }
+}
+
+/// Which summary a package was parsed from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageFormat {
+    /// `FPackageFileSummary` of a `.uasset` in a pak
+    Legacy,
+    /// `FZenPackageSummary` of an IoStore package chunk
+    Zen,
+}
+
+/// A custom version recorded in a versioned package
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CustomVersion {
+    pub guid: String,
+    pub version: i32,
+}
+
+/// Versions a package was saved with
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PackageVersions {
+    /// Layout version of the legacy summary (negative); `None` for zen packages
+    pub legacy_file_version: Option<i32>,
+    pub file_version_ue4: i32,
+    pub file_version_ue5: i32,
+    pub licensee_version: i32,
+    /// Saved without versions; the object versions above come from the supplied engine version
+    pub unversioned: bool,
+    /// Engine build that saved the package, e.g. `5.3.2-29314046+++UE5+Release-5.3`
+    pub saved_by_engine: Option<String>,
+    pub custom_versions: Vec<CustomVersion>,
+}
+
+/// Reference from one table entry to another object
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ObjectRef {
+    Null,
+    /// Index into [`PackageSummary::imports`]
+    Import { index: usize },
+    /// Index into [`PackageSummary::exports`]
+    Export { index: usize },
+    /// Zen reference to a script object by the hash of its path
+    ScriptImport { hash: u64 },
+    /// Zen reference to a public export of an imported package
+    PackageImport { package: u32, export_hash: u32 },
+}
+
+impl ObjectRef {
+    /// Decode a legacy `FPackageIndex`
+    pub(crate) fn from_package_index(index: i32) -> Self {
+        match index {
+            0 => ObjectRef::Null,
+            i if i < 0 => ObjectRef::Import { index: (-(i as i64) - 1) as usize },
+            i => ObjectRef::Export { index: i as usize - 1 },
+        }
+    }
+
+    /// Decode a zen `FPackageObjectIndex`
+    fn from_package_object_index(value: u64) -> Self {
+        let index = value & ((1 << 62) - 1);
+        match value >> 62 {
+            0 => ObjectRef::Export { index: index as usize },
+            1 => ObjectRef::ScriptImport { hash: index },
+            2 => ObjectRef::PackageImport { package: (index >> 32) as u32, export_hash: index as u32 },
+            _ => ObjectRef::Null,
+        }
+    }
+}
+
+/// An entry of the import table
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportEntry {
+    /// Name of the imported object; zen imports only store [`ImportEntry::global_ref`]
+    pub object_name: Option<String>,
+    pub class_package: Option<String>,
+    pub class_name: Option<String>,
+    pub outer: ObjectRef,
+    /// Package the object is imported from, when the summary records it
+    pub package_name: Option<String>,
+    /// The zen reference this import stands for; `None` for legacy imports
+    pub global_ref: Option<ObjectRef>,
+}
+
+/// An entry of the export table
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportEntry {
+    pub object_name: String,
+    pub class: ObjectRef,
+    /// Class name resolved through the tables; unknown for zen script classes
+    pub class_name: Option<String>,
+    pub super_struct: ObjectRef,
+    pub template: ObjectRef,
+    pub outer: ObjectRef,
+    pub object_flags: u32,
+    /// Offset of the serialized object in the cooked package (`.uasset` followed by `.uexp`)
+    pub serial_offset: u64,
+    pub serial_size: u64,
+}
+
+/// The parsed summary and tables of a cooked package
+#[derive(Debug, Clone, Serialize)]
+pub struct PackageSummary {
+    pub format: PackageFormat,
+    pub package_name: Option<String>,
+    pub package_flags: u32,
+    /// Size of the summary and the tables following it
+    pub header_size: u64,
+    /// Size of the legacy header export offsets are relative to; the same as
+    /// `header_size` except in zen packages
+    pub cooked_header_size: u64,
+    pub versions: PackageVersions,
+    pub names: Vec<String>,
+    pub imports: Vec<ImportEntry>,
+    pub exports: Vec<ExportEntry>,
+    /// Packages referenced through soft object paths; not stored in zen summaries
+    pub soft_package_references: Vec<String>,
+    /// Soft object paths exports refer to by index; only in legacy UE 5.1+ packages
+    pub soft_object_paths: Vec<String>,
+    /// Offset bulk data offsets are relative to unless flagged otherwise; legacy packages only
+    pub bulk_data_start_offset: i64,
+    /// Bulk data of UE 5.2+ packages, which exports refer to by index
+    pub data_resources: Vec<DataResource>,
+}
+
+/// Where a bulk data payload of a UE 5.2+ package is stored
+///
+/// Read from the data resource table of legacy packages and from the bulk
+/// data map of zen packages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DataResource {
+    /// `EBulkDataFlags` of the payload
+    pub flags: u32,
+    pub serial_offset: i64,
+    pub serial_size: i64,
+    /// Size once decompressed; the same as `serial_size` in zen packages
+    pub raw_size: i64,
+}
+
+impl PackageSummary {
+    /// Parse a legacy `.uasset` or a zen package chunk
+    ///
+    /// `engine` is required for unversioned legacy packages; for zen packages
+    /// it selects the 5.0-5.2 or 5.3+ layout, which is otherwise detected.
+    pub fn parse(data: &[u8], engine: Option<EngineVersion>) -> Result<Self> {
+        let mut summary = if data.get(..4) == Some(&PACKAGE_FILE_TAG.to_le_bytes()[..]) {
+            parse_legacy(data, engine)?
+        } else {
+            parse_zen(data, engine)?
+        };
+
+        let names: Vec<Option<String>> = summary.exports.iter().map(|e| summary.object_name(e.class).map(str::to_string)).collect();
+        for (export, class_name) in summary.exports.iter_mut().zip(names) {
+            export.class_name = class_name;
+        }
+        Ok(summary)
+    }
+
+    /// Read and parse a loose `.uasset`
+    pub fn open<P: AsRef<Path>>(path: P, engine: Option<EngineVersion>) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(|e| UeToolError::IoError(format!("Failed to read {}: {}", path.display(), e)))?;
+        Self::parse(&data, engine)
+    }
+
+    /// Name of the import or export a reference points to
+    pub fn object_name(&self, object: ObjectRef) -> Option<&str> {
+        match object {
+            ObjectRef::Import { index } => self.imports.get(index)?.object_name.as_deref(),
+            ObjectRef::Export { index } => Some(&self.exports.get(index)?.object_name),
+            _ => None,
+        }
+    }
+
+    /// The asset export of the package at `path`
+    ///
+    /// That is the top-level export named after the package (or its `_C`
+    /// generated class for blueprints), falling back to the first top-level export.
+    pub fn primary_export(&self, path: &str) -> Option<&ExportEntry> {
+        let stem = path.rsplit('/').next().unwrap_or(path);
+        let stem = stem.split_once('.').map_or(stem, |(name, _)| name);
+        let class = format!("{}_C", stem);
+        let top_level = || self.exports.iter().filter(|e| e.outer == ObjectRef::Null);
+        top_level()
+            .find(|e| e.object_name.eq_ignore_ascii_case(stem))
+            .or_else(|| top_level().find(|e| e.object_name.eq_ignore_ascii_case(&class)))
+            .or_else(|| top_level().next())
+    }
+
+    /// Class of the asset export of the package at `path`
+    ///
+    /// Zen packages reference native classes by hash; pass the game's
+    /// [`ScriptObjects`] to resolve them.
+    pub fn primary_class(&self, path: &str, script_objects: Option<&ScriptObjects>) -> Option<String> {
+        self.export_class(self.primary_export(path)?, script_objects)
+    }
+
+    /// Class name of an export, resolving zen script classes through `script_objects`
+    pub fn export_class(&self, export: &ExportEntry, script_objects: Option<&ScriptObjects>) -> Option<String> {
+        if let Some(name) = &export.class_name {
+            return Some(name.clone());
+        }
+        match export.class {
+            ObjectRef::ScriptImport { hash } => script_objects?.name(hash).map(str::to_string),
+            _ => None,
+        }
+    }
+
+    /// The serialized data of export `index`
+    ///
+    /// `package` is the whole package: a legacy `.uasset` followed by its
+    /// `.uexp`, or a zen package chunk.
+    pub fn export_data<'a>(&self, package: &'a [u8], index: usize) -> Result<&'a [u8]> {
+        let export = self
+            .exports
+            .get(index)
+            .ok_or_else(|| UeToolError::invalid_argument(format!("Export {} is out of range", index)))?;
+        let start = (self.header_size + export.serial_offset)
+            .checked_sub(self.cooked_header_size)
+            .ok_or_else(|| UeToolError::invalid_format(format!("Export {} starts inside the package header", export.object_name)))?;
+        usize::try_from(start)
+            .ok()
+            .zip(usize::try_from(export.serial_size).ok())
+            .and_then(|(start, size)| package.get(start..start.checked_add(size)?))
+            .ok_or_else(|| UeToolError::invalid_format(format!("Export {} runs past the end of the package", export.object_name)))
+    }
+}
+
+#[cfg(test)]
+impl PackageSummary {
+    /// A summary with only names and exports, for tests that read exports
+    pub(crate) fn for_test(format: PackageFormat, names: &[&str], exports: Vec<ExportEntry>) -> Self {
+        PackageSummary {
+            format,
+            package_name: None,
+            package_flags: 0,
+            header_size: 0,
+            cooked_header_size: 0,
+            versions: PackageVersions::default(),
+            names: names.iter().map(|n| n.to_string()).collect(),
+            imports: Vec::new(),
+            exports,
+            soft_package_references: Vec::new(),
+            soft_object_paths: Vec::new(),
+            bulk_data_start_offset: 0,
+            data_resources: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl ExportEntry {
+    /// An export of a native class, for tests that read exports
+    pub(crate) fn for_test(object_name: &str, class_name: &str, serial_size: usize) -> Self {
+        ExportEntry {
+            object_name: object_name.to_string(),
+            class: ObjectRef::Null,
+            class_name: Some(class_name.to_string()),
+            super_struct: ObjectRef::Null,
+            template: ObjectRef::Null,
+            outer: ObjectRef::Null,
+            object_flags: 0,
+            serial_offset: 0,
+            serial_size: serial_size as u64,
+        }
+    }
+}
+
+/// Names of the native objects zen packages reference by hash
+///
+/// IoStore games store them in the `ScriptObjects` chunk of `global.utoc`.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptObjects {
+    names: HashMap<u64, String>,
+}
+
+impl ScriptObjects {
+    /// The script objects for packages in `container`
+    ///
+    /// Read from the `global.utoc` next to an IoStore container; `None` for
+    /// paks, or when it cannot be read.
+    pub(crate) fn for_container(container: &Path, aes_key: Option<&str>) -> Option<Self> {
+        if ContainerKind::from_path(container) != Some(ContainerKind::IoStore) {
+            return None;
+        }
+        let global = container.with_file_name("global.utoc");
+        Self::open(&global, aes_key)
+            .inspect_err(|e| tracing::warn!(global = %global.display(), error = %e, "Native class names unavailable"))
+            .ok()
+    }
+
+    /// Read the script objects from a game's `global.utoc`
+    pub fn open<P: AsRef<Path>>(global_utoc: P, aes_key: Option<&str>) -> Result<Self> {
+        let global_utoc = global_utoc.as_ref();
+        let reader = ContainerReader::open(global_utoc, ContainerKind::IoStore, aes_key)?;
+        let data = reader
+            .read_chunk_of_type(retoc::EIoChunkType::ScriptObjects)?
+            .ok_or_else(|| UeToolError::UtocError(format!("{} has no script objects", global_utoc.display())))?;
+        Self::parse(&data)
+    }
+
+    /// Parse a `ScriptObjects` chunk: a name batch followed by the script object entries
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let mut r = Cursor::new(data);
+        let names = read_name_batch(&mut r)?;
+        let count = r.read_count()?;
+        let mut objects = HashMap::with_capacity(count.min(1 << 16));
+        for _ in 0..count {
+            let (index, number) = (r.read_u32()?, r.read_u32()?);
+            let global = r.read_u64()?;
+            r.read_fixed::<16>()?; // OuterIndex, CDOClassIndex
+            if let Some(name) = names.get((index & 0x3FFF_FFFF) as usize) {
+                if let ObjectRef::ScriptImport { hash } = ObjectRef::from_package_object_index(global) {
+                    objects.insert(hash, name_with_number(name, number));
+                }
+            }
+        }
+        Ok(Self { names: objects })
+    }
+
+    /// Name of the script object with this hash
+    pub fn name(&self, hash: u64) -> Option<&str> {
+        self.names.get(&hash).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+impl Unpacker {
+    /// Parse the summary of a package stored in a `.pak` or `.utoc` container
+    ///
+    /// `path` is resolved like [`Unpacker::read_entry`], so a package name
+    /// selects its header.
+    pub fn inspect_package<P: AsRef<Path>>(&mut self, container: P, path: &str, options: &InspectOptions) -> Result<PackageSummary> {
+        let container = container.as_ref();
+        let _span = tracing::info_span!("inspect_package", container = %container.display(), path).entered();
+
+        let mut extract = ExtractOptions::new();
+        extract.aes_key = options.aes_key.clone();
+        let data = self.read_entry(container, path, &extract)?;
+        PackageSummary::parse(&data, options.engine_version)
+    }
+
+    /// Set [`Package::class`] to the class of each package's asset export
+    ///
+    /// Package headers are read from `container`, the `.pak` or `.utoc` the
+    /// packages were listed from. For IoStore containers, native class names
+    /// come from the `global.utoc` next to the container. Packages whose
+    /// header cannot be read are left unclassified.
+    pub fn classify_packages<P: AsRef<Path>>(&mut self, container: P, packages: &mut [Package], options: &InspectOptions) -> Result<()> {
+        let container = container.as_ref();
+        let _span = tracing::info_span!("classify_packages", container = %container.display()).entered();
+
+        let kind = ContainerKind::from_path(container)
+            .ok_or_else(|| UeToolError::invalid_format(format!("{} is not a .pak or .utoc file", container.display())))?;
+        let mut reader = ContainerReader::open(container, kind, options.aes_key.as_deref())?;
+        let script_objects = ScriptObjects::for_container(container, options.aes_key.as_deref());
+
+        let mut progress = self.progress.phase(Phase::Analyze, packages.len() as u64, 0);
+        for package in packages.iter_mut() {
+            cancel::check(self.cancellation.as_ref())?;
+            let Some(header) = package.header() else {
+                continue;
+            };
+            let path = header.path.as_str().to_string();
+            progress.entry_started(&path, header.size);
+            match reader.read(&path).and_then(|data| PackageSummary::parse(&data, options.engine_version)) {
+                Ok(summary) => package.class = summary.primary_class(&package.name, script_objects.as_ref()),
+                // Unversioned and no engine version given: every package would fail the same way
+                Err(e @ UeToolError::InvalidArgument(_)) => return Err(e),
+                Err(e) => tracing::warn!(package = %package.name, error = %e, "Failed to read package header"),
+            }
+            progress.entry_finished(&path, 0);
+        }
+        progress.finish();
+        Ok(())
+    }
+}
+
+fn parse_legacy(data: &[u8], engine: Option<EngineVersion>) -> Result<PackageSummary> {
+    let mut r = Cursor::new(data);
+    r.read_u32()?;
+    let legacy_version = r.read_i32()?;
+    if !(-9..=-2).contains(&legacy_version) {
+        return Err(UeToolError::invalid_format(format!("Unsupported package file version {}", legacy_version)));
+    }
+    if legacy_version != -4 {
+        r.read_i32()?; // LegacyUE3Version
+    }
+
+    let mut versions = PackageVersions { legacy_file_version: Some(legacy_version), ..Default::default() };
+    versions.file_version_ue4 = r.read_i32()?;
+    if legacy_version <= -8 {
+        versions.file_version_ue5 = r.read_i32()?;
+    }
+    versions.licensee_version = r.read_i32()?;
+    versions.custom_versions = read_custom_versions(&mut r, legacy_version)?;
+    if versions.file_version_ue4 == 0 && versions.file_version_ue5 == 0 && versions.licensee_version == 0 {
+        let engine = engine.ok_or_else(|| {
+            UeToolError::invalid_argument("Package is saved unversioned; the engine version it was cooked with is required")
+        })?;
+        (versions.file_version_ue4, versions.file_version_ue5) = engine.object_versions();
+        versions.unversioned = true;
+    }
+    let (ue4, ue5) = (versions.file_version_ue4, versions.file_version_ue5);
+
+    if ue5 >= VER_UE5_PACKAGE_SAVED_HASH {
+        r.read_fixed::<20>()?; // SavedHash
+    }
+    let header_size = r.read_i32()?;
+    let package_name = r.read_fstring()?;
+    let package_flags = r.read_u32()?;
+    let has_editor_data = package_flags & PKG_FILTER_EDITOR_ONLY == 0;
+
+    let (name_count, name_offset) = (r.read_count()?, r.read_i32()?);
+    let (soft_path_count, soft_path_offset) = if ue5 >= VER_UE5_ADD_SOFTOBJECTPATH_LIST {
+        (r.read_count()?, r.read_i32()?)
+    } else {
+        (0, 0)
+    };
+    if ue4 >= VER_UE4_ADDED_PACKAGE_SUMMARY_LOCALIZATION_ID && has_editor_data {
+        r.read_fstring()?; // LocalizationId
+    }
+    if ue4 >= VER_UE4_SERIALIZE_TEXT_IN_PACKAGES {
+        r.read_fixed::<8>()?; // GatherableTextDataCount, GatherableTextDataOffset
+    }
+    let (export_count, export_offset) = (r.read_count()?, r.read_i32()?);
+    let (import_count, import_offset) = (r.read_count()?, r.read_i32()?);
+    if ue5 >= VER_UE5_VERSE_CELLS {
+        r.read_fixed::<16>()?; // CellExport/CellImport counts and offsets
+    }
+    if ue5 >= VER_UE5_METADATA_SERIALIZATION_OFFSET {
+        r.read_i32()?; // MetaDataOffset
+    }
+    r.read_i32()?; // DependsOffset
+    let (soft_count, soft_offset) = if ue4 >= VER_UE4_ADD_STRING_ASSET_REFERENCES_MAP {
+        (r.read_count()?, r.read_i32()?)
+    } else {
+        (0, 0)
+    };
+    if ue4 >= VER_UE4_ADDED_SEARCHABLE_NAMES {
+        r.read_i32()?; // SearchableNamesOffset
+    }
+    r.read_i32()?; // ThumbnailTableOffset
+    if ue5 < VER_UE5_PACKAGE_SAVED_HASH {
+        r.read_guid()?;
+    }
+    if has_editor_data && ue4 >= VER_UE4_ADDED_PACKAGE_OWNER {
+        r.read_guid()?; // PersistentGuid
+        if ue4 < VER_UE4_NON_OUTER_PACKAGE_IMPORT {
+            r.read_guid()?; // OwnerPersistentGuid
+        }
+    }
+    r.read_array_of(|r| r.read_fixed::<8>())?; // Generations
+    let saved_by_engine = if ue4 >= VER_UE4_ENGINE_VERSION_OBJECT {
+        read_engine_version(&mut r)?
+    } else {
+        None
+    };
+    // Only bulk data needs the rest of the summary; don't fail inspection over it
+    let (bulk_data_start_offset, data_resource_offset) = read_bulk_data_offsets(&mut r, &versions, legacy_version)
+        .unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Failed to read the bulk data offsets of the summary");
+            (0, 0)
+        });
+
+    seek(&mut r, name_offset as i64, "Name map")?;
+    let mut names = Vec::with_capacity(name_count.min(4096));
+    for _ in 0..name_count {
+        names.push(r.read_fstring()?);
+        if ue4 >= VER_UE4_NAME_HASHES_SERIALIZED {
+            r.read_u32()?; // NonCasePreservingHash, CasePreservingHash
+        }
+    }
+
+    seek(&mut r, import_offset as i64, "Import table")?;
+    let mut imports = Vec::with_capacity(import_count.min(4096));
+    for _ in 0..import_count {
+        let class_package = read_name(&mut r, &names)?;
+        let class_name = read_name(&mut r, &names)?;
+        let outer = ObjectRef::from_package_index(r.read_i32()?);
+        let object_name = read_name(&mut r, &names)?;
+        let package_name = if ue4 >= VER_UE4_NON_OUTER_PACKAGE_IMPORT && has_editor_data {
+            Some(read_name(&mut r, &names)?).filter(|name| name != "None")
+        } else {
+            None
+        };
+        if ue5 >= VER_UE5_OPTIONAL_RESOURCES {
+            r.read_bool32()?; // bImportOptional
+        }
+        imports.push(ImportEntry {
+            object_name: Some(object_name),
+            class_package: Some(class_package),
+            class_name: Some(class_name),
+            outer,
+            package_name,
+            global_ref: None,
+        });
+    }
+
+    seek(&mut r, export_offset as i64, "Export table")?;
+    let mut exports = Vec::with_capacity(export_count.min(4096));
+    for _ in 0..export_count {
+        let class = ObjectRef::from_package_index(r.read_i32()?);
+        let super_struct = ObjectRef::from_package_index(r.read_i32()?);
+        let template = if ue4 >= VER_UE4_TEMPLATE_INDEX_IN_COOKED_EXPORTS {
+            ObjectRef::from_package_index(r.read_i32()?)
+        } else {
+            ObjectRef::Null
+        };
+        let outer = ObjectRef::from_package_index(r.read_i32()?);
+        let object_name = read_name(&mut r, &names)?;
+        let object_flags = r.read_u32()?;
+        let (serial_size, serial_offset) = if ue4 >= VER_UE4_64BIT_EXPORTMAP_SERIALSIZES {
+            (r.read_u64()?, r.read_u64()?)
+        } else {
+            (r.read_i32()? as u64, r.read_i32()? as u64)
+        };
+        r.read_fixed::<12>()?; // bForcedExport, bNotForClient, bNotForServer
+        if ue5 < VER_UE5_REMOVE_OBJECT_EXPORT_PACKAGE_GUID {
+            r.read_guid()?;
+        }
+        if ue5 >= VER_UE5_TRACK_OBJECT_EXPORT_IS_INHERITED {
+            r.read_bool32()?;
+        }
+        r.read_u32()?; // PackageFlags
+        if ue4 >= VER_UE4_LOAD_FOR_EDITOR_GAME {
+            r.read_bool32()?; // bNotAlwaysLoadedForEditorGame
+        }
+        if ue4 >= VER_UE4_COOKED_ASSETS_IN_EDITOR_SUPPORT {
+            r.read_bool32()?; // bIsAsset
+        }
+        if ue5 >= VER_UE5_OPTIONAL_RESOURCES {
+            r.read_bool32()?; // bGeneratePublicHash
+        }
+        if ue4 >= VER_UE4_PRELOAD_DEPENDENCIES_IN_COOKED_EXPORTS {
+            r.read_fixed::<20>()?; // FirstExportDependency and the four dependency counts
+        }
+        if ue5 >= VER_UE5_SCRIPT_SERIALIZATION_OFFSET {
+            r.read_fixed::<16>()?; // ScriptSerializationStartOffset, ScriptSerializationEndOffset
+        }
+        exports.push(ExportEntry {
+            object_name,
+            class,
+            class_name: None,
+            super_struct,
+            template,
+            outer,
+            object_flags,
+            serial_offset,
+            serial_size,
+        });
+    }
+
+    let mut soft_package_references = Vec::with_capacity(soft_count.min(4096));
+    if soft_count > 0 {
+        seek(&mut r, soft_offset as i64, "Soft package references")?;
+        for _ in 0..soft_count {
+            soft_package_references.push(if ue4 >= VER_UE4_ADDED_SOFT_OBJECT_PATH {
+                read_name(&mut r, &names)?
+            } else {
+                r.read_fstring()?
+            });
+        }
+    }
+
+    let mut soft_object_paths = Vec::with_capacity(soft_path_count.min(4096));
+    if soft_path_count > 0 {
+        seek(&mut r, soft_path_offset as i64, "Soft object paths")?;
+        for _ in 0..soft_path_count {
+            let package = read_name(&mut r, &names)?;
+            let asset = read_name(&mut r, &names)?;
+            let sub_path = r.read_fstring()?;
+            soft_object_paths.push(soft_object_path(&package, &asset, &sub_path));
+        }
+    }
+    let data_resources = if data_resource_offset > 0 {
+        read_data_resources(&mut r, data_resource_offset).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Failed to read data resources");
+            Vec::new()
+        })
+    } else {
+        Vec::new()
+    };
+    versions.saved_by_engine = saved_by_engine;
+
+    Ok(PackageSummary {
+        format: PackageFormat::Legacy,
+        package_name: Some(package_name).filter(|name| !name.is_empty() && name != "None"),
+        package_flags,
+        header_size: header_size.max(0) as u64,
+        cooked_header_size: header_size.max(0) as u64,
+        versions,
+        names,
+        imports,
+        exports,
+        soft_package_references,
+        soft_object_paths,
+        bulk_data_start_offset,
+        data_resources,
+    })
+}
+
+fn parse_zen(data: &[u8], engine: Option<EngineVersion>) -> Result<PackageSummary> {
+    if engine.is_some_and(|v| v < EngineVersion::UE5_0) {
+        return Err(UeToolError::invalid_format("UE4 IoStore package summaries are not supported"));
+    }
+
+    let mut r = Cursor::new(data);
+    let has_versioning_info = r.read_bool32()?;
+    let header_size = r.read_u32()?;
+    let package_name = (r.read_u32()?, r.read_u32()?);
+    let package_flags = r.read_u32()?;
+    let cooked_header_size = r.read_u32()?;
+    let imported_hashes_offset = r.read_i32()?;
+    let import_map_offset = r.read_i32()?;
+    let export_map_offset = r.read_i32()?;
+    let export_bundle_entries_offset = r.read_i32()?;
+    // 5.0-5.2 end with GraphDataOffset; 5.3 replaced it with three offsets
+    let tail = [r.read_i32()?, r.read_i32()?, r.read_i32()?];
+
+    let in_header = |offset: i32| offset >= 0 && offset as u64 <= header_size as u64;
+    if header_size as usize > data.len()
+        || !(in_header(import_map_offset) && import_map_offset <= export_map_offset && export_map_offset <= export_bundle_entries_offset)
+        || !in_header(export_bundle_entries_offset)
+    {
+        return Err(UeToolError::invalid_format("Not a cooked package: no package file tag or zen package summary"));
+    }
+    let has_imported_package_names = match engine {
+        Some(version) => version >= EngineVersion::UE5_3,
+        None => {
+            tail[0] >= export_bundle_entries_offset && tail[0] <= tail[1] && tail[1] <= tail[2] && in_header(tail[2])
+        }
+    };
+    r.set_position(if has_imported_package_names { 52 } else { 44 });
+
+    let mut versions = PackageVersions::default();
+    if has_versioning_info {
+        r.read_u32()?; // EZenPackageVersion
+        versions.file_version_ue4 = r.read_i32()?;
+        versions.file_version_ue5 = r.read_i32()?;
+        versions.licensee_version = r.read_i32()?;
+        versions.custom_versions = r.read_array_of(|r| {
+            Ok(CustomVersion { guid: format_guid(&r.read_guid()?), version: r.read_i32()? })
+        })?;
+    } else {
+        versions.unversioned = true;
+        if let Some(version) = engine {
+            (versions.file_version_ue4, versions.file_version_ue5) = version.object_versions();
+        }
+    }
+
+    let names = read_name_batch(&mut r)?;
+    // 5.2+ headers have the bulk data map between the names and the imported export hashes
+    let mut data_resources = Vec::new();
+    let gap = (imported_hashes_offset as u64).saturating_sub(r.position());
+    let size = if gap >= 8 { r.read_u64()? } else { 0 };
+    if size % ZEN_BULK_DATA_ENTRY_SIZE == 0 && size <= gap.saturating_sub(8) {
+        for _ in 0..size / ZEN_BULK_DATA_ENTRY_SIZE {
+            let serial_offset = r.read_u64()? as i64;
+            r.read_u64()?; // DuplicateSerialOffset
+            let serial_size = r.read_u64()? as i64;
+            let flags = r.read_u32()?;
+            r.read_u32()?; // Padding
+            data_resources.push(DataResource { flags, serial_offset, serial_size, raw_size: serial_size });
+        }
+    }
+    let mapped_name = |(index, number): (u32, u32)| -> Result<String> {
+        let base = names
+            .get((index & 0x3FFF_FFFF) as usize)
+            .ok_or_else(|| UeToolError::invalid_format(format!("Name index {} is out of range", index)))?;
+        Ok(name_with_number(base, number))
+    };
+
+    let imported_packages = if has_imported_package_names {
+        seek(&mut r, tail[2] as i64, "Imported package names")?;
+        read_imported_package_names(&mut r).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Failed to read imported package names");
+            Vec::new()
+        })
+    } else {
+        Vec::new()
+    };
+
+    seek(&mut r, import_map_offset as i64, "Import map")?;
+    let import_count = (export_map_offset - import_map_offset) as usize / 8;
+    let mut imports = Vec::with_capacity(import_count);
+    for _ in 0..import_count {
+        let global_ref = ObjectRef::from_package_object_index(r.read_u64()?);
+        let package_name = match global_ref {
+            ObjectRef::PackageImport { package, .. } => imported_packages.get(package as usize).cloned(),
+            _ => None,
+        };
+        imports.push(ImportEntry {
+            object_name: None,
+            class_package: None,
+            class_name: None,
+            outer: ObjectRef::Null,
+            package_name,
+            global_ref: Some(global_ref),
+        });
+    }
+
+    seek(&mut r, export_map_offset as i64, "Export map")?;
+    let export_count = (export_bundle_entries_offset - export_map_offset) as usize / ZEN_EXPORT_ENTRY_SIZE;
+    let mut exports = Vec::with_capacity(export_count);
+    for _ in 0..export_count {
+        let serial_offset = r.read_u64()?;
+        let serial_size = r.read_u64()?;
+        let object_name = mapped_name((r.read_u32()?, r.read_u32()?))?;
+        let outer = ObjectRef::from_package_object_index(r.read_u64()?);
+        let class = ObjectRef::from_package_object_index(r.read_u64()?);
+        let super_struct = ObjectRef::from_package_object_index(r.read_u64()?);
+        let template = ObjectRef::from_package_object_index(r.read_u64()?);
+        r.read_u64()?; // PublicExportHash
+        let object_flags = r.read_u32()?;
+        r.read_u32()?; // FilterFlags and padding
+        exports.push(ExportEntry {
+            object_name,
+            class,
+            class_name: None,
+            super_struct,
+            template,
+            outer,
+            object_flags,
+            serial_offset,
+            serial_size,
+        });
+    }
+
+    Ok(PackageSummary {
+        format: PackageFormat::Zen,
+        package_name: Some(mapped_name(package_name)?),
+        package_flags,
+        header_size: header_size as u64,
+        cooked_header_size: cooked_header_size as u64,
+        versions,
+        names,
+        imports,
+        exports,
+        soft_package_references: Vec::new(),
+        soft_object_paths: Vec::new(),
+        bulk_data_start_offset: 0,
+        data_resources,
+    })
+}
+
+/// Read the legacy summary fields after the saved engine version, up to
+/// the data resource table: (`BulkDataStartOffset`, `DataResourceOffset`)
+fn read_bulk_data_offsets(r: &mut Cursor<&[u8]>, versions: &PackageVersions, legacy_version: i32) -> Result<(i64, i32)> {
+    let (ue4, ue5) = (versions.file_version_ue4, versions.file_version_ue5);
+    if ue4 >= VER_UE4_PACKAGE_SUMMARY_HAS_COMPATIBLE_ENGINE_VERSION {
+        read_engine_version(r)?;
+    }
+    r.read_u32()?; // CompressionFlags
+    if r.read_count()? != 0 {
+        return Err(UeToolError::invalid_format("Packages with compressed chunks are not supported"));
+    }
+    r.read_u32()?; // PackageSource
+    r.read_array_of(|r| r.read_fstring())?; // AdditionalPackagesToCook
+    if legacy_version > -7 {
+        r.read_i32()?; // NumTextureAllocations
+    }
+    r.read_i32()?; // AssetRegistryDataOffset
+    let bulk_data_start_offset = r.read_u64()? as i64;
+    if ue4 >= VER_UE4_WORLD_LEVEL_INFO {
+        r.read_i32()?; // WorldTileInfoDataOffset
+    }
+    if ue4 >= VER_UE4_CHANGED_CHUNKID_TO_BE_AN_ARRAY_OF_CHUNKIDS {
+        r.read_array_of(|r| r.read_i32())?; // ChunkIDs
+    } else if ue4 >= VER_UE4_ADDED_CHUNKID_TO_ASSETDATA_AND_UPACKAGE {
+        r.read_i32()?; // ChunkID
+    }
+    if ue4 >= VER_UE4_PRELOAD_DEPENDENCIES_IN_COOKED_EXPORTS {
+        r.read_fixed::<8>()?; // PreloadDependencyCount, PreloadDependencyOffset
+    }
+    if ue5 >= VER_UE5_NAMES_REFERENCED_FROM_EXPORT_DATA {
+        r.read_i32()?; // NamesReferencedFromExportDataCount
+    }
+    if ue5 >= VER_UE5_PAYLOAD_TOC {
+        r.read_u64()?; // PayloadTocOffset
+    }
+    let data_resource_offset = if ue5 >= VER_UE5_DATA_RESOURCES { r.read_i32()? } else { 0 };
+    Ok((bulk_data_start_offset, data_resource_offset))
+}
+
+/// Read the `FObjectDataResource` table of a legacy UE 5.2+ package
+fn read_data_resources(r: &mut Cursor<&[u8]>, offset: i32) -> Result<Vec<DataResource>> {
+    seek(r, offset as i64, "Data resources")?;
+    let version = r.read_u32()?;
+    if !(1..=2).contains(&version) {
+        return Err(UeToolError::invalid_format(format!("Unsupported data resource version {}", version)));
+    }
+    r.read_array_of(|r| {
+        r.read_u32()?; // EObjectDataResourceFlags
+        if version >= 2 {
+            r.read_u8()?; // CookedIndex
+        }
+        let serial_offset = r.read_u64()? as i64;
+        r.read_u64()?; // DuplicateSerialOffset
+        let serial_size = r.read_u64()? as i64;
+        let raw_size = r.read_u64()? as i64;
+        r.read_i32()?; // OuterIndex
+        let flags = r.read_u32()?; // LegacyBulkDataFlags
+        Ok(DataResource { flags, serial_offset, serial_size, raw_size })
+    })
+}
+
+/// Read an `FNameMap` batch as written by `SaveNameBatch`
+pub(crate) fn read_name_batch(r: &mut Cursor<&[u8]>) -> Result<Vec<String>> {
+    let count = r.read_u32()? as usize;
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    let string_bytes = r.read_u32()? as usize;
+    r.read_u64()?; // HashVersion
+    r.read_bytes(count.saturating_mul(8))?; // Hashes
+    let headers = r.read_bytes(count.saturating_mul(2))?;
+    let strings = r.read_bytes(string_bytes)?;
+
+    let truncated = || UeToolError::invalid_format("Name batch is truncated");
+    let mut names = Vec::with_capacity(count);
+    let mut pos = 0;
+    for header in headers.chunks_exact(2) {
+        let len = (((header[0] & 0x7F) as usize) << 8) | header[1] as usize;
+        if header[0] & 0x80 != 0 {
+            pos += pos % 2;
+            let bytes = strings.get(pos..pos + len * 2).ok_or_else(truncated)?;
+            let wide: Vec<u16> = bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+            names.push(
+                String::from_utf16(&wide).map_err(|e| UeToolError::invalid_format(format!("Invalid UTF-16 name: {}", e)))?,
+            );
+            pos += len * 2;
+        } else {
+            let bytes = strings.get(pos..pos + len).ok_or_else(truncated)?;
+            names.push(bytes.iter().map(|&b| b as char).collect());
+            pos += len;
+        }
+    }
+    Ok(names)
+}
+
+/// Read the 5.3+ imported package names: a name batch followed by each name's number
+fn read_imported_package_names(r: &mut Cursor<&[u8]>) -> Result<Vec<String>> {
+    let names = read_name_batch(r)?;
+    names.iter().map(|name| Ok(name_with_number(name, r.read_u32()?))).collect()
+}
+
+fn read_custom_versions(r: &mut Cursor<&[u8]>, legacy_version: i32) -> Result<Vec<CustomVersion>> {
+    match legacy_version {
+        -2 => r.read_array_of(|r| {
+            let tag = r.read_u32()?;
+            Ok(CustomVersion { guid: format!("{:08X}", tag), version: r.read_i32()? })
+        }),
+        -5..=-3 => r.read_array_of(|r| {
+            let guid = format_guid(&r.read_guid()?);
+            let version = r.read_i32()?;
+            r.read_fstring()?; // FriendlyName
+            Ok(CustomVersion { guid, version })
+        }),
+        _ => r.read_array_of(|r| Ok(CustomVersion { guid: format_guid(&r.read_guid()?), version: r.read_i32()? })),
+    }
+}
+
+/// Read an `FEngineVersion`; `None` when it is all zero, as in unversioned packages
+fn read_engine_version(r: &mut Cursor<&[u8]>) -> Result<Option<String>> {
+    let (major, minor, patch) = (r.read_u16()?, r.read_u16()?, r.read_u16()?);
+    let changelist = r.read_u32()? & 0x7FFF_FFFF;
+    let branch = r.read_fstring()?;
+    if major == 0 && minor == 0 && patch == 0 && changelist == 0 {
+        return Ok(None);
+    }
+    Ok(Some(format!("{}.{}.{}-{}+{}", major, minor, patch, changelist, branch)))
+}
+
+/// Read an `FName` serialized as (name map index, number)
+pub(crate) fn read_name(r: &mut Cursor<&[u8]>, names: &[String]) -> Result<String> {
+    let index = r.read_i32()?;
+    let number = r.read_u32()?;
+    let base = usize::try_from(index)
+        .ok()
+        .and_then(|i| names.get(i))
+        .ok_or_else(|| UeToolError::invalid_format(format!("Name index {} is out of range", index)))?;
+    Ok(name_with_number(base, number))
+}
+
+/// Format an `FName`; a non-zero number is stored one higher than the suffix it adds
+pub(crate) fn name_with_number(base: &str, number: u32) -> String {
+    match number {
+        0 => base.to_string(),
+        n => format!("{}_{}", base, n - 1),
+    }
+}
+
+/// Format an `FSoftObjectPath` as `/Package.Asset:SubPath`
+pub(crate) fn soft_object_path(package: &str, asset: &str, sub_path: &str) -> String {
+    let mut path = match (package, asset) {
+        ("None", _) => String::new(),
+        (package, "None") => package.to_string(),
+        (package, asset) => format!("{}.{}", package, asset),
+    };
+    if !sub_path.is_empty() {
+        path.push(':');
+        path.push_str(sub_path);
+    }
+    path
+}
+
+/// Format an `FGuid` the way UE prints it: four uint32 as uppercase hex
+pub(crate) fn format_guid(guid: &[u8; 16]) -> String {
+    guid.chunks_exact(4)
+        .map(|c| format!("{:08X}", u32::from_le_bytes([c[0], c[1], c[2], c[3]])))
+        .collect()
+}
+
+fn seek(r: &mut Cursor<&[u8]>, offset: i64, what: &str) -> Result<()> {
+    if offset < 0 || offset as u64 > r.get_ref().len() as u64 {
+        return Err(UeToolError::invalid_format(format!("{} offset {} is outside the package", what, offset)));
+    }
+    r.set_position(offset as u64);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::{write_fstring, write_name_batch};
+
+    const NAMES: [&str; 7] = ["/Script/CoreUObject", "Class", "Package", "/Script/Engine", "Texture2D", "T_Icon", "/Game/UI/T_Other"];
+
+    fn i32s(out: &mut Vec<u8>, values: &[i32]) {
+        for v in values {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+
+    /// A cooked, versioned UE 4.27 package with the tables at `offsets` (names, imports, exports, soft references)
+    fn legacy_package(offsets: [i32; 4]) -> Vec<u8> {
+        let mut out = PACKAGE_FILE_TAG.to_le_bytes().to_vec();
+        i32s(&mut out, &[-7, 864, 522, 0, 0, 0]);
+        write_fstring(&mut out, "None");
+        out.extend_from_slice(&PKG_FILTER_EDITOR_ONLY.to_le_bytes());
+        i32s(&mut out, &[NAMES.len() as i32, offsets[0], 0, 0, 1, offsets[2], 2, offsets[1], 0, 1, offsets[3], 0, 0]);
+        out.extend_from_slice(&[0; 16]);
+        i32s(&mut out, &[0]);
+        for v in [4u16, 27, 2] {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        i32s(&mut out, &[18319896]);
+        write_fstring(&mut out, "++UE4+Release-4.27");
+        out
+    }
+
+    #[test]
+    fn test_parse_legacy_summary() {
+        let mut offsets = [0; 4];
+        let mut tables = Vec::new();
+        offsets[0] = tables.len() as i32;
+        for name in NAMES {
+            write_fstring(&mut tables, name);
+            tables.extend_from_slice(&[0; 4]);
+        }
+        offsets[1] = tables.len() as i32;
+        i32s(&mut tables, &[0, 0, 2, 0, 0, 3, 0]);
+        i32s(&mut tables, &[0, 0, 1, 0, -1, 4, 0]);
+        offsets[2] = tables.len() as i32;
+        i32s(&mut tables, &[-2, 0, 0, 0, 5, 0, 0x1]);
+        tables.extend_from_slice(&120u64.to_le_bytes());
+        tables.extend_from_slice(&900u64.to_le_bytes());
+        tables.extend_from_slice(&[0; 12 + 16 + 4 + 8 + 20]);
+        offsets[3] = tables.len() as i32;
+        i32s(&mut tables, &[6, 0]);
+
+        let header_len = legacy_package(offsets).len() as i32;
+        let mut data = legacy_package(offsets.map(|o| o + header_len));
+        data.extend_from_slice(&tables);
+
+        let summary = PackageSummary::parse(&data, None).unwrap();
+        assert_eq!(summary.format, PackageFormat::Legacy);
+        assert_eq!(summary.versions.file_version_ue4, 522);
+        assert!(summary.versions.saved_by_engine.as_deref().unwrap().starts_with("4.27.2-18319896"));
+        assert_eq!(summary.names.len(), NAMES.len());
+        assert_eq!(summary.imports[1].outer, ObjectRef::Import { index: 0 });
+        assert_eq!(summary.exports[0].object_name, "T_Icon");
+        assert_eq!(summary.exports[0].class_name.as_deref(), Some("Texture2D"));
+        assert_eq!((summary.exports[0].serial_size, summary.exports[0].serial_offset), (120, 900));
+        assert_eq!(summary.soft_package_references, ["/Game/UI/T_Other"]);
+    }
+
+    #[test]
+    fn test_unversioned_legacy_needs_engine_version() {
+        let mut data = PACKAGE_FILE_TAG.to_le_bytes().to_vec();
+        i32s(&mut data, &[-8, 864, 0, 0, 0, 0]);
+        assert!(PackageSummary::parse(&data, None).is_err());
+    }
+
+    #[test]
+    fn test_parse_zen_summary() {
+        let mut body = Vec::new();
+        write_name_batch(&mut body, &["/Game/UI/T_Icon", "T_Icon"]);
+        body.extend_from_slice(&ZEN_BULK_DATA_ENTRY_SIZE.to_le_bytes());
+        for value in [0x40u64, u64::MAX, 0x1000, 0x0001_0501] {
+            body.extend_from_slice(&value.to_le_bytes());
+        }
+        let import_map = 52 + body.len() as i32;
+        body.extend_from_slice(&((2u64 << 62) | 0x1_0000_0002).to_le_bytes());
+        let export_map = 52 + body.len() as i32;
+        body.extend_from_slice(&0x2000u64.to_le_bytes());
+        body.extend_from_slice(&64u64.to_le_bytes());
+        body.extend_from_slice(&1u32.to_le_bytes());
+        body.extend_from_slice(&2u32.to_le_bytes());
+        body.extend_from_slice(&u64::MAX.to_le_bytes());
+        body.extend_from_slice(&(1u64 << 62 | 0xABC).to_le_bytes());
+        body.extend_from_slice(&[0xFF; 16]);
+        body.extend_from_slice(&[0; 16]);
+        let imported_names = 52 + body.len() as i32;
+        write_name_batch(&mut body, &["/Script/CoreUObject", "/Game/UI/Shared"]);
+        body.extend_from_slice(&[0; 8]);
+
+        let mut data = Vec::new();
+        i32s(&mut data, &[0, 52 + body.len() as i32, 0, 0, PKG_FILTER_EDITOR_ONLY as i32, 0x2000, import_map]);
+        i32s(&mut data, &[import_map, export_map, imported_names, imported_names, imported_names, imported_names]);
+        data.extend_from_slice(&body);
+
+        let summary = PackageSummary::parse(&data, None).unwrap();
+        assert_eq!(summary.format, PackageFormat::Zen);
+        assert_eq!(summary.package_name.as_deref(), Some("/Game/UI/T_Icon"));
+        assert_eq!(summary.imports[0].package_name.as_deref(), Some("/Game/UI/Shared"));
+        assert_eq!(summary.exports[0].object_name, "T_Icon_1");
+        assert_eq!(summary.exports[0].class, ObjectRef::ScriptImport { hash: 0xABC });
+        assert_eq!(summary.exports[0].outer, ObjectRef::Null);
+        assert_eq!(summary.exports[0].serial_size, 64);
+        assert_eq!(summary.data_resources, [DataResource { flags: 0x0001_0501, serial_offset: 0x40, serial_size: 0x1000, raw_size: 0x1000 }]);
+    }
+
+    #[test]
+    fn test_script_object_classes() {
+        let mut data = Vec::new();
+        write_name_batch(&mut data, &["Texture2D"]);
+        i32s(&mut data, &[1, 0, 0]);
+        data.extend_from_slice(&(1u64 << 62 | 0xABC).to_le_bytes());
+        data.extend_from_slice(&[0xFF; 16]);
+        let script_objects = ScriptObjects::parse(&data).unwrap();
+        assert_eq!(script_objects.name(0xABC), Some("Texture2D"));
+
+        let export = |name: &str, class| ExportEntry {
+            object_name: name.to_string(),
+            class,
+            class_name: None,
+            super_struct: ObjectRef::Null,
+            template: ObjectRef::Null,
+            outer: ObjectRef::Null,
+            object_flags: 0,
+            serial_offset: 0,
+            serial_size: 0,
+        };
+        let summary = PackageSummary::for_test(
+            PackageFormat::Zen,
+            &[],
+            vec![export("Other", ObjectRef::Null), export("T_Icon", ObjectRef::ScriptImport { hash: 0xABC })],
+        );
+        assert_eq!(summary.primary_class("Marvel/Content/UI/T_Icon", Some(&script_objects)).as_deref(), Some("Texture2D"));
+        assert_eq!(summary.primary_class("Marvel/Content/UI/T_Icon", None), None);
+    }
+
+    #[test]
+    fn test_engine_version_from_str() {
+        assert_eq!("5.3".parse::<EngineVersion>().unwrap(), EngineVersion::UE5_3);
+        assert_eq!("UE4_27".parse::<EngineVersion>().unwrap(), EngineVersion::UE4_27);
+        assert!("6.0".parse::<EngineVersion>().is_err());
+    }
+}
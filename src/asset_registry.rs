@@ -0,0 +1,667 @@
+//! Parsing of `AssetRegistry.bin`
+//!
+//! Cooked games ship the project's asset registry as `AssetRegistry.bin`,
+//! usually at the root of the project in the first pak. It records every
+//! cooked asset with its class and tags, the dependency graph between
+//! packages, and per-package data such as disk size and imported classes.
+//!
+//! Registries written by UE 4.25 through 5.4 (`FAssetRegistryVersion`
+//! `FixedTags` to `AssetPackageDataHasPackageLocation`) are supported.
+//! Editor registries older than that use a different tag layout.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::asset::{format_guid, name_with_number, read_name_batch};
+use crate::binary::ReadExt;
+use crate::error::{Result, UeToolError};
+use crate::package::ContainerKind;
+use crate::types::InspectOptions;
+use crate::vfs::{normalize_path, ContainerReader};
+use crate::Unpacker;
+
+/// File name of the cooked asset registry
+pub const ASSET_REGISTRY_FILE: &str = "AssetRegistry.bin";
+
+/// `FAssetRegistryVersion::GUID`
+const VERSION_GUID: [u32; 4] = [0x717F_9EE7, 0xE9B0_493A, 0x88B3_9132, 0x1B38_8107];
+
+// FAssetRegistryVersion::Type values that changed the layout
+const VER_ADDED_COOKED_MD5_HASH: u32 = 6;
+const VER_FIXED_TAGS: u32 = 8;
+const VER_WORKSPACE_DOMAIN: u32 = 9;
+const VER_PACKAGE_IMPORTED_CLASSES: u32 = 10;
+const VER_PACKAGE_FILE_SUMMARY_VERSION_CHANGE: u32 = 11;
+const VER_ADDED_CHUNK_HASHES: u32 = 13;
+const VER_CLASS_PATHS: u32 = 14;
+const VER_REMOVE_ASSET_PATH_FNAMES: u32 = 15;
+const VER_ADDED_HEADER: u32 = 16;
+const VER_PACKAGE_DATA_HAS_EXTENSION: u32 = 17;
+const VER_PACKAGE_DATA_HAS_LOCATION: u32 = 18;
+
+// Magic numbers around the tag store; the old one stores texts after export paths
+const STORE_BEGIN_MAGIC: u32 = 0x1234_5678;
+const STORE_OLD_BEGIN_MAGIC: u32 = 0x1234_5679;
+const STORE_END_MAGIC: u32 = 0x8765_4321;
+
+// Flag bits stored per dependency (FPropertyCombinationPack3 / TPropertyCombinationSet<1>)
+const PACKAGE_FLAG_SET_WIDTH: usize = 5;
+const MANAGE_FLAG_SET_WIDTH: usize = 1;
+
+/// A parsed `AssetRegistry.bin`
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetRegistry {
+    /// `FAssetRegistryVersion` the registry was written with
+    pub version: u32,
+    /// Editor-only tags were stripped when cooking
+    pub filter_editor_only: bool,
+    pub assets: Vec<AssetData>,
+    /// Dependency graph nodes; empty if the dependency section could not be read
+    pub dependencies: Vec<DependsNode>,
+    /// Per-package data; empty if the package section could not be read
+    pub packages: Vec<AssetPackageData>,
+}
+
+/// One asset of the registry
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetData {
+    /// Full object path, e.g. `/Game/UI/Icon.Icon`
+    pub object_path: String,
+    pub package_name: String,
+    pub package_path: String,
+    pub asset_name: String,
+    /// Class name; a full path such as `/Script/Engine.Texture2D` from UE 5.1
+    pub class: String,
+    pub tags: BTreeMap<String, String>,
+    pub chunk_ids: Vec<i32>,
+    pub package_flags: u32,
+}
+
+impl AssetData {
+    /// Whether the asset's class is `class`, by short name or full path
+    pub fn is_class(&self, class: &str) -> bool {
+        self.class.eq_ignore_ascii_case(class) || short_class(&self.class).eq_ignore_ascii_case(class)
+    }
+}
+
+/// One node of the registry's dependency graph
+///
+/// Nodes are usually packages, but may also be primary asset ids or
+/// searchable names. Dependencies and referencers are listed by identifier.
+#[derive(Debug, Clone, Serialize)]
+pub struct DependsNode {
+    pub identifier: String,
+    pub package_dependencies: Vec<String>,
+    pub name_dependencies: Vec<String>,
+    pub manage_dependencies: Vec<String>,
+    pub referencers: Vec<String>,
+}
+
+/// Per-package data of the registry
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetPackageData {
+    pub package_name: String,
+    pub disk_size: i64,
+    /// MD5 of the cooked package, as hex
+    pub cooked_hash: Option<String>,
+    pub file_version_ue4: Option<i32>,
+    pub file_version_ue5: Option<i32>,
+    pub licensee_version: Option<i32>,
+    pub flags: u32,
+    pub imported_classes: Vec<String>,
+    /// Extension of the package header, e.g. `.uasset`
+    pub extension: Option<String>,
+}
+
+impl AssetRegistry {
+    /// Parse an `AssetRegistry.bin`
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let mut r = Cursor::new(data);
+        let guid = r.read_guid()?;
+        let expected: Vec<u8> = VERSION_GUID.iter().flat_map(|v| v.to_le_bytes()).collect();
+        if guid[..] != expected[..] {
+            return Err(UeToolError::invalid_format(format!(
+                "Not an asset registry: version GUID {} does not match",
+                format_guid(&guid)
+            )));
+        }
+        let version = r.read_u32()?;
+        if version < VER_FIXED_TAGS {
+            return Err(UeToolError::invalid_format(format!(
+                "Asset registry version {} predates UE 4.25 and is not supported",
+                version
+            )));
+        }
+        if version > VER_PACKAGE_DATA_HAS_LOCATION {
+            return Err(UeToolError::invalid_format(format!(
+                "Asset registry version {} is newer than UE 5.4 and is not supported",
+                version
+            )));
+        }
+        let filter_editor_only = version >= VER_ADDED_HEADER && r.read_bool32()?;
+
+        let names = read_name_batch(&mut r)?;
+        let mut reader = RegistryReader { r, names, version };
+        let store = TagStore::read(&mut reader)?;
+
+        let count = reader.r.read_count()?;
+        let mut assets = Vec::with_capacity(count.min(1 << 16));
+        for _ in 0..count {
+            assets.push(reader.asset_data(&store)?);
+        }
+
+        let section_size = reader.r.read_u64()?;
+        let section_end = reader.r.position().saturating_add(section_size);
+        if section_end > data.len() as u64 {
+            return Err(UeToolError::invalid_format("Dependency section runs past the end of the registry"));
+        }
+        let dependencies = reader
+            .dependencies()
+            .inspect_err(|e| tracing::warn!(error = %e, "Failed to read asset registry dependencies"))
+            .unwrap_or_default();
+        reader.r.set_position(section_end);
+
+        let packages = reader
+            .r
+            .read_count()
+            .and_then(|count| (0..count).map(|_| reader.package_data()).collect())
+            .inspect_err(|e| tracing::warn!(error = %e, "Failed to read asset registry package data"))
+            .unwrap_or_default();
+
+        Ok(Self { version, filter_editor_only, assets, dependencies, packages })
+    }
+
+    /// Parse an `AssetRegistry.bin` on disk
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(|e| UeToolError::io_error(format!("Failed to read {}: {}", path.display(), e)))?;
+        Self::parse(&data)
+    }
+
+    /// The asset with this object path
+    pub fn asset(&self, object_path: &str) -> Option<&AssetData> {
+        self.assets.iter().find(|a| a.object_path.eq_ignore_ascii_case(object_path))
+    }
+
+    /// Assets of a class, by short name or full path
+    pub fn assets_of_class<'a>(&'a self, class: &'a str) -> impl Iterator<Item = &'a AssetData> + 'a {
+        self.assets.iter().filter(move |a| a.is_class(class))
+    }
+
+    /// The dependency node of a package or other identifier
+    pub fn dependencies_of(&self, identifier: &str) -> Option<&DependsNode> {
+        self.dependencies.iter().find(|n| n.identifier.eq_ignore_ascii_case(identifier))
+    }
+
+    /// The package data of a package
+    pub fn package(&self, package_name: &str) -> Option<&AssetPackageData> {
+        self.packages.iter().find(|p| p.package_name.eq_ignore_ascii_case(package_name))
+    }
+}
+
+impl Unpacker {
+    /// Load the `AssetRegistry.bin` stored in a `.pak` or `.utoc` container
+    ///
+    /// When the container holds several registries, such as one per plugin,
+    /// the one with the shortest path (the project's) is read.
+    pub fn load_asset_registry<P: AsRef<Path>>(&mut self, container: P, options: &InspectOptions) -> Result<AssetRegistry> {
+        let container = container.as_ref();
+        let _span = tracing::info_span!("load_asset_registry", container = %container.display()).entered();
+
+        if !container.exists() {
+            return Err(UeToolError::file_not_found(container));
+        }
+        let kind = ContainerKind::from_path(container)
+            .ok_or_else(|| UeToolError::invalid_format(format!("{} is not a .pak or .utoc file", container.display())))?;
+        let mut reader = ContainerReader::open(container, kind, options.aes_key.as_deref())?;
+        let (_, raw) = reader
+            .entries()
+            .into_iter()
+            .map(|(full, raw)| (normalize_path(&full), raw))
+            .filter(|(path, _)| {
+                let name = path.rsplit('/').next().unwrap_or(path);
+                name.eq_ignore_ascii_case(ASSET_REGISTRY_FILE)
+            })
+            .min_by_key(|(path, _)| (path.len(), path.clone()))
+            .ok_or_else(|| UeToolError::file_not_found(ASSET_REGISTRY_FILE))?;
+        AssetRegistry::parse(&reader.read(&raw)?)
+    }
+}
+
+/// Registry archive state: the name batch and the registry version
+struct RegistryReader<'a> {
+    r: Cursor<&'a [u8]>,
+    names: Vec<String>,
+    version: u32,
+}
+
+impl RegistryReader<'_> {
+    fn name_at(&self, index: u32) -> Result<&str> {
+        self.names
+            .get(index as usize)
+            .map(String::as_str)
+            .ok_or_else(|| UeToolError::invalid_format(format!("Name index {} is out of range", index)))
+    }
+
+    /// Read an `FName` as (name batch index, number)
+    fn name(&mut self) -> Result<String> {
+        let index = self.r.read_u32()?;
+        let number = self.r.read_u32()?;
+        Ok(name_with_number(self.name_at(index)?, number))
+    }
+
+    /// Read a name stored without a number
+    fn numberless_name(&mut self) -> Result<String> {
+        let index = self.r.read_u32()?;
+        Ok(self.name_at(index)?.to_string())
+    }
+
+    /// Read a class: an `FName` before UE 5.1, an `FTopLevelAssetPath` after
+    fn class(&mut self, numberless: bool) -> Result<String> {
+        let read = |this: &mut Self| if numberless { this.numberless_name() } else { this.name() };
+        if self.version < VER_CLASS_PATHS {
+            return read(self);
+        }
+        let package = read(self)?;
+        let asset = read(self)?;
+        Ok(top_level_path(&package, &asset))
+    }
+
+    /// Read an `FAssetRegistryExportPath` and format it as `Class'Package.Object'`
+    fn export_path(&mut self, numberless: bool) -> Result<String> {
+        let class = self.class(numberless)?;
+        let (object, package) = if numberless {
+            (self.numberless_name()?, self.numberless_name()?)
+        } else {
+            (self.name()?, self.name()?)
+        };
+        let path = top_level_path(&package, &object);
+        Ok(if is_none(&class) { path } else { format!("{}'{}'", class, path) })
+    }
+
+    fn asset_data(&mut self, store: &TagStore) -> Result<AssetData> {
+        let old_object_path = if self.version < VER_REMOVE_ASSET_PATH_FNAMES { Some(self.name()?) } else { None };
+        let package_path = self.name()?;
+        let class = self.class(false)?;
+        let package_name = self.name()?;
+        let asset_name = self.name()?;
+        let tags = store.map(self.r.read_u64()?)?;
+        self.skip_bundles()?;
+        let chunk_ids = self.r.read_array_of(|r| r.read_i32())?;
+        let package_flags = self.r.read_u32()?;
+        Ok(AssetData {
+            object_path: old_object_path.unwrap_or_else(|| top_level_path(&package_name, &asset_name)),
+            package_name,
+            package_path,
+            asset_name,
+            class,
+            tags,
+            chunk_ids,
+            package_flags,
+        })
+    }
+
+    /// Skip `FAssetBundleData`: bundle names with their soft object paths
+    fn skip_bundles(&mut self) -> Result<()> {
+        for _ in 0..self.r.read_count()? {
+            self.name()?;
+            for _ in 0..self.r.read_count()? {
+                self.name()?;
+                if self.version >= VER_REMOVE_ASSET_PATH_FNAMES {
+                    self.name()?;
+                }
+                self.r.read_fstring()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the dependency nodes, resolving node indices to identifiers
+    fn dependencies(&mut self) -> Result<Vec<DependsNode>> {
+        let count = self.r.read_count()?;
+        let mut raw = Vec::with_capacity(count.min(1 << 16));
+        for _ in 0..count {
+            let identifier = self.asset_identifier()?;
+            let package = self.dependency_indices(PACKAGE_FLAG_SET_WIDTH)?;
+            let name = self.dependency_indices(0)?;
+            let manage = self.dependency_indices(MANAGE_FLAG_SET_WIDTH)?;
+            let referencers = self.dependency_indices(0)?;
+            raw.push((identifier, package, name, manage, referencers));
+        }
+
+        let identifiers: Vec<String> = raw.iter().map(|node| node.0.clone()).collect();
+        let resolve = |indices: Vec<i32>| -> Result<Vec<String>> {
+            indices
+                .into_iter()
+                .map(|i| {
+                    usize::try_from(i)
+                        .ok()
+                        .and_then(|i| identifiers.get(i))
+                        .cloned()
+                        .ok_or_else(|| UeToolError::invalid_format(format!("Dependency node index {} is out of range", i)))
+                })
+                .collect()
+        };
+        raw.into_iter()
+            .map(|(identifier, package, name, manage, referencers)| {
+                Ok(DependsNode {
+                    identifier,
+                    package_dependencies: resolve(package)?,
+                    name_dependencies: resolve(name)?,
+                    manage_dependencies: resolve(manage)?,
+                    referencers: resolve(referencers)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Read an `FAssetIdentifier` and format it the way UE prints it
+    fn asset_identifier(&mut self) -> Result<String> {
+        let fields = self.r.read_u8()?;
+        let field = |this: &mut Self, bit: u8| if fields & bit != 0 { this.name().map(Some) } else { Ok(None) };
+        let package = field(self, 1 << 0)?;
+        let primary_asset_type = field(self, 1 << 1)?;
+        let object = field(self, 1 << 2)?;
+        let value = field(self, 1 << 3)?;
+
+        let mut out = match (primary_asset_type, package) {
+            (Some(kind), _) => format!("{}:{}", kind, object.as_deref().unwrap_or_default()),
+            (None, Some(package)) => match &object {
+                Some(object) => format!("{}.{}", package, object),
+                None => package,
+            },
+            (None, None) => object.unwrap_or_default(),
+        };
+        if let Some(value) = value {
+            out.push_str("::");
+            out.push_str(&value);
+        }
+        Ok(out)
+    }
+
+    /// Read one dependency list: node indices followed by their flag bits
+    fn dependency_indices(&mut self, flag_set_width: usize) -> Result<Vec<i32>> {
+        let indices = self.r.read_array_of(|r| r.read_i32())?;
+        let flag_words = (indices.len() * flag_set_width).div_ceil(32);
+        self.r.read_bytes(flag_words * 4)?;
+        Ok(indices)
+    }
+
+    fn package_data(&mut self) -> Result<AssetPackageData> {
+        let package_name = self.name()?;
+        let disk_size = self.r.read_u64()? as i64;
+        self.r.read_guid()?; // PackageGuid, unused in cooked games
+        let cooked_hash = if self.version >= VER_ADDED_COOKED_MD5_HASH && self.r.read_bool32()? {
+            Some(self.r.read_fixed::<16>()?.iter().map(|b| format!("{:02x}", b)).collect())
+        } else {
+            None
+        };
+        if self.version >= VER_ADDED_CHUNK_HASHES {
+            let count = self.r.read_count()?;
+            self.r.read_bytes(count.saturating_mul(12 + 20))?; // FIoChunkId -> FIoHash
+        }
+
+        let mut data = AssetPackageData {
+            package_name,
+            disk_size,
+            cooked_hash,
+            file_version_ue4: None,
+            file_version_ue5: None,
+            licensee_version: None,
+            flags: 0,
+            imported_classes: Vec::new(),
+            extension: None,
+        };
+        if self.version >= VER_WORKSPACE_DOMAIN {
+            data.file_version_ue4 = Some(self.r.read_i32()?);
+            if self.version >= VER_PACKAGE_FILE_SUMMARY_VERSION_CHANGE {
+                data.file_version_ue5 = Some(self.r.read_i32()?);
+            }
+            data.licensee_version = Some(self.r.read_i32()?);
+            data.flags = self.r.read_u32()?;
+            self.r.read_array_of(|r| {
+                r.read_guid()?;
+                r.read_i32()
+            })?; // CustomVersions
+        }
+        if self.version >= VER_PACKAGE_IMPORTED_CLASSES {
+            for _ in 0..self.r.read_count()? {
+                let class = self.name()?;
+                data.imported_classes.push(class);
+            }
+        }
+        if self.version >= VER_PACKAGE_DATA_HAS_EXTENSION {
+            data.extension = Some(self.r.read_fstring()?);
+        }
+        if self.version >= VER_PACKAGE_DATA_HAS_LOCATION {
+            self.r.read_u8()?; // PackageLocation
+        }
+        Ok(data)
+    }
+}
+
+/// The tag values shared by every asset (`FixedTagPrivate::FStore`)
+struct TagStore {
+    numberless_names: Vec<String>,
+    names: Vec<String>,
+    numberless_export_paths: Vec<String>,
+    export_paths: Vec<String>,
+    texts: Vec<String>,
+    ansi_offsets: Vec<u32>,
+    wide_offsets: Vec<u32>,
+    ansi: Vec<u8>,
+    wide: Vec<u16>,
+    numberless_pairs: Vec<(String, u32)>,
+    pairs: Vec<(String, u32)>,
+}
+
+impl TagStore {
+    fn read(reader: &mut RegistryReader<'_>) -> Result<Self> {
+        let texts_first = match reader.r.read_u32()? {
+            STORE_BEGIN_MAGIC => true,
+            STORE_OLD_BEGIN_MAGIC => false,
+            magic => return Err(UeToolError::invalid_format(format!("Bad tag store magic {:#010x}", magic))),
+        };
+        let mut counts = [0usize; 11];
+        for count in &mut counts {
+            *count = reader.r.read_u32()? as usize;
+        }
+        let [numberless_names, names, numberless_export_paths, export_paths, texts, ansi_offsets, wide_offsets, ansi_bytes, wide_chars, numberless_pairs, pairs] =
+            counts;
+
+        let read_texts = |reader: &mut RegistryReader<'_>| -> Result<Vec<String>> {
+            (0..texts).map(|_| reader.r.read_fstring()).collect()
+        };
+        let mut store_texts = Vec::new();
+        if texts_first {
+            reader.r.read_u32()?; // TextDataBytes
+            store_texts = read_texts(reader)?;
+        }
+        let mut store = Self {
+            numberless_names: (0..numberless_names).map(|_| reader.numberless_name()).collect::<Result<_>>()?,
+            names: (0..names).map(|_| reader.name()).collect::<Result<_>>()?,
+            numberless_export_paths: (0..numberless_export_paths)
+                .map(|_| reader.export_path(true))
+                .collect::<Result<_>>()?,
+            export_paths: (0..export_paths).map(|_| reader.export_path(false)).collect::<Result<_>>()?,
+            texts: Vec::new(),
+            ansi_offsets: Vec::new(),
+            wide_offsets: Vec::new(),
+            ansi: Vec::new(),
+            wide: Vec::new(),
+            numberless_pairs: Vec::new(),
+            pairs: Vec::new(),
+        };
+        store.texts = if texts_first { store_texts } else { read_texts(reader)? };
+        store.ansi_offsets = (0..ansi_offsets).map(|_| reader.r.read_u32()).collect::<Result<_>>()?;
+        store.wide_offsets = (0..wide_offsets).map(|_| reader.r.read_u32()).collect::<Result<_>>()?;
+        store.ansi = reader.r.read_bytes(ansi_bytes)?;
+        store.wide = reader
+            .r
+            .read_bytes(wide_chars.saturating_mul(2))?
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        for _ in 0..numberless_pairs {
+            let key = reader.numberless_name()?;
+            store.numberless_pairs.push((key, reader.r.read_u32()?));
+        }
+        for _ in 0..pairs {
+            let key = reader.name()?;
+            store.pairs.push((key, reader.r.read_u32()?));
+        }
+
+        let end = reader.r.read_u32()?;
+        if end != STORE_END_MAGIC {
+            return Err(UeToolError::invalid_format(format!("Bad tag store end magic {:#010x}", end)));
+        }
+        Ok(store)
+    }
+
+    /// Resolve an asset's tag map from its packed `FPartialMapHandle`
+    fn map(&self, handle: u64) -> Result<BTreeMap<String, String>> {
+        let numberless = handle >> 63 != 0;
+        let len = ((handle >> 32) & 0xFFFF) as usize;
+        let begin = handle as u32 as usize;
+        let pairs = if numberless { &self.numberless_pairs } else { &self.pairs };
+        let pairs = pairs
+            .get(begin..begin + len)
+            .ok_or_else(|| UeToolError::invalid_format(format!("Tag map {}..{} is out of range", begin, begin + len)))?;
+        pairs.iter().map(|(key, value)| Ok((key.clone(), self.value(*value)?))).collect()
+    }
+
+    /// Resolve an `FValueId`: the value type in the low 3 bits, its index above
+    fn value(&self, id: u32) -> Result<String> {
+        let index = (id >> 3) as usize;
+        let out_of_range = || UeToolError::invalid_format(format!("Tag value {:#x} is out of range", id));
+        let value = match id & 0x7 {
+            0 => {
+                let start = *self.ansi_offsets.get(index).ok_or_else(out_of_range)? as usize;
+                let bytes = self.ansi.get(start..).ok_or_else(out_of_range)?;
+                bytes.iter().take_while(|&&b| b != 0).map(|&b| b as char).collect()
+            }
+            1 => {
+                let start = *self.wide_offsets.get(index).ok_or_else(out_of_range)? as usize;
+                let chars = self.wide.get(start..).ok_or_else(out_of_range)?;
+                let chars: Vec<u16> = chars.iter().copied().take_while(|&c| c != 0).collect();
+                String::from_utf16_lossy(&chars)
+            }
+            2 => self.numberless_names.get(index).ok_or_else(out_of_range)?.clone(),
+            3 => self.names.get(index).ok_or_else(out_of_range)?.clone(),
+            4 => self.numberless_export_paths.get(index).ok_or_else(out_of_range)?.clone(),
+            5 => self.export_paths.get(index).ok_or_else(out_of_range)?.clone(),
+            6 => self.texts.get(index).ok_or_else(out_of_range)?.clone(),
+            kind => return Err(UeToolError::invalid_format(format!("Unknown tag value type {}", kind))),
+        };
+        Ok(value)
+    }
+}
+
+fn is_none(name: &str) -> bool {
+    name.is_empty() || name == "None"
+}
+
+/// Format an `FTopLevelAssetPath` as `Package.Asset`
+fn top_level_path(package: &str, asset: &str) -> String {
+    match (is_none(package), is_none(asset)) {
+        (true, _) => String::new(),
+        (false, true) => package.to_string(),
+        (false, false) => format!("{}.{}", package, asset),
+    }
+}
+
+/// Short name of a class given by name or path: `/Script/Engine.Texture2D` -> `Texture2D`
+fn short_class(class: &str) -> &str {
+    class.rsplit('.').next().unwrap_or(class)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::{write_fstring, write_name_batch};
+
+    fn u32s(out: &mut Vec<u8>, values: &[u32]) {
+        for v in values {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+
+    /// A UE 5.3 registry with one texture asset, its package node and package data
+    fn registry() -> Vec<u8> {
+        const NAMES: [&str; 9] =
+            ["None", "/Game/UI", "/Script/Engine", "Texture2D", "/Game/UI/Icon", "Icon", "Format", "PF_DXT5", "Width"];
+        let mut data = Vec::new();
+        u32s(&mut data, &VERSION_GUID);
+        u32s(&mut data, &[VER_PACKAGE_DATA_HAS_EXTENSION, 1]);
+        write_name_batch(&mut data, &NAMES);
+
+        // Tag store: one numberless name, one ANSI string, two numberless pairs
+        u32s(&mut data, &[STORE_BEGIN_MAGIC, 1, 0, 0, 0, 0, 1, 0, 4, 0, 2, 0]);
+        u32s(&mut data, &[0]); // TextDataBytes
+        u32s(&mut data, &[7]); // "PF_DXT5"
+        u32s(&mut data, &[0]); // ANSI offset
+        data.extend_from_slice(b"256\0");
+        u32s(&mut data, &[6, 2, 8, 0]); // Format = name 0, Width = ANSI 0
+        u32s(&mut data, &[STORE_END_MAGIC]);
+
+        // One asset
+        u32s(&mut data, &[1]);
+        u32s(&mut data, &[1, 0, 2, 0, 3, 0, 4, 0, 5, 0]);
+        data.extend_from_slice(&((1u64 << 63) | (2u64 << 32)).to_le_bytes());
+        u32s(&mut data, &[0, 0, 0x8000_0000]); // no bundles, no chunks, flags
+
+        // Dependencies: the texture package, referenced by nothing
+        let mut section = Vec::new();
+        u32s(&mut section, &[1]);
+        section.push(1);
+        u32s(&mut section, &[4, 0, 0, 0, 0, 0]);
+        data.extend_from_slice(&(section.len() as u64).to_le_bytes());
+        data.extend_from_slice(&section);
+
+        // Package data
+        u32s(&mut data, &[1, 4, 0]);
+        data.extend_from_slice(&2048u64.to_le_bytes());
+        data.resize(data.len() + 16, 0);
+        u32s(&mut data, &[0, 0, 522, 1009, 0, 0, 0, 1, 3, 0]);
+        write_fstring(&mut data, ".uasset");
+        data
+    }
+
+    #[test]
+    fn test_parse_asset_registry() {
+        let registry = AssetRegistry::parse(&registry()).unwrap();
+        assert_eq!(registry.version, VER_PACKAGE_DATA_HAS_EXTENSION);
+        assert!(registry.filter_editor_only);
+
+        let asset = registry.asset("/Game/UI/Icon.Icon").unwrap();
+        assert_eq!(asset.class, "/Script/Engine.Texture2D");
+        assert!(asset.is_class("texture2d"));
+        assert_eq!(asset.package_path, "/Game/UI");
+        assert_eq!(asset.tags["Format"], "PF_DXT5");
+        assert_eq!(asset.tags["Width"], "256");
+        assert_eq!(registry.assets_of_class("Texture2D").count(), 1);
+
+        let node = registry.dependencies_of("/Game/UI/Icon").unwrap();
+        assert!(node.package_dependencies.is_empty());
+
+        let package = registry.package("/Game/UI/Icon").unwrap();
+        assert_eq!(package.disk_size, 2048);
+        assert_eq!(package.file_version_ue5, Some(1009));
+        assert_eq!(package.imported_classes, vec!["Texture2D"]);
+        assert_eq!(package.extension.as_deref(), Some(".uasset"));
+    }
+
+    #[test]
+    fn test_rejects_unknown_registry() {
+        assert!(AssetRegistry::parse(&[0u8; 32]).is_err());
+
+        let mut data = Vec::new();
+        u32s(&mut data, &VERSION_GUID);
+        u32s(&mut data, &[VER_FIXED_TAGS - 1]);
+        assert!(AssetRegistry::parse(&data).is_err());
+    }
+}
@@ -0,0 +1,561 @@
+//! CPU decoders for block compressed (BCn) texture data
+//!
+//! Each decoder turns one 4x4 block into 16 pixels in row-major order.
+//! BC1-BC5 and BC7 decode to RGBA8; BC6H decodes to RGB half floats, since
+//! it stores HDR colors.
+
+/// Decode a BC1 (DXT1) block
+pub(crate) fn bc1(block: &[u8]) -> [[u8; 4]; 16] {
+    color_block(block, true)
+}
+
+/// Decode a BC2 (DXT3) block: explicit 4-bit alpha followed by a BC1 color block
+pub(crate) fn bc2(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut pixels = color_block(&block[8..], false);
+    let alpha = u64::from_le_bytes(block[..8].try_into().unwrap());
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        pixel[3] = ((alpha >> (4 * i)) & 0xF) as u8 * 17;
+    }
+    pixels
+}
+
+/// Decode a BC3 (DXT5) block: interpolated alpha followed by a BC1 color block
+pub(crate) fn bc3(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut pixels = color_block(&block[8..], false);
+    for (pixel, alpha) in pixels.iter_mut().zip(channel_block(&block[..8])) {
+        pixel[3] = alpha;
+    }
+    pixels
+}
+
+/// Decode a BC4 block as grayscale
+pub(crate) fn bc4(block: &[u8]) -> [[u8; 4]; 16] {
+    channel_block(block).map(|v| [v, v, v, 255])
+}
+
+/// Decode a BC5 block, rebuilding blue as the Z of a unit normal
+///
+/// UE stores normal maps as BC5 with only X and Y.
+pub(crate) fn bc5(block: &[u8]) -> [[u8; 4]; 16] {
+    let (red, green) = (channel_block(&block[..8]), channel_block(&block[8..]));
+    let mut pixels = [[0u8; 4]; 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let x = red[i] as f32 / 127.5 - 1.0;
+        let y = green[i] as f32 / 127.5 - 1.0;
+        let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+        *pixel = [red[i], green[i], ((z + 1.0) * 127.5).round() as u8, 255];
+    }
+    pixels
+}
+
+/// The RGB565 color block of BC1-BC3
+///
+/// Only BC1 has the three-color mode with transparent black, selected when
+/// the first endpoint is not greater than the second.
+fn color_block(block: &[u8], allow_alpha: bool) -> [[u8; 4]; 16] {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let indices = u32::from_le_bytes(block[4..8].try_into().unwrap());
+    let (e0, e1) = (rgb565(c0), rgb565(c1));
+    let mix = |a: u8, b: u8, wa: u32, wb: u32| ((a as u32 * wa + b as u32 * wb) / (wa + wb)) as u8;
+
+    let mut palette = [[0u8; 4]; 4];
+    palette[0] = [e0[0], e0[1], e0[2], 255];
+    palette[1] = [e1[0], e1[1], e1[2], 255];
+    if c0 > c1 || !allow_alpha {
+        palette[2] = [mix(e0[0], e1[0], 2, 1), mix(e0[1], e1[1], 2, 1), mix(e0[2], e1[2], 2, 1), 255];
+        palette[3] = [mix(e0[0], e1[0], 1, 2), mix(e0[1], e1[1], 1, 2), mix(e0[2], e1[2], 1, 2), 255];
+    } else {
+        palette[2] = [mix(e0[0], e1[0], 1, 1), mix(e0[1], e1[1], 1, 1), mix(e0[2], e1[2], 1, 1), 255];
+        palette[3] = [0, 0, 0, 0];
+    }
+
+    let mut pixels = [[0u8; 4]; 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        *pixel = palette[((indices >> (2 * i)) & 3) as usize];
+    }
+    pixels
+}
+
+fn rgb565(color: u16) -> [u8; 3] {
+    let (r, g, b) = ((color >> 11) as u8, ((color >> 5) & 0x3F) as u8, (color & 0x1F) as u8);
+    [(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2)]
+}
+
+/// The 8-byte interpolated single channel block of BC3-BC5
+fn channel_block(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (block[0] as u32, block[1] as u32);
+    let mut palette = [a0 as u8, a1 as u8, 0, 0, 0, 0, 0, 255];
+    if a0 > a1 {
+        for k in 1..7 {
+            palette[k + 1] = (((7 - k as u32) * a0 + k as u32 * a1) / 7) as u8;
+        }
+    } else {
+        for k in 1..5 {
+            palette[k + 1] = (((5 - k as u32) * a0 + k as u32 * a1) / 5) as u8;
+        }
+    }
+
+    let mut bits = [0u8; 8];
+    bits[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(bits);
+    let mut values = [0u8; 16];
+    for (i, value) in values.iter_mut().enumerate() {
+        *value = palette[((indices >> (3 * i)) & 7) as usize];
+    }
+    values
+}
+
+/// Little-endian bit reader over one 16-byte block
+struct Bits {
+    value: u128,
+    position: u32,
+}
+
+impl Bits {
+    fn new(block: &[u8]) -> Self {
+        Bits { value: u128::from_le_bytes(block[..16].try_into().unwrap()), position: 0 }
+    }
+
+    fn read(&mut self, count: u32) -> u32 {
+        if count == 0 {
+            return 0;
+        }
+        let value = (self.value >> self.position) as u32 & (u32::MAX >> (32 - count));
+        self.position += count;
+        value
+    }
+}
+
+/// Subset of each pixel in the 64 two-subset partitions, one bit per pixel
+const PARTITIONS_2: [u16; 64] = [
+    0xCCCC, 0x8888, 0xEEEE, 0xECC8, 0xC880, 0xFEEC, 0xFEC8, 0xEC80,
+    0xC800, 0xFFEC, 0xFE80, 0xE800, 0xFFE8, 0xFF00, 0xFFF0, 0xF000,
+    0xF710, 0x008E, 0x7100, 0x08CE, 0x008C, 0x7310, 0x3100, 0x8CCE,
+    0x088C, 0x3110, 0x6666, 0x366C, 0x17E8, 0x0FF0, 0x718E, 0x399C,
+    0xAAAA, 0xF0F0, 0x5A5A, 0x33CC, 0x3C3C, 0x55AA, 0x9696, 0xA55A,
+    0x73CE, 0x13C8, 0x324C, 0x3BDC, 0x6996, 0xC33C, 0x9966, 0x0660,
+    0x0272, 0x04E4, 0x4E40, 0x2720, 0xC936, 0x936C, 0x39C6, 0x639C,
+    0x9336, 0x9CC6, 0x817E, 0xE718, 0xCCF0, 0x0FCC, 0x7744, 0xEE22,
+];
+
+/// Subset of each pixel in the 64 three-subset partitions, two bits per pixel
+const PARTITIONS_3: [u32; 64] = [
+    0xAA685050, 0x6A5A5040, 0x5A5A4200, 0x5450A0A8, 0xA5A50000, 0xA0A05050, 0x5555A0A0, 0x5A5A5050,
+    0xAA550000, 0xAA555500, 0xAAAA5500, 0x90909090, 0x94949494, 0xA4A4A4A4, 0xA9A59450, 0x2A0A4250,
+    0xA5945040, 0x0A425054, 0xA5A5A500, 0x55A0A0A0, 0xA8A85454, 0x6A6A4040, 0xA4A45000, 0x1A1A0500,
+    0x0050A4A4, 0xAAA59090, 0x14696914, 0x69691400, 0xA08585A0, 0xAA821414, 0x50A4A450, 0x6A5A0200,
+    0xA9A58000, 0x5090A0A8, 0xA8A09050, 0x24242424, 0x00AA5500, 0x24924924, 0x24499224, 0x50A50A50,
+    0x500AA550, 0xAAAA4444, 0x66660000, 0xA5A0A5A0, 0x50A050A0, 0x69286928, 0x44AAAA44, 0x66666600,
+    0xAA444444, 0x54A854A8, 0x95809580, 0x96969600, 0xA85454A8, 0x80959580, 0xAA141414, 0x96960000,
+    0xAAAA1414, 0xA05050A0, 0xA0A5A5A0, 0x96000000, 0x40804080, 0xA9A8A9A8, 0xAAAAAA44, 0x2A4A5254,
+];
+
+/// Anchor pixel of the second subset of two-subset partitions
+const ANCHORS_2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,
+    15, 2, 8, 2, 2, 8, 8, 15, 2, 8, 2, 2, 8, 8, 2, 2,
+    15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6,
+    6, 2, 6, 8, 15, 15, 2, 2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+/// Anchor pixel of the second subset of three-subset partitions
+const ANCHORS_3_SECOND: [u8; 64] = [
+    3, 3, 15, 15, 8, 3, 15, 15, 8, 8, 6, 6, 6, 5, 3, 3,
+    3, 3, 8, 15, 3, 3, 6, 10, 5, 8, 8, 6, 8, 5, 15, 15,
+    8, 15, 3, 5, 6, 10, 8, 15, 15, 3, 15, 5, 15, 15, 15, 15,
+    3, 15, 5, 5, 5, 8, 5, 10, 5, 10, 8, 13, 15, 12, 3, 3,
+];
+
+/// Anchor pixel of the third subset of three-subset partitions
+const ANCHORS_3_THIRD: [u8; 64] = [
+    15, 8, 8, 3, 15, 15, 3, 8, 15, 15, 15, 15, 15, 15, 15, 8,
+    15, 8, 15, 3, 15, 8, 15, 8, 3, 15, 6, 10, 15, 15, 10, 8,
+    15, 3, 15, 10, 10, 8, 9, 10, 6, 15, 8, 15, 3, 6, 6, 8,
+    15, 3, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 3, 15, 15, 8,
+];
+
+const WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+fn weights(index_bits: u32) -> &'static [u32] {
+    match index_bits {
+        2 => &WEIGHTS_2,
+        3 => &WEIGHTS_3,
+        _ => &WEIGHTS_4,
+    }
+}
+
+/// Subset of pixel `i`, and whether it is the anchor of its subset
+fn subset(subsets: u32, partition: usize, i: usize) -> (usize, bool) {
+    match subsets {
+        2 => {
+            let s = ((PARTITIONS_2[partition] >> i) & 1) as usize;
+            (s, i == 0 || (s == 1 && i == ANCHORS_2[partition] as usize))
+        }
+        3 => {
+            let s = ((PARTITIONS_3[partition] >> (2 * i)) & 3) as usize;
+            let anchor = match s {
+                0 => 0,
+                1 => ANCHORS_3_SECOND[partition],
+                _ => ANCHORS_3_THIRD[partition],
+            };
+            (s, i == anchor as usize)
+        }
+        _ => (0, i == 0),
+    }
+}
+
+/// Layout of a BC7 mode
+struct Bc7Mode {
+    subsets: u32,
+    partition_bits: u32,
+    rotation_bits: u32,
+    selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    /// A p-bit per endpoint, or one shared by both endpoints of a subset
+    endpoint_pbits: bool,
+    shared_pbits: bool,
+    index_bits: u32,
+    /// Bits of the second index set of modes 4 and 5
+    index_bits_2: u32,
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    // Mode 0
+    Bc7Mode {
+        subsets: 3, partition_bits: 4, rotation_bits: 0, selection_bits: 0, color_bits: 4, alpha_bits: 0,
+        endpoint_pbits: true, shared_pbits: false, index_bits: 3, index_bits_2: 0,
+    },
+    // Mode 1
+    Bc7Mode {
+        subsets: 2, partition_bits: 6, rotation_bits: 0, selection_bits: 0, color_bits: 6, alpha_bits: 0,
+        endpoint_pbits: false, shared_pbits: true, index_bits: 3, index_bits_2: 0,
+    },
+    // Mode 2
+    Bc7Mode {
+        subsets: 3, partition_bits: 6, rotation_bits: 0, selection_bits: 0, color_bits: 5, alpha_bits: 0,
+        endpoint_pbits: false, shared_pbits: false, index_bits: 2, index_bits_2: 0,
+    },
+    // Mode 3
+    Bc7Mode {
+        subsets: 2, partition_bits: 6, rotation_bits: 0, selection_bits: 0, color_bits: 7, alpha_bits: 0,
+        endpoint_pbits: true, shared_pbits: false, index_bits: 2, index_bits_2: 0,
+    },
+    // Mode 4
+    Bc7Mode {
+        subsets: 1, partition_bits: 0, rotation_bits: 2, selection_bits: 1, color_bits: 5, alpha_bits: 6,
+        endpoint_pbits: false, shared_pbits: false, index_bits: 2, index_bits_2: 3,
+    },
+    // Mode 5
+    Bc7Mode {
+        subsets: 1, partition_bits: 0, rotation_bits: 2, selection_bits: 0, color_bits: 7, alpha_bits: 8,
+        endpoint_pbits: false, shared_pbits: false, index_bits: 2, index_bits_2: 2,
+    },
+    // Mode 6
+    Bc7Mode {
+        subsets: 1, partition_bits: 0, rotation_bits: 0, selection_bits: 0, color_bits: 7, alpha_bits: 7,
+        endpoint_pbits: true, shared_pbits: false, index_bits: 4, index_bits_2: 0,
+    },
+    // Mode 7
+    Bc7Mode {
+        subsets: 2, partition_bits: 6, rotation_bits: 0, selection_bits: 0, color_bits: 5, alpha_bits: 5,
+        endpoint_pbits: true, shared_pbits: false, index_bits: 2, index_bits_2: 0,
+    },
+];
+
+/// Decode a BC7 block; reserved modes decode to transparent black
+pub(crate) fn bc7(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut bits = Bits::new(block);
+    let Some(mode) = (0..8).find(|&m| block[0] & (1 << m) != 0) else {
+        return [[0; 4]; 16];
+    };
+    bits.read(mode as u32 + 1);
+    let Bc7Mode { subsets, partition_bits, rotation_bits, selection_bits, color_bits, alpha_bits, endpoint_pbits, shared_pbits, index_bits, index_bits_2 } =
+        BC7_MODES[mode];
+
+    let partition = bits.read(partition_bits) as usize;
+    let rotation = bits.read(rotation_bits);
+    let selection = bits.read(selection_bits);
+
+    let endpoints = subsets as usize * 2;
+    let mut colors = [[0u32; 4]; 6];
+    for channel in 0..3 {
+        for endpoint in colors.iter_mut().take(endpoints) {
+            endpoint[channel] = bits.read(color_bits);
+        }
+    }
+    for endpoint in colors.iter_mut().take(endpoints) {
+        endpoint[3] = if alpha_bits > 0 { bits.read(alpha_bits) } else { 255 };
+    }
+
+    let (mut color_bits, mut alpha_bits) = (color_bits, alpha_bits);
+    if endpoint_pbits || shared_pbits {
+        let pbits: Vec<u32> = if endpoint_pbits {
+            (0..endpoints).map(|_| bits.read(1)).collect()
+        } else {
+            (0..subsets).flat_map(|_| {
+                let p = bits.read(1);
+                [p, p]
+            }).collect()
+        };
+        for (endpoint, p) in colors.iter_mut().zip(&pbits) {
+            for channel in &mut endpoint[..3] {
+                *channel = (*channel << 1) | p;
+            }
+            if alpha_bits > 0 {
+                endpoint[3] = (endpoint[3] << 1) | p;
+            }
+        }
+        color_bits += 1;
+        if alpha_bits > 0 {
+            alpha_bits += 1;
+        }
+    }
+    let expand = |value: u32, bits: u32| {
+        let value = value << (8 - bits);
+        value | (value >> bits)
+    };
+    for endpoint in colors.iter_mut().take(endpoints) {
+        for channel in &mut endpoint[..3] {
+            *channel = expand(*channel, color_bits);
+        }
+        if alpha_bits > 0 {
+            endpoint[3] = expand(endpoint[3], alpha_bits);
+        }
+    }
+
+    let mut primary = [0u32; 16];
+    for (i, index) in primary.iter_mut().enumerate() {
+        let anchor = subset(subsets, partition, i).1;
+        *index = bits.read(index_bits - anchor as u32);
+    }
+    let mut secondary = [0u32; 16];
+    if index_bits_2 > 0 {
+        for (i, index) in secondary.iter_mut().enumerate() {
+            *index = bits.read(index_bits_2 - (i == 0) as u32);
+        }
+    }
+
+    let interpolate = |e0: u32, e1: u32, weight: u32| ((64 - weight) * e0 + weight * e1 + 32) >> 6;
+    let mut pixels = [[0u8; 4]; 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let s = subset(subsets, partition, i).0;
+        let (e0, e1) = (colors[2 * s], colors[2 * s + 1]);
+        let (color_weight, alpha_weight) = if index_bits_2 == 0 {
+            let w = weights(index_bits)[primary[i] as usize];
+            (w, w)
+        } else if selection == 0 {
+            (weights(index_bits)[primary[i] as usize], weights(index_bits_2)[secondary[i] as usize])
+        } else {
+            (weights(index_bits_2)[secondary[i] as usize], weights(index_bits)[primary[i] as usize])
+        };
+        for channel in 0..3 {
+            pixel[channel] = interpolate(e0[channel], e1[channel], color_weight) as u8;
+        }
+        pixel[3] = interpolate(e0[3], e1[3], alpha_weight) as u8;
+        match rotation {
+            1 => pixel.swap(0, 3),
+            2 => pixel.swap(1, 3),
+            3 => pixel.swap(2, 3),
+            _ => {}
+        }
+    }
+    pixels
+}
+
+// BC6H endpoint fields: endpoint (w, x, y, z) * 3 + channel, and the partition
+const RW: u8 = 0;
+const GW: u8 = 1;
+const BW: u8 = 2;
+const RX: u8 = 3;
+const GX: u8 = 4;
+const BX: u8 = 5;
+const RY: u8 = 6;
+const GY: u8 = 7;
+const BY: u8 = 8;
+const RZ: u8 = 9;
+const GZ: u8 = 10;
+const BZ: u8 = 11;
+const D: u8 = 12;
+
+/// A BC6H mode: mode bits, header fields as (field, bit count, shift),
+/// transformed endpoints, endpoint bits, delta bits per channel
+type Bc6hMode = (u32, &'static [(u8, u8, u8)], bool, u32, [u32; 3]);
+
+const BC6H_MODES: [Bc6hMode; 14] = [
+    (0x00, &[(GY, 1, 4), (BY, 1, 4), (BZ, 1, 4), (RW, 10, 0), (GW, 10, 0), (BW, 10, 0), (RX, 5, 0), (GZ, 1, 4), (GY, 4, 0), (GX, 5, 0), (BZ, 1, 0), (GZ, 4, 0), (BX, 5, 0), (BZ, 1, 1), (BY, 4, 0), (RY, 5, 0), (BZ, 1, 2), (RZ, 5, 0), (BZ, 1, 3), (D, 5, 0)], true, 10, [5, 5, 5]),
+    (0x01, &[(GY, 1, 5), (GZ, 1, 4), (GZ, 1, 5), (RW, 7, 0), (BZ, 1, 0), (BZ, 1, 1), (BY, 1, 4), (GW, 7, 0), (BY, 1, 5), (BZ, 1, 2), (GY, 1, 4), (BW, 7, 0), (BZ, 1, 3), (BZ, 1, 5), (BZ, 1, 4), (RX, 6, 0), (GY, 4, 0), (GX, 6, 0), (GZ, 4, 0), (BX, 6, 0), (BY, 4, 0), (RY, 6, 0), (RZ, 6, 0), (D, 5, 0)], true, 7, [6, 6, 6]),
+    (0x02, &[(RW, 10, 0), (GW, 10, 0), (BW, 10, 0), (RX, 5, 0), (RW, 1, 10), (GY, 4, 0), (GX, 4, 0), (GW, 1, 10), (BZ, 1, 0), (GZ, 4, 0), (BX, 4, 0), (BW, 1, 10), (BZ, 1, 1), (BY, 4, 0), (RY, 5, 0), (BZ, 1, 2), (RZ, 5, 0), (BZ, 1, 3), (D, 5, 0)], true, 11, [5, 4, 4]),
+    (0x06, &[(RW, 10, 0), (GW, 10, 0), (BW, 10, 0), (RX, 4, 0), (RW, 1, 10), (GZ, 1, 4), (GY, 4, 0), (GX, 5, 0), (GW, 1, 10), (GZ, 4, 0), (BX, 4, 0), (BW, 1, 10), (BZ, 1, 1), (BY, 4, 0), (RY, 4, 0), (BZ, 1, 0), (BZ, 1, 2), (RZ, 4, 0), (GY, 1, 4), (BZ, 1, 3), (D, 5, 0)], true, 11, [4, 5, 4]),
+    (0x0A, &[(RW, 10, 0), (GW, 10, 0), (BW, 10, 0), (RX, 4, 0), (RW, 1, 10), (BY, 1, 4), (GY, 4, 0), (GX, 4, 0), (GW, 1, 10), (BZ, 1, 0), (GZ, 4, 0), (BX, 5, 0), (BW, 1, 10), (BY, 4, 0), (RY, 4, 0), (BZ, 1, 1), (BZ, 1, 2), (RZ, 4, 0), (BZ, 1, 4), (BZ, 1, 3), (D, 5, 0)], true, 11, [4, 4, 5]),
+    (0x0E, &[(RW, 9, 0), (BY, 1, 4), (GW, 9, 0), (GY, 1, 4), (BW, 9, 0), (BZ, 1, 4), (RX, 5, 0), (GZ, 1, 4), (GY, 4, 0), (GX, 5, 0), (BZ, 1, 0), (GZ, 4, 0), (BX, 5, 0), (BZ, 1, 1), (BY, 4, 0), (RY, 5, 0), (BZ, 1, 2), (RZ, 5, 0), (BZ, 1, 3), (D, 5, 0)], true, 9, [5, 5, 5]),
+    (0x12, &[(RW, 8, 0), (GZ, 1, 4), (BY, 1, 4), (GW, 8, 0), (BZ, 1, 2), (GY, 1, 4), (BW, 8, 0), (BZ, 1, 3), (BZ, 1, 4), (RX, 6, 0), (GY, 4, 0), (GX, 5, 0), (BZ, 1, 0), (GZ, 4, 0), (BX, 5, 0), (BZ, 1, 1), (BY, 4, 0), (RY, 6, 0), (RZ, 6, 0), (D, 5, 0)], true, 8, [6, 5, 5]),
+    (0x16, &[(RW, 8, 0), (BZ, 1, 0), (BY, 1, 4), (GW, 8, 0), (GY, 1, 5), (GY, 1, 4), (BW, 8, 0), (GZ, 1, 5), (BZ, 1, 4), (RX, 5, 0), (GZ, 1, 4), (GY, 4, 0), (GX, 6, 0), (GZ, 4, 0), (BX, 5, 0), (BZ, 1, 1), (BY, 4, 0), (RY, 5, 0), (BZ, 1, 2), (RZ, 5, 0), (BZ, 1, 3), (D, 5, 0)], true, 8, [5, 6, 5]),
+    (0x1A, &[(RW, 8, 0), (BZ, 1, 1), (BY, 1, 4), (GW, 8, 0), (BY, 1, 5), (GY, 1, 4), (BW, 8, 0), (BZ, 1, 5), (BZ, 1, 4), (RX, 5, 0), (GZ, 1, 4), (GY, 4, 0), (GX, 5, 0), (BZ, 1, 0), (GZ, 4, 0), (BX, 6, 0), (BY, 4, 0), (RY, 5, 0), (BZ, 1, 2), (RZ, 5, 0), (BZ, 1, 3), (D, 5, 0)], true, 8, [5, 5, 6]),
+    (0x1E, &[(RW, 6, 0), (GZ, 1, 4), (BZ, 1, 0), (BZ, 1, 1), (BY, 1, 4), (GW, 6, 0), (GY, 1, 5), (BY, 1, 5), (BZ, 1, 2), (GY, 1, 4), (BW, 6, 0), (GZ, 1, 5), (BZ, 1, 3), (BZ, 1, 5), (BZ, 1, 4), (RX, 6, 0), (GY, 4, 0), (GX, 6, 0), (GZ, 4, 0), (BX, 6, 0), (BY, 4, 0), (RY, 6, 0), (RZ, 6, 0), (D, 5, 0)], false, 6, [6, 6, 6]),
+    (0x03, &[(RW, 10, 0), (GW, 10, 0), (BW, 10, 0), (RX, 10, 0), (GX, 10, 0), (BX, 10, 0)], false, 10, [10, 10, 10]),
+    (0x07, &[(RW, 10, 0), (GW, 10, 0), (BW, 10, 0), (RX, 9, 0), (RW, 1, 10), (GX, 9, 0), (GW, 1, 10), (BX, 9, 0), (BW, 1, 10)], true, 11, [9, 9, 9]),
+    (0x0B, &[(RW, 10, 0), (GW, 10, 0), (BW, 10, 0), (RX, 8, 0), (RW, 1, 11), (RW, 1, 10), (GX, 8, 0), (GW, 1, 11), (GW, 1, 10), (BX, 8, 0), (BW, 1, 11), (BW, 1, 10)], true, 12, [8, 8, 8]),
+    (0x0F, &[(RW, 10, 0), (GW, 10, 0), (BW, 10, 0), (RX, 4, 0), (RW, 1, 15), (RW, 1, 14), (RW, 1, 13), (RW, 1, 12), (RW, 1, 11), (RW, 1, 10), (GX, 4, 0), (GW, 1, 15), (GW, 1, 14), (GW, 1, 13), (GW, 1, 12), (GW, 1, 11), (GW, 1, 10), (BX, 4, 0), (BW, 1, 15), (BW, 1, 14), (BW, 1, 13), (BW, 1, 12), (BW, 1, 11), (BW, 1, 10)], true, 16, [4, 4, 4]),
+];
+
+/// Decode an unsigned BC6H block to RGB half floats; reserved modes decode to black
+pub(crate) fn bc6h(block: &[u8]) -> [[u16; 3]; 16] {
+    let mut bits = Bits::new(block);
+    let mut mode_bits = bits.read(2);
+    if mode_bits > 1 {
+        mode_bits |= bits.read(3) << 2;
+    }
+    let Some(&(_, fields, transformed, endpoint_bits, delta_bits)) = BC6H_MODES.iter().find(|m| m.0 == mode_bits) else {
+        return [[0; 3]; 16];
+    };
+
+    let mut values = [0u32; 13];
+    for &(field, count, shift) in fields {
+        values[field as usize] |= bits.read(count as u32) << shift;
+    }
+    let partition = values[D as usize] as usize;
+    let subsets = if mode_bits & 3 == 3 { 1 } else { 2 };
+
+    let mut endpoints = [[0u32; 3]; 4];
+    for (e, endpoint) in endpoints.iter_mut().enumerate().take(subsets * 2) {
+        for channel in 0..3 {
+            endpoint[channel] = values[e * 3 + channel];
+        }
+    }
+    let mask = (1u32 << endpoint_bits) - 1;
+    if transformed {
+        for endpoint in 1..subsets * 2 {
+            for channel in 0..3 {
+                let bits = delta_bits[channel];
+                let delta = ((endpoints[endpoint][channel] << (32 - bits)) as i32 >> (32 - bits)) as u32;
+                endpoints[endpoint][channel] = endpoints[0][channel].wrapping_add(delta) & mask;
+            }
+        }
+    }
+    let unquantize = |value: u32| -> u32 {
+        if endpoint_bits >= 15 {
+            value
+        } else if value == 0 {
+            0
+        } else if value == mask {
+            0xFFFF
+        } else {
+            ((value << 16) + 0x8000) >> endpoint_bits
+        }
+    };
+    for endpoint in endpoints.iter_mut() {
+        for channel in endpoint.iter_mut() {
+            *channel = unquantize(*channel);
+        }
+    }
+
+    let index_bits = if subsets == 1 { 4 } else { 3 };
+    let mut pixels = [[0u16; 3]; 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let (s, anchor) = subset(subsets as u32, partition, i);
+        let weight = weights(index_bits)[bits.read(index_bits - anchor as u32) as usize];
+        let (e0, e1) = (endpoints[2 * s], endpoints[2 * s + 1]);
+        for channel in 0..3 {
+            let value = ((64 - weight) * e0[channel] + weight * e1[channel] + 32) >> 6;
+            pixel[channel] = ((value * 31) >> 6) as u16;
+        }
+    }
+    pixels
+}
+
+/// Convert an IEEE half float to `f32`
+pub(crate) fn half_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1F) as i32;
+    let mantissa = (half & 0x3FF) as f32;
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        31 if mantissa == 0.0 => f32::INFINITY,
+        31 => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pack (value, bit count) fields into a block, least significant bit first
+    fn pack(fields: &[(u32, u32)]) -> [u8; 16] {
+        let (mut value, mut position) = (0u128, 0);
+        for &(field, count) in fields {
+            value |= (field as u128) << position;
+            position += count;
+        }
+        assert!(position <= 128);
+        value.to_le_bytes()
+    }
+
+    #[test]
+    fn test_layout_tables() {
+        for p in 0..64 {
+            assert_eq!(subset(2, p, 0).0, 0);
+            assert_eq!(subset(2, p, ANCHORS_2[p] as usize).0, 1, "two-subset partition {}", p);
+            assert_eq!(subset(3, p, 0).0, 0);
+            assert_eq!(subset(3, p, ANCHORS_3_SECOND[p] as usize).0, 1, "three-subset partition {}", p);
+            assert_eq!(subset(3, p, ANCHORS_3_THIRD[p] as usize).0, 2, "three-subset partition {}", p);
+        }
+        // Every BC6H header ends where the indices start
+        for (mode, fields, _, _, _) in BC6H_MODES {
+            let header = if mode > 1 { 5 } else { 2 };
+            let length: u32 = header + fields.iter().map(|f| f.1 as u32).sum::<u32>();
+            assert_eq!(length, if mode & 3 == 3 { 65 } else { 82 }, "BC6H mode {:#x}", mode);
+        }
+    }
+
+    #[test]
+    fn test_bc1() {
+        // Pure red and blue endpoints, pixel i uses index i % 4
+        let mut block = Vec::new();
+        block.extend_from_slice(&0xF800u16.to_le_bytes());
+        block.extend_from_slice(&0x001Fu16.to_le_bytes());
+        block.extend_from_slice(&0xE4E4E4E4u32.to_le_bytes());
+        let pixels = bc1(&block);
+        assert_eq!(pixels[0], [255, 0, 0, 255]);
+        assert_eq!(pixels[1], [0, 0, 255, 255]);
+        assert_eq!(pixels[2], [170, 0, 85, 255]);
+        assert_eq!(pixels[3], [85, 0, 170, 255]);
+
+        // Swapped endpoints select the three-color mode, but only for BC1
+        block[..4].copy_from_slice(&[0x1F, 0, 0, 0xF8]);
+        assert_eq!(bc1(&block)[3], [0, 0, 0, 0]);
+        let bc3_block: Vec<u8> = [255, 0, 0, 0, 0, 0, 0, 0].iter().chain(&block).copied().collect();
+        assert_eq!(bc3(&bc3_block)[3], [170, 0, 85, 255]);
+    }
+
+    #[test]
+    fn test_bc7_mode6() {
+        // Mode 6: one subset, 7-bit RGBA endpoints with p-bits, 4-bit indices
+        let mut fields = vec![(1 << 6, 7)];
+        fields.extend([(0, 7), (127, 7), (0, 7), (64, 7), (0, 7), (0, 7), (127, 7), (127, 7)]);
+        fields.extend([(0, 1), (1, 1)]);
+        fields.push((0, 3));
+        fields.extend(std::iter::repeat_n((15, 4), 15));
+        let pixels = bc7(&pack(&fields));
+        assert_eq!(pixels[0], [0, 0, 0, 254]);
+        assert_eq!(pixels[1], [255, 129, 1, 255]);
+    }
+
+    #[test]
+    fn test_bc6h_mode11() {
+        // Mode 11: one subset, untransformed 10-bit endpoints, 4-bit indices
+        let mut fields = vec![(0x03, 5)];
+        fields.extend([(0, 10), (0, 10), (0, 10), (1023, 10), (512, 10), (0, 10)]);
+        fields.push((0, 3));
+        fields.extend(std::iter::repeat_n((15, 4), 15));
+        let pixels = bc6h(&pack(&fields));
+        assert_eq!(pixels[0], [0, 0, 0]);
+        assert_eq!(pixels[1], [0x7BFF, 15887, 0]);
+        assert_eq!(half_to_f32(0x3C00), 1.0);
+        assert_eq!(half_to_f32(0x7BFF), 65504.0);
+    }
+}
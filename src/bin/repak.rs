@@ -3,11 +3,16 @@
 //! This executable provides the same functionality as the original repak tool
 //! but using pure Rust implementation from the rust-ue-tools library.
 
-use std::path::PathBuf;
-use clap::{Parser, Subcommand, Args};
+use std::path::{Path, PathBuf};
+use clap::{Parser, Subcommand};
+use std::io::Write;
 use std::process;
 
-use rust_ue_tools::{Unpacker, PakUnpackOptions, error::Result};
+use rust_ue_tools::{AssetRegistry, DependencyGraph, DependencyOptions, EngineVersion, ExtractOptions, InspectOptions, ListingCache, LocMeta, Locres, LocresVersion, Mappings, PackageSummary, PropertyReader, ScriptObjects, TableFormat, TextureFormat, TextureOptions, UeToolError, Unpacker, PakUnpackOptions, Package, SoundBank, StreamedWem, VerifyOptions};
+use rust_ue_tools::cli;
+use rust_ue_tools::logging::{init_cli_logging, LogFormat};
+use rust_ue_tools::progress::progress_bar_callback;
+use rust_ue_tools::signature::keys;
 
 /// REPAK CLI - Unreal Engine PAK file manipulation
 #[derive(Parser, Debug)]
@@ -16,6 +21,14 @@ use rust_ue_tools::{Unpacker, PakUnpackOptions, error::Result};
 #[command(version = "1.0.0")]
 #[command(long_about = None)]
 struct Cli {
+    /// Increase log verbosity (-v info, -vv debug, -vvv trace)
+    #[arg(short = 'v', long = "verbose", action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Log output format (logs are written to stderr)
+    #[arg(long = "log-format", value_enum, default_value_t = LogFormat::Text, global = true)]
+    log_format: LogFormat,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -63,9 +76,29 @@ enum Commands {
         #[arg(long = "json")]
         json: bool,
         
-        /// Filter by pattern
+        /// Filter by pattern; with --packages, keeps packages whose name or any file matches
         #[arg(short = 'p', long = "pattern")]
         pattern: Option<String>,
+        
+        /// Group companion files (.uexp, .ubulk, .uptnl) into one row per package
+        #[arg(long = "packages")]
+        packages: bool,
+
+        /// Annotate packages with the class of their asset export (implies --packages)
+        #[arg(long = "classes")]
+        classes: bool,
+
+        /// Only list packages of this class, e.g. Texture2D (implies --classes)
+        #[arg(long = "class", value_name = "CLASS")]
+        class: Option<String>,
+
+        /// Engine version the game was built with, e.g. 5.3 (needed to classify unversioned packages)
+        #[arg(short = 'e', long = "engine-version")]
+        engine_version: Option<EngineVersion>,
+
+        /// Read the pak index even if a cached listing is available
+        #[arg(long = "no-cache")]
+        no_cache: bool,
     },
     
     /// Show PAK file information
@@ -78,21 +111,381 @@ enum Commands {
         #[arg(short = 'k', long = "key")]
         key: Option<String>,
     },
+
+    /// Check the index and every entry against their recorded SHA-1 hashes
+    Verify {
+        /// Path to the .pak file to verify (or a .utoc with --sig)
+        #[arg(value_name = "PAK_FILE")]
+        pak_file: PathBuf,
+
+        /// AES encryption key (hex format)
+        #[arg(short = 'k', long = "key")]
+        key: Option<String>,
+
+        /// Output in JSON format
+        #[arg(long = "json")]
+        json: bool,
+
+        /// Check the container against its .sig file instead
+        #[arg(long = "sig")]
+        sig: bool,
+
+        /// PEM public key used to check the .sig signature
+        #[arg(long = "public-key", requires = "sig")]
+        public_key: Option<PathBuf>,
+    },
+
+    /// Extract selected files or packages without unpacking everything
+    Get {
+        /// Path to the .pak (or .utoc) file
+        #[arg(value_name = "PAK_FILE")]
+        pak_file: PathBuf,
+
+        /// File paths, package names or /Game/ package paths to extract
+        #[arg(value_name = "PATH", required = true)]
+        paths: Vec<String>,
+
+        /// Output directory, or `-` to write a single file to stdout
+        #[arg(short = 'o', long = "output", default_value = ".")]
+        output: PathBuf,
+
+        /// AES encryption key (hex format)
+        #[arg(short = 'k', long = "key")]
+        key: Option<String>,
+
+        /// Force overwrite of existing files
+        #[arg(short = 'f', long = "force")]
+        force: bool,
+    },
+
+    /// Write a signed .sig file for a .pak or .utoc
+    Sign {
+        /// Path to the .pak or .utoc file to sign
+        #[arg(value_name = "CONTAINER")]
+        container: PathBuf,
+
+        /// PEM private key (PKCS#8 or PKCS#1)
+        #[arg(long = "private-key")]
+        private_key: PathBuf,
+    },
+
+    /// Show the summary, name map, imports and exports of a cooked package
+    Inspect {
+        /// A .pak or .utoc file, or a loose .uasset
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Package to inspect inside a container (path or package name)
+        #[arg(value_name = "PATH")]
+        path: Option<String>,
+
+        /// Engine version the game was built with, e.g. 5.3 (needed for unversioned packages)
+        #[arg(short = 'e', long = "engine-version")]
+        engine_version: Option<EngineVersion>,
+
+        /// Decode export properties using this .usmap mappings file
+        #[arg(short = 'm', long = "mappings", value_name = "USMAP")]
+        mappings: Option<PathBuf>,
+
+        /// AES encryption key (hex format)
+        #[arg(short = 'k', long = "key")]
+        key: Option<String>,
+
+        /// Output in JSON format
+        #[arg(long = "json")]
+        json: bool,
+    },
+
+    /// Show package dependencies, or extract a package with everything it needs
+    Deps {
+        /// Path to the .pak or .utoc file
+        #[arg(value_name = "CONTAINER")]
+        container: PathBuf,
+
+        /// Package to query (name like /Game/UI/Icon, or path); the whole graph if omitted
+        #[arg(value_name = "PACKAGE")]
+        package: Option<String>,
+
+        /// List the packages that depend on PACKAGE instead
+        #[arg(long = "reverse", requires = "package", conflicts_with = "closure")]
+        reverse: bool,
+
+        /// Follow dependencies transitively
+        #[arg(long = "closure", requires = "package")]
+        closure: bool,
+
+        /// Follow soft references too
+        #[arg(long = "soft")]
+        soft: bool,
+
+        /// Keep /Script/ packages
+        #[arg(long = "script")]
+        script: bool,
+
+        /// Extract PACKAGE and its dependency closure into this directory
+        #[arg(long = "extract", value_name = "DIR", requires = "package")]
+        extract: Option<PathBuf>,
+
+        /// Print the graph in Graphviz DOT format
+        #[arg(long = "dot", conflicts_with = "json")]
+        dot: bool,
+
+        /// Output in JSON format
+        #[arg(long = "json")]
+        json: bool,
+
+        /// Engine version the game was built with, e.g. 5.3 (needed for unversioned paks)
+        #[arg(short = 'e', long = "engine-version")]
+        engine_version: Option<EngineVersion>,
+
+        /// AES encryption key (hex format)
+        #[arg(short = 'k', long = "key")]
+        key: Option<String>,
+
+        /// Force overwrite of existing files when extracting
+        #[arg(short = 'f', long = "force")]
+        force: bool,
+    },
+
+    /// List the assets recorded in a game's AssetRegistry.bin
+    Registry {
+        /// A .pak or .utoc file holding AssetRegistry.bin, or a loose AssetRegistry.bin
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Only list assets of this class (short name or full path)
+        #[arg(long = "class")]
+        class: Option<String>,
+
+        /// Print each asset's tags
+        #[arg(long = "tags")]
+        tags: bool,
+
+        /// AES encryption key (hex format)
+        #[arg(short = 'k', long = "key")]
+        key: Option<String>,
+
+        /// Output in JSON format
+        #[arg(long = "json")]
+        json: bool,
+    },
+
+    /// Export a DataTable, CurveTable or StringTable to JSON or CSV
+    ExportTable {
+        /// Path to the .pak or .utoc file
+        #[arg(value_name = "CONTAINER")]
+        container: PathBuf,
+
+        /// Table asset inside the container (path or package name)
+        #[arg(value_name = "ASSET")]
+        asset: String,
+
+        /// .usmap mappings file of the game
+        #[arg(short = 'm', long = "mappings", value_name = "USMAP")]
+        mappings: PathBuf,
+
+        /// Write the table to this file instead of stdout
+        #[arg(short = 'o', long = "output")]
+        output: Option<PathBuf>,
+
+        /// Output format: json or csv (default: from the output extension, else json)
+        #[arg(long = "format")]
+        format: Option<TableFormat>,
+
+        /// Engine version the game was built with, e.g. 5.3 (needed for unversioned packages)
+        #[arg(short = 'e', long = "engine-version")]
+        engine_version: Option<EngineVersion>,
+
+        /// AES encryption key (hex format)
+        #[arg(short = 'k', long = "key")]
+        key: Option<String>,
+    },
+
+    /// Export a texture, or every texture in a container, to PNG or DDS
+    Texture {
+        /// Path to the .pak or .utoc file
+        #[arg(value_name = "CONTAINER")]
+        container: PathBuf,
+
+        /// Texture asset inside the container (path or package name); exports every texture if omitted
+        #[arg(value_name = "ASSET")]
+        asset: Option<String>,
+
+        /// Output file for one texture, or output directory for every texture
+        #[arg(short = 'o', long = "output")]
+        output: Option<PathBuf>,
+
+        /// Output format: png or dds (default: from the output extension, else png)
+        #[arg(long = "format")]
+        format: Option<TextureFormat>,
+
+        /// Mip to export, 0 being the largest (default: the largest mip in the container)
+        #[arg(long = "mip")]
+        mip: Option<usize>,
+
+        /// Only export textures whose package path matches this glob (repeatable)
+        #[arg(long = "include", value_name = "PATTERN", conflicts_with = "asset")]
+        include: Vec<String>,
+
+        /// Engine version the game was built with, e.g. 5.3 (needed for unversioned packages)
+        #[arg(short = 'e', long = "engine-version")]
+        engine_version: Option<EngineVersion>,
+
+        /// AES encryption key (hex format)
+        #[arg(short = 'k', long = "key")]
+        key: Option<String>,
+
+        /// Force overwrite of existing files
+        #[arg(short = 'f', long = "force")]
+        force: bool,
+
+        /// Quiet mode (minimal output)
+        #[arg(short = 'q', long = "quiet")]
+        quiet: bool,
+    },
+
+    /// List the media and events of Wwise soundbanks, or extract their .wem files
+    Bnk {
+        /// A .pak or .utoc file, or a loose .bnk
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// .bnk inside a container; every bank is summarized if omitted
+        #[arg(value_name = "PATH")]
+        path: Option<String>,
+
+        /// Extract the bank's embedded and streamed .wem files to this directory
+        #[arg(short = 'x', long = "extract", value_name = "DIR")]
+        extract: Option<PathBuf>,
+
+        /// Output as JSON
+        #[arg(long = "json")]
+        json: bool,
+
+        /// AES encryption key (hex format)
+        #[arg(short = 'k', long = "key")]
+        key: Option<String>,
+
+        /// Force overwrite of existing files
+        #[arg(short = 'f', long = "force")]
+        force: bool,
+    },
+
+    /// Export the strings of a .locres file, or show a .locmeta
+    Locres {
+        /// A .pak or .utoc file, or a loose .locres or .locmeta
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// .locres or .locmeta inside a container
+        #[arg(value_name = "PATH")]
+        path: Option<String>,
+
+        /// Write the strings to this file instead of stdout
+        #[arg(short = 'o', long = "output")]
+        output: Option<PathBuf>,
+
+        /// Output format: json or csv (default: from the output extension, else json)
+        #[arg(long = "format")]
+        format: Option<TableFormat>,
+
+        /// AES encryption key (hex format)
+        #[arg(short = 'k', long = "key")]
+        key: Option<String>,
+    },
+
+    /// Build a .locres from JSON written by `repak locres`
+    LocresBuild {
+        /// Edited JSON export
+        #[arg(value_name = "JSON")]
+        json: PathBuf,
+
+        /// Path of the .locres to write
+        #[arg(value_name = "OUTPUT")]
+        output: PathBuf,
+
+        /// Write this .locres version instead of the one recorded in the JSON
+        #[arg(long = "version", value_name = "VERSION")]
+        locres_version: Option<LocresVersion>,
+    },
 }
 
 fn main() {
     let cli = Cli::parse();
+    init_cli_logging(cli.verbose, cli.log_format);
     
     match cli.command {
         Commands::Unpack { pak_file, output, quiet, force, key, strip_prefix } => {
             handle_unpack(pak_file, output, quiet, force, key, strip_prefix);
         }
-        Commands::List { pak_file, key, json, pattern } => {
-            handle_list(pak_file, key, json, pattern);
+        Commands::List { pak_file, key, json, pattern, packages, classes, class, engine_version, no_cache } => {
+            let filter = ClassFilter { classify: classes || class.is_some(), class, engine_version };
+            if packages || filter.classify {
+                handle_list_packages(pak_file, key, json, pattern, filter, no_cache);
+            } else {
+                handle_list(pak_file, key, json, pattern, no_cache);
+            }
         }
         Commands::Info { pak_file, key } => {
             handle_info(pak_file, key);
         }
+        Commands::Verify { pak_file, json, sig: true, public_key, .. } => {
+            handle_verify_signature(pak_file, public_key, json);
+        }
+        Commands::Verify { pak_file, key, json, .. } => {
+            handle_verify(pak_file, key, json);
+        }
+        Commands::Get { pak_file, paths, output, key, force } => {
+            handle_get(pak_file, paths, output, key, force);
+        }
+        Commands::Sign { container, private_key } => {
+            handle_sign(container, private_key);
+        }
+        Commands::Inspect { file, path, engine_version, mappings, key, json } => {
+            handle_inspect(file, path, engine_version, mappings, key, json);
+        }
+        Commands::Deps { container, package, reverse, closure, soft, script, extract, dot, json, engine_version, key, force } => {
+            let mut options = DependencyOptions::new().with_include_script(script);
+            options.engine_version = engine_version;
+            if let Some(ref k) = key {
+                options = options.with_aes_key(k);
+            }
+            let query = DepsQuery { package, reverse, closure, soft };
+            handle_deps(container, query, extract, dot, json, options, force);
+        }
+        Commands::Registry { file, class, tags, key, json } => {
+            handle_registry(file, class, tags, key, json);
+        }
+        Commands::ExportTable { container, asset, mappings, output, format, engine_version, key } => {
+            handle_export_table(container, asset, mappings, output, format, engine_version, key);
+        }
+        Commands::Texture { container, asset, output, format, mip, include, engine_version, key, force, quiet } => {
+            let format = format.unwrap_or_else(|| match output.as_ref().and_then(|path| path.extension()) {
+                Some(ext) if ext.eq_ignore_ascii_case("dds") => TextureFormat::Dds,
+                _ => TextureFormat::Png,
+            });
+            let mut options = TextureOptions::new().with_format(format).with_force(force);
+            options.mip = mip;
+            options.engine_version = engine_version;
+            if let Some(ref k) = key {
+                options = options.with_aes_key(k);
+            }
+            match asset {
+                Some(asset) => handle_texture(container, asset, output, options),
+                None => handle_textures(container, output, include, options, quiet),
+            }
+        }
+        Commands::Bnk { file, path, extract, json, key, force } => {
+            let mut options = ExtractOptions::new().with_force(force);
+            options.aes_key = key;
+            handle_bnk(file, path, extract, json, options);
+        }
+        Commands::Locres { file, path, output, format, key } => {
+            handle_locres(file, path, output, format, key);
+        }
+        Commands::LocresBuild { json, output, locres_version } => {
+            handle_locres_build(json, output, locres_version);
+        }
     }
 }
 
@@ -103,13 +496,16 @@ fn handle_unpack(pak_file: PathBuf, output: PathBuf, quiet: bool, force: bool, k
         process::exit(1);
     }
     
-    if !pak_file.extension().map_or(false, |ext| ext == "pak") {
+    if pak_file.extension().is_none_or(|ext| ext != "pak") {
         eprintln!("Error: File must have .pak extension");
         process::exit(1);
     }
     
     // Create unpacker instance
     let mut unpacker = Unpacker::new();
+    if !quiet {
+        unpacker = unpacker.with_progress_callback(progress_bar_callback());
+    }
     
     // Build options
     let mut options = PakUnpackOptions::new()
@@ -150,49 +546,35 @@ fn handle_unpack(pak_file: PathBuf, output: PathBuf, quiet: bool, force: bool, k
     }
 }
 
-fn handle_list(pak_file: PathBuf, key: Option<String>, json: bool, pattern: Option<String>) {
+fn handle_list(pak_file: PathBuf, key: Option<String>, json: bool, pattern: Option<String>, no_cache: bool) {
     // Validate input file
     if !pak_file.exists() {
         eprintln!("Error: PAK file not found: {}", pak_file.display());
         process::exit(1);
     }
     
-    if !pak_file.extension().map_or(false, |ext| ext == "pak") {
+    if pak_file.extension().is_none_or(|ext| ext != "pak") {
         eprintln!("Error: File must have .pak extension");
         process::exit(1);
     }
     
     // Create unpacker instance
-    let mut unpacker = Unpacker::new();
+    let mut unpacker = Unpacker::new().with_listing_cache(ListingCache::for_cli(no_cache));
     
-    // Build options
-    let mut options = PakUnpackOptions::new()
-        .with_quiet(true);
-    
-    if let Some(ref k) = key {
-        options = options.with_aes_key(k);
-    }
-    
-    // Apply pattern filter if provided
-    if let Some(ref pat) = pattern {
-        match glob::Pattern::new(pat) {
-            Ok(pattern) => {
-                options = options.with_include_patterns(vec![pattern]);
-            }
-            Err(e) => {
-                eprintln!("Error: Invalid pattern '{}': {}", pat, e);
-                process::exit(1);
-            }
-        }
-    }
+    // Parse pattern filter if provided
+    let pattern = parse_pattern(pattern);
     
     // List files
     if !json {
         println!("Listing contents of {}", pak_file.display());
     }
     
-    match unpacker.pak_unpacker.list_files(&pak_file, &options) {
-        Ok(file_paths) => {
+    match unpacker.get_pak_file_list(&pak_file, key.as_deref()) {
+        Ok(mut file_paths) => {
+            if let Some(ref pattern) = pattern {
+                file_paths.retain(|p| pattern.matches(p.as_str()));
+            }
+
             if json {
                 // JSON output format
                 let output = serde_json::json!({
@@ -219,6 +601,89 @@ fn handle_list(pak_file: PathBuf, key: Option<String>, json: bool, pattern: Opti
     }
 }
 
+/// Parse a `--pattern` glob, exiting on a malformed one
+fn parse_pattern(pattern: Option<String>) -> Option<glob::Pattern> {
+    match pattern.as_deref().map(glob::Pattern::new) {
+        Some(Ok(pattern)) => Some(pattern),
+        Some(Err(e)) => {
+            eprintln!("Error: Invalid pattern '{}': {}", pattern.unwrap_or_default(), e);
+            process::exit(1);
+        }
+        None => None,
+    }
+}
+
+fn handle_list_packages(pak_file: PathBuf, key: Option<String>, json: bool, pattern: Option<String>, filter: ClassFilter, no_cache: bool) {
+    // Validate input file
+    if !pak_file.exists() {
+        eprintln!("Error: PAK file not found: {}", pak_file.display());
+        process::exit(1);
+    }
+    
+    let pattern = parse_pattern(pattern);
+    
+    let mut unpacker = Unpacker::new().with_listing_cache(ListingCache::for_cli(no_cache));
+    let mut options = PakUnpackOptions::new()
+        .with_quiet(true);
+    
+    if let Some(ref k) = key {
+        options = options.with_aes_key(k);
+    }
+    
+    match unpacker.list_pak_packages(&pak_file, &options) {
+        Ok(mut packages) => {
+            if let Some(ref pattern) = pattern {
+                packages.retain(|p| pattern.matches(&p.name) || p.files.iter().any(|f| pattern.matches(f.path.as_str())));
+            }
+            filter.apply(&mut unpacker, &pak_file, &mut packages, key);
+            if json {
+                let output = serde_json::json!({
+                    "file": pak_file.to_string_lossy(),
+                    "package_count": packages.len(),
+                    "orphan_count": packages.iter().filter(|p| p.is_orphan()).count(),
+                    "packages": packages
+                });
+                println!("{}", serde_json::to_string_pretty(&output).unwrap_or_else(|e| {
+                    eprintln!("Error serializing JSON: {}", e);
+                    process::exit(1);
+                }));
+            } else {
+                cli::print_packages(&packages);
+            }
+        }
+        Err(e) => {
+            eprintln!("Error listing PAK file: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// `list --classes` / `--class` settings
+struct ClassFilter {
+    classify: bool,
+    class: Option<String>,
+    engine_version: Option<EngineVersion>,
+}
+
+impl ClassFilter {
+    /// Classify the listed packages and drop those of other classes
+    fn apply(&self, unpacker: &mut Unpacker, container: &Path, packages: &mut Vec<Package>, key: Option<String>) {
+        if !self.classify {
+            return;
+        }
+        let mut options = InspectOptions::new();
+        options.aes_key = key;
+        options.engine_version = self.engine_version;
+        if let Err(e) = unpacker.classify_packages(container, packages, &options) {
+            eprintln!("Error classifying packages: {}", e);
+            process::exit(1);
+        }
+        if let Some(ref class) = self.class {
+            packages.retain(|p| p.is_class(class));
+        }
+    }
+}
+
 fn handle_info(pak_file: PathBuf, key: Option<String>) {
     // Validate input file
     if !pak_file.exists() {
@@ -226,7 +691,7 @@ fn handle_info(pak_file: PathBuf, key: Option<String>) {
         process::exit(1);
     }
     
-    if !pak_file.extension().map_or(false, |ext| ext == "pak") {
+    if pak_file.extension().is_none_or(|ext| ext != "pak") {
         eprintln!("Error: File must have .pak extension");
         process::exit(1);
     }
@@ -249,6 +714,7 @@ fn handle_info(pak_file: PathBuf, key: Option<String>) {
             println!("PAK File Information:");
             println!("  File: {}", info["file_path"]);
             println!("  Files: {}", info["file_count"]);
+            println!("  Signed: {}", if info["signed"].as_bool().unwrap_or(false) { "yes" } else { "no" });
             if let Some(file_names) = info["file_names"].as_array() {
                 println!("  File Names (first 10):");
                 for file in file_names.iter().take(10) {
@@ -264,4 +730,776 @@ fn handle_info(pak_file: PathBuf, key: Option<String>) {
             process::exit(1);
         }
     }
-}
\ No newline at end of file
+}
+
+fn handle_verify(pak_file: PathBuf, key: Option<String>, json: bool) {
+    if !pak_file.exists() {
+        eprintln!("Error: PAK file not found: {}", pak_file.display());
+        process::exit(1);
+    }
+
+    let mut unpacker = Unpacker::new();
+    if !json {
+        unpacker = unpacker.with_progress_callback(progress_bar_callback());
+    }
+
+    let mut options = VerifyOptions::new();
+    if let Some(ref k) = key {
+        options = options.with_aes_key(k);
+    }
+
+    let report = match unpacker.verify_pak(&pak_file, &options) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Error verifying PAK file: {}", e);
+            process::exit(1);
+        }
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap_or_else(|e| {
+            eprintln!("Error serializing JSON: {}", e);
+            process::exit(1);
+        }));
+    } else {
+        for issue in &report.index_issues {
+            println!("INDEX {:?}: {}", issue.kind, issue.detail);
+        }
+        for entry in &report.corrupt {
+            for issue in &entry.issues {
+                println!("{} {:?}: {}", entry.path, issue.kind, issue.detail);
+            }
+        }
+        println!(
+            "Checked {} entries: {} corrupt, index {}",
+            report.entries_checked,
+            report.corrupt.len(),
+            if report.index_issues.is_empty() { "OK" } else { "CORRUPT" }
+        );
+    }
+
+    if !report.is_ok() {
+        process::exit(2);
+    }
+}
+
+fn handle_verify_signature(container: PathBuf, public_key: Option<PathBuf>, json: bool) {
+    if !container.exists() {
+        eprintln!("Error: File not found: {}", container.display());
+        process::exit(1);
+    }
+
+    let mut unpacker = Unpacker::new();
+    let result = match public_key {
+        Some(ref path) => keys::load_public_key(path).and_then(|key| unpacker.verify_signature_with_key(&container, &key)),
+        None => unpacker.verify_signature(&container),
+    };
+    let report = match result {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Error verifying signature: {}", e);
+            process::exit(1);
+        }
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap_or_else(|e| {
+            eprintln!("Error serializing JSON: {}", e);
+            process::exit(1);
+        }));
+    } else {
+        for chunk in &report.tampered_chunks {
+            let crc = |c: Option<u32>| c.map_or("missing".to_string(), |c| format!("{:08x}", c));
+            println!(
+                "Chunk {} at offset {}: expected {}, found {}",
+                chunk.index, chunk.offset, crc(chunk.expected), crc(chunk.actual)
+            );
+        }
+        let signature = match report.signature_valid {
+            Some(true) => "valid",
+            Some(false) => "INVALID",
+            None => "not checked (no --public-key)",
+        };
+        println!(
+            "Checked {} chunks: {} tampered, signature {}",
+            report.chunk_count,
+            report.tampered_chunks.len(),
+            signature
+        );
+    }
+
+    if !report.is_ok() {
+        process::exit(2);
+    }
+}
+
+fn handle_sign(container: PathBuf, private_key: PathBuf) {
+    if !container.exists() {
+        eprintln!("Error: File not found: {}", container.display());
+        process::exit(1);
+    }
+
+    let mut unpacker = Unpacker::new();
+    match keys::load_private_key(&private_key).and_then(|key| unpacker.sign_container(&container, &key)) {
+        Ok(path) => println!("Wrote {}", path.display()),
+        Err(e) => {
+            eprintln!("Error signing {}: {}", container.display(), e);
+            process::exit(1);
+        }
+    }
+}
+
+fn handle_get(pak_file: PathBuf, paths: Vec<String>, output: PathBuf, key: Option<String>, force: bool) {
+    let mut unpacker = Unpacker::new();
+    let mut options = ExtractOptions::new().with_force(force);
+    if let Some(ref k) = key {
+        options = options.with_aes_key(k);
+    }
+
+    if output.as_os_str() == "-" {
+        if paths.len() != 1 {
+            eprintln!("Error: Only one path can be written to stdout");
+            process::exit(1);
+        }
+        let data = unpacker.read_entry(&pak_file, &paths[0], &options).unwrap_or_else(|e| {
+            eprintln!("Error reading {}: {}", paths[0], e);
+            process::exit(1);
+        });
+        if let Err(e) = std::io::stdout().lock().write_all(&data) {
+            eprintln!("Error writing to stdout: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    match unpacker.extract_many(&pak_file, &paths, &output, &options) {
+        Ok(files) => {
+            for file in &files {
+                println!("{} ({} bytes)", file.output_path.display(), file.size);
+            }
+        }
+        Err(e) => {
+            for file in &e.partial {
+                println!("{} ({} bytes)", file.output_path.display(), file.size);
+            }
+            eprintln!("Error extracting from {}: {}", pak_file.display(), e);
+            process::exit(1);
+        }
+    }
+}
+
+fn handle_inspect(
+    file: PathBuf,
+    path: Option<String>,
+    engine_version: Option<EngineVersion>,
+    mappings: Option<PathBuf>,
+    key: Option<String>,
+    json: bool,
+) {
+    let is_container = file
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pak") || ext.eq_ignore_ascii_case("utoc"));
+    let package = match (is_container, path) {
+        (true, Some(path)) => {
+            let mut options = ExtractOptions::new();
+            options.aes_key = key.clone();
+            Unpacker::new().read_package(&file, &path, &options)
+        }
+        (true, None) => {
+            eprintln!("Error: Name the package to inspect inside {}", file.display());
+            process::exit(1);
+        }
+        (false, _) => read_loose_package(&file),
+    };
+
+    let package = package.unwrap_or_else(|e| {
+        eprintln!("Error inspecting {}: {}", file.display(), e);
+        process::exit(1);
+    });
+    let summary = PackageSummary::parse(&package, engine_version).unwrap_or_else(|e| {
+        eprintln!("Error inspecting {}: {}", file.display(), e);
+        process::exit(1);
+    });
+
+    let properties = mappings.map(|usmap| {
+        let mappings = Mappings::open(&usmap).unwrap_or_else(|e| {
+            eprintln!("Error loading mappings {}: {}", usmap.display(), e);
+            process::exit(1);
+        });
+        let script_objects = file
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("utoc"))
+            .then(|| ScriptObjects::open(file.with_file_name("global.utoc"), key.as_deref()).ok())
+            .flatten();
+        let mut reader = PropertyReader::new(&summary, &mappings);
+        if let Some(script_objects) = &script_objects {
+            reader = reader.with_script_objects(script_objects);
+        }
+        reader.read_exports(&package)
+    });
+
+    if json {
+        let out = match &properties {
+            Some(properties) => serde_json::to_string_pretty(&serde_json::json!({ "summary": summary, "properties": properties })),
+            None => serde_json::to_string_pretty(&summary),
+        };
+        match out {
+            Ok(out) => println!("{}", out),
+            Err(e) => {
+                eprintln!("Error serializing summary: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let versions = &summary.versions;
+    println!("Package: {}", summary.package_name.as_deref().unwrap_or("(unnamed)"));
+    println!("  Format: {:?}", summary.format);
+    println!(
+        "  Versions: UE4 {}, UE5 {}, licensee {}{}",
+        versions.file_version_ue4,
+        versions.file_version_ue5,
+        versions.licensee_version,
+        if versions.unversioned { " (unversioned)" } else { "" }
+    );
+    if let Some(ref engine) = versions.saved_by_engine {
+        println!("  Saved by: {}", engine);
+    }
+    println!("  Flags: {:#010x}", summary.package_flags);
+    println!("  Header size: {} bytes", summary.header_size);
+    println!("  Names: {}", summary.names.len());
+
+    println!("Imports ({}):", summary.imports.len());
+    for (i, import) in summary.imports.iter().enumerate() {
+        match (&import.object_name, &import.global_ref) {
+            (Some(name), _) => println!("  [{}] {} ({})", i, name, import.class_name.as_deref().unwrap_or("?")),
+            (None, Some(global)) => println!(
+                "  [{}] {:?}{}",
+                i,
+                global,
+                import.package_name.as_deref().map(|p| format!(" from {}", p)).unwrap_or_default()
+            ),
+            (None, None) => println!("  [{}] ?", i),
+        }
+    }
+
+    println!("Exports ({}):", summary.exports.len());
+    for (i, export) in summary.exports.iter().enumerate() {
+        println!(
+            "  [{}] {} ({}) offset {} size {}",
+            i,
+            export.object_name,
+            export.class_name.as_deref().unwrap_or("?"),
+            export.serial_offset,
+            export.serial_size
+        );
+    }
+
+    if !summary.soft_package_references.is_empty() {
+        println!("Soft package references ({}):", summary.soft_package_references.len());
+        for reference in &summary.soft_package_references {
+            println!("  {}", reference);
+        }
+    }
+
+    if let Some(properties) = properties {
+        println!("Properties:");
+        for export in properties {
+            match (export.properties, export.error) {
+                (Some(value), _) => println!(
+                    "  {}: {}",
+                    export.export,
+                    serde_json::to_string_pretty(&value).unwrap_or_default().replace('\n', "\n  ")
+                ),
+                (None, error) => println!("  {}: failed: {}", export.export, error.unwrap_or_default()),
+            }
+        }
+    }
+}
+
+/// Read a loose package header and the `.uexp` next to it, if any
+fn read_loose_package(header: &Path) -> rust_ue_tools::Result<Vec<u8>> {
+    let read = |path: &Path| {
+        std::fs::read(path).map_err(|e| UeToolError::io_error(format!("Failed to read {}: {}", path.display(), e)))
+    };
+    let mut data = read(header)?;
+    let exports = header.with_extension("uexp");
+    if exports.exists() {
+        data.extend_from_slice(&read(&exports)?);
+    }
+    Ok(data)
+}
+
+fn handle_registry(file: PathBuf, class: Option<String>, tags: bool, key: Option<String>, json: bool) {
+    let is_container = file
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pak") || ext.eq_ignore_ascii_case("utoc"));
+    let result = if is_container {
+        let mut options = InspectOptions::new();
+        if let Some(ref k) = key {
+            options = options.with_aes_key(k);
+        }
+        Unpacker::new().load_asset_registry(&file, &options)
+    } else {
+        AssetRegistry::open(&file)
+    };
+
+    let mut registry = result.unwrap_or_else(|e| {
+        eprintln!("Error reading asset registry from {}: {}", file.display(), e);
+        process::exit(1);
+    });
+    if let Some(ref class) = class {
+        registry.assets.retain(|asset| asset.is_class(class));
+    }
+
+    if json {
+        match serde_json::to_string_pretty(&registry) {
+            Ok(out) => println!("{}", out),
+            Err(e) => {
+                eprintln!("Error serializing registry: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    println!(
+        "Asset registry version {}: {} assets, {} dependency nodes, {} packages",
+        registry.version,
+        registry.assets.len(),
+        registry.dependencies.len(),
+        registry.packages.len()
+    );
+    for asset in &registry.assets {
+        println!("  {} ({})", asset.object_path, asset.class);
+        if tags {
+            for (key, value) in &asset.tags {
+                println!("      {} = {}", key, value);
+            }
+        }
+    }
+}
+
+fn handle_export_table(
+    container: PathBuf,
+    asset: String,
+    mappings: PathBuf,
+    output: Option<PathBuf>,
+    format: Option<TableFormat>,
+    engine_version: Option<EngineVersion>,
+    key: Option<String>,
+) {
+    let format = format.unwrap_or_else(|| {
+        match output.as_ref().and_then(|path| path.extension()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => TableFormat::Csv,
+            _ => TableFormat::Json,
+        }
+    });
+    let mappings = Mappings::open(&mappings).unwrap_or_else(|e| {
+        eprintln!("Error loading mappings {}: {}", mappings.display(), e);
+        process::exit(1);
+    });
+
+    let mut options = InspectOptions::new();
+    if let Some(ref k) = key {
+        options = options.with_aes_key(k);
+    }
+    if let Some(version) = engine_version {
+        options = options.with_engine_version(version);
+    }
+    let table = Unpacker::new()
+        .export_table(&container, &asset, &mappings, &options)
+        .and_then(|table| table.render(format).map(|out| (table, out)));
+    let (table, out) = table.unwrap_or_else(|e| {
+        eprintln!("Error exporting {} from {}: {}", asset, container.display(), e);
+        process::exit(1);
+    });
+
+    match output {
+        Some(path) => {
+            if let Err(e) = std::fs::write(&path, out) {
+                eprintln!("Error writing {}: {}", path.display(), e);
+                process::exit(1);
+            }
+            println!("Exported {} {} ({} rows) to {}", table.kind, table.name, table.rows.len(), path.display());
+        }
+        None => print!("{}", out),
+    }
+}
+
+fn handle_texture(container: PathBuf, asset: String, output: Option<PathBuf>, options: TextureOptions) {
+    let mut unpacker = Unpacker::new();
+    let texture = unpacker.read_texture(&container, &asset, &options).unwrap_or_else(|e| {
+        eprintln!("Error reading {} from {}: {}", asset, container.display(), e);
+        process::exit(1);
+    });
+    let output = output.unwrap_or_else(|| {
+        let stem = asset.rsplit('/').next().unwrap_or(&asset);
+        let stem = stem.split_once('.').map_or(stem, |(name, _)| name);
+        PathBuf::from(format!("{}.{}", stem, options.format.extension()))
+    });
+
+    let written = texture.mip_index(options.mip).and_then(|mip| {
+        let data = texture.render(options.format, Some(mip))?;
+        if output.exists() && !options.force {
+            return Err(UeToolError::invalid_argument(format!("{} already exists; use --force to overwrite", output.display())));
+        }
+        std::fs::write(&output, data).map_err(|e| UeToolError::io_error(format!("Failed to write {}: {}", output.display(), e)))?;
+        Ok(mip)
+    });
+    match written {
+        Ok(mip) => {
+            let level = &texture.mips[mip];
+            println!(
+                "Exported {} ({}, mip {} of {}, {}x{}) to {}",
+                texture.name,
+                texture.pixel_format,
+                mip,
+                texture.mips.len(),
+                level.width,
+                level.height,
+                output.display()
+            );
+        }
+        Err(e) => {
+            eprintln!("Error exporting {}: {}", texture.name, e);
+            process::exit(1);
+        }
+    }
+}
+
+fn handle_textures(container: PathBuf, output: Option<PathBuf>, include: Vec<String>, options: TextureOptions, quiet: bool) {
+    let patterns = include.iter().map(|p| glob::Pattern::new(p).map_err(|e| (p, e))).collect::<Result<Vec<_>, _>>();
+    let patterns = patterns.unwrap_or_else(|(pattern, e)| {
+        eprintln!("Error: Invalid pattern '{}': {}", pattern, e);
+        process::exit(1);
+    });
+    let options = options.with_include_patterns(patterns);
+    let output = output.unwrap_or_else(|| PathBuf::from("."));
+
+    let mut unpacker = Unpacker::new();
+    if !quiet {
+        unpacker = unpacker.with_progress_callback(progress_bar_callback());
+    }
+    let (files, error) = match unpacker.export_textures(&container, &output, &options) {
+        Ok(files) => (files, None),
+        Err(e) => (e.partial, Some(e.error)),
+    };
+
+    let failed = files.iter().filter(|f| f.error.is_some()).count();
+    for file in &files {
+        match &file.error {
+            Some(e) => eprintln!("Failed {}: {}", file.original_path.as_str(), e),
+            None if !quiet => println!("{} ({} bytes)", file.output_path.display(), file.size),
+            None => {}
+        }
+    }
+    if let Some(e) = error {
+        eprintln!("Error exporting textures from {}: {}", container.display(), e);
+        process::exit(1);
+    }
+    println!("Exported {} textures to {} ({} failed)", files.len() - failed, output.display(), failed);
+    if failed > 0 {
+        process::exit(1);
+    }
+}
+
+fn handle_bnk(file: PathBuf, path: Option<String>, extract: Option<PathBuf>, json: bool, options: ExtractOptions) {
+    let is_container = file
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pak") || ext.eq_ignore_ascii_case("utoc"));
+    if !is_container {
+        let bank = SoundBank::open(&file).unwrap_or_else(|e| {
+            eprintln!("Error reading {}: {}", file.display(), e);
+            process::exit(1);
+        });
+        match extract {
+            Some(dir) => extract_embedded_wems(&bank, &dir, options.force),
+            None => print_bank(&file.display().to_string(), &bank, &[], json),
+        }
+        return;
+    }
+
+    let mut unpacker = Unpacker::new();
+    let Some(path) = path else {
+        if extract.is_some() {
+            eprintln!("Error: Name the .bnk to extract inside {}", file.display());
+            process::exit(1);
+        }
+        let banks = unpacker.index_soundbanks(&file, &options).unwrap_or_else(|e| {
+            eprintln!("Error reading soundbanks from {}: {}", file.display(), e);
+            process::exit(1);
+        });
+        if json {
+            println!("{}", serde_json::to_string_pretty(&banks).unwrap_or_else(|e| {
+                eprintln!("Error serializing JSON: {}", e);
+                process::exit(1);
+            }));
+            return;
+        }
+        for index in &banks {
+            println!(
+                "{}: bank {} (v{}), {} embedded, {} streamed ({} missing), {} events",
+                index.path,
+                index.bank.id,
+                index.bank.version,
+                index.bank.media.len(),
+                index.streamed.len(),
+                index.missing().count(),
+                index.bank.events().count()
+            );
+        }
+        println!("{} soundbanks", banks.len());
+        return;
+    };
+
+    if let Some(dir) = extract {
+        let (files, error) = match unpacker.extract_wems(&file, &path, &dir, &options) {
+            Ok(files) => (files, None),
+            Err(e) => (e.partial, Some(e.error)),
+        };
+        for missing in files.iter().filter(|f| f.error.is_some()) {
+            eprintln!("Warning: {} is not in {}", missing.original_path.as_str(), file.display());
+        }
+        let written = files.iter().filter(|f| f.error.is_none()).count();
+        if let Some(e) = error {
+            eprintln!("Error extracting media of {}: {} ({} files written)", path, e, written);
+            process::exit(1);
+        }
+        println!("Extracted {} .wem files to {}", written, dir.display());
+        return;
+    }
+
+    let index = unpacker.index_soundbank(&file, &path, &options).unwrap_or_else(|e| {
+        eprintln!("Error reading {} from {}: {}", path, file.display(), e);
+        process::exit(1);
+    });
+    if json {
+        println!("{}", serde_json::to_string_pretty(&index).unwrap_or_else(|e| {
+            eprintln!("Error serializing JSON: {}", e);
+            process::exit(1);
+        }));
+        return;
+    }
+    print_bank(&index.path, &index.bank, &index.streamed, false);
+}
+
+fn print_bank(name: &str, bank: &SoundBank, streamed: &[StreamedWem], json: bool) {
+    if json {
+        println!("{}", serde_json::to_string_pretty(bank).unwrap_or_else(|e| {
+            eprintln!("Error serializing JSON: {}", e);
+            process::exit(1);
+        }));
+        return;
+    }
+    println!("{}: bank {} (v{}), {} objects", name, bank.id, bank.version, bank.objects.len());
+    for media in &bank.media {
+        println!("  embedded {}.wem ({} bytes)", media.id, media.size);
+    }
+    for id in bank.streamed_media() {
+        match streamed.iter().find(|w| w.id == id) {
+            Some(StreamedWem { path: Some(path), .. }) => println!("  streamed {}.wem: {}", id, path),
+            Some(_) => println!("  streamed {}.wem: not in the container", id),
+            None => println!("  streamed {}.wem", id),
+        }
+    }
+    for event in bank.events() {
+        let media: Vec<String> = bank.event_media(event.id).iter().map(|m| m.id.to_string()).collect();
+        println!("  event {}: {} actions, media [{}]", event.id, event.references.len(), media.join(", "));
+    }
+}
+
+fn extract_embedded_wems(bank: &SoundBank, dir: &Path, force: bool) {
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        eprintln!("Error creating {}: {}", dir.display(), e);
+        process::exit(1);
+    }
+    for media in &bank.media {
+        let dest = dir.join(format!("{}.wem", media.id));
+        if dest.exists() && !force {
+            eprintln!("Error: {} already exists; use --force to overwrite", dest.display());
+            process::exit(1);
+        }
+        let written = bank
+            .media_data(media.id)
+            .ok_or_else(|| UeToolError::invalid_format(format!("Media {} runs past the end of the DATA section", media.id)))
+            .and_then(|data| std::fs::write(&dest, data).map_err(|e| UeToolError::io_error(format!("Failed to write {}: {}", dest.display(), e))));
+        if let Err(e) = written {
+            eprintln!("Error extracting {}: {}", media.id, e);
+            process::exit(1);
+        }
+    }
+    println!("Extracted {} embedded .wem files to {}", bank.media.len(), dir.display());
+}
+
+fn handle_locres(file: PathBuf, path: Option<String>, output: Option<PathBuf>, format: Option<TableFormat>, key: Option<String>) {
+    let is_container = file
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pak") || ext.eq_ignore_ascii_case("utoc"));
+    let data = match (is_container, &path) {
+        (true, Some(path)) => {
+            let mut options = ExtractOptions::new();
+            options.aes_key = key;
+            Unpacker::new().read_entry(&file, path, &options)
+        }
+        (true, None) => {
+            eprintln!("Error: Name the .locres or .locmeta to read inside {}", file.display());
+            process::exit(1);
+        }
+        (false, _) => std::fs::read(&file).map_err(|e| UeToolError::io_error(format!("Failed to read {}: {}", file.display(), e))),
+    };
+    let data = data.unwrap_or_else(|e| {
+        eprintln!("Error reading {}: {}", file.display(), e);
+        process::exit(1);
+    });
+
+    let name = path.as_deref().map(Path::new).unwrap_or(&file);
+    if name.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("locmeta")) {
+        let meta = LocMeta::parse(&data).unwrap_or_else(|e| {
+            eprintln!("Error parsing {}: {}", name.display(), e);
+            process::exit(1);
+        });
+        println!("Native culture: {} ({})", meta.native_culture, meta.native_locres);
+        if !meta.compiled_cultures.is_empty() {
+            println!("Compiled cultures: {}", meta.compiled_cultures.join(", "));
+        }
+        return;
+    }
+
+    let format = format.unwrap_or_else(|| {
+        match output.as_ref().and_then(|path| path.extension()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => TableFormat::Csv,
+            _ => TableFormat::Json,
+        }
+    });
+    let locres = Locres::parse(&data).and_then(|locres| locres.render(format).map(|out| (locres, out)));
+    let (locres, out) = locres.unwrap_or_else(|e| {
+        eprintln!("Error parsing {}: {}", name.display(), e);
+        process::exit(1);
+    });
+
+    match output {
+        Some(path) => {
+            if let Err(e) = std::fs::write(&path, out) {
+                eprintln!("Error writing {}: {}", path.display(), e);
+                process::exit(1);
+            }
+            println!(
+                "Exported {} strings in {} namespaces ({} .locres) to {}",
+                locres.entry_count(),
+                locres.namespaces.len(),
+                locres.version,
+                path.display()
+            );
+        }
+        None => print!("{}", out),
+    }
+}
+
+fn handle_locres_build(json: PathBuf, output: PathBuf, locres_version: Option<LocresVersion>) {
+    let locres = std::fs::read_to_string(&json)
+        .map_err(|e| UeToolError::io_error(format!("Failed to read {}: {}", json.display(), e)))
+        .and_then(|text| Locres::from_json(&text));
+    let mut locres = locres.unwrap_or_else(|e| {
+        eprintln!("Error reading {}: {}", json.display(), e);
+        process::exit(1);
+    });
+    if let Some(version) = locres_version {
+        locres.version = version;
+    }
+
+    if let Err(e) = locres.save(&output) {
+        eprintln!("Error writing {}: {}", output.display(), e);
+        process::exit(1);
+    }
+    println!("Wrote {} strings ({} .locres) to {}", locres.entry_count(), locres.version, output.display());
+}
+
+/// What `repak deps` was asked about
+struct DepsQuery {
+    package: Option<String>,
+    reverse: bool,
+    closure: bool,
+    soft: bool,
+}
+
+fn handle_deps(container: PathBuf, query: DepsQuery, extract: Option<PathBuf>, dot: bool, json: bool, options: DependencyOptions, force: bool) {
+    let mut unpacker = Unpacker::new();
+    let graph = unpacker.dependency_graph(&container, &options).unwrap_or_else(|e| {
+        eprintln!("Error reading dependencies of {}: {}", container.display(), e);
+        process::exit(1);
+    });
+    for (path, error) in &graph.errors {
+        eprintln!("Warning: {}: {}", path, error);
+    }
+
+    let Some(package) = query.package else {
+        print_graph(&graph, dot, json);
+        return;
+    };
+    let Some(node) = graph.get(&package) else {
+        eprintln!("Error: Package not found: {}", package);
+        process::exit(1);
+    };
+
+    if let Some(dest) = extract {
+        let mut extract_options = ExtractOptions::new().with_force(force);
+        extract_options.aes_key = options.aes_key.clone();
+        match unpacker.extract_with_dependencies(&container, &graph, &node.name, &dest, query.soft, &extract_options) {
+            Ok(files) => println!("Extracted {} files to {}", files.len(), dest.display()),
+            Err(e) => {
+                eprintln!("Error extracting {}: {} ({} files written)", node.name, e, e.partial.len());
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let nodes = if query.reverse {
+        graph.dependents(&node.name)
+    } else if query.closure {
+        graph.closure(&node.name, query.soft).into_iter().skip(1).collect()
+    } else {
+        graph.dependencies(&node.name)
+    };
+
+    if dot {
+        // Restrict the graph to the queried package and its results
+        let keep: Vec<&str> = nodes.iter().map(|n| n.name.as_str()).chain([node.name.as_str()]).collect();
+        let mut subgraph = DependencyGraph { container: graph.container.clone(), ..Default::default() };
+        for n in &nodes {
+            subgraph.packages.insert(n.name.clone(), (*n).clone());
+        }
+        subgraph.packages.insert(node.name.clone(), node.clone());
+        for n in subgraph.packages.values_mut() {
+            n.dependencies.retain(|d| keep.contains(&d.package.as_str()));
+        }
+        print!("{}", subgraph.to_dot());
+    } else if json {
+        let names: Vec<&str> = nodes.iter().map(|n| n.name.as_str()).collect();
+        println!("{}", serde_json::json!({ "package": node.name, "packages": names }));
+    } else {
+        for n in nodes {
+            println!("{}{}", n.name, if n.path.is_none() { " (external)" } else { "" });
+        }
+    }
+}
+
+fn print_graph(graph: &DependencyGraph, dot: bool, json: bool) {
+    if dot {
+        print!("{}", graph.to_dot());
+    } else if json {
+        match serde_json::to_string_pretty(graph) {
+            Ok(out) => println!("{}", out),
+            Err(e) => {
+                eprintln!("Error serializing graph: {}", e);
+                process::exit(1);
+            }
+        }
+    } else {
+        for node in graph.packages.values().filter(|n| n.path.is_some()) {
+            println!("{} ({} dependencies)", node.name, node.dependencies.len());
+        }
+    }
+}
@@ -3,11 +3,14 @@
 //! This executable provides the same functionality as the original retoc_cli tool
 //! but using pure Rust implementation from the rust-ue-tools library.
 
-use std::path::PathBuf;
-use clap::{Parser, Subcommand, Args};
+use std::path::{Path, PathBuf};
+use clap::{Parser, Subcommand};
 use std::process;
 
-use rust_ue_tools::{Unpacker, UtocListOptions, error::Result};
+use rust_ue_tools::{EngineVersion, InspectOptions, ListingCache, Unpacker, UtocListOptions, Package};
+use rust_ue_tools::cli;
+use rust_ue_tools::logging::{init_cli_logging, LogFormat};
+use rust_ue_tools::progress::progress_bar_callback;
 
 /// RETOC CLI - Unreal Engine UTOC file manipulation
 #[derive(Parser, Debug)]
@@ -16,6 +19,14 @@ use rust_ue_tools::{Unpacker, UtocListOptions, error::Result};
 #[command(version = "1.0.0")]
 #[command(long_about = None)]
 struct Cli {
+    /// Increase log verbosity (-v info, -vv debug, -vvv trace)
+    #[arg(short = 'v', long = "verbose", action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Log output format (logs are written to stderr)
+    #[arg(long = "log-format", value_enum, default_value_t = LogFormat::Text, global = true)]
+    log_format: LogFormat,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -39,6 +50,26 @@ enum Commands {
         /// Quiet mode (minimal output)
         #[arg(short = 'q', long = "quiet")]
         quiet: bool,
+        
+        /// Group bulk data chunks (.ubulk, .uptnl) into one row per package
+        #[arg(long = "packages")]
+        packages: bool,
+
+        /// Annotate packages with the class of their asset export (implies --packages)
+        #[arg(long = "classes")]
+        classes: bool,
+
+        /// Only list packages of this class, e.g. Texture2D (implies --classes)
+        #[arg(long = "class", value_name = "CLASS")]
+        class: Option<String>,
+
+        /// Engine version the game was built with, e.g. 5.3 (needed to classify unversioned packages)
+        #[arg(short = 'e', long = "engine-version")]
+        engine_version: Option<EngineVersion>,
+
+        /// Read the .utoc even if a cached listing is available
+        #[arg(long = "no-cache")]
+        no_cache: bool,
     },
     
     /// Show UTOC file information (equivalent to original retoc_cli info)
@@ -82,10 +113,16 @@ enum Commands {
 
 fn main() {
     let cli = Cli::parse();
+    init_cli_logging(cli.verbose, cli.log_format);
     
     match cli.command {
-        Commands::List { utoc_file, json, key, quiet } => {
-            handle_list(utoc_file, json, key, quiet);
+        Commands::List { utoc_file, json, key, quiet, packages, classes, class, engine_version, no_cache } => {
+            let filter = ClassFilter { classify: classes || class.is_some(), class, engine_version };
+            if packages || filter.classify {
+                handle_list_packages(utoc_file, json, key, filter, no_cache);
+            } else {
+                handle_list(utoc_file, json, key, quiet, no_cache);
+            }
         }
         Commands::Info { utoc_file, key, json } => {
             handle_info(utoc_file, key, json);
@@ -96,20 +133,23 @@ fn main() {
     }
 }
 
-fn handle_list(utoc_file: PathBuf, json: bool, key: Option<String>, quiet: bool) {
+fn handle_list(utoc_file: PathBuf, json: bool, key: Option<String>, quiet: bool, no_cache: bool) {
     // Validate input file
     if !utoc_file.exists() {
         eprintln!("Error: UTOC file not found: {}", utoc_file.display());
         process::exit(1);
     }
     
-    if !utoc_file.extension().map_or(false, |ext| ext == "utoc") {
+    if utoc_file.extension().is_none_or(|ext| ext != "utoc") {
         eprintln!("Error: File must have .utoc extension");
         process::exit(1);
     }
     
     // Create unpacker instance
-    let mut unpacker = Unpacker::new();
+    let mut unpacker = Unpacker::new().with_listing_cache(ListingCache::for_cli(no_cache));
+    if !quiet && !json {
+        unpacker = unpacker.with_progress_callback(progress_bar_callback());
+    }
     
     // Build options
     let mut options = UtocListOptions::new()
@@ -163,21 +203,87 @@ fn handle_list(utoc_file: PathBuf, json: bool, key: Option<String>, quiet: bool)
     }
 }
 
-fn handle_info(utoc_file: PathBuf, key: Option<String>, json: bool) {
+fn handle_list_packages(utoc_file: PathBuf, json: bool, key: Option<String>, filter: ClassFilter, no_cache: bool) {
     // Validate input file
     if !utoc_file.exists() {
         eprintln!("Error: UTOC file not found: {}", utoc_file.display());
         process::exit(1);
     }
     
-    if !utoc_file.extension().map_or(false, |ext| ext == "utoc") {
+    let mut unpacker = Unpacker::new().with_listing_cache(ListingCache::for_cli(no_cache));
+    let mut options = UtocListOptions::new()
+        .with_json_format(json);
+    
+    if let Some(ref k) = key {
+        options = options.with_aes_key(k);
+    }
+    
+    match unpacker.list_utoc_packages(&utoc_file, &options) {
+        Ok(mut packages) => {
+            filter.apply(&mut unpacker, &utoc_file, &mut packages, key);
+            if json {
+                let output = serde_json::json!({
+                    "file": utoc_file.to_string_lossy(),
+                    "package_count": packages.len(),
+                    "orphan_count": packages.iter().filter(|p| p.is_orphan()).count(),
+                    "packages": packages
+                });
+                println!("{}", serde_json::to_string_pretty(&output).unwrap_or_else(|e| {
+                    eprintln!("Error serializing JSON: {}", e);
+                    process::exit(1);
+                }));
+            } else {
+                cli::print_packages(&packages);
+            }
+        }
+        Err(e) => {
+            eprintln!("Error listing UTOC file: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// `list --classes` / `--class` settings
+struct ClassFilter {
+    classify: bool,
+    class: Option<String>,
+    engine_version: Option<EngineVersion>,
+}
+
+impl ClassFilter {
+    /// Classify the listed packages and drop those of other classes
+    fn apply(&self, unpacker: &mut Unpacker, container: &Path, packages: &mut Vec<Package>, key: Option<String>) {
+        if !self.classify {
+            return;
+        }
+        let mut options = InspectOptions::new();
+        options.aes_key = key;
+        options.engine_version = self.engine_version;
+        if let Err(e) = unpacker.classify_packages(container, packages, &options) {
+            eprintln!("Error classifying packages: {}", e);
+            process::exit(1);
+        }
+        if let Some(ref class) = self.class {
+            packages.retain(|p| p.is_class(class));
+        }
+    }
+}
+
+fn handle_info(utoc_file: PathBuf, key: Option<String>, _json: bool) {
+    // Validate input file
+    if !utoc_file.exists() {
+        eprintln!("Error: UTOC file not found: {}", utoc_file.display());
+        process::exit(1);
+    }
+    
+    if utoc_file.extension().is_none_or(|ext| ext != "utoc") {
         eprintln!("Error: File must have .utoc extension");
         process::exit(1);
     }
     
     println!("Analyzing UTOC file: {}", utoc_file.display());
     
-    // Use the list function and format as info
+    // Use the detailed listing and format it as info
     let mut unpacker = Unpacker::new();
     let mut options = UtocListOptions::new()
         .with_json_format(true);
@@ -186,18 +292,19 @@ fn handle_info(utoc_file: PathBuf, key: Option<String>, json: bool) {
         options = options.with_aes_key(k);
     }
     
-    match unpacker.list_utoc(&utoc_file, &options) {
-        Ok(asset_paths) => {
+    match unpacker.utoc_lister.list_detailed(&utoc_file, &options) {
+        Ok(info) => {
             let info_output = serde_json::json!({
                 "file": utoc_file.to_string_lossy(),
                 "file_size": utoc_file.metadata().map(|m| m.len()).unwrap_or(0),
                 "modified": utoc_file.metadata()
                     .and_then(|m| m.modified())
                     .map_or_else(|_| "unknown".to_string(), |t| format!("{:?}", t)),
-                "asset_count": asset_paths.len(),
-                "compression_methods": [],
+                "asset_count": info.assets.len(),
+                "compression_methods": info.metadata.compression_methods,
                 "encryption": key.is_some(),
-                "assets": asset_paths.iter().map(|p| p.as_str()).collect::<Vec<_>>()
+                "signed": info.metadata.signed,
+                "assets": info.assets.iter().map(|p| p.as_str()).collect::<Vec<_>>()
             });
             
             println!("{}", serde_json::to_string_pretty(&info_output).unwrap_or_else(|e| {
@@ -219,7 +326,7 @@ fn handle_info(utoc_file: PathBuf, key: Option<String>, json: bool) {
     }
 }
 
-fn handle_extract(utoc_file: PathBuf, output: PathBuf, key: Option<String>, force: bool, quiet: bool) {
+fn handle_extract(_utoc_file: PathBuf, _output: PathBuf, _key: Option<String>, _force: bool, _quiet: bool) {
     eprintln!("Error: UTOC extraction not yet implemented");
     process::exit(1);
 }
\ No newline at end of file
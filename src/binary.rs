@@ -0,0 +1,173 @@
+//! Little-endian binary reading helpers shared by the format parsers
+//!
+//! Unreal Engine serializes almost everything little-endian with a handful of
+//! recurring composite types (FString, FGuid, length-prefixed arrays). These
+//! helpers keep the individual parsers focused on layout rather than byte
+//! plumbing.
+
+use std::io::Read;
+
+use crate::error::{Result, UeToolError};
+
+/// Upper bound for any byte length we are willing to allocate from untrusted input
+const MAX_SANE_LENGTH: usize = 1024 * 1024 * 1024;
+
+/// Upper bound for any element count read from untrusted input
+const MAX_SANE_COUNT: usize = 64 * 1024 * 1024;
+
+/// Extension trait adding UE primitive readers to any `Read`
+pub(crate) trait ReadExt: Read {
+    fn read_fixed<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut buf = [0u8; N];
+        self.read_exact(&mut buf)
+            .map_err(|e| UeToolError::InvalidFormat(format!("Unexpected end of data: {}", e)))?;
+        Ok(buf)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<Vec<u8>> {
+        if len > MAX_SANE_LENGTH {
+            return Err(UeToolError::InvalidFormat(format!("Refusing to read {} bytes", len)));
+        }
+        let mut buf = vec![0u8; len];
+        self.read_exact(&mut buf)
+            .map_err(|e| UeToolError::InvalidFormat(format!("Unexpected end of data: {}", e)))?;
+        Ok(buf)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_fixed::<1>()?[0])
+    }
+
+    fn read_bool(&mut self) -> Result<bool> {
+        Ok(self.read_u8()? != 0)
+    }
+
+    /// Read a 32-bit UE `bool` (serialized as a full uint32)
+    fn read_bool32(&mut self) -> Result<bool> {
+        Ok(self.read_u32()? != 0)
+    }
+
+    fn read_u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.read_fixed()?))
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.read_fixed()?))
+    }
+
+    fn read_i32(&mut self) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.read_fixed()?))
+    }
+
+    fn read_u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.read_fixed()?))
+    }
+
+    fn read_guid(&mut self) -> Result<[u8; 16]> {
+        self.read_fixed()
+    }
+
+    /// Read a count prefix (int32) and validate it is non-negative and sane
+    fn read_count(&mut self) -> Result<usize> {
+        let count = self.read_i32()?;
+        if count < 0 || count as usize > MAX_SANE_COUNT {
+            return Err(UeToolError::InvalidFormat(format!("Invalid element count: {}", count)));
+        }
+        Ok(count as usize)
+    }
+
+    /// Read an `FString`: int32 length (negative for UTF-16) including the terminator
+    fn read_fstring(&mut self) -> Result<String> {
+        let len = self.read_i32()?;
+        if len == 0 {
+            return Ok(String::new());
+        }
+
+        if len > 0 {
+            let bytes = self.read_bytes(len as usize)?;
+            let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+            // Narrow strings are Latin-1; map bytes straight to code points
+            Ok(bytes[..end].iter().map(|&b| b as char).collect())
+        } else {
+            let units = len.checked_neg()
+                .ok_or_else(|| UeToolError::InvalidFormat("Invalid string length".to_string()))? as usize;
+            let bytes = self.read_bytes(units * 2)?;
+            let wide: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .take_while(|&c| c != 0)
+                .collect();
+            String::from_utf16(&wide)
+                .map_err(|e| UeToolError::InvalidFormat(format!("Invalid UTF-16 string: {}", e)))
+        }
+    }
+
+    /// Read an int32-prefixed array using the supplied element reader
+    fn read_array_of<T, F>(&mut self, mut read_one: F) -> Result<Vec<T>>
+    where
+        Self: Sized,
+        F: FnMut(&mut Self) -> Result<T>,
+    {
+        let count = self.read_count()?;
+        let mut items = Vec::with_capacity(count.min(4096));
+        for _ in 0..count {
+            items.push(read_one(self)?);
+        }
+        Ok(items)
+    }
+}
+
+impl<R: Read + ?Sized> ReadExt for R {}
+
+/// Write an `FString` the way UE does (ASCII when possible, UTF-16 otherwise)
+pub(crate) fn write_fstring(out: &mut Vec<u8>, value: &str) {
+    if value.is_empty() {
+        out.extend_from_slice(&0i32.to_le_bytes());
+    } else if value.is_ascii() {
+        out.extend_from_slice(&(value.len() as i32 + 1).to_le_bytes());
+        out.extend_from_slice(value.as_bytes());
+        out.push(0);
+    } else {
+        let wide: Vec<u16> = value.encode_utf16().collect();
+        out.extend_from_slice(&(-(wide.len() as i32 + 1)).to_le_bytes());
+        for unit in wide {
+            out.extend_from_slice(&unit.to_le_bytes());
+        }
+        out.extend_from_slice(&[0, 0]);
+    }
+}
+
+/// Write ASCII names as a `SaveNameBatch` batch with zeroed hashes
+#[cfg(test)]
+pub(crate) fn write_name_batch(out: &mut Vec<u8>, names: &[&str]) {
+    let string_bytes: usize = names.iter().map(|n| n.len()).sum();
+    out.extend_from_slice(&(names.len() as u32).to_le_bytes());
+    out.extend_from_slice(&(string_bytes as u32).to_le_bytes());
+    out.extend_from_slice(&0xC1640000u64.to_le_bytes());
+    out.resize(out.len() + names.len() * 8, 0);
+    for name in names {
+        out.extend_from_slice(&[(name.len() >> 8) as u8, name.len() as u8]);
+    }
+    for name in names {
+        out.extend_from_slice(name.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_fstring_roundtrip() {
+        let mut buf = Vec::new();
+        write_fstring(&mut buf, "../../../Game/");
+        write_fstring(&mut buf, "Zürich ✓");
+        write_fstring(&mut buf, "");
+
+        let mut cursor = Cursor::new(buf);
+        assert_eq!(cursor.read_fstring().unwrap(), "../../../Game/");
+        assert_eq!(cursor.read_fstring().unwrap(), "Zürich ✓");
+        assert_eq!(cursor.read_fstring().unwrap(), "");
+    }
+}
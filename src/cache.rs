@@ -0,0 +1,447 @@
+//! Persistent cache of container listings
+//!
+//! Listing a container means opening it and parsing its index, which adds up
+//! when the same mods folder is scanned many times a day. [`ListingCache`]
+//! keeps listings in a JSON file and reuses them while the files behind them
+//! are unchanged: each entry records a [`Fingerprint`] (size, modification
+//! time and a hash of the file's first and last 4 KiB, which cover the utoc
+//! header and the pak footer) for every file it was read from, and any
+//! mismatch makes the entry stale.
+//!
+//! AES keys are never stored; options that change a listing are folded into
+//! the cache key as a short digest.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{Result, UeToolError};
+use crate::types::{AssetPath, FileEntry};
+
+/// Bumped whenever the cache file layout or listing semantics change
+const CACHE_VERSION: u32 = 2;
+
+/// Bytes hashed from each end of a file
+const EDGE_BYTES: u64 = 4096;
+
+/// Environment variable that overrides the cache directory
+pub const CACHE_DIR_ENV: &str = "RUST_UE_TOOLS_CACHE_DIR";
+
+/// What a cached listing was produced by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListingKind {
+    /// Raw pak index file names
+    Pak,
+    /// Asset paths listed from a .utoc
+    Utoc,
+    /// A container found by a folder scan
+    Container,
+    /// All containers inside a ZIP, RAR or 7z archive
+    Archive,
+    /// Pak index entries with sizes, for package listings
+    PakEntries,
+    /// .utoc chunks with sizes, for package listings
+    UtocEntries,
+}
+
+/// Identity of a file on disk at the time it was listed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified_secs: u64,
+    pub modified_nanos: u32,
+    /// SHA-256 of the first and last 4 KiB, hex encoded
+    pub footer_hash: String,
+}
+
+impl Fingerprint {
+    /// Fingerprint a file as it currently is on disk
+    pub fn of<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = File::open(path)
+            .map_err(|e| UeToolError::IoError(format!("Failed to open {}: {}", path.display(), e)))?;
+        let metadata = file
+            .metadata()
+            .map_err(|e| UeToolError::IoError(format!("Failed to stat {}: {}", path.display(), e)))?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+
+        let size = metadata.len();
+        let mut hasher = Sha256::new();
+        let mut buf = Vec::with_capacity(EDGE_BYTES as usize);
+        (&mut file).take(EDGE_BYTES).read_to_end(&mut buf)?;
+        hasher.update(&buf);
+        if size > EDGE_BYTES {
+            buf.clear();
+            file.seek(SeekFrom::Start(size.saturating_sub(EDGE_BYTES).max(EDGE_BYTES)))?;
+            file.take(EDGE_BYTES).read_to_end(&mut buf)?;
+            hasher.update(&buf);
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            size,
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+            footer_hash: hex::encode(hasher.finalize()),
+        })
+    }
+}
+
+/// One cached listing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub kind: ListingKind,
+    /// Every file the listing was read from
+    pub fingerprints: Vec<Fingerprint>,
+    pub assets: Vec<AssetPath>,
+    /// File entries with sizes, for the `*Entries` kinds
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entries: Vec<FileEntry>,
+    /// Seconds since the Unix epoch when the listing was stored
+    pub cached_at: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+struct CacheState {
+    path: PathBuf,
+    entries: BTreeMap<String, CacheEntry>,
+    dirty: bool,
+}
+
+impl CacheState {
+    fn save(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        // Forget listings of files that have since been deleted
+        self.entries
+            .retain(|_, entry| entry.fingerprints.iter().all(|f| f.path.exists()));
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| UeToolError::IoError(format!("Failed to create cache directory: {}", e)))?;
+        }
+
+        let file = CacheFile { version: CACHE_VERSION, entries: std::mem::take(&mut self.entries) };
+        let json = serde_json::to_vec(&file);
+        self.entries = file.entries;
+        let json = json.map_err(|e| UeToolError::SerializationError(format!("Failed to serialize cache: {}", e)))?;
+
+        // Write next to the cache and rename so readers never see a partial file
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, json)
+            .map_err(|e| UeToolError::IoError(format!("Failed to write cache {}: {}", temp_path.display(), e)))?;
+        fs::rename(&temp_path, &self.path)
+            .map_err(|e| UeToolError::IoError(format!("Failed to replace cache {}: {}", self.path.display(), e)))?;
+
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+impl Drop for CacheState {
+    fn drop(&mut self) {
+        if let Err(e) = self.save() {
+            tracing::warn!(cache = %self.path.display(), error = %e, "Failed to save listing cache");
+        }
+    }
+}
+
+/// Shared handle to an on-disk listing cache
+///
+/// Clones share the same entries. Changes are written back by
+/// [`ListingCache::save`], or when the last clone is dropped.
+#[derive(Clone, Default)]
+pub struct ListingCache {
+    state: Option<Arc<Mutex<CacheState>>>,
+}
+
+impl ListingCache {
+    /// Open the cache stored at `path`, starting empty if it is missing or unreadable
+    pub fn open<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+        let entries = match fs::read(&path) {
+            Ok(bytes) => match serde_json::from_slice::<CacheFile>(&bytes) {
+                Ok(file) if file.version == CACHE_VERSION => file.entries,
+                Ok(file) => {
+                    tracing::debug!(cache = %path.display(), version = file.version, "Discarding cache from another version");
+                    BTreeMap::new()
+                }
+                Err(e) => {
+                    tracing::warn!(cache = %path.display(), error = %e, "Discarding unreadable listing cache");
+                    BTreeMap::new()
+                }
+            },
+            Err(_) => BTreeMap::new(),
+        };
+
+        tracing::debug!(cache = %path.display(), entries = entries.len(), "Opened listing cache");
+        Self {
+            state: Some(Arc::new(Mutex::new(CacheState { path, entries, dirty: false }))),
+        }
+    }
+
+    /// Open the cache in the default location, see [`ListingCache::default_path`]
+    pub fn open_default() -> Result<Self> {
+        let path = Self::default_path()
+            .ok_or_else(|| UeToolError::Other("No cache directory available".to_string()))?;
+        Ok(Self::open(path))
+    }
+
+    /// `$RUST_UE_TOOLS_CACHE_DIR/listings.json`, else `listings.json` in the user cache directory
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = match std::env::var_os(CACHE_DIR_ENV) {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => dirs::cache_dir()?.join("rust-ue-tools"),
+        };
+        Some(dir.join("listings.json"))
+    }
+
+    /// A cache that stores nothing
+    pub fn disabled() -> Self {
+        Self { state: None }
+    }
+
+    /// The cache of the command-line tools: the default one unless `no_cache`
+    ///
+    /// Falls back to a disabled cache, with a warning, when the default
+    /// location is unavailable.
+    pub fn for_cli(no_cache: bool) -> Self {
+        if no_cache {
+            return Self::disabled();
+        }
+        Self::open_default().unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Listing cache unavailable");
+            Self::disabled()
+        })
+    }
+
+    /// Whether listings are cached
+    pub fn is_enabled(&self) -> bool {
+        self.state.is_some()
+    }
+
+    /// Number of cached listings
+    pub fn len(&self) -> usize {
+        self.with_state(|state| state.entries.len()).unwrap_or(0)
+    }
+
+    /// Whether no listings are cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Look up a listing, returning it only if none of `files` changed since it was stored
+    pub fn get(&self, kind: ListingKind, options_digest: &str, files: &[&Path]) -> Option<Vec<AssetPath>> {
+        self.lookup(kind, options_digest, files, |entry| entry.assets.clone())
+    }
+
+    /// Look up the file entries of a listing, as [`ListingCache::get`] does its assets
+    pub fn get_entries(&self, kind: ListingKind, options_digest: &str, files: &[&Path]) -> Option<Vec<FileEntry>> {
+        self.lookup(kind, options_digest, files, |entry| entry.entries.clone())
+    }
+
+    /// Store a listing read from `files`; the first file names the entry
+    pub fn insert(&self, kind: ListingKind, options_digest: &str, files: &[&Path], assets: &[AssetPath]) {
+        self.store(kind, options_digest, files, assets.to_vec(), Vec::new());
+    }
+
+    /// Store the file entries of a listing read from `files`
+    pub fn insert_entries(&self, kind: ListingKind, options_digest: &str, files: &[&Path], entries: &[FileEntry]) {
+        self.store(kind, options_digest, files, Vec::new(), entries.to_vec());
+    }
+
+    fn lookup<T>(&self, kind: ListingKind, options_digest: &str, files: &[&Path], read: impl FnOnce(&CacheEntry) -> T) -> Option<T> {
+        let key = cache_key(kind, options_digest, files.first()?);
+        let cached = self.with_state(|state| state.entries.get(&key).map(|e| e.fingerprints.clone()))??;
+
+        let current: Vec<Fingerprint> = files.iter().map(Fingerprint::of).collect::<Result<_>>().ok()?;
+        if !same_files(&cached, &current) {
+            tracing::debug!(key = %key, "Listing cache entry is stale");
+            return None;
+        }
+
+        tracing::trace!(key = %key, "Listing cache hit");
+        self.with_state(|state| state.entries.get(&key).map(read))?
+    }
+
+    fn store(&self, kind: ListingKind, options_digest: &str, files: &[&Path], assets: Vec<AssetPath>, entries: Vec<FileEntry>) {
+        let Some(first) = files.first() else {
+            return;
+        };
+        if !self.is_enabled() {
+            return;
+        }
+
+        let fingerprints = match files.iter().map(Fingerprint::of).collect::<Result<Vec<_>>>() {
+            Ok(fingerprints) => fingerprints,
+            Err(e) => {
+                tracing::debug!(file = %first.display(), error = %e, "Not caching listing");
+                return;
+            }
+        };
+
+        let entry = CacheEntry {
+            kind,
+            fingerprints,
+            assets,
+            entries,
+            cached_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        };
+        let key = cache_key(kind, options_digest, first);
+        self.with_state(|state| {
+            state.entries.insert(key, entry);
+            state.dirty = true;
+        });
+    }
+
+    /// Drop every listing that was read from `path`
+    pub fn invalidate<P: AsRef<Path>>(&self, path: P) {
+        let path = normalize(path.as_ref());
+        self.with_state(|state| {
+            let before = state.entries.len();
+            state
+                .entries
+                .retain(|_, entry| !entry.fingerprints.iter().any(|f| normalize(&f.path) == path));
+            state.dirty |= state.entries.len() != before;
+        });
+    }
+
+    /// Drop every listing
+    pub fn clear(&self) {
+        self.with_state(|state| {
+            state.dirty |= !state.entries.is_empty();
+            state.entries.clear();
+        });
+    }
+
+    /// Write pending changes to disk
+    pub fn save(&self) -> Result<()> {
+        self.with_state(CacheState::save).unwrap_or(Ok(()))
+    }
+
+    fn with_state<T>(&self, f: impl FnOnce(&mut CacheState) -> T) -> Option<T> {
+        let state = self.state.as_ref()?;
+        let mut state = state.lock().ok()?;
+        Some(f(&mut state))
+    }
+}
+
+impl std::fmt::Debug for ListingCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = self.with_state(|state| state.path.clone());
+        f.debug_struct("ListingCache").field("path", &path).finish()
+    }
+}
+
+/// Digest of the options that change a listing, e.g. the AES key and strip prefix
+///
+/// Only a prefix of a SHA-256 is kept, so keys cannot be recovered from the cache.
+pub fn options_digest(parts: &[Option<&str>]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        match part {
+            Some(part) => {
+                hasher.update([1u8]);
+                hasher.update(part.as_bytes());
+            }
+            None => hasher.update([0u8]),
+        }
+        hasher.update([0xffu8]);
+    }
+    hex::encode(&hasher.finalize()[..8])
+}
+
+fn cache_key(kind: ListingKind, options_digest: &str, path: &Path) -> String {
+    let kind = match kind {
+        ListingKind::Pak => "pak",
+        ListingKind::Utoc => "utoc",
+        ListingKind::Container => "container",
+        ListingKind::Archive => "archive",
+        ListingKind::PakEntries => "pak-entries",
+        ListingKind::UtocEntries => "utoc-entries",
+    };
+    format!("{}:{}:{}", kind, options_digest, normalize(path).display())
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Compare fingerprints ignoring how the paths were spelled
+fn same_files(cached: &[Fingerprint], current: &[Fingerprint]) -> bool {
+    cached.len() == current.len()
+        && cached.iter().zip(current).all(|(a, b)| {
+            a.size == b.size
+                && a.modified_secs == b.modified_secs
+                && a.modified_nanos == b.modified_nanos
+                && a.footer_hash == b.footer_hash
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listing_round_trip_and_invalidation() {
+        let dir = tempfile::tempdir().unwrap();
+        let container = dir.path().join("Mod_P.pak");
+        fs::write(&container, b"first").unwrap();
+        let cache_path = dir.path().join("cache").join("listings.json");
+        let digest = options_digest(&[None]);
+        let assets = vec![AssetPath::new("Game/A.uasset")];
+
+        let cache = ListingCache::open(&cache_path);
+        cache.insert(ListingKind::Pak, &digest, &[&container], &assets);
+        cache.save().unwrap();
+        drop(cache);
+
+        let cache = ListingCache::open(&cache_path);
+        assert_eq!(cache.get(ListingKind::Pak, &digest, &[&container]), Some(assets.clone()));
+        assert_eq!(cache.get(ListingKind::Utoc, &digest, &[&container]), None);
+        assert_eq!(cache.get(ListingKind::Pak, &options_digest(&[Some("key")]), &[&container]), None);
+
+        // A different size makes the entry stale
+        fs::write(&container, b"second version").unwrap();
+        assert_eq!(cache.get(ListingKind::Pak, &digest, &[&container]), None);
+
+        cache.invalidate(&container);
+        assert!(cache.is_empty());
+
+        let entries = vec![FileEntry { path: AssetPath::new("Game/A.uasset"), size: 42, is_compressed: false, compression: None }];
+        cache.insert_entries(ListingKind::PakEntries, &digest, &[&container], &entries);
+        let cached = cache.get_entries(ListingKind::PakEntries, &digest, &[&container]).unwrap();
+        assert_eq!((cached[0].path.clone(), cached[0].size), (entries[0].path.clone(), 42));
+        assert_eq!(cache.get(ListingKind::Pak, &digest, &[&container]), None);
+    }
+
+    #[test]
+    fn test_disabled_cache_stores_nothing() {
+        let cache = ListingCache::disabled();
+        cache.insert(ListingKind::Pak, "", &[Path::new("missing.pak")], &[]);
+        assert!(cache.is_empty());
+        assert!(cache.save().is_ok());
+    }
+}
@@ -0,0 +1,153 @@
+//! Cooperative cancellation and timeouts for long running operations
+//!
+//! A [`CancellationToken`] is shared between the caller and an operation such
+//! as [`crate::PakUnpacker::unpack`]. The operation checks it between entries
+//! and stops early once it is cancelled or its deadline passes, returning
+//! whatever it had finished so far inside an [`Interrupted`] error.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::error::{Result, UeToolError};
+
+/// Shared flag used to stop an operation from another thread
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    /// Create a token that is only cancelled explicitly
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a token that also expires after the given duration
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self::new().timeout_after(timeout)
+    }
+
+    /// Set (or replace) the deadline, measured from now
+    ///
+    /// Clones made before this call keep their previous deadline but still
+    /// share the cancellation flag.
+    pub fn timeout_after(mut self, timeout: Duration) -> Self {
+        self.deadline = Some(Instant::now() + timeout);
+        self
+    }
+
+    /// Request cancellation; every clone of this token observes it
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether `cancel` has been called on this token or one of its clones
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Whether the deadline, if any, has passed
+    pub fn is_expired(&self) -> bool {
+        self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+
+    /// Time left before the deadline, if one is set
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.map(|d| d.saturating_duration_since(Instant::now()))
+    }
+
+    /// Return `Cancelled` or `Timeout` if the operation should stop
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(UeToolError::Cancelled)
+        } else if self.is_expired() {
+            Err(UeToolError::Timeout)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Check an optional token, treating `None` as never cancelled
+pub(crate) fn check(token: Option<&CancellationToken>) -> Result<()> {
+    token.map_or(Ok(()), CancellationToken::check)
+}
+
+/// An operation that stopped early, with the results it completed
+///
+/// Returned for cancellation and timeouts as well as ordinary failures, so a
+/// caller stopping a 20 minute unpack still learns which files were written.
+#[derive(Debug)]
+pub struct Interrupted<T> {
+    /// Results completed before the operation stopped
+    pub partial: T,
+    /// Why the operation stopped
+    pub error: UeToolError,
+}
+
+impl<T> Interrupted<T> {
+    /// Create an interruption carrying the given partial results
+    pub fn new(partial: T, error: UeToolError) -> Self {
+        Self { partial, error }
+    }
+
+    /// Whether the operation stopped because it was cancelled or timed out
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.error, UeToolError::Cancelled | UeToolError::Timeout)
+    }
+
+    /// Transform the partial results, keeping the error
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Interrupted<U> {
+        Interrupted { partial: f(self.partial), error: self.error }
+    }
+}
+
+impl<T: Default> From<UeToolError> for Interrupted<T> {
+    fn from(error: UeToolError) -> Self {
+        Self { partial: T::default(), error }
+    }
+}
+
+impl<T> From<Interrupted<T>> for UeToolError {
+    fn from(interrupted: Interrupted<T>) -> Self {
+        interrupted.error
+    }
+}
+
+impl<T> std::fmt::Display for Interrupted<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl<T: std::fmt::Debug> std::error::Error for Interrupted<T> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Result of an operation that returns partial results when interrupted
+pub type PartialResult<T> = std::result::Result<T, Interrupted<T>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_is_shared_between_clones() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(clone.check().is_ok());
+
+        token.cancel();
+        assert!(matches!(clone.check(), Err(UeToolError::Cancelled)));
+    }
+
+    #[test]
+    fn test_timeout_expires() {
+        let token = CancellationToken::with_timeout(Duration::ZERO);
+        assert!(matches!(token.check(), Err(UeToolError::Timeout)));
+    }
+}
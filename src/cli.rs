@@ -8,7 +8,9 @@ use clap::{Parser, Subcommand, Args};
 use serde_json;
 
 use crate::error::{Result, UeToolError};
-use crate::{Unpacker, PakUnpackOptions, UtocListOptions, AssetPath};
+use crate::{ConfigOptions, ConflictOptions, DiffOptions, ListingCache, Package, Unpacker, PakUnpackOptions, PatchOptions, UtocListOptions};
+use crate::logging::{init_cli_logging, LogFormat};
+use crate::progress::progress_bar_callback;
 
 /// CLI arguments for UE file manipulation tools
 #[derive(Parser, Debug)]
@@ -17,6 +19,14 @@ use crate::{Unpacker, PakUnpackOptions, UtocListOptions, AssetPath};
 #[command(version = "1.0.0")]
 #[command(long_about = None)]
 struct Cli {
+    /// Increase log verbosity (-v info, -vv debug, -vvv trace)
+    #[arg(short = 'v', long = "verbose", action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Log output format (logs are written to stderr)
+    #[arg(long = "log-format", value_enum, default_value_t = LogFormat::Text, global = true)]
+    log_format: LogFormat,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -29,6 +39,14 @@ enum Commands {
     Retoc(RetocArgs),
     /// Extract asset paths from archives
     Extract(ExtractArgs),
+    /// Report assets provided by more than one container in a folder
+    Conflicts(ConflictsArgs),
+    /// Compare two containers or directories
+    Diff(DiffArgs),
+    /// Write a _P pak with the files of an edited tree that differ from a base pak
+    MakePatch(MakePatchArgs),
+    /// Show the config a game runs with, merged from the .ini files in its containers
+    Config(ConfigArgs),
 }
 
 #[derive(Args, Debug)]
@@ -79,6 +97,10 @@ enum RetocAction {
         /// AES encryption key (hex format)
         #[arg(short = 'k', long = "key")]
         key: Option<String>,
+
+        /// Read the .utoc even if a cached listing is available
+        #[arg(long = "no-cache")]
+        no_cache: bool,
     },
     
     /// Extract detailed information about UTOC file
@@ -95,7 +117,7 @@ enum RetocAction {
 
 #[derive(Args, Debug)]
 struct ExtractArgs {
-    /// Path to the archive file (ZIP or RAR)
+    /// Path to the archive file (ZIP, RAR or 7z)
     #[arg(value_name = "ARCHIVE_FILE")]
     archive_file: PathBuf,
     
@@ -110,11 +132,116 @@ struct ExtractArgs {
     /// Quiet mode (minimal output)
     #[arg(short = 'q', long = "quiet")]
     quiet: bool,
+
+    /// Extract the archive even if a cached listing is available
+    #[arg(long = "no-cache")]
+    no_cache: bool,
+}
+
+#[derive(Args, Debug)]
+struct ConflictsArgs {
+    /// Game install, Content/Paks directory or mods folder to check
+    #[arg(value_name = "FOLDER")]
+    folder: PathBuf,
+
+    /// AES encryption key (hex format)
+    #[arg(short = 'k', long = "key")]
+    key: Option<String>,
+
+    /// Output in JSON format
+    #[arg(long = "json")]
+    json: bool,
+
+    /// Skip reading and hashing entries to compare their contents
+    #[arg(long = "no-hash")]
+    no_hash: bool,
+}
+
+#[derive(Args, Debug)]
+struct DiffArgs {
+    /// Old .pak, .utoc or directory
+    #[arg(value_name = "OLD")]
+    old: PathBuf,
+
+    /// New .pak, .utoc or directory
+    #[arg(value_name = "NEW")]
+    new: PathBuf,
+
+    /// AES encryption key (hex format), used for both sides
+    #[arg(short = 'k', long = "key")]
+    key: Option<String>,
+
+    /// Only print summary counts
+    #[arg(long = "stat")]
+    stat: bool,
+
+    /// Output in JSON format
+    #[arg(long = "json")]
+    json: bool,
+
+    /// Compare packages instead of files
+    #[arg(long = "packages")]
+    packages: bool,
+}
+
+#[derive(Args, Debug)]
+struct MakePatchArgs {
+    /// Base .pak the edited tree was unpacked from
+    #[arg(value_name = "BASE_PAK")]
+    base: PathBuf,
+
+    /// Directory holding the edited files
+    #[arg(value_name = "EDITED_DIR")]
+    edited: PathBuf,
+
+    /// Directory to write the patch to (defaults to the base pak's directory)
+    #[arg(short = 'o', long = "output")]
+    output: Option<PathBuf>,
+
+    /// Force overwrite of an existing patch pak
+    #[arg(short = 'f', long = "force")]
+    force: bool,
+
+    /// AES encryption key (hex format) of the base pak
+    #[arg(short = 'k', long = "key")]
+    key: Option<String>,
+
+    /// Quiet mode (minimal output)
+    #[arg(short = 'q', long = "quiet")]
+    quiet: bool,
+}
+
+#[derive(Args, Debug)]
+struct ConfigArgs {
+    /// Game install, Content/Paks directory or mods folder to read
+    #[arg(value_name = "FOLDER")]
+    folder: PathBuf,
+
+    /// Config category to show, e.g. Engine or Game (default: all)
+    #[arg(value_name = "CATEGORY")]
+    category: Option<String>,
+
+    /// Only show this section
+    #[arg(long = "section", requires = "category")]
+    section: Option<String>,
+
+    /// Also merge the platform inis of this platform, e.g. Windows
+    #[arg(long = "platform")]
+    platform: Option<String>,
+
+    /// AES encryption key (hex format)
+    #[arg(short = 'k', long = "key")]
+    key: Option<String>,
+
+    /// Output in JSON format
+    #[arg(long = "json")]
+    json: bool,
 }
 
 /// Main CLI entry point
 pub fn run_cli() -> Result<()> {
     let cli = Cli::parse();
+    init_cli_logging(cli.verbose, cli.log_format);
     
     match cli.command {
         Commands::Unpack(args) => {
@@ -126,6 +253,18 @@ pub fn run_cli() -> Result<()> {
         Commands::Extract(args) => {
             handle_extract(args)
         }
+        Commands::Conflicts(args) => {
+            handle_conflicts(args)
+        }
+        Commands::Diff(args) => {
+            handle_diff(args)
+        }
+        Commands::MakePatch(args) => {
+            handle_make_patch(args)
+        }
+        Commands::Config(args) => {
+            handle_config(args)
+        }
     }
 }
 
@@ -136,12 +275,15 @@ fn handle_unpack(args: UnpackArgs) -> Result<()> {
         return Err(UeToolError::file_not_found(&args.pak_file));
     }
     
-    if !args.pak_file.extension().map_or(false, |ext| ext == "pak") {
+    if args.pak_file.extension().is_none_or(|ext| ext != "pak") {
         return Err(UeToolError::invalid_format("File must have .pak extension"));
     }
     
     // Create unpacker instance
     let mut unpacker = Unpacker::new();
+    if !args.quiet {
+        unpacker = unpacker.with_progress_callback(progress_bar_callback());
+    }
     
     // Build options
     let mut options = PakUnpackOptions::new()
@@ -181,7 +323,7 @@ fn handle_unpack(args: UnpackArgs) -> Result<()> {
             if !args.quiet {
                 eprintln!("Error unpacking PAK file: {}", e);
             }
-            Err(e)
+            Err(e.into())
         }
     }
 }
@@ -189,8 +331,8 @@ fn handle_unpack(args: UnpackArgs) -> Result<()> {
 /// Handle UTOC file operations (replicates: retoc_cli list <utoc_file> --json)
 fn handle_retoc(args: RetocArgs) -> Result<()> {
     match args.action {
-        RetocAction::List { utoc_file, json, key } => {
-            handle_retoc_list(utoc_file, json, key)
+        RetocAction::List { utoc_file, json, key, no_cache } => {
+            handle_retoc_list(utoc_file, json, key, no_cache)
         }
         RetocAction::Info { utoc_file, key } => {
             handle_retoc_info(utoc_file, key)
@@ -198,18 +340,18 @@ fn handle_retoc(args: RetocArgs) -> Result<()> {
     }
 }
 
-fn handle_retoc_list(utoc_file: PathBuf, json: bool, key: Option<String>) -> Result<()> {
+fn handle_retoc_list(utoc_file: PathBuf, json: bool, key: Option<String>, no_cache: bool) -> Result<()> {
     // Validate input file
     if !utoc_file.exists() {
         return Err(UeToolError::file_not_found(&utoc_file));
     }
     
-    if !utoc_file.extension().map_or(false, |ext| ext == "utoc") {
+    if utoc_file.extension().is_none_or(|ext| ext != "utoc") {
         return Err(UeToolError::invalid_format("File must have .utoc extension"));
     }
     
     // Create unpacker instance
-    let mut unpacker = Unpacker::new();
+    let mut unpacker = Unpacker::new().with_listing_cache(ListingCache::for_cli(no_cache));
     
     // Build options
     let mut options = UtocListOptions::new()
@@ -253,7 +395,7 @@ fn handle_retoc_list(utoc_file: PathBuf, json: bool, key: Option<String>) -> Res
             } else {
                 eprintln!("Error listing UTOC file: {}", e);
             }
-            Err(e)
+            Err(e.into())
         }
     }
 }
@@ -264,7 +406,7 @@ fn handle_retoc_info(utoc_file: PathBuf, key: Option<String>) -> Result<()> {
         return Err(UeToolError::file_not_found(&utoc_file));
     }
     
-    if !utoc_file.extension().map_or(false, |ext| ext == "utoc") {
+    if utoc_file.extension().is_none_or(|ext| ext != "utoc") {
         return Err(UeToolError::invalid_format("File must have .utoc extension"));
     }
     
@@ -281,9 +423,9 @@ fn handle_retoc_info(utoc_file: PathBuf, key: Option<String>) -> Result<()> {
     
     println!("Analyzing UTOC file: {}", utoc_file.display());
     
-    // For now, use the standard list function and format as info
-    match unpacker.list_utoc(&utoc_file, &options) {
-        Ok(asset_paths) => {
+    // Use the detailed listing and format it as info
+    match unpacker.utoc_lister.list_detailed(&utoc_file, &options) {
+        Ok(info) => {
             let info_output = serde_json::json!({
                 "file": utoc_file.to_string_lossy(),
                 "file_size": utoc_file.metadata().map(|m| m.len()).unwrap_or(0),
@@ -291,10 +433,11 @@ fn handle_retoc_info(utoc_file: PathBuf, key: Option<String>) -> Result<()> {
                     .ok()
                     .and_then(|m| m.modified().ok().map(|t| format!("{:?}", t)))
                     .unwrap_or_else(|| "unknown".to_string()),
-                "asset_count": asset_paths.len(),
-                "compression_methods": [],
+                "asset_count": info.assets.len(),
+                "compression_methods": info.metadata.compression_methods,
                 "encryption": key.is_some(),
-                "assets": asset_paths.iter().map(|p| p.as_str()).collect::<Vec<_>>()
+                "signed": info.metadata.signed,
+                "assets": info.assets.iter().map(|p| p.as_str()).collect::<Vec<_>>()
             });
             
             println!("{}", serde_json::to_string_pretty(&info_output)?);
@@ -324,14 +467,17 @@ fn handle_extract(args: ExtractArgs) -> Result<()> {
         .unwrap_or("")
         .to_lowercase();
     
-    if !matches!(ext.as_str(), "zip" | "rar") {
+    if !matches!(ext.as_str(), "zip" | "rar" | "7z") {
         return Err(UeToolError::invalid_argument(format!(
-            "Unsupported archive type: {}. Only ZIP and RAR are supported.", ext
+            "Unsupported archive type: {}. Only ZIP, RAR and 7z are supported.", ext
         )));
     }
     
     // Create unpacker instance
-    let mut unpacker = Unpacker::new();
+    let mut unpacker = Unpacker::new().with_listing_cache(ListingCache::for_cli(args.no_cache));
+    if !args.quiet {
+        unpacker = unpacker.with_progress_callback(progress_bar_callback());
+    }
     
     // Perform extraction
     if !args.quiet {
@@ -361,20 +507,222 @@ fn handle_extract(args: ExtractArgs) -> Result<()> {
             if !args.quiet {
                 eprintln!("Error extracting from archive: {}", e);
             }
-            Err(e)
+            Err(e.into())
+        }
+    }
+}
+
+/// Handle conflict detection over a folder of containers
+fn handle_conflicts(args: ConflictsArgs) -> Result<()> {
+    if !args.folder.is_dir() {
+        return Err(UeToolError::file_not_found(&args.folder));
+    }
+
+    let mut unpacker = Unpacker::new();
+    if !args.json {
+        unpacker = unpacker.with_progress_callback(progress_bar_callback());
+    }
+
+    let mut options = ConflictOptions::new().with_hash_contents(!args.no_hash);
+    if let Some(ref key) = args.key {
+        options = options.with_aes_key(key);
+    }
+
+    let report = unpacker.find_conflicts(&args.folder, &options)?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    for (path, error) in &report.errors {
+        eprintln!("Skipped {}: {}", path.display(), error);
+    }
+    println!(
+        "Found {} conflicting paths across {} containers ({} with differing contents)",
+        report.conflicts.len(),
+        report.containers,
+        report.differing().count()
+    );
+    for conflict in &report.conflicts {
+        let status = match conflict.contents_differ {
+            Some(true) => "differs",
+            Some(false) => "identical",
+            None => "unknown",
+        };
+        println!("{} [{}]", conflict.path, status);
+        for (i, provider) in conflict.providers.iter().enumerate() {
+            let marker = if i == 0 { "*" } else { " " };
+            let hash = provider.sha256.as_deref().map(|h| &h[..12]).unwrap_or("-");
+            println!("  {} {} (order {}, {})", marker, provider.container.display(), provider.order, hash);
+            if let Some(ref error) = provider.error {
+                println!("      error: {}", error);
+            }
         }
     }
+    Ok(())
+}
+
+/// Handle comparison of two containers
+fn handle_diff(args: DiffArgs) -> Result<()> {
+    let mut unpacker = Unpacker::new();
+    if !args.json {
+        unpacker = unpacker.with_progress_callback(progress_bar_callback());
+    }
+
+    let mut options = DiffOptions::new().with_package_level(args.packages);
+    if let Some(ref key) = args.key {
+        options = options.with_aes_key(key);
+    }
+
+    let report = unpacker.diff(&args.old, &args.new, &options)?;
+    let stat = report.stat();
+
+    if args.json {
+        let output = if args.stat {
+            serde_json::to_string_pretty(&stat)?
+        } else {
+            serde_json::to_string_pretty(&report)?
+        };
+        println!("{}", output);
+        return Ok(());
+    }
+
+    if !args.stat {
+        for entry in &report.entries {
+            let size = |d: &Option<crate::EntryDigest>| d.as_ref().map_or("-".to_string(), |d| d.size.to_string());
+            println!("{} {} ({} -> {})", entry.change, entry.path, size(&entry.old), size(&entry.new));
+        }
+    }
+    println!(
+        "{} added, {} removed, {} modified, {} unchanged (+{} / -{} bytes)",
+        stat.added, stat.removed, stat.modified, stat.unchanged, stat.bytes_added, stat.bytes_removed
+    );
+    Ok(())
+}
+
+/// Handle patch pak generation
+fn handle_make_patch(args: MakePatchArgs) -> Result<()> {
+    if !args.base.exists() {
+        return Err(UeToolError::file_not_found(&args.base));
+    }
+
+    let mut unpacker = Unpacker::new();
+    if !args.quiet {
+        unpacker = unpacker.with_progress_callback(progress_bar_callback());
+    }
+
+    let mut options = PatchOptions::new().with_force(args.force);
+    if let Some(ref key) = args.key {
+        options = options.with_aes_key(key);
+    }
+    if let Some(ref output) = args.output {
+        options = options.with_output_dir(output);
+    }
+
+    let result = unpacker.make_patch(&args.base, &args.edited, &options)?;
+
+    if !args.quiet {
+        println!(
+            "Wrote {} ({} added, {} modified, mount point {})",
+            result.path.display(),
+            result.added.len(),
+            result.modified.len(),
+            result.mount_point
+        );
+        for path in &result.removed {
+            println!("  not removed (patches cannot delete files): {}", path);
+        }
+    }
+    Ok(())
+}
+
+/// Handle showing the merged config of a game
+fn handle_config(args: ConfigArgs) -> Result<()> {
+    if !args.folder.is_dir() {
+        return Err(UeToolError::file_not_found(&args.folder));
+    }
+
+    let mut options = ConfigOptions::new();
+    if let Some(ref key) = args.key {
+        options = options.with_aes_key(key);
+    }
+    if let Some(ref platform) = args.platform {
+        options = options.with_platform(platform);
+    }
+
+    let set = Unpacker::new().read_configs(&args.folder, &options)?;
+    for (path, error) in &set.errors {
+        eprintln!("Skipped {}: {}", path, error);
+    }
+
+    let Some(ref category) = args.category else {
+        if args.json {
+            println!("{}", serde_json::to_string_pretty(&set.to_json())?);
+            return Ok(());
+        }
+        for config in set.configs.values() {
+            println!("{} ({} sections)", config.name, config.sections.len());
+            for source in &config.sources {
+                println!("  {} from {}", source.path, source.container.display());
+            }
+        }
+        return Ok(());
+    };
+
+    let mut config = set
+        .get(category)
+        .cloned()
+        .ok_or_else(|| UeToolError::invalid_argument(format!("No {} config found", category)))?;
+    if let Some(ref section) = args.section {
+        config.sections.retain(|s| s.name.eq_ignore_ascii_case(section));
+        if config.sections.is_empty() {
+            return Err(UeToolError::invalid_argument(format!("No [{}] section in the {} config", section, config.name)));
+        }
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&config.to_json())?);
+    } else {
+        print!("{}", config.to_ini());
+    }
+    Ok(())
+}
+
+/// Print a package listing as the `--packages` flag of the binaries shows it
+pub fn print_packages(packages: &[Package]) {
+    println!("Found {} packages:", packages.len());
+    for package in packages {
+        println!("  {}", package_line(package));
+    }
+}
+
+/// One row of a package listing; issues are labelled `ORPHAN` only when
+/// [`Package::is_orphan`] agrees, as the JSON `orphan_count` does
+fn package_line(package: &Package) -> String {
+    let kinds: Vec<String> = package.files.iter()
+        .map(|f| format!("{:?}", f.kind))
+        .collect();
+    let mut line = format!("{} ({} bytes) [{}]", package.name, package.total_size, kinds.join(", "));
+    if let Some(ref class) = package.class {
+        line.push_str(&format!(" {}", class));
+    }
+    if !package.issues.is_empty() {
+        let issues: Vec<String> = package.issues.iter().map(|i| i.to_string()).collect();
+        let label = if package.is_orphan() { "ORPHAN" } else { "ISSUES" };
+        line.push_str(&format!(" {}: {}", label, issues.join(", ")));
+    }
+    line
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tempfile::tempdir;
 
     #[test]
     fn test_cli_parsing() {
         // Test basic CLI parsing
-        let cli = Cli::parse_from(&["ue-tools", "unpack", "test.pak", "-o", "output", "-q", "-f"]);
+        let cli = Cli::parse_from(["ue-tools", "unpack", "test.pak", "-o", "output", "-q", "-f"]);
         
         match cli.command {
             Commands::Unpack(args) => {
@@ -387,16 +735,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_package_line() {
+        let mut package = Package {
+            name: "Game/Maps/Arena".to_string(),
+            files: Vec::new(),
+            total_size: 10,
+            issues: vec![crate::PackageIssue::DuplicateHeader],
+            class: None,
+        };
+        assert_eq!(package_line(&package), "Game/Maps/Arena (10 bytes) [] ISSUES: both .uasset and .umap");
+        package.issues = vec![crate::PackageIssue::MissingExports];
+        assert_eq!(package_line(&package), "Game/Maps/Arena (10 bytes) [] ORPHAN: missing .uexp");
+    }
+
+    #[test]
+    fn test_logging_flags_parsing() {
+        let cli = Cli::parse_from(["ue-tools", "unpack", "test.pak", "-o", "output", "-vv", "--log-format", "json"]);
+        assert_eq!(cli.verbose, 2);
+        assert_eq!(cli.log_format, LogFormat::Json);
+    }
+
     #[test]
     fn test_retoc_cli_parsing() {
-        let cli = Cli::parse_from(&["ue-tools", "retoc", "list", "test.utoc", "--json"]);
+        let cli = Cli::parse_from(["ue-tools", "retoc", "list", "test.utoc", "--json", "--no-cache"]);
         
         match cli.command {
             Commands::Retoc(args) => {
                 match args.action {
-                    RetocAction::List { utoc_file, json, .. } => {
+                    RetocAction::List { utoc_file, json, no_cache, .. } => {
                         assert_eq!(utoc_file, PathBuf::from("test.utoc"));
                         assert!(json);
+                        assert!(no_cache);
                     }
                     _ => panic!("Expected List subcommand"),
                 }
@@ -404,4 +774,65 @@ mod tests {
             _ => panic!("Expected Retoc command"),
         }
     }
+
+    #[test]
+    fn test_conflicts_cli_parsing() {
+        let cli = Cli::parse_from(["ue-tools", "conflicts", "~mods", "--json", "--no-hash"]);
+
+        match cli.command {
+            Commands::Conflicts(args) => {
+                assert_eq!(args.folder, PathBuf::from("~mods"));
+                assert!(args.json);
+                assert!(args.no_hash);
+            }
+            _ => panic!("Expected Conflicts command"),
+        }
+    }
+
+    #[test]
+    fn test_diff_cli_parsing() {
+        let cli = Cli::parse_from(["ue-tools", "diff", "old.pak", "new.utoc", "--stat", "--json"]);
+
+        match cli.command {
+            Commands::Diff(args) => {
+                assert_eq!(args.old, PathBuf::from("old.pak"));
+                assert_eq!(args.new, PathBuf::from("new.utoc"));
+                assert!(args.stat);
+                assert!(args.json);
+                assert!(!args.packages);
+            }
+            _ => panic!("Expected Diff command"),
+        }
+    }
+
+    #[test]
+    fn test_make_patch_cli_parsing() {
+        let cli = Cli::parse_from(["ue-tools", "make-patch", "Mod.pak", "Mod", "-o", "~mods", "-f"]);
+
+        match cli.command {
+            Commands::MakePatch(args) => {
+                assert_eq!(args.base, PathBuf::from("Mod.pak"));
+                assert_eq!(args.edited, PathBuf::from("Mod"));
+                assert_eq!(args.output, Some(PathBuf::from("~mods")));
+                assert!(args.force);
+            }
+            _ => panic!("Expected MakePatch command"),
+        }
+    }
+
+    #[test]
+    fn test_config_cli_parsing() {
+        let cli = Cli::parse_from(["ue-tools", "config", "MarvelRivals", "Engine", "--section", "Core.Log", "--platform", "Windows"]);
+
+        match cli.command {
+            Commands::Config(args) => {
+                assert_eq!(args.folder, PathBuf::from("MarvelRivals"));
+                assert_eq!(args.category.as_deref(), Some("Engine"));
+                assert_eq!(args.section.as_deref(), Some("Core.Log"));
+                assert_eq!(args.platform.as_deref(), Some("Windows"));
+                assert!(!args.json);
+            }
+            _ => panic!("Expected Config command"),
+        }
+    }
 }
\ No newline at end of file
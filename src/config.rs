@@ -0,0 +1,501 @@
+//! Reading and merging `.ini` config files from a game's containers
+//!
+//! UE builds each config category (`Engine`, `Game`, `Input`...) by layering
+//! ini files: the engine's `Base<Category>.ini`, its platform override, then
+//! the project's `Default<Category>.ini` and its platform override. Within a
+//! layer, each line of a section is applied to the values so far:
+//!
+//! - `Key=Value` replaces the first value of the key, or adds it;
+//! - `+Key=Value` adds the value unless the key already has it;
+//! - `.Key=Value` adds the value even if it is a duplicate;
+//! - `-Key=Value` removes the first copy of the value;
+//! - `!Key=...` removes every value of the key.
+//!
+//! Keys and section names are case-insensitive. Each ini path is read from
+//! the container that wins it under load order, so a mod shipping its own
+//! `DefaultGame.ini` replaces the game's before merging.
+
+use std::collections::{hash_map, BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::cancel;
+use crate::error::{Result, UeToolError};
+use crate::progress::Phase;
+use crate::types::ConfigOptions;
+use crate::vfs::{ContainerReader, GameInstall, VirtualFileSystem};
+use crate::Unpacker;
+
+/// How a line of an ini section changes the values of its key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum IniOp {
+    /// `Key=Value`
+    Set,
+    /// `+Key=Value`
+    AddUnique,
+    /// `.Key=Value`
+    Add,
+    /// `-Key=Value`
+    Remove,
+    /// `!Key=...`
+    Clear,
+}
+
+/// One `Key=Value` line of an ini file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IniLine {
+    pub op: IniOp,
+    pub key: String,
+    pub value: String,
+}
+
+/// A `[Section]` of an ini file, as written
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IniSection {
+    pub name: String,
+    pub lines: Vec<IniLine>,
+}
+
+/// A single parsed ini file, with its array operators not yet applied
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct IniFile {
+    pub sections: Vec<IniSection>,
+}
+
+impl IniFile {
+    /// Parse ini text
+    ///
+    /// Lines outside a section, comments (`;` or `#`) and lines with an
+    /// unknown prefix such as `@Key` are skipped. A quoted value has its
+    /// quotes removed and `\"` and `\\` unescaped.
+    pub fn parse(text: &str) -> Self {
+        let mut sections: Vec<IniSection> = Vec::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                sections.push(IniSection { name: name.trim().to_string(), lines: Vec::new() });
+                continue;
+            }
+            let (Some(section), Some((key, value))) = (sections.last_mut(), line.split_once('=')) else {
+                continue;
+            };
+
+            let (op, key) = match key.chars().next() {
+                Some('+') => (IniOp::AddUnique, &key[1..]),
+                Some('.') => (IniOp::Add, &key[1..]),
+                Some('-') => (IniOp::Remove, &key[1..]),
+                Some('!') => (IniOp::Clear, &key[1..]),
+                Some(c) if c.is_alphanumeric() || c == '_' => (IniOp::Set, key),
+                _ => continue,
+            };
+            let key = key.trim();
+            if key.is_empty() {
+                continue;
+            }
+            section.lines.push(IniLine { op, key: key.to_string(), value: unquote(value.trim()) });
+        }
+        IniFile { sections }
+    }
+
+    /// Parse an ini file's bytes, which may be UTF-8 or UTF-16 with a BOM
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let text = if let Some(utf16) = data.strip_prefix(&[0xFF, 0xFE]) {
+            let units: Vec<u16> = utf16.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+            String::from_utf16(&units).map_err(|e| UeToolError::invalid_format(format!("Invalid UTF-16 ini: {}", e)))?
+        } else {
+            let data = data.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(data);
+            String::from_utf8_lossy(data).into_owned()
+        };
+        Ok(Self::parse(&text))
+    }
+}
+
+/// Remove the quotes around a value, unescaping what they enclose
+fn unquote(value: &str) -> String {
+    let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).filter(|_| value.len() >= 2) else {
+        return value.to_string();
+    };
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some(next @ ('"' | '\\'))) => {
+                out.push(next);
+                chars.next();
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// A section with its operators applied
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigSection {
+    pub name: String,
+    /// Values in order; a key appears once per array element
+    pub values: Vec<(String, String)>,
+}
+
+impl ConfigSection {
+    /// The first value of `key`
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v.as_str())
+    }
+
+    /// Every value of `key`, for array keys
+    pub fn get_all(&self, key: &str) -> Vec<&str> {
+        self.values.iter().filter(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v.as_str()).collect()
+    }
+
+    fn apply(&mut self, line: &IniLine) {
+        let is_key = |k: &String| k.eq_ignore_ascii_case(&line.key);
+        match line.op {
+            IniOp::Set => match self.values.iter_mut().find(|(k, _)| is_key(k)) {
+                Some((_, value)) => value.clone_from(&line.value),
+                None => self.values.push((line.key.clone(), line.value.clone())),
+            },
+            IniOp::AddUnique => {
+                if !self.values.iter().any(|(k, v)| is_key(k) && *v == line.value) {
+                    self.values.push((line.key.clone(), line.value.clone()));
+                }
+            }
+            IniOp::Add => self.values.push((line.key.clone(), line.value.clone())),
+            IniOp::Remove => {
+                if let Some(index) = self.values.iter().position(|(k, v)| is_key(k) && *v == line.value) {
+                    self.values.remove(index);
+                }
+            }
+            IniOp::Clear => self.values.retain(|(k, _)| !is_key(k)),
+        }
+    }
+}
+
+/// An ini file that went into a merged config
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigSource {
+    /// Path of the ini in the virtual filesystem
+    pub path: String,
+    /// Container the path was read from
+    pub container: PathBuf,
+}
+
+/// The effective config of one category, e.g. `Engine`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Config {
+    pub name: String,
+    /// Ini files merged into this config, in the order they were applied
+    pub sources: Vec<ConfigSource>,
+    pub sections: Vec<ConfigSection>,
+}
+
+impl Config {
+    /// An empty config for category `name`
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self { name: name.into(), ..Default::default() }
+    }
+
+    /// Apply an ini file on top of the values so far
+    pub fn merge(&mut self, ini: &IniFile) {
+        for section in &ini.sections {
+            let index = match self.sections.iter().position(|s| s.name.eq_ignore_ascii_case(&section.name)) {
+                Some(index) => index,
+                None => {
+                    self.sections.push(ConfigSection { name: section.name.clone(), values: Vec::new() });
+                    self.sections.len() - 1
+                }
+            };
+            for line in &section.lines {
+                self.sections[index].apply(line);
+            }
+        }
+    }
+
+    /// The section called `name`
+    pub fn section(&self, name: &str) -> Option<&ConfigSection> {
+        self.sections.iter().find(|s| s.name.eq_ignore_ascii_case(name))
+    }
+
+    /// The first value of `key` in `section`
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.section(section)?.get(key)
+    }
+
+    /// Every value of `key` in `section`
+    pub fn get_all(&self, section: &str, key: &str) -> Vec<&str> {
+        self.section(section).map(|s| s.get_all(key)).unwrap_or_default()
+    }
+
+    /// Sections as a JSON object of keys; keys with several values become arrays
+    pub fn to_json(&self) -> Value {
+        let mut sections = Map::new();
+        for section in &self.sections {
+            let mut keys: Map<String, Value> = Map::new();
+            for (key, value) in &section.values {
+                match keys.get_mut(key) {
+                    Some(Value::Array(items)) => items.push(Value::String(value.clone())),
+                    Some(first) => *first = Value::Array(vec![first.take(), Value::String(value.clone())]),
+                    None => {
+                        keys.insert(key.clone(), Value::String(value.clone()));
+                    }
+                }
+            }
+            sections.insert(section.name.clone(), Value::Object(keys));
+        }
+        Value::Object(sections)
+    }
+
+    /// The merged values written back as ini text
+    pub fn to_ini(&self) -> String {
+        let mut out = String::new();
+        for (i, section) in self.sections.iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            out.push_str(&format!("[{}]\n", section.name));
+            for (key, value) in &section.values {
+                // Repeated keys need the add operator to survive a round trip
+                let repeated = section.values.iter().filter(|(k, _)| k.eq_ignore_ascii_case(key)).count() > 1;
+                out.push_str(&format!("{}{}={}\n", if repeated { "." } else { "" }, key, value));
+            }
+        }
+        out
+    }
+}
+
+/// The merged configs of a game install, by category
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConfigSet {
+    /// Configs by category name, e.g. `Engine`
+    pub configs: BTreeMap<String, Config>,
+    /// Ini files that could not be read, with the reason
+    pub errors: Vec<(String, String)>,
+}
+
+impl ConfigSet {
+    /// The config of category `name`, e.g. `Game`
+    pub fn get(&self, name: &str) -> Option<&Config> {
+        self.configs.iter().find(|(category, _)| category.eq_ignore_ascii_case(name)).map(|(_, config)| config)
+    }
+
+    /// Every config as a JSON object of categories
+    pub fn to_json(&self) -> Value {
+        Value::Object(self.configs.iter().map(|(name, config)| (name.clone(), config.to_json())).collect())
+    }
+}
+
+/// Where an ini file sits in the config hierarchy
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IniLayer {
+    category: String,
+    platform: Option<String>,
+    /// Shipped with the engine rather than the project
+    engine: bool,
+    plugin: bool,
+}
+
+impl IniLayer {
+    /// Classify `<Root>/[Plugins/<Name>/]Config/[<Platform>/]<Prefix><Category>.ini`
+    ///
+    /// `None` for inis outside a `Config` directory, such as plugin
+    /// descriptors or localization settings.
+    fn classify(path: &str) -> Option<Self> {
+        let parts: Vec<&str> = path.split('/').collect();
+        let config = parts.iter().rposition(|p| p.eq_ignore_ascii_case("Config"))?;
+        let (file, dirs) = parts[config + 1..].split_last()?;
+        let platform = match dirs {
+            [] => None,
+            [platform] => Some(platform.to_string()),
+            _ => return None,
+        };
+        let stem = strip_suffix_ignore_case(file, ".ini")?;
+
+        let category = ["Base", "Default"].iter().find_map(|prefix| strip_prefix_ignore_case(stem, prefix)).unwrap_or(stem);
+        let category = platform.as_deref().and_then(|p| strip_prefix_ignore_case(category, p)).unwrap_or(category);
+        if category.is_empty() {
+            return None;
+        }
+
+        Some(IniLayer {
+            category: category.to_string(),
+            platform,
+            engine: parts[0].eq_ignore_ascii_case("Engine"),
+            plugin: parts[..config].iter().any(|p| p.eq_ignore_ascii_case("Plugins")),
+        })
+    }
+
+    /// Whether the layer is merged for `platform`; platform layers need one
+    fn applies_to(&self, platform: Option<&str>) -> bool {
+        match (&self.platform, platform) {
+            (Some(layer), Some(wanted)) => layer.eq_ignore_ascii_case(wanted),
+            (Some(_), None) => false,
+            (None, _) => true,
+        }
+    }
+
+    /// Position in the merge order: engine, engine platform, project, project platform
+    fn rank(&self) -> (bool, bool, bool) {
+        (!self.engine, self.platform.is_some(), self.plugin)
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(value: &'a str, prefix: &str) -> Option<&'a str> {
+    value.get(..prefix.len()).filter(|head| head.eq_ignore_ascii_case(prefix)).map(|_| &value[prefix.len()..])
+}
+
+fn strip_suffix_ignore_case<'a>(value: &'a str, suffix: &str) -> Option<&'a str> {
+    let split = value.len().checked_sub(suffix.len())?;
+    value.get(split..).filter(|tail| tail.eq_ignore_ascii_case(suffix)).map(|_| &value[..split])
+}
+
+impl Unpacker {
+    /// Read and merge the config files of every container in a folder
+    ///
+    /// `folder` may be a game install, a `Content/Paks` directory or a bare
+    /// mods folder. Each ini path is read from the container that wins it
+    /// under load order; see [`crate::vfs`]. Platform inis are only merged
+    /// for [`ConfigOptions::platform`], and skipped when none is given.
+    pub fn read_configs<P: AsRef<Path>>(&mut self, folder: P, options: &ConfigOptions) -> Result<ConfigSet> {
+        let folder = folder.as_ref();
+        let _span = tracing::info_span!("read_configs", folder = %folder.display()).entered();
+
+        let install = match GameInstall::open(folder) {
+            Ok(install) => install,
+            Err(_) => GameInstall::from_paks_dir(folder)?,
+        };
+        let vfs = install.mount(options.aes_key.as_deref())?;
+        self.merge_configs(&vfs, options)
+    }
+
+    /// Merge the config files of an already mounted game
+    pub fn merge_configs(&mut self, vfs: &VirtualFileSystem, options: &ConfigOptions) -> Result<ConfigSet> {
+        let mut layers: Vec<(IniLayer, String)> = vfs
+            .files()
+            .into_iter()
+            .filter_map(|file| {
+                let layer = IniLayer::classify(file.path)?;
+                layer.applies_to(options.platform.as_deref()).then(|| (layer, file.path.to_string()))
+            })
+            .collect();
+        layers.sort_by(|(a, a_path), (b, b_path)| a.rank().cmp(&b.rank()).then_with(|| a_path.cmp(b_path)));
+
+        let mut set = ConfigSet::default();
+        let mut readers: HashMap<usize, ContainerReader> = HashMap::new();
+        let mut progress = self.progress.phase(Phase::Analyze, layers.len() as u64, 0);
+        for (layer, path) in layers {
+            cancel::check(self.cancellation.as_ref())?;
+            progress.entry_started(&path, 0);
+            let Some(&(container, raw)) = vfs.candidates(&path).first() else {
+                continue;
+            };
+            let reader = match readers.entry(container) {
+                hash_map::Entry::Occupied(slot) => Ok(slot.into_mut()),
+                hash_map::Entry::Vacant(slot) => vfs.open_container(container).map(|reader| slot.insert(reader)),
+            };
+            match reader.and_then(|reader| reader.read(raw)).and_then(|data| IniFile::from_bytes(&data)) {
+                Ok(ini) => {
+                    let config = set.configs.entry(layer.category.clone()).or_insert_with(|| Config::new(&layer.category));
+                    config.merge(&ini);
+                    config.sources.push(ConfigSource { path: path.clone(), container: vfs.containers()[container].path.clone() });
+                }
+                Err(e) => {
+                    tracing::warn!(path = %path, error = %e, "Failed to read config file");
+                    set.errors.push((path.clone(), e.to_string()));
+                }
+            }
+            progress.entry_finished(&path, 0);
+        }
+        progress.finish();
+        tracing::debug!(configs = set.configs.len(), "Merged config files");
+        Ok(set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = r#"
+; Engine defaults
+[/Script/Engine.Engine]
+bSmoothFrameRate=true
++ActiveGameNameRedirects=(OldGameName="A")
++ActiveGameNameRedirects=(OldGameName="B")
++Paths=Content
+
+[Core.Log]
+LogTemp=Log
+"#;
+
+    const DEFAULT: &str = r#"
+[/script/engine.engine]
+bSmoothFrameRate=False
+-ActiveGameNameRedirects=(OldGameName="A")
++ActiveGameNameRedirects=(OldGameName="B")
+.Paths=Content
+!Missing=ClearArray
+GameName="Marvel \"Rivals\""
+
+[Core.Log]
+!LogTemp=ClearArray
+@Ignored=Name
+"#;
+
+    #[test]
+    fn test_merge_ini() {
+        let default = IniFile::parse(DEFAULT);
+        assert_eq!(default.sections[1].lines, [IniLine { op: IniOp::Clear, key: "LogTemp".into(), value: "ClearArray".into() }]);
+
+        let mut config = Config::new("Engine");
+        config.merge(&IniFile::parse(BASE));
+        config.merge(&default);
+        assert_eq!(config.sections.len(), 2);
+        assert_eq!(config.get("/Script/Engine.Engine", "bsmoothframerate"), Some("False"));
+        assert_eq!(config.get_all("/Script/Engine.Engine", "ActiveGameNameRedirects"), ["(OldGameName=\"B\")"]);
+        assert_eq!(config.get_all("/Script/Engine.Engine", "Paths"), ["Content", "Content"]);
+        assert_eq!(config.get("/Script/Engine.Engine", "GameName"), Some("Marvel \"Rivals\""));
+        assert!(config.section("Core.Log").unwrap().values.is_empty());
+
+        let json = config.to_json();
+        assert_eq!(json["/Script/Engine.Engine"]["Paths"], serde_json::json!(["Content", "Content"]));
+        assert!(config.to_ini().contains("\n.Paths=Content\n.Paths=Content\n"));
+
+        // Removing a duplicated value only drops one copy
+        config.merge(&IniFile::parse("[/Script/Engine.Engine]\n-Paths=Content\n"));
+        assert_eq!(config.get_all("/Script/Engine.Engine", "Paths"), ["Content"]);
+
+        let utf16: Vec<u8> = [0xFF, 0xFE].into_iter().chain("[A]\nKey=1\n".encode_utf16().flat_map(u16::to_le_bytes)).collect();
+        assert_eq!(IniFile::from_bytes(&utf16).unwrap().sections[0].lines[0].value, "1");
+    }
+
+    #[test]
+    fn test_classify_layers() {
+        let layer = |path: &str| IniLayer::classify(path).map(|l| (l.category, l.platform, l.engine, l.plugin));
+        assert_eq!(layer("Engine/Config/BaseEngine.ini"), Some(("Engine".into(), None, true, false)));
+        assert_eq!(layer("Engine/Config/Windows/BaseWindowsEngine.ini"), Some(("Engine".into(), Some("Windows".into()), true, false)));
+        assert_eq!(layer("Marvel/Config/DefaultGame.ini"), Some(("Game".into(), None, false, false)));
+        assert_eq!(layer("Marvel/Config/Windows/WindowsEngine.ini"), Some(("Engine".into(), Some("Windows".into()), false, false)));
+        assert_eq!(layer("Marvel/Plugins/Wwise/Config/DefaultWwise.ini"), Some(("Wwise".into(), None, false, true)));
+        assert_eq!(layer("Engine/Config/Base.ini"), None);
+        assert_eq!(layer("Marvel/Content/Localization/Game/Game.ini"), None);
+
+        let mut ranks = [
+            IniLayer::classify("Marvel/Config/Windows/WindowsEngine.ini").unwrap().rank(),
+            IniLayer::classify("Marvel/Config/DefaultEngine.ini").unwrap().rank(),
+            IniLayer::classify("Engine/Config/BaseEngine.ini").unwrap().rank(),
+        ];
+        ranks.sort();
+        assert_eq!(ranks, [(false, false, false), (true, false, false), (true, true, false)]);
+
+        let windows = IniLayer::classify("Marvel/Config/Windows/WindowsEngine.ini").unwrap();
+        let default = IniLayer::classify("Marvel/Config/DefaultEngine.ini").unwrap();
+        assert!(windows.applies_to(Some("windows")) && !windows.applies_to(Some("Android")));
+        assert!(!windows.applies_to(None));
+        assert!(default.applies_to(None) && default.applies_to(Some("Android")));
+    }
+}
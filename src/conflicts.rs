@@ -0,0 +1,213 @@
+//! Detection of assets overridden by more than one container
+//!
+//! When two mods ship the same asset, the game loads one of them and the
+//! other is silently ignored. This module mounts a folder of containers as a
+//! [`VirtualFileSystem`], reports every path provided more than once with the
+//! winner under load order, and optionally hashes each copy to tell real
+//! conflicts from identical duplicates.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::cancel;
+use crate::error::Result;
+use crate::package::ContainerKind;
+use crate::progress::Phase;
+use crate::types::ConflictOptions;
+use crate::vfs::{GameInstall, VirtualFileSystem};
+use crate::Unpacker;
+
+/// One container providing a conflicting path
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictProvider {
+    pub container: PathBuf,
+    pub kind: ContainerKind,
+    pub order: i32,
+    /// SHA-256 of the entry, when contents were hashed
+    pub sha256: Option<String>,
+    /// Why the entry could not be read for hashing
+    pub error: Option<String>,
+}
+
+/// A path provided by more than one container
+#[derive(Debug, Clone, Serialize)]
+pub struct Conflict {
+    pub path: String,
+    /// Providers in load order, the winner first
+    pub providers: Vec<ConflictProvider>,
+    /// Whether the copies differ; `None` when they were not all hashed
+    pub contents_differ: Option<bool>,
+}
+
+impl Conflict {
+    /// The provider the game loads
+    pub fn winner(&self) -> &ConflictProvider {
+        &self.providers[0]
+    }
+
+    fn compare_hashes(&mut self) {
+        let hashes: Vec<_> = self.providers.iter().map(|p| p.sha256.as_deref()).collect();
+        let known: Vec<_> = hashes.iter().flatten().collect();
+        self.contents_differ = if known.windows(2).any(|w| w[0] != w[1]) {
+            Some(true)
+        } else if known.len() == hashes.len() {
+            Some(false)
+        } else {
+            None
+        };
+    }
+}
+
+/// Every conflict found in a folder of containers
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConflictReport {
+    /// Number of containers mounted
+    pub containers: usize,
+    /// Conflicts sorted by path
+    pub conflicts: Vec<Conflict>,
+    /// Containers that could not be mounted, with the reason
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl ConflictReport {
+    /// Conflicts whose copies are known to differ
+    pub fn differing(&self) -> impl Iterator<Item = &Conflict> {
+        self.conflicts.iter().filter(|c| c.contents_differ == Some(true))
+    }
+}
+
+impl Unpacker {
+    /// Find every asset path provided by more than one container in a folder
+    ///
+    /// `folder` may be a game install, a `Content/Paks` directory or a bare
+    /// mods folder. Winners follow the rules of [`VirtualFileSystem`]. With
+    /// [`ConflictOptions::hash_contents`] set, every copy is read and hashed;
+    /// note that an IoStore package and a legacy pak copy of the same asset
+    /// are stored in different formats and always hash differently.
+    pub fn find_conflicts<P: AsRef<Path>>(&mut self, folder: P, options: &ConflictOptions) -> Result<ConflictReport> {
+        let folder = folder.as_ref();
+        let _span = tracing::info_span!("find_conflicts", folder = %folder.display()).entered();
+
+        let install = match GameInstall::open(folder) {
+            Ok(install) => install,
+            Err(_) => GameInstall::from_paks_dir(folder)?,
+        };
+        let vfs = install.mount(options.aes_key.as_deref())?;
+
+        let mut conflicts: Vec<Conflict> = vfs
+            .overridden()
+            .into_iter()
+            .map(|file| Conflict {
+                path: file.path.to_string(),
+                providers: std::iter::once(file.container)
+                    .chain(file.shadowed)
+                    .map(|c| ConflictProvider {
+                        container: c.path.clone(),
+                        kind: c.kind,
+                        order: c.order,
+                        sha256: None,
+                        error: None,
+                    })
+                    .collect(),
+                contents_differ: None,
+            })
+            .collect();
+        tracing::debug!(conflicts = conflicts.len(), "Found overridden paths");
+
+        if options.hash_contents {
+            self.hash_conflicts(&vfs, &mut conflicts)?;
+        }
+
+        Ok(ConflictReport {
+            containers: vfs.containers().len(),
+            conflicts,
+            errors: vfs.errors().to_vec(),
+        })
+    }
+
+    /// Hash every copy of every conflict, opening each container once
+    fn hash_conflicts(&self, vfs: &VirtualFileSystem, conflicts: &mut [Conflict]) -> Result<()> {
+        // container index -> (conflict, provider, stored path)
+        let mut reads: BTreeMap<usize, Vec<(usize, usize, String)>> = BTreeMap::new();
+        for (i, conflict) in conflicts.iter().enumerate() {
+            for (j, (container, raw)) in vfs.candidates(&conflict.path).into_iter().enumerate() {
+                reads.entry(container).or_default().push((i, j, raw.to_string()));
+            }
+        }
+
+        let total = reads.values().map(Vec::len).sum::<usize>() as u64;
+        let mut progress = self.progress.phase(Phase::Hash, total, 0);
+
+        for (container, entries) in reads {
+            cancel::check(self.cancellation.as_ref())?;
+            let mut reader = match vfs.open_container(container) {
+                Ok(reader) => reader,
+                Err(e) => {
+                    for (i, j, _) in entries {
+                        conflicts[i].providers[j].error = Some(e.to_string());
+                    }
+                    continue;
+                }
+            };
+
+            for (i, j, raw) in entries {
+                cancel::check(self.cancellation.as_ref())?;
+                progress.entry_started(&raw, 0);
+                let provider = &mut conflicts[i].providers[j];
+                match reader.read(&raw) {
+                    Ok(data) => {
+                        provider.sha256 = Some(hex::encode(Sha256::digest(&data)));
+                        progress.entry_finished(&raw, data.len() as u64);
+                    }
+                    Err(e) => {
+                        provider.error = Some(e.to_string());
+                        progress.entry_finished(&raw, 0);
+                    }
+                }
+            }
+        }
+        progress.finish();
+
+        for conflict in conflicts.iter_mut() {
+            conflict.compare_hashes();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(sha256: Option<&str>) -> ConflictProvider {
+        ConflictProvider {
+            container: PathBuf::from("a.pak"),
+            kind: ContainerKind::Pak,
+            order: 3,
+            sha256: sha256.map(str::to_string),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_compare_hashes() {
+        let mut conflict = Conflict {
+            path: "Game/A.uasset".to_string(),
+            providers: vec![provider(Some("aa")), provider(Some("aa"))],
+            contents_differ: None,
+        };
+        conflict.compare_hashes();
+        assert_eq!(conflict.contents_differ, Some(false));
+
+        conflict.providers.push(provider(None));
+        conflict.compare_hashes();
+        assert_eq!(conflict.contents_differ, None);
+
+        conflict.providers.push(provider(Some("bb")));
+        conflict.compare_hashes();
+        assert_eq!(conflict.contents_differ, Some(true));
+    }
+}
@@ -0,0 +1,395 @@
+//! Package dependency graphs
+//!
+//! Shipping a working mod means shipping every package an asset pulls in.
+//! For paks the graph is built from each package's import table and soft
+//! package references (see [`crate::asset`]). For IoStore containers it comes
+//! from the store entries of the container header, which list the package
+//! IDs each package imports. Packages from outside the container are kept as
+//! leaf nodes without a path.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt::Write as _;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::asset::{ObjectRef, PackageSummary};
+use crate::binary::ReadExt;
+use crate::cancel::{self, PartialResult};
+use crate::error::{Result, UeToolError};
+use crate::package::{ContainerKind, PackageFileKind};
+use crate::progress::Phase;
+use crate::toc_index::TocIndex;
+use crate::types::{DependencyOptions, ExtractOptions, UnpackedFile};
+use crate::vfs::{normalize_path, ContainerReader};
+use crate::Unpacker;
+
+/// Signature at the start of an `FIoContainerHeader` (UE 5.0+)
+const CONTAINER_HEADER_SIGNATURE: u32 = 0x496F_436E;
+
+/// Container header version whose store entries no longer start with export counts (UE 5.3)
+const CONTAINER_HEADER_NO_EXPORT_INFO: u32 = 3;
+
+/// `EIoChunkType::ExportBundleData`, the chunk holding a package
+const CHUNK_TYPE_EXPORT_BUNDLE_DATA: u8 = 1;
+
+/// How a package depends on another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyKind {
+    /// Objects are imported; the package cannot load without it
+    Hard,
+    /// Referenced through a soft object path and loaded on demand
+    Soft,
+}
+
+/// An edge of the graph
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Dependency {
+    pub package: String,
+    pub kind: DependencyKind,
+}
+
+/// A package and the packages it depends on
+#[derive(Debug, Clone, Serialize)]
+pub struct PackageNode {
+    /// Package name, e.g. `/Game/UI/Icon`, or the hex package ID of an
+    /// IoStore import from another container
+    pub name: String,
+    /// Package path in the container without extension, e.g.
+    /// `Marvel/Content/UI/Icon`; `None` for packages from elsewhere
+    pub path: Option<String>,
+    pub dependencies: Vec<Dependency>,
+}
+
+/// Dependencies between the packages of a container
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DependencyGraph {
+    pub container: PathBuf,
+    /// Every package, keyed by name
+    pub packages: BTreeMap<String, PackageNode>,
+    /// Package headers that could not be parsed, with the reason
+    pub errors: Vec<(String, String)>,
+}
+
+impl DependencyGraph {
+    /// Look a package up by name or by container path, ignoring case
+    pub fn get(&self, package: &str) -> Option<&PackageNode> {
+        if let Some(node) = self.packages.get(package) {
+            return Some(node);
+        }
+        let name = package.to_lowercase();
+        let path = normalize_path(&name);
+        let path = PackageFileKind::classify(&path).1;
+        self.packages.values().find(|node| {
+            node.name.to_lowercase() == name || node.path.as_deref().is_some_and(|p| p.to_lowercase() == path)
+        })
+    }
+
+    /// Packages `package` depends on directly
+    pub fn dependencies(&self, package: &str) -> Vec<&PackageNode> {
+        self.get(package)
+            .map(|node| node.dependencies.iter().filter_map(|d| self.packages.get(&d.package)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Packages that depend on `package` directly
+    pub fn dependents(&self, package: &str) -> Vec<&PackageNode> {
+        let Some(target) = self.get(package) else {
+            return Vec::new();
+        };
+        self.packages
+            .values()
+            .filter(|node| node.dependencies.iter().any(|d| d.package == target.name))
+            .collect()
+    }
+
+    /// `package` and everything it depends on transitively, breadth first
+    pub fn closure(&self, package: &str, include_soft: bool) -> Vec<&PackageNode> {
+        let Some(root) = self.get(package) else {
+            return Vec::new();
+        };
+        let mut seen = HashSet::from([root.name.as_str()]);
+        let mut queue = VecDeque::from([root]);
+        let mut closure = Vec::new();
+        while let Some(node) = queue.pop_front() {
+            closure.push(node);
+            for dependency in &node.dependencies {
+                if (include_soft || dependency.kind == DependencyKind::Hard) && seen.insert(dependency.package.as_str()) {
+                    queue.extend(self.packages.get(&dependency.package));
+                }
+            }
+        }
+        closure
+    }
+
+    /// The graph in Graphviz DOT format; soft edges and outside packages are dashed
+    pub fn to_dot(&self) -> String {
+        let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+        let mut out = String::from("digraph dependencies {\n");
+        for node in self.packages.values() {
+            if node.path.is_none() {
+                let _ = writeln!(out, "  {} [style=dashed];", quote(&node.name));
+            }
+            for dependency in &node.dependencies {
+                let style = if dependency.kind == DependencyKind::Soft { " [style=dashed]" } else { "" };
+                let _ = writeln!(out, "  {} -> {}{};", quote(&node.name), quote(&dependency.package), style);
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    fn insert(&mut self, name: String, path: Option<String>, dependencies: Vec<Dependency>) {
+        for dependency in &dependencies {
+            self.packages
+                .entry(dependency.package.clone())
+                .or_insert_with(|| PackageNode { name: dependency.package.clone(), path: None, dependencies: Vec::new() });
+        }
+        let node = self
+            .packages
+            .entry(name.clone())
+            .or_insert_with(|| PackageNode { name, path: None, dependencies: Vec::new() });
+        node.path = path;
+        node.dependencies = dependencies;
+    }
+}
+
+impl Unpacker {
+    /// Build the dependency graph of the packages in a `.pak` or `.utoc`
+    ///
+    /// Unversioned paks need [`DependencyOptions::engine_version`] to parse
+    /// their package headers. IoStore containers need UE 5.0 or later.
+    pub fn dependency_graph<P: AsRef<Path>>(&mut self, container: P, options: &DependencyOptions) -> Result<DependencyGraph> {
+        let container = container.as_ref();
+        let _span = tracing::info_span!("dependency_graph", container = %container.display()).entered();
+
+        if !container.exists() {
+            return Err(UeToolError::file_not_found(container));
+        }
+        let graph = match ContainerKind::from_path(container) {
+            Some(ContainerKind::Pak) => self.pak_dependencies(container, options)?,
+            Some(ContainerKind::IoStore) => iostore_dependencies(container, options)?,
+            None => {
+                return Err(UeToolError::invalid_format(format!("{} is not a .pak or .utoc file", container.display())))
+            }
+        };
+        tracing::info!(packages = graph.packages.len(), errors = graph.errors.len(), "Built dependency graph");
+        Ok(graph)
+    }
+
+    /// Extract `package` and every package it depends on from `container`
+    ///
+    /// Packages of the closure that live outside the container (engine and
+    /// script packages, other containers) are skipped. Files are laid out as
+    /// by [`Unpacker::extract_many`].
+    pub fn extract_with_dependencies<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        container: P,
+        graph: &DependencyGraph,
+        package: &str,
+        dest_dir: Q,
+        include_soft: bool,
+        options: &ExtractOptions,
+    ) -> PartialResult<Vec<UnpackedFile>> {
+        let root = graph.get(package).ok_or_else(|| UeToolError::file_not_found(package))?;
+        let paths: Vec<&str> = graph
+            .closure(&root.name, include_soft)
+            .into_iter()
+            .filter_map(|node| node.path.as_deref())
+            .collect();
+        tracing::debug!(package = %root.name, packages = paths.len(), "Extracting dependency closure");
+        self.extract_many(container, &paths, dest_dir, options)
+    }
+
+    fn pak_dependencies(&mut self, container: &Path, options: &DependencyOptions) -> Result<DependencyGraph> {
+        let mut reader = ContainerReader::open(container, ContainerKind::Pak, options.aes_key.as_deref())?;
+        let headers: Vec<(String, String)> = reader
+            .entries()
+            .into_iter()
+            .map(|(full, raw)| (normalize_path(&full), raw))
+            .filter(|(full, _)| PackageFileKind::classify(full).0.is_header())
+            .collect();
+
+        let mut graph = DependencyGraph { container: container.to_path_buf(), ..Default::default() };
+        let mut progress = self.progress.phase(Phase::Analyze, headers.len() as u64, 0);
+        for (path, raw) in &headers {
+            cancel::check(self.cancellation.as_ref())?;
+            progress.entry_started(path, 0);
+            let data = reader.read(raw)?;
+            let dependencies = match PackageSummary::parse(&data, options.engine_version) {
+                Ok(summary) => legacy_dependencies(&summary, options.include_script),
+                // Unversioned and no engine version given: every package would fail the same way
+                Err(e @ UeToolError::InvalidArgument(_)) => return Err(e),
+                Err(e) => {
+                    tracing::warn!(path = %path, error = %e, "Failed to parse package header");
+                    graph.errors.push((path.clone(), e.to_string()));
+                    Vec::new()
+                }
+            };
+            progress.entry_finished(path, data.len() as u64);
+
+            let package_path = PackageFileKind::classify(path).1;
+            graph.insert(package_name(package_path), Some(package_path.to_string()), dependencies);
+        }
+        progress.finish();
+        Ok(graph)
+    }
+}
+
+fn iostore_dependencies(container: &Path, options: &DependencyOptions) -> Result<DependencyGraph> {
+    let toc = TocIndex::open(container, options.aes_key.as_deref())?;
+    let paths: HashMap<u64, String> = toc
+        .chunks
+        .iter()
+        .filter(|chunk| chunk.chunk_type == CHUNK_TYPE_EXPORT_BUNDLE_DATA)
+        .filter_map(|chunk| {
+            let path = normalize_path(chunk.path.as_deref()?);
+            let id = u64::from_le_bytes(chunk.id[..8].try_into().ok()?);
+            Some((id, PackageFileKind::classify(&path).1.to_string()))
+        })
+        .collect();
+
+    let reader = ContainerReader::open(container, ContainerKind::IoStore, options.aes_key.as_deref())?;
+    let data = reader
+        .read_chunk_of_type(retoc::EIoChunkType::ContainerHeader)?
+        .ok_or_else(|| UeToolError::UtocError(format!("{} has no container header", container.display())))?;
+
+    let name_of = |id: u64| paths.get(&id).map_or_else(|| format!("0x{:016x}", id), |path| package_name(path));
+    let mut graph = DependencyGraph { container: container.to_path_buf(), ..Default::default() };
+    for (id, imported) in parse_container_header(&data)? {
+        let dependencies = imported
+            .into_iter()
+            .map(|import| Dependency { package: name_of(import), kind: DependencyKind::Hard })
+            .collect();
+        graph.insert(name_of(id), paths.get(&id).cloned(), dependencies);
+    }
+    Ok(graph)
+}
+
+/// Packages a legacy package imports from, then the ones it soft references
+fn legacy_dependencies(summary: &PackageSummary, include_script: bool) -> Vec<Dependency> {
+    let hard = summary
+        .imports
+        .iter()
+        .filter(|import| import.outer == ObjectRef::Null && import.class_name.as_deref() == Some("Package"))
+        .filter_map(|import| Some((import.object_name.clone()?, DependencyKind::Hard)));
+    let soft = summary.soft_package_references.iter().map(|p| (p.clone(), DependencyKind::Soft));
+
+    let mut dependencies: Vec<Dependency> = Vec::new();
+    for (package, kind) in hard.chain(soft) {
+        let skip = (!include_script && package.starts_with("/Script/"))
+            || summary.package_name.as_ref() == Some(&package)
+            || dependencies.iter().any(|d| d.package == package);
+        if !skip {
+            dependencies.push(Dependency { package, kind });
+        }
+    }
+    dependencies
+}
+
+/// Each package's ID and the IDs of the packages it imports, from an `FIoContainerHeader`
+fn parse_container_header(data: &[u8]) -> Result<Vec<(u64, Vec<u64>)>> {
+    let mut r = Cursor::new(data);
+    if r.read_u32()? != CONTAINER_HEADER_SIGNATURE {
+        return Err(UeToolError::UtocError(
+            "Unsupported container header; only UE 5.0 and later are supported".to_string(),
+        ));
+    }
+    let version = r.read_u32()?;
+    r.read_u64()?; // ContainerId
+    let package_ids = r.read_array_of(|r| r.read_u64())?;
+    let store_entries_len = r.read_count()?;
+    let store_entries = r.read_bytes(store_entries_len)?;
+
+    // FFilePackageStoreEntry: [ExportCount, ExportBundleCount,] ImportedPackages, ShaderMapHashes;
+    // each array is (count, offset from the array field itself)
+    let (entry_size, imports_field) = if version >= CONTAINER_HEADER_NO_EXPORT_INFO { (16, 0) } else { (24, 8) };
+    let truncated = || UeToolError::UtocError("Container header store entries are truncated".to_string());
+    package_ids
+        .into_iter()
+        .enumerate()
+        .map(|(i, id)| {
+            let field = i * entry_size + imports_field;
+            let view = store_entries.get(field..field + 8).ok_or_else(truncated)?;
+            let count = u32::from_le_bytes([view[0], view[1], view[2], view[3]]) as usize;
+            let start = field + u32::from_le_bytes([view[4], view[5], view[6], view[7]]) as usize;
+            let imports = store_entries.get(start..start + count * 8).ok_or_else(truncated)?;
+            Ok((id, imports.chunks_exact(8).map(|c| u64::from_le_bytes(c.try_into().unwrap())).collect()))
+        })
+        .collect()
+}
+
+/// Package name of a package path: `Marvel/Content/UI/Icon` is `/Game/UI/Icon`,
+/// `Engine/Content/X` is `/Engine/X` and `.../Plugins/Foo/Content/X` is `/Foo/X`
+fn package_name(path: &str) -> String {
+    let Some(at) = path.to_ascii_lowercase().find("/content/") else {
+        return format!("/{}", path);
+    };
+    let (root, rest) = (&path[..at], &path[at + "/content/".len()..]);
+    let mount = match root.rsplit_once('/') {
+        None if root.eq_ignore_ascii_case("engine") => "Engine",
+        None => "Game",
+        Some((_, plugin)) => plugin,
+    };
+    format!("/{}/{}", mount, rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph() -> DependencyGraph {
+        let hard = |p: &str| Dependency { package: p.to_string(), kind: DependencyKind::Hard };
+        let mut graph = DependencyGraph::default();
+        graph.insert("/Game/UI/A".into(), Some("Marvel/Content/UI/A".into()), vec![hard("/Game/UI/B")]);
+        graph.insert(
+            "/Game/UI/B".into(),
+            Some("Marvel/Content/UI/B".into()),
+            vec![hard("/Engine/Tex"), Dependency { package: "/Game/UI/C".into(), kind: DependencyKind::Soft }],
+        );
+        graph.insert("/Game/UI/C".into(), Some("Marvel/Content/UI/C".into()), Vec::new());
+        graph
+    }
+
+    #[test]
+    fn test_graph_queries() {
+        let graph = graph();
+        let names = |nodes: Vec<&PackageNode>| nodes.into_iter().map(|n| n.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(graph.dependents("Marvel/Content/UI/B.uasset")), ["/Game/UI/A"]);
+        assert_eq!(names(graph.closure("/game/ui/a", false)), ["/Game/UI/A", "/Game/UI/B", "/Engine/Tex"]);
+        assert_eq!(graph.closure("/Game/UI/A", true).len(), 4);
+        assert!(graph.to_dot().contains("\"/Game/UI/B\" -> \"/Game/UI/C\" [style=dashed];"));
+    }
+
+    #[test]
+    fn test_package_name() {
+        assert_eq!(package_name("Marvel/Content/UI/Icon"), "/Game/UI/Icon");
+        assert_eq!(package_name("Engine/Content/Tex"), "/Engine/Tex");
+        assert_eq!(package_name("Marvel/Plugins/Wwise/Content/Bank"), "/Wwise/Bank");
+    }
+
+    #[test]
+    fn test_parse_container_header() {
+        let mut data = Vec::new();
+        for v in [CONTAINER_HEADER_SIGNATURE, CONTAINER_HEADER_NO_EXPORT_INFO] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        data.extend_from_slice(&7u64.to_le_bytes());
+        data.extend_from_slice(&2i32.to_le_bytes());
+        data.extend_from_slice(&0xAAu64.to_le_bytes());
+        data.extend_from_slice(&0xBBu64.to_le_bytes());
+
+        // Two 16 byte entries, then the imports of the first
+        let mut store = Vec::new();
+        for v in [1u32, 32, 0, 0, 0, 0, 0, 0] {
+            store.extend_from_slice(&v.to_le_bytes());
+        }
+        store.extend_from_slice(&0xBBu64.to_le_bytes());
+        data.extend_from_slice(&(store.len() as i32).to_le_bytes());
+        data.extend_from_slice(&store);
+
+        assert_eq!(parse_container_header(&data).unwrap(), [(0xAA, vec![0xBB]), (0xBB, vec![])]);
+    }
+}
@@ -0,0 +1,418 @@
+//! Comparison of two containers or loose directories
+//!
+//! Either side of a diff may be a `.pak`, a `.utoc` or a directory of loose
+//! files such as an unpacked pak. Entries are matched by their normalized
+//! path, case-insensitively, and compared by size and SHA-1/SHA-256.
+//!
+//! A legacy package and its IoStore conversion are stored in different
+//! formats, so a pak and a `.utoc` are compared at the package level instead:
+//! the `.uasset` header is skipped, the export data of the `.uexp` is matched
+//! against the export data following the zen header, and bulk data files are
+//! compared as they are.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::cancel;
+use crate::error::{Result, UeToolError};
+use crate::package::{ContainerKind, PackageFileKind};
+use crate::progress::{Phase, PhaseProgress};
+use crate::types::DiffOptions;
+use crate::vfs::{normalize_path, ContainerReader};
+use crate::Unpacker;
+
+/// Tag ending every legacy `.uexp` and `.uasset`
+const PACKAGE_FILE_TAG: [u8; 4] = 0x9E2A83C1u32.to_le_bytes();
+
+/// Matching key -> (display name, files with their role and stored path)
+type EntryGroups = BTreeMap<String, (String, Vec<(PackageFileKind, String)>)>;
+
+/// Granularity at which two sides are compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiffLevel {
+    /// Every file entry separately
+    File,
+    /// One entry per package, comparing export and bulk data only
+    Package,
+}
+
+/// How an entry changed between the old and the new side
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+impl std::fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChangeKind::Added => write!(f, "A"),
+            ChangeKind::Removed => write!(f, "D"),
+            ChangeKind::Modified => write!(f, "M"),
+        }
+    }
+}
+
+/// Size and content hashes of one side of an entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryDigest {
+    pub size: u64,
+    pub sha1: String,
+    pub sha256: String,
+}
+
+impl EntryDigest {
+    fn of(parts: &[&[u8]]) -> Self {
+        let mut sha1 = Sha1::new();
+        let mut sha256 = Sha256::new();
+        for part in parts {
+            sha1.update(part);
+            sha256.update(part);
+        }
+        Self {
+            size: parts.iter().map(|p| p.len() as u64).sum(),
+            sha1: hex::encode(sha1.finalize()),
+            sha256: hex::encode(sha256.finalize()),
+        }
+    }
+}
+
+/// An entry that differs between the two sides
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffEntry {
+    /// File path, or package name at [`DiffLevel::Package`]
+    pub path: String,
+    pub change: ChangeKind,
+    pub old: Option<EntryDigest>,
+    pub new: Option<EntryDigest>,
+}
+
+/// Counts and byte totals of a diff
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffStat {
+    pub added: usize,
+    pub removed: usize,
+    pub modified: usize,
+    pub unchanged: usize,
+    pub bytes_added: u64,
+    pub bytes_removed: u64,
+}
+
+/// Result of comparing two containers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffReport {
+    pub old: PathBuf,
+    pub new: PathBuf,
+    pub level: DiffLevel,
+    /// Changed entries sorted by path
+    pub entries: Vec<DiffEntry>,
+    /// Number of entries identical on both sides
+    pub unchanged: usize,
+}
+
+impl DiffReport {
+    /// Whether both sides have the same contents
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Changed entries of one kind
+    pub fn changes(&self, change: ChangeKind) -> impl Iterator<Item = &DiffEntry> {
+        self.entries.iter().filter(move |e| e.change == change)
+    }
+
+    /// Summary counts, as shown by `--stat`
+    ///
+    /// A modified entry counts its old size as removed and its new size as added.
+    pub fn stat(&self) -> DiffStat {
+        let mut stat = DiffStat { unchanged: self.unchanged, ..DiffStat::default() };
+        for entry in &self.entries {
+            match entry.change {
+                ChangeKind::Added => stat.added += 1,
+                ChangeKind::Removed => stat.removed += 1,
+                ChangeKind::Modified => stat.modified += 1,
+            }
+            stat.bytes_removed += entry.old.as_ref().map_or(0, |d| d.size);
+            stat.bytes_added += entry.new.as_ref().map_or(0, |d| d.size);
+        }
+        stat
+    }
+}
+
+/// One side of a diff
+enum Source {
+    Container(ContainerKind, ContainerReader),
+    /// Loose files, with the prefix their relative paths are stored under
+    Directory(PathBuf, String),
+}
+
+impl Source {
+    fn open(path: &Path, aes_key: Option<&str>) -> Result<Self> {
+        if path.is_dir() {
+            return Ok(Source::Directory(path.to_path_buf(), String::new()));
+        }
+        if !path.exists() {
+            return Err(UeToolError::file_not_found(path));
+        }
+
+        let kind = ContainerKind::from_path(path).ok_or_else(|| {
+            UeToolError::invalid_format(format!("{} is not a .pak, a .utoc or a directory", path.display()))
+        })?;
+        Ok(Source::Container(kind, ContainerReader::open(path, kind, aes_key)?))
+    }
+
+    /// Loose files are laid out like a legacy pak
+    fn kind(&self) -> ContainerKind {
+        match self {
+            Source::Container(kind, _) => *kind,
+            Source::Directory(..) => ContainerKind::Pak,
+        }
+    }
+
+    /// Every entry as (normalized path, path to read it by)
+    fn entries(&self) -> Vec<(String, String)> {
+        match self {
+            Source::Container(_, reader) => reader.entries().into_iter().map(|(full, raw)| (normalize_path(&full), raw)).collect(),
+            Source::Directory(root, prefix) => WalkDir::new(root)
+                .into_iter()
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().is_file())
+                .filter_map(|entry| {
+                    let relative = entry.path().strip_prefix(root).ok()?.to_string_lossy().replace('\\', "/");
+                    Some((format!("{}{}", prefix, relative), entry.path().to_string_lossy().into_owned()))
+                })
+                .collect(),
+        }
+    }
+
+    fn read(&mut self, raw: &str) -> Result<Vec<u8>> {
+        match self {
+            Source::Container(_, reader) => reader.read(raw),
+            Source::Directory(..) => {
+                fs::read(raw).map_err(|e| UeToolError::IoError(format!("Failed to read {}: {}", raw, e)))
+            }
+        }
+    }
+}
+
+impl Unpacker {
+    /// Compare two containers or directories and report added, removed and modified entries
+    ///
+    /// `old` and `new` may each be a `.pak`, a `.utoc` or a directory of loose
+    /// files. Comparing an IoStore container with anything else, or setting
+    /// [`DiffOptions::package_level`], compares packages rather than files;
+    /// see the module documentation for what that compares.
+    pub fn diff<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, old: P, new: Q, options: &DiffOptions) -> Result<DiffReport> {
+        let (old, new) = (old.as_ref(), new.as_ref());
+        let _span = tracing::info_span!("diff", old = %old.display(), new = %new.display()).entered();
+
+        let aes_key = options.aes_key.as_deref();
+        let old_source = Source::open(old, aes_key)?;
+        let new_source = Source::open(new, aes_key)?;
+        self.diff_sources(old, new, old_source, new_source, options)
+    }
+
+    /// Compare a container with a directory whose files are relative to `prefix`
+    ///
+    /// A pak unpacked by [`crate::PakUnpacker`] is relative to its mount
+    /// point, which `prefix` puts back.
+    pub(crate) fn diff_tree(&mut self, old: &Path, tree: &Path, prefix: &str, options: &DiffOptions) -> Result<DiffReport> {
+        let _span = tracing::info_span!("diff", old = %old.display(), new = %tree.display()).entered();
+
+        let old_source = Source::open(old, options.aes_key.as_deref())?;
+        let new_source = Source::Directory(tree.to_path_buf(), prefix.to_string());
+        self.diff_sources(old, tree, old_source, new_source, options)
+    }
+
+    fn diff_sources(
+        &mut self,
+        old: &Path,
+        new: &Path,
+        mut old_source: Source,
+        mut new_source: Source,
+        options: &DiffOptions,
+    ) -> Result<DiffReport> {
+        let level = if options.package_level || old_source.kind() != new_source.kind() {
+            DiffLevel::Package
+        } else {
+            DiffLevel::File
+        };
+
+        let old_entries = group_entries(old_source.entries(), level);
+        let new_entries = group_entries(new_source.entries(), level);
+        let total = old_entries.values().chain(new_entries.values()).map(|(_, files)| files.len()).sum::<usize>();
+        let mut progress = self.progress.phase(Phase::Hash, total as u64, 0);
+
+        let old_digests = self.digest_entries(&mut old_source, &old_entries, level, &mut progress)?;
+        let new_digests = self.digest_entries(&mut new_source, &new_entries, level, &mut progress)?;
+        progress.finish();
+
+        let keys: BTreeSet<&String> = old_entries.keys().chain(new_entries.keys()).collect();
+        let mut entries = Vec::new();
+        let mut unchanged = 0;
+        for key in keys {
+            let old_digest = old_digests.get(key).cloned();
+            let new_digest = new_digests.get(key).cloned();
+            let change = match (&old_digest, &new_digest) {
+                (None, Some(_)) => ChangeKind::Added,
+                (Some(_), None) => ChangeKind::Removed,
+                (Some(a), Some(b)) if a != b => ChangeKind::Modified,
+                _ => {
+                    unchanged += 1;
+                    continue;
+                }
+            };
+            let path = new_entries.get(key).or_else(|| old_entries.get(key)).map(|(name, _)| name.clone()).unwrap_or_default();
+            entries.push(DiffEntry { path, change, old: old_digest, new: new_digest });
+        }
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        tracing::debug!(changed = entries.len(), unchanged, "Compared containers");
+        Ok(DiffReport {
+            old: old.to_path_buf(),
+            new: new.to_path_buf(),
+            level,
+            entries,
+            unchanged,
+        })
+    }
+
+    fn digest_entries(
+        &self,
+        source: &mut Source,
+        entries: &EntryGroups,
+        level: DiffLevel,
+        progress: &mut PhaseProgress,
+    ) -> Result<BTreeMap<String, EntryDigest>> {
+        let container = source.kind();
+        let mut digests = BTreeMap::new();
+        for (key, (_, files)) in entries {
+            let mut parts = Vec::with_capacity(files.len());
+            for (file_kind, raw) in files {
+                cancel::check(self.cancellation.as_ref())?;
+                progress.entry_started(raw, 0);
+                let data = source.read(raw)?;
+                progress.entry_finished(raw, data.len() as u64);
+                parts.push((*file_kind, data));
+            }
+
+            let digest = match level {
+                DiffLevel::File => EntryDigest::of(&[&parts[0].1]),
+                DiffLevel::Package => {
+                    let has_exports = parts.iter().any(|(kind, _)| *kind == PackageFileKind::Exports);
+                    let payloads: Vec<&[u8]> = parts
+                        .iter()
+                        .map(|(kind, data)| package_payload(container, *kind, has_exports, data))
+                        .collect();
+                    EntryDigest::of(&payloads)
+                }
+            };
+            digests.insert(key.clone(), digest);
+        }
+        Ok(digests)
+    }
+}
+
+/// Group entries by lowercased path, or by package name at the package level
+///
+/// Files are sorted by role, so parts of a package are hashed in the same
+/// order on both sides.
+fn group_entries(entries: Vec<(String, String)>, level: DiffLevel) -> EntryGroups {
+    let mut groups = EntryGroups::new();
+    for (path, raw) in entries {
+        let (kind, name) = match level {
+            DiffLevel::File => (PackageFileKind::Other, path.as_str()),
+            DiffLevel::Package => PackageFileKind::classify(&path),
+        };
+        groups
+            .entry(name.to_lowercase())
+            .or_insert_with(|| (name.to_string(), Vec::new()))
+            .1
+            .push((kind, raw));
+    }
+    for (_, files) in groups.values_mut() {
+        files.sort();
+    }
+    groups
+}
+
+/// The bytes of a package file that survive conversion between pak and IoStore
+///
+/// Legacy headers are dropped when a `.uexp` holds the exports, and the
+/// trailing package tag is stripped from `.uexp` files. An IoStore package
+/// chunk is reduced to the export data following its zen header, whose size
+/// is the second `u32` of the header.
+fn package_payload(container: ContainerKind, kind: PackageFileKind, has_exports: bool, data: &[u8]) -> &[u8] {
+    match (container, kind) {
+        (ContainerKind::Pak, PackageFileKind::Asset | PackageFileKind::Map) if has_exports => &[],
+        (ContainerKind::Pak, PackageFileKind::Exports) => data.strip_suffix(&PACKAGE_FILE_TAG).unwrap_or(data),
+        (ContainerKind::IoStore, PackageFileKind::Asset | PackageFileKind::Map) => {
+            let header_size = data
+                .get(4..8)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+                .filter(|&size| size <= data.len());
+            header_size.map_or(data, |size| &data[size..])
+        }
+        _ => data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_payload_matches_across_formats() {
+        let exports = [1u8, 2, 3, 4];
+        let mut uexp = exports.to_vec();
+        uexp.extend_from_slice(&PACKAGE_FILE_TAG);
+        let mut zen = vec![0u8, 0, 0, 0, 12, 0, 0, 0, 9, 9, 9, 9];
+        zen.extend_from_slice(&exports);
+
+        let pak = package_payload(ContainerKind::Pak, PackageFileKind::Exports, true, &uexp);
+        let iostore = package_payload(ContainerKind::IoStore, PackageFileKind::Asset, false, &zen);
+        assert_eq!(pak, iostore);
+        assert!(package_payload(ContainerKind::Pak, PackageFileKind::Asset, true, b"header").is_empty());
+    }
+
+    #[test]
+    fn test_group_entries_by_package() {
+        let entries = vec![
+            ("Game/Content/Hero.uexp".to_string(), "Hero.uexp".to_string()),
+            ("Game/Content/Hero.uasset".to_string(), "Hero.uasset".to_string()),
+            ("Game/Content/hero.ubulk".to_string(), "hero.ubulk".to_string()),
+        ];
+        let groups = group_entries(entries, DiffLevel::Package);
+        let (name, files) = &groups["game/content/hero"];
+        assert_eq!(name, "Game/Content/Hero");
+        assert_eq!(
+            files.iter().map(|(kind, _)| *kind).collect::<Vec<_>>(),
+            [PackageFileKind::Asset, PackageFileKind::Exports, PackageFileKind::Bulk]
+        );
+    }
+
+    #[test]
+    fn test_stat() {
+        let digest = |size| Some(EntryDigest { size, sha1: String::new(), sha256: String::new() });
+        let report = DiffReport {
+            old: PathBuf::from("a.pak"),
+            new: PathBuf::from("b.pak"),
+            level: DiffLevel::File,
+            entries: vec![
+                DiffEntry { path: "A".into(), change: ChangeKind::Added, old: None, new: digest(10) },
+                DiffEntry { path: "B".into(), change: ChangeKind::Modified, old: digest(5), new: digest(7) },
+            ],
+            unchanged: 3,
+        };
+        let stat = report.stat();
+        assert_eq!((stat.added, stat.modified, stat.removed, stat.unchanged), (1, 1, 0, 3));
+        assert_eq!((stat.bytes_added, stat.bytes_removed), (17, 5));
+    }
+}
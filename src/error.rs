@@ -113,6 +113,7 @@ impl From<std::io::Error> for UeToolError {
     }
 }
 
+#[cfg(feature = "archive")]
 impl From<zip::result::ZipError> for UeToolError {
     fn from(error: zip::result::ZipError) -> Self {
         Self::IoError(format!("Zip error: {}", error))
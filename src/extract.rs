@@ -0,0 +1,276 @@
+//! Extraction of selected entries without unpacking a whole container
+//!
+//! Entries are looked up by any of the names a user is likely to have at hand:
+//!
+//! - the path with the mount point, with or without the `../../../` prefix,
+//!   e.g. `Marvel/Content/UI/Icon.uasset`;
+//! - the path relative to a pak's mount point;
+//! - a package name without extension, e.g. `Marvel/Content/UI/Icon`, or a
+//!   `/Game/UI/Icon` package path, which select every file of the package.
+//!
+//! Matching is case-insensitive. Works for `.pak` and `.utoc` containers.
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::cancel::{self, Interrupted, PartialResult};
+use crate::error::{Result, UeToolError};
+use crate::package::{ContainerKind, PackageFileKind};
+use crate::progress::Phase;
+use crate::types::{AssetPath, ExtractOptions, UnpackedFile};
+use crate::vfs::{normalize_path, ContainerReader};
+use crate::Unpacker;
+
+/// An entry of an open container: (path with the mount point, normalized; path as stored)
+pub(crate) type Entry = (String, String);
+
+impl Unpacker {
+    /// Read the bytes of one entry into memory
+    ///
+    /// A package name selects the package header (`.uasset` or `.umap`).
+    pub fn read_entry<P: AsRef<Path>>(&mut self, container: P, path: &str, options: &ExtractOptions) -> Result<Vec<u8>> {
+        let (mut reader, entries) = open_container(container.as_ref(), options)?;
+        let entry = select_one(&entries, path)?;
+        reader.read(&entry.1)
+    }
+
+    /// Read a package's header followed by its `.uexp`, if it has one
+    ///
+    /// This is the layout export offsets are relative to. `path` may name the
+    /// package or any of its header and export files.
+    pub fn read_package<P: AsRef<Path>>(&mut self, container: P, path: &str, options: &ExtractOptions) -> Result<Vec<u8>> {
+        let (mut reader, entries) = open_container(container.as_ref(), options)?;
+        let package = match PackageFileKind::classify(path) {
+            (PackageFileKind::Asset | PackageFileKind::Map | PackageFileKind::Exports, name) => name,
+            _ => path,
+        };
+        let files = select(&entries, package);
+        let header = files
+            .iter()
+            .find(|(full, _)| PackageFileKind::classify(full).0.is_header())
+            .ok_or_else(|| UeToolError::file_not_found(path))?;
+
+        let mut data = reader.read(&header.1)?;
+        if let Some(exports) = files.iter().find(|(full, _)| PackageFileKind::classify(full).0 == PackageFileKind::Exports) {
+            data.extend_from_slice(&reader.read(&exports.1)?);
+        }
+        Ok(data)
+    }
+
+    /// Extract one entry to the file `dest`
+    ///
+    /// A package name selects the package header (`.uasset` or `.umap`); use
+    /// [`Unpacker::extract_many`] to get its companion files as well.
+    pub fn extract_entry<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        container: P,
+        path: &str,
+        dest: Q,
+        options: &ExtractOptions,
+    ) -> Result<UnpackedFile> {
+        let container = container.as_ref();
+        let _span = tracing::info_span!("extract_entry", container = %container.display(), path).entered();
+
+        let (mut reader, entries) = open_container(container, options)?;
+        let entry = select_one(&entries, path)?;
+        write_entry(&mut reader, entry, dest.as_ref(), options)
+    }
+
+    /// Extract every entry matching `paths` under `dest_dir`, keeping their paths
+    ///
+    /// Files are written to `dest_dir/<path with mount point>`, the layout of
+    /// an unpacked pak with the `../../../` prefix stripped. A path that
+    /// matches nothing, or an entry whose path would land outside `dest_dir`,
+    /// fails the whole call before anything is written. If
+    /// writing fails or is cancelled part way through, the files already
+    /// written are returned inside the [`Interrupted`] error.
+    pub fn extract_many<P: AsRef<Path>, Q: AsRef<Path>, S: AsRef<str>>(
+        &mut self,
+        container: P,
+        paths: &[S],
+        dest_dir: Q,
+        options: &ExtractOptions,
+    ) -> PartialResult<Vec<UnpackedFile>> {
+        let (container, dest_dir) = (container.as_ref(), dest_dir.as_ref());
+        let _span = tracing::info_span!("extract_many", container = %container.display(), paths = paths.len()).entered();
+
+        let (mut reader, entries) = open_container(container, options)?;
+        let mut selected: Vec<(&Entry, PathBuf)> = Vec::new();
+        for path in paths {
+            let matches = select(&entries, path.as_ref());
+            if matches.is_empty() {
+                return Err(UeToolError::file_not_found(path.as_ref()).into());
+            }
+            for entry in matches {
+                if !selected.iter().any(|(e, _)| *e == entry) {
+                    selected.push((entry, join_entry_path(dest_dir, &entry.0)?));
+                }
+            }
+        }
+
+        let mut progress = self.progress.phase(Phase::Unpack, selected.len() as u64, 0);
+        let mut extracted = Vec::with_capacity(selected.len());
+        for (entry, dest) in selected {
+            if let Err(e) = cancel::check(self.cancellation.as_ref()) {
+                return Err(Interrupted::new(extracted, e));
+            }
+            progress.entry_started(&entry.0, 0);
+            match write_entry(&mut reader, entry, &dest, options) {
+                Ok(file) => {
+                    progress.entry_finished(&entry.0, file.size);
+                    extracted.push(file);
+                }
+                Err(e) => return Err(Interrupted::new(extracted, e)),
+            }
+        }
+        progress.finish();
+        Ok(extracted)
+    }
+}
+
+pub(crate) fn open_container(container: &Path, options: &ExtractOptions) -> Result<(ContainerReader, Vec<Entry>)> {
+    if !container.exists() {
+        return Err(UeToolError::file_not_found(container));
+    }
+    let kind = ContainerKind::from_path(container)
+        .ok_or_else(|| UeToolError::invalid_format(format!("{} is not a .pak or .utoc file", container.display())))?;
+    let reader = ContainerReader::open(container, kind, options.aes_key.as_deref())?;
+    let entries = reader.entries().into_iter().map(|(full, raw)| (normalize_path(&full), raw)).collect();
+    Ok((reader, entries))
+}
+
+/// `dest_dir` joined with an entry path from a container
+///
+/// Container paths come from the file, so one with `..`, a root or a drive
+/// could write anywhere; those are refused rather than cleaned up.
+pub(crate) fn join_entry_path(dest_dir: &Path, path: &str) -> Result<PathBuf> {
+    let path = path.replace('\\', "/");
+    let relative = Path::new(&path);
+    let safe = relative.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        && relative.components().any(|c| matches!(c, Component::Normal(_)))
+        && !path.split('/').any(|part| part.contains(':'));
+    if !safe {
+        return Err(UeToolError::invalid_format(format!(
+            "Entry path {} would be written outside {}",
+            path,
+            dest_dir.display()
+        )));
+    }
+    Ok(dest_dir.join(relative))
+}
+
+fn write_entry(reader: &mut ContainerReader, entry: &Entry, dest: &Path, options: &ExtractOptions) -> Result<UnpackedFile> {
+    let data = reader.read(&entry.1)?;
+    write_output(dest, &data, options.force)?;
+    tracing::trace!(entry = %entry.0, bytes = data.len(), "Extracted entry");
+
+    Ok(UnpackedFile {
+        original_path: AssetPath::new(entry.0.clone()),
+        output_path: dest.to_path_buf(),
+        size: data.len() as u64,
+        error: None,
+    })
+}
+
+/// Write `data` to `dest`, creating its parent directories; an existing file
+/// is only replaced with `force`
+pub(crate) fn write_output(dest: &Path, data: &[u8], force: bool) -> Result<()> {
+    if dest.exists() && !force {
+        return Err(UeToolError::invalid_argument(format!(
+            "{} already exists; use force to overwrite",
+            dest.display()
+        )));
+    }
+    if let Some(parent) = dest.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| UeToolError::IoError(format!("Failed to create directory: {}", e)))?;
+    }
+    fs::write(dest, data).map_err(|e| UeToolError::IoError(format!("Failed to write {}: {}", dest.display(), e)))
+}
+
+/// The single file a path names; package names resolve to their header
+fn select_one<'a>(entries: &'a [Entry], path: &str) -> Result<&'a Entry> {
+    match select(entries, path).as_slice() {
+        [] => Err(UeToolError::file_not_found(path)),
+        [entry] => Ok(entry),
+        matches => matches
+            .iter()
+            .find(|(full, _)| PackageFileKind::classify(full).0.is_header())
+            .copied()
+            .ok_or_else(|| UeToolError::invalid_argument(format!("{} matches {} entries", path, matches.len()))),
+    }
+}
+
+/// Entries a path names: an exact file, or every file of a package
+pub(crate) fn select<'a>(entries: &'a [Entry], path: &str) -> Vec<&'a Entry> {
+    let is_package_path = path.starts_with('/') && Path::new(path).extension().is_none();
+    let query = normalize_path(path).to_lowercase();
+
+    if !is_package_path {
+        let files: Vec<_> = entries
+            .iter()
+            .filter(|(full, raw)| full.to_lowercase() == query || normalize_path(raw).to_lowercase() == query)
+            .collect();
+        if !files.is_empty() {
+            return files;
+        }
+    }
+
+    // `/Game/X` lives under `<Project>/Content/X`, `/<Root>/X` under `<Root>/Content/X`
+    let package = |full: &str| PackageFileKind::classify(full).1.to_lowercase();
+    match query.split_once('/').filter(|_| is_package_path) {
+        Some(("game", rest)) => {
+            let suffix = format!("/content/{}", rest);
+            entries
+                .iter()
+                .filter(|(full, _)| {
+                    let name = package(full);
+                    name.ends_with(&suffix)
+                        && name.matches('/').count() == suffix.matches('/').count()
+                        && !name.starts_with("engine/")
+                })
+                .collect()
+        }
+        Some((root, rest)) => {
+            let name = format!("{}/content/{}", root, rest);
+            entries.iter().filter(|(full, _)| package(full) == name).collect()
+        }
+        None => entries.iter().filter(|(full, _)| package(full) == query).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<Entry> {
+        ["Marvel/Content/UI/Icon.uasset", "Marvel/Content/UI/Icon.uexp", "Marvel/Content/UI/Icon.ubulk", "Engine/Content/UI/Icon.uasset"]
+            .iter()
+            .map(|p| (p.to_string(), p.trim_start_matches("Marvel/").to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_select_by_file_path() {
+        let entries = entries();
+        assert_eq!(select(&entries, "../../../marvel/content/ui/icon.uexp").len(), 1);
+        assert_eq!(select(&entries, "Content/UI/Icon.ubulk").len(), 1);
+    }
+
+    #[test]
+    fn test_select_by_package() {
+        let entries = entries();
+        assert_eq!(select(&entries, "Marvel/Content/UI/Icon").len(), 3);
+        assert_eq!(select(&entries, "/Game/UI/Icon").len(), 3);
+        assert_eq!(select(&entries, "/Engine/UI/Icon").len(), 1);
+        assert_eq!(select_one(&entries, "/Game/UI/Icon").unwrap().0, "Marvel/Content/UI/Icon.uasset");
+    }
+
+    #[test]
+    fn test_join_entry_path() {
+        let dest = Path::new("out");
+        assert_eq!(join_entry_path(dest, "Marvel/Content/Icon.uasset").unwrap(), dest.join("Marvel/Content/Icon.uasset"));
+        for path in ["Game/../../../../etc/x", "/etc/x", "C:/Windows/x", "Game\\..\\..\\x", ""] {
+            assert!(join_entry_path(dest, path).is_err(), "{}", path);
+        }
+    }
+}
@@ -9,36 +9,118 @@
 //! - List contents of .utoc files (similar to `retoc_cli list`)
 //! - Support for AES encrypted files
 //! - Compression support (Oodle, Zstd, Zlib, etc.)
-//! - Archive support (ZIP and RAR files)
+//! - Archive support (ZIP, RAR and 7z files)
 //! - Progress reporting for long operations
+//! - Folder scanning that pairs .pak/.utoc/.ucas files into containers
+//! - Optional on-disk cache of container listings
+//! - Load-order-aware virtual filesystem over a game's Paks directory
+//! - Parsing of cooked package summaries (name map, imports, exports)
+//! - Parsing of `AssetRegistry.bin` (assets, tags, dependencies, package data)
+//! - `.usmap` mappings and decoding of unversioned properties to JSON
+//! - DataTable, CurveTable and StringTable export to JSON and CSV
+//! - Reading and writing `.locres` localization files, and `.locmeta` parsing
+//! - Texture export to PNG (BC1-BC7, BGRA8, G8 and RGBA16F decoded on the CPU) or DDS
+//! - Wwise `.bnk` soundbank indexing and `.wem` extraction, embedded or streamed
+//! - Reading `.ini` config files from containers and merging them in load order
+//! - Package dependency graphs with DOT and JSON export
+//!
+//! ## Cargo features
+//!
+//! - `archive` (default): extract ZIP, RAR and 7z archives containing mods
+//! - `progress` (default): terminal progress bars via `indicatif`
+//! - `cli` (default): the `repak`, `retoc` and `rust-ue-tools` binaries
+//! - `parallel`: scan folders on the `rayon` thread pool and re-export `rayon`
+//! - `python`: PyO3 bindings (`pyo3` is kept as an alias)
 //!
 
-use std::path::{Path, PathBuf};
-use std::io::{BufReader, BufWriter};
-use std::fs::{self, File};
-use std::process::Command;
-use serde::{Deserialize, Serialize};
-use thiserror::Error;
+use std::path::Path;
+#[cfg(feature = "archive")]
+use std::time::Duration;
+
+use cache::ListingKind;
 
 pub mod pak_unpack;
 pub mod utoc_list;
 pub mod error;
 pub mod types;
+pub mod package;
+pub mod pak_index;
+pub mod toc_index;
+pub mod scan;
+pub mod cache;
+pub mod vfs;
+pub mod conflicts;
+pub mod diff;
+pub mod patch;
+pub mod verify;
+pub mod signature;
+pub mod extract;
+pub mod asset;
+pub mod asset_registry;
+pub mod usmap;
+pub mod properties;
+pub mod tables;
+pub mod locres;
+pub mod texture;
+pub mod wwise;
+pub mod config;
+pub mod deps;
+pub mod cancel;
+pub mod progress;
+mod binary;
+mod bcn;
+#[cfg(feature = "archive")]
+mod archive;
+#[cfg(feature = "cli")]
+pub mod logging;
+#[cfg(feature = "cli")]
 pub mod cli;
+#[cfg(feature = "python")]
 pub mod python_bindings;
 
 pub use error::{Result, UeToolError};
 pub use pak_unpack::PakUnpacker;
 pub use utoc_list::UtocLister;
-pub use types::{AssetPath, CompressionMethod, PakUnpackOptions, UtocListOptions};
+pub use types::{AssetPath, CompressionMethod, ConfigOptions, ConflictOptions, DependencyOptions, DiffOptions, ExtractOptions, InspectOptions, PakUnpackOptions, PatchOptions, ScanOptions, TextureOptions, UtocListOptions, VerifyOptions};
+pub use package::{group_packages, ContainerKind, Package, PackageFile, PackageFileKind, PackageIssue};
+pub use scan::{ContainerFiles, ContainerMap, ScannedContainer};
+pub use cache::ListingCache;
+pub use vfs::{GameInstall, MountedContainer, ResolvedFile, VirtualFileSystem};
+pub use conflicts::{Conflict, ConflictProvider, ConflictReport};
+pub use diff::{ChangeKind, DiffEntry, DiffLevel, DiffReport, DiffStat, EntryDigest};
+pub use patch::{patch_file_name, PatchResult};
+pub use verify::{CorruptEntry, VerifyIssue, VerifyIssueKind, VerifyReport};
+pub use signature::{SignatureFile, SignatureReport, TamperedChunk};
+pub use asset::{DataResource, EngineVersion, ExportEntry, ImportEntry, ObjectRef, PackageFormat, PackageSummary, ScriptObjects};
+pub use asset_registry::{AssetData, AssetPackageData, AssetRegistry, DependsNode};
+pub use usmap::{MappedEnum, MappedProperty, MappedStruct, Mappings, PropertyType};
+pub use properties::{ExportProperties, PropertyReader};
+pub use tables::{Table, TableFormat, TableKind};
+pub use locres::{LocMeta, Locres, LocresEntry, LocresNamespace, LocresVersion};
+pub use texture::{PixelFormat, Texture2D, TextureFormat, TextureMip};
+pub use wwise::{BankMedia, HircKind, HircObject, MediaRef, MediaSource, SoundBank, SoundBankIndex, StreamedWem};
+pub use config::{Config, ConfigSection, ConfigSet, ConfigSource, IniFile, IniLine, IniOp, IniSection};
+pub use deps::{Dependency, DependencyGraph, DependencyKind, PackageNode};
+pub use cancel::{CancellationToken, Interrupted, PartialResult};
+pub use progress::{Phase, ProgressCallback, ProgressEvent, ProgressSink};
 
 // Re-export common types for convenience
+#[cfg(feature = "parallel")]
 pub use rayon::prelude::*;
 
+/// Default time allowed for an external archive tool (e.g. rar) to finish
+#[cfg(feature = "archive")]
+pub const DEFAULT_ARCHIVE_TOOL_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
 /// Main entry point for unpacking pak files
 pub struct Unpacker {
     pub pak_unpacker: PakUnpacker,
     pub utoc_lister: UtocLister,
+    cancellation: Option<CancellationToken>,
+    #[cfg(feature = "archive")]
+    archive_tool_timeout: Duration,
+    progress: ProgressSink,
+    cache: ListingCache,
 }
 
 impl Unpacker {
@@ -47,9 +129,64 @@ impl Unpacker {
         Self {
             pak_unpacker: PakUnpacker::new(),
             utoc_lister: UtocLister::new(),
+            cancellation: None,
+            #[cfg(feature = "archive")]
+            archive_tool_timeout: DEFAULT_ARCHIVE_TOOL_TIMEOUT,
+            progress: ProgressSink::disabled(),
+            cache: ListingCache::disabled(),
         }
     }
 
+    /// Set a progress callback shared by every operation of this unpacker
+    pub fn with_progress_callback(mut self, callback: ProgressCallback) -> Self {
+        let sink = ProgressSink::new(callback);
+        self.pak_unpacker = self.pak_unpacker.with_progress_sink(sink.clone());
+        self.utoc_lister = self.utoc_lister.with_progress_sink(sink.clone());
+        self.progress = sink;
+        self
+    }
+
+    /// Set a cancellation token shared by every operation of this unpacker
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.pak_unpacker = self.pak_unpacker.with_cancellation_token(token.clone());
+        self.utoc_lister = self.utoc_lister.with_cancellation_token(token.clone());
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// The cancellation token shared by this unpacker's operations, if any
+    pub fn cancellation_token(&self) -> Option<&CancellationToken> {
+        self.cancellation.as_ref()
+    }
+
+    /// The progress sink shared by this unpacker's operations
+    pub fn progress_sink(&self) -> &ProgressSink {
+        &self.progress
+    }
+
+    /// Reuse container listings from an on-disk cache
+    ///
+    /// `list_utoc`, `get_pak_file_list`, `list_pak_packages`,
+    /// `list_utoc_packages`, `scan_folder` and archive extraction return cached
+    /// listings for files that are unchanged since they were stored, and store
+    /// new listings as they are read.
+    pub fn with_listing_cache(mut self, cache: ListingCache) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// The listing cache used by this unpacker; disabled unless one was set
+    pub fn listing_cache(&self) -> &ListingCache {
+        &self.cache
+    }
+
+    /// Set how long an external archive tool may run before it is killed
+    #[cfg(feature = "archive")]
+    pub fn with_archive_tool_timeout(mut self, timeout: Duration) -> Self {
+        self.archive_tool_timeout = timeout;
+        self
+    }
+
     /// Unpack a pak file to the specified output directory
     ///
     /// # Arguments
@@ -61,9 +198,12 @@ impl Unpacker {
         pak_path: P,
         output_dir: P,
         options: &PakUnpackOptions,
-    ) -> Result<Vec<AssetPath>> {
-        let unpacked_files = self.pak_unpacker.unpack(pak_path, output_dir, options)?;
-        Ok(unpacked_files.into_iter().map(|f| f.original_path).collect())
+    ) -> PartialResult<Vec<AssetPath>> {
+        let to_paths = |files: Vec<types::UnpackedFile>| files.into_iter().map(|f| f.original_path).collect();
+        self.pak_unpacker
+            .unpack(pak_path, output_dir, options)
+            .map(to_paths)
+            .map_err(|interrupted| interrupted.map(to_paths))
     }
 
     /// List contents of a.utoc file
@@ -75,8 +215,16 @@ impl Unpacker {
         &mut self,
         utoc_path: P,
         options: &UtocListOptions,
-    ) -> Result<Vec<AssetPath>> {
-        self.utoc_lister.list(utoc_path, options)
+    ) -> PartialResult<Vec<AssetPath>> {
+        let utoc_path = utoc_path.as_ref();
+        let digest = cache::options_digest(&[options.aes_key.as_deref()]);
+        if let Some(assets) = self.cache.get(ListingKind::Utoc, &digest, &[utoc_path]) {
+            return Ok(assets);
+        }
+
+        let assets = self.utoc_lister.list(utoc_path, options)?;
+        self.cache.insert(ListingKind::Utoc, &digest, &[utoc_path], &assets);
+        Ok(assets)
     }
 
     /// Get file list from a pak file without reading content (for solo pak files)
@@ -90,270 +238,66 @@ impl Unpacker {
         aes_key: Option<&str>,
     ) -> Result<Vec<AssetPath>> {
         let pak_path = pak_path.as_ref();
-
-        if !pak_path.exists() {
-            return Err(UeToolError::file_not_found(pak_path));
+        let digest = cache::options_digest(&[aes_key]);
+        if let Some(assets) = self.cache.get(ListingKind::Pak, &digest, &[pak_path]) {
+            return Ok(assets);
         }
 
-        // Create PakBuilder and open the pak file
-        let mut builder = repak::PakBuilder::new();
-        if let Some(ref key) = aes_key {
-            if let Ok(aes_key) = key.parse::<repak::utils::AesKey>() {
-                builder = builder.key(aes_key.0);
-            } else {
-                return Err(UeToolError::InvalidAesKey(format!("Invalid AES key format: {}", key)));
-            }
-        }
-
-        let pak_file = File::open(pak_path)
-            .map_err(|e| UeToolError::IoError(format!("Failed to open PAK file: {}", e)))?;
-        let mut reader = BufReader::new(pak_file);
-        
-        let pak = builder.reader(&mut reader)
-            .map_err(|e| UeToolError::PakError(format!("Failed to read PAK file: {}", e)))?;
+        let (pak, _) = pak_unpack::open_pak(pak_path, aes_key)?;
 
         // Just return the file list without reading content
-        let files = pak.files();
-        Ok(files.into_iter().map(|path| AssetPath::new(path)).collect())
+        let assets: Vec<AssetPath> = pak.files().into_iter().map(AssetPath::new).collect();
+        self.cache.insert(ListingKind::Pak, &digest, &[pak_path], &assets);
+        Ok(assets)
     }
 
-    /// Extract asset paths from an archive file (ZIP or RAR) containing pak/utoc files
+    /// List the packages in a pak file, grouping companion files together
     ///
-    /// This function will:
-    /// 1. Detect archive type (ZIP or RAR)
-    /// 2. Extract the archive to a temporary directory
-    /// 3. Find and unpack any .pak files
-    /// 4. List contents of any .utoc files
-    /// 5. Return all discovered asset paths
-    pub fn extract_asset_paths_from_archive<P: AsRef<Path>>(
+    /// # Arguments
+    /// * `pak_path` - Path to the .pak file
+    /// * `options` - Unpack options (only the aes key is used)
+    pub fn list_pak_packages<P: AsRef<Path>>(
         &mut self,
-        archive_path: P,
-        aes_key: Option<&str>,
-        keep_temp: bool,
-    ) -> Result<Vec<AssetPath>> {
-        use tempfile::TempDir;
-        use walkdir::WalkDir;
-
-        // Create temporary directory
-        let temp_dir = TempDir::new()
-            .map_err(|e| UeToolError::IoError(format!("Failed to create temp directory: {}", e)))?;
-        let temp_path = temp_dir.path().to_path_buf();
-
-        // Detect archive type and extract
-        let archive_path = archive_path.as_ref();
-        let archive_type = Self::detect_archive_type(archive_path)?;
-
-        match archive_type {
-            ArchiveType::Zip => {
-                self.extract_zip_archive(archive_path, &temp_path)?;
-            }
-            ArchiveType::Rar => {
-                self.extract_rar_archive(archive_path, &temp_path)?;
-            }
-        }
-
-        let mut all_assets = Vec::new();
-
-        // Process extracted UE files (pak and utoc)
-        println!("Processing extracted UE files:");
-
-        // Find all pak and utoc files
-        let pak_files: Vec<PathBuf> = WalkDir::new(&temp_path)
-            .into_iter()
-            .filter_map(|entry| entry.ok())
-            .filter(|entry| entry.file_type().is_file())
-            .filter(|entry| entry.path().extension().map_or(false, |ext| ext == "pak"))
-            .map(|entry| entry.path().to_path_buf())
-            .collect();
-
-        let utoc_files: Vec<PathBuf> = WalkDir::new(&temp_path)
-            .into_iter()
-            .filter_map(|entry| entry.ok())
-            .filter(|entry| entry.file_type().is_file())
-            .filter(|entry| entry.path().extension().map_or(false, |ext| ext == "utoc"))
-            .map(|entry| entry.path().to_path_buf())
-            .collect();
-
-        // Create a set of pak file names without extension for matching
-        let mut pak_names = std::collections::HashSet::new();
-        for pak_file in &pak_files {
-            if let Some(name) = pak_file.file_stem() {
-                pak_names.insert(name.to_string_lossy().to_string());
-            }
-        }
-
-        // Process pak files - handle both solo pak and bundle pak
-        for pak_file in &pak_files {
-            let pak_name = pak_file.file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("");
-
-            // Check if this pak has a corresponding utoc file (bundle scenario)
-            let has_utoc = utoc_files.iter().any(|utoc| {
-                utoc.file_stem()
-                    .and_then(|s| s.to_str())
-                    .map(|utoc_name| utoc_name == pak_name)
-                    .unwrap_or(false)
-            });
-
-            if has_utoc {
-                // Bundle scenario: Skip pak file processing since we'll get assets from utoc
-                println!("Skipping pak file {} (has corresponding utoc)", pak_file.display());
-                continue;
-            }
-
-            // Solo pak scenario: Get file list without reading content
-            println!("Processing solo pak file: {}", pak_file.display());
-            match self.get_pak_file_list(pak_file, aes_key) {
-                Ok(assets) => {
-                    println!("  Found {} assets in solo pak file", assets.len());
-                    all_assets.extend(assets);
-                }
-                Err(e) => println!("Warning: Failed to read pak file {}: {}", pak_file.display(), e),
+        pak_path: P,
+        options: &PakUnpackOptions,
+    ) -> Result<Vec<Package>> {
+        let pak_path = pak_path.as_ref();
+        let digest = cache::options_digest(&[options.aes_key.as_deref()]);
+        let entries = match self.cache.get_entries(ListingKind::PakEntries, &digest, &[pak_path]) {
+            Some(entries) => entries,
+            None => {
+                let entries = self.pak_unpacker.list_entries(pak_path, options)?;
+                self.cache.insert_entries(ListingKind::PakEntries, &digest, &[pak_path], &entries);
+                entries
             }
-        }
-
-        // Process utoc files (for bundles)
-        let utoc_options = UtocListOptions {
-            aes_key: aes_key.map(|s| s.to_string()),
-            json_format: false,
         };
-
-        for utoc_file in &utoc_files {
-            println!("Processing utoc file: {}", utoc_file.display());
-            match self.list_utoc(utoc_file, &utoc_options) {
-                Ok(assets) => {
-                    println!("  Found {} assets in utoc file", assets.len());
-                    all_assets.extend(assets);
-                }
-                Err(e) => println!("Warning: Failed to list utoc file {}: {}", utoc_file.display(), e),
-            }
-        }
-
-        if keep_temp {
-            // Return temp dir so caller can access it
-            drop(temp_dir);
-        }
-
-        Ok(all_assets)
-    }
-
-    /// Detect the type of archive file
-    fn detect_archive_type(archive_path: &Path) -> Result<ArchiveType> {
-        let extension = archive_path
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("")
-            .to_lowercase();
-
-        match extension.as_str() {
-            "zip" => Ok(ArchiveType::Zip),
-            "rar" => Ok(ArchiveType::Rar),
-            _ => Err(UeToolError::InvalidArgument(format!(
-                "Unsupported archive type: {}", extension
-            ))),
-        }
-    }
-
-    /// Extract a ZIP archive to the specified directory
-    fn extract_zip_archive(&self, archive_path: &Path, dest_dir: &Path) -> Result<()> {
-        let zip_file = File::open(archive_path)
-            .map_err(|e| UeToolError::IoError(format!("Failed to open zip file: {}", e)))?;
-
-        let mut zip_archive = zip::ZipArchive::new(zip_file)
-            .map_err(|e| UeToolError::IoError(format!("Failed to open zip archive: {}", e)))?;
-
-        for i in 0..zip_archive.len() {
-            let mut file = zip_archive.by_index(i)
-                .map_err(|e| UeToolError::IoError(format!("Failed to read zip entry {}: {}", i, e)))?;
-
-            let out_path = dest_dir.join(file.name());
-            if file.is_dir() {
-                fs::create_dir_all(&out_path)
-                    .map_err(|e| UeToolError::IoError(format!("Failed to create directory {}: {}", out_path.display(), e)))?;
-            } else {
-                if let Some(parent) = out_path.parent() {
-                    fs::create_dir_all(parent)
-                        .map_err(|e| UeToolError::IoError(format!("Failed to create parent directory {}: {}", parent.display(), e)))?;
-                }
-                let mut out_file = File::create(&out_path)
-                    .map_err(|e| UeToolError::IoError(format!("Failed to create file {}: {}", out_path.display(), e)))?;
-                std::io::copy(&mut file, &mut out_file)
-                    .map_err(|e| UeToolError::IoError(format!("Failed to copy file data: {}", e)))?;
-            }
-        }
-
-        Ok(())
-    }
-
-    /// Extract a RAR archive to the specified directory using external tools
-    fn extract_rar_archive(&self, archive_path: &Path, dest_dir: &Path) -> Result<()> {
-        use std::process::Command;
-
-        // Try to find RAR tool (similar to Python implementation)
-        let rar_tool = Self::find_rar_tool()?;
-
-        // Run the RAR extraction command
-        let output = Command::new(&rar_tool)
-            .args(&["x", "-y"]) // x = extract, -y = assume yes to all prompts
-            .arg(archive_path)
-            .arg(dest_dir)
-            .output()
-            .map_err(|e| UeToolError::IoError(format!("Failed to run RAR tool: {}", e)))?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(UeToolError::IoError(format!(
-                "RAR extraction failed: {}", stderr
-            )));
-        }
-
-        Ok(())
+        Ok(group_packages(&entries, ContainerKind::Pak))
     }
 
-    /// Find the RAR tool executable (similar to Python implementation)
-    fn find_rar_tool() -> Result<String> {
-        // Check environment variable first
-        if let Ok(env_tool) = std::env::var("RAR_TOOL_PATH") {
-            if Path::new(&env_tool).exists() {
-                return Ok(env_tool);
-            }
-        }
-
-        // Check common WinRAR locations
-        let winrar_paths = [
-            r"C:\Program Files\WinRAR\rar.exe",
-            r"C:\Program Files (x86)\WinRAR\rar.exe",
-            r"C:\WinRAR\rar.exe",
-        ];
-
-        for path in &winrar_paths {
-            if Path::new(path).exists() {
-                return Ok(path.to_string());
-            }
-        }
-
-        // Check if rar.exe is in PATH
-        if let Ok(output) = Command::new("where").arg("rar.exe").output() {
-            if output.status.success() {
-                let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                if !path.is_empty() {
-                    return Ok(path);
-                }
+    /// List the packages in a .utoc container, grouping bulk data chunks with their package
+    ///
+    /// # Arguments
+    /// * `utoc_path` - Path to the .utoc file
+    /// * `options` - List options (aes key, format, etc.)
+    pub fn list_utoc_packages<P: AsRef<Path>>(
+        &mut self,
+        utoc_path: P,
+        options: &UtocListOptions,
+    ) -> Result<Vec<Package>> {
+        let utoc_path = utoc_path.as_ref();
+        let digest = cache::options_digest(&[options.aes_key.as_deref()]);
+        let entries = match self.cache.get_entries(ListingKind::UtocEntries, &digest, &[utoc_path]) {
+            Some(entries) => entries,
+            None => {
+                let entries = self.utoc_lister.list_entries(utoc_path, options)?;
+                self.cache.insert_entries(ListingKind::UtocEntries, &digest, &[utoc_path], &entries);
+                entries
             }
-        }
-
-        Err(UeToolError::IoError("No RAR tool found. Please install WinRAR or ensure rar.exe is in PATH".to_string()))
+        };
+        Ok(group_packages(&entries, ContainerKind::IoStore))
     }
 }
 
-/// Archive type enumeration
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum ArchiveType {
-    Zip,
-    Rar,
-}
-
 impl Default for Unpacker {
     fn default() -> Self {
         Self::new()
@@ -367,8 +311,8 @@ mod tests {
     #[test]
     fn test_unpacker_creation() {
         let unpacker = Unpacker::new();
-        // Just verify it can be created
-        assert!(!std::ptr::null(&unpacker.pak_unpacker));
-        assert!(!std::ptr::null(&unpacker.utoc_lister));
+        // A fresh unpacker has no token or progress sink attached
+        assert!(unpacker.cancellation_token().is_none());
+        assert!(!unpacker.progress_sink().is_enabled());
     }
 }
\ No newline at end of file
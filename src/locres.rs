@@ -0,0 +1,535 @@
+//! Localization resources: `.locres` string tables and `.locmeta` metadata
+//!
+//! A `.locres` maps (namespace, key) pairs to a localized string and the hash
+//! of the native source string it translates. All versions are read:
+//!
+//! - legacy (4.0-4.19): no magic, strings stored inline;
+//! - compact: strings deduplicated into a table at the end of the file;
+//! - optimized: namespace and key CRC32 hashes, string reference counts;
+//! - CityHash: namespace and key hashes switched to CityHash64 of UTF-16.
+//!
+//! [`Locres::to_bytes`] writes any version back, recomputing the namespace
+//! and key hashes, so an exported JSON can be edited and packed into a
+//! translation mod.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{Cursor, Seek, SeekFrom};
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::binary::{write_fstring, ReadExt};
+use crate::error::{Result, UeToolError};
+use crate::tables::{csv_escape, TableFormat};
+use crate::types::ExtractOptions;
+use crate::Unpacker;
+
+/// `FTextLocalizationResourceString::LocResMagic`
+const LOCRES_MAGIC: [u32; 4] = [0x7574140E, 0xFC034A67, 0x9D90154A, 0x1B7F37C3];
+
+/// `FTextLocalizationMetaDataResource::LocMetaMagic`
+const LOCMETA_MAGIC: [u32; 4] = [0xA14CEE4F, 0x83554868, 0xBD464C6C, 0x7C50DA70];
+
+/// `.locres` format version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum LocresVersion {
+    Legacy,
+    Compact,
+    Optimized,
+    CityHash,
+}
+
+impl LocresVersion {
+    fn from_u8(version: u8) -> Result<Self> {
+        match version {
+            0 => Ok(LocresVersion::Legacy),
+            1 => Ok(LocresVersion::Compact),
+            2 => Ok(LocresVersion::Optimized),
+            3 => Ok(LocresVersion::CityHash),
+            v => Err(UeToolError::invalid_format(format!("Unsupported .locres version {}", v))),
+        }
+    }
+}
+
+impl FromStr for LocresVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "legacy" | "0" => Ok(LocresVersion::Legacy),
+            "compact" | "1" => Ok(LocresVersion::Compact),
+            "optimized" | "crc32" | "2" => Ok(LocresVersion::Optimized),
+            "cityhash" | "3" => Ok(LocresVersion::CityHash),
+            _ => Err(format!("Unknown .locres version: {} (expected legacy, compact, optimized or cityhash)", s)),
+        }
+    }
+}
+
+impl fmt::Display for LocresVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LocresVersion::Legacy => write!(f, "legacy"),
+            LocresVersion::Compact => write!(f, "compact"),
+            LocresVersion::Optimized => write!(f, "optimized"),
+            LocresVersion::CityHash => write!(f, "cityhash"),
+        }
+    }
+}
+
+/// One localized string
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocresEntry {
+    pub key: String,
+    /// CRC32 of the native source string; the game ignores entries whose hash doesn't match
+    pub source_hash: u32,
+    pub string: String,
+}
+
+/// The entries of one namespace, in file order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocresNamespace {
+    pub name: String,
+    pub entries: Vec<LocresEntry>,
+}
+
+/// A parsed `.locres` file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Locres {
+    pub version: LocresVersion,
+    pub namespaces: Vec<LocresNamespace>,
+}
+
+impl Locres {
+    /// Parse a `.locres` file of any version
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let mut r = Cursor::new(data);
+        let version = if data.len() >= 17 && read_magic(&mut r)? == LOCRES_MAGIC {
+            LocresVersion::from_u8(r.read_u8()?)?
+        } else {
+            r.set_position(0);
+            LocresVersion::Legacy
+        };
+
+        let strings = if version >= LocresVersion::Compact {
+            let offset = r.read_u64()?;
+            let body = r.position();
+            r.seek(SeekFrom::Start(offset))
+                .map_err(|e| UeToolError::invalid_format(format!("Invalid .locres string table offset: {}", e)))?;
+            let strings = r.read_array_of(|r| {
+                let string = r.read_fstring()?;
+                if version >= LocresVersion::Optimized {
+                    r.read_i32()?; // Reference count
+                }
+                Ok(string)
+            })?;
+            r.set_position(body);
+            strings
+        } else {
+            Vec::new()
+        };
+        if version >= LocresVersion::Optimized {
+            r.read_u32()?; // Entry count
+        }
+
+        let namespaces = r.read_array_of(|r| {
+            if version >= LocresVersion::Optimized {
+                r.read_u32()?; // Namespace hash
+            }
+            let name = r.read_fstring()?;
+            let entries = r.read_array_of(|r| {
+                if version >= LocresVersion::Optimized {
+                    r.read_u32()?; // Key hash
+                }
+                let key = r.read_fstring()?;
+                let source_hash = r.read_u32()?;
+                let string = if version >= LocresVersion::Compact {
+                    let index = r.read_i32()?;
+                    usize::try_from(index)
+                        .ok()
+                        .and_then(|i| strings.get(i))
+                        .cloned()
+                        .ok_or_else(|| UeToolError::invalid_format(format!("String index {} out of range for key {}", index, key)))?
+                } else {
+                    r.read_fstring()?
+                };
+                Ok(LocresEntry { key, source_hash, string })
+            })?;
+            Ok(LocresNamespace { name, entries })
+        })?;
+
+        Ok(Locres { version, namespaces })
+    }
+
+    /// Read and parse a `.locres` file from disk
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(|e| UeToolError::io_error(format!("Failed to read {}: {}", path.display(), e)))?;
+        Self::parse(&data)
+    }
+
+    /// Parse the JSON written by [`Locres::render`]
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| UeToolError::DeserializationError(format!("Invalid .locres JSON: {}", e)))
+    }
+
+    /// Serialize in [`Locres::version`], recomputing namespace and key hashes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let version = self.version;
+        let mut out = Vec::new();
+        if version >= LocresVersion::Compact {
+            for part in LOCRES_MAGIC {
+                out.extend_from_slice(&part.to_le_bytes());
+            }
+            out.push(version as u8);
+            out.extend_from_slice(&0u64.to_le_bytes()); // String table offset, patched below
+        }
+        if version >= LocresVersion::Optimized {
+            out.extend_from_slice(&(self.entry_count() as u32).to_le_bytes());
+        }
+
+        // Compact and later store each distinct string once with a reference count
+        let mut strings: Vec<(&str, i32)> = Vec::new();
+        let mut string_indices: HashMap<&str, usize> = HashMap::new();
+        out.extend_from_slice(&(self.namespaces.len() as u32).to_le_bytes());
+        for namespace in &self.namespaces {
+            if version >= LocresVersion::Optimized {
+                out.extend_from_slice(&key_hash(&namespace.name, version).to_le_bytes());
+            }
+            write_fstring(&mut out, &namespace.name);
+            out.extend_from_slice(&(namespace.entries.len() as u32).to_le_bytes());
+            for entry in &namespace.entries {
+                if version >= LocresVersion::Optimized {
+                    out.extend_from_slice(&key_hash(&entry.key, version).to_le_bytes());
+                }
+                write_fstring(&mut out, &entry.key);
+                out.extend_from_slice(&entry.source_hash.to_le_bytes());
+                if version == LocresVersion::Legacy {
+                    write_fstring(&mut out, &entry.string);
+                    continue;
+                }
+                let index = *string_indices.entry(&entry.string).or_insert_with(|| {
+                    strings.push((&entry.string, 0));
+                    strings.len() - 1
+                });
+                strings[index].1 += 1;
+                out.extend_from_slice(&(index as i32).to_le_bytes());
+            }
+        }
+
+        if version >= LocresVersion::Compact {
+            let offset = out.len() as u64;
+            out[17..25].copy_from_slice(&offset.to_le_bytes());
+            out.extend_from_slice(&(strings.len() as i32).to_le_bytes());
+            for (string, references) in strings {
+                write_fstring(&mut out, string);
+                if version >= LocresVersion::Optimized {
+                    out.extend_from_slice(&references.to_le_bytes());
+                }
+            }
+        }
+        out
+    }
+
+    /// Write the file to disk in [`Locres::version`]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_bytes()).map_err(|e| UeToolError::io_error(format!("Failed to write {}: {}", path.display(), e)))
+    }
+
+    /// Total number of entries across namespaces
+    pub fn entry_count(&self) -> usize {
+        self.namespaces.iter().map(|n| n.entries.len()).sum()
+    }
+
+    /// The localized string for a namespace and key
+    pub fn get(&self, namespace: &str, key: &str) -> Option<&str> {
+        self.namespaces
+            .iter()
+            .find(|n| n.name == namespace)?
+            .entries
+            .iter()
+            .find(|e| e.key == key)
+            .map(|e| e.string.as_str())
+    }
+
+    /// Render as JSON (readable back with [`Locres::from_json`]) or as a
+    /// `Namespace,Key,SourceHash,String` CSV
+    pub fn render(&self, format: TableFormat) -> Result<String> {
+        match format {
+            TableFormat::Json => serde_json::to_string_pretty(self).map_err(|e| UeToolError::SerializationError(e.to_string())),
+            TableFormat::Csv => {
+                let mut out = String::from("Namespace,Key,SourceHash,String\n");
+                for namespace in &self.namespaces {
+                    for entry in &namespace.entries {
+                        out.push_str(&format!(
+                            "{},{},{},{}\n",
+                            csv_escape(&namespace.name),
+                            csv_escape(&entry.key),
+                            entry.source_hash,
+                            csv_escape(&entry.string)
+                        ));
+                    }
+                }
+                Ok(out)
+            }
+        }
+    }
+}
+
+/// A parsed `.locmeta` file: the native culture of a localization target
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocMeta {
+    pub version: u8,
+    pub native_culture: String,
+    /// Path of the native culture's `.locres`
+    pub native_locres: String,
+    /// Cultures the target was compiled for; empty before version 1
+    pub compiled_cultures: Vec<String>,
+}
+
+impl LocMeta {
+    /// Parse a `.locmeta` file
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let mut r = Cursor::new(data);
+        if read_magic(&mut r)? != LOCMETA_MAGIC {
+            return Err(UeToolError::invalid_format("Not a .locmeta file"));
+        }
+        let version = r.read_u8()?;
+        if version > 1 {
+            return Err(UeToolError::invalid_format(format!("Unsupported .locmeta version {}", version)));
+        }
+        let native_culture = r.read_fstring()?;
+        let native_locres = r.read_fstring()?;
+        let compiled_cultures = if version >= 1 { r.read_array_of(|r| r.read_fstring())? } else { Vec::new() };
+        Ok(LocMeta { version, native_culture, native_locres, compiled_cultures })
+    }
+
+    /// Read and parse a `.locmeta` file from disk
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(|e| UeToolError::io_error(format!("Failed to read {}: {}", path.display(), e)))?;
+        Self::parse(&data)
+    }
+}
+
+impl Unpacker {
+    /// Read a `.locres` from a `.pak` or `.utoc`
+    pub fn read_locres<P: AsRef<Path>>(&mut self, container: P, path: &str, options: &ExtractOptions) -> Result<Locres> {
+        Locres::parse(&self.read_entry(container, path, options)?)
+    }
+
+    /// Read a `.locmeta` from a `.pak` or `.utoc`
+    pub fn read_locmeta<P: AsRef<Path>>(&mut self, container: P, path: &str, options: &ExtractOptions) -> Result<LocMeta> {
+        LocMeta::parse(&self.read_entry(container, path, options)?)
+    }
+}
+
+fn read_magic(r: &mut Cursor<&[u8]>) -> Result<[u32; 4]> {
+    Ok([r.read_u32()?, r.read_u32()?, r.read_u32()?, r.read_u32()?])
+}
+
+/// Hash of a namespace or key, as stored by optimized `.locres` files
+fn key_hash(value: &str, version: LocresVersion) -> u32 {
+    if version >= LocresVersion::CityHash {
+        let utf16: Vec<u8> = value.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let hash = cityhash64(&utf16);
+        (hash as u32).wrapping_add(((hash >> 32) as u32).wrapping_mul(23))
+    } else {
+        str_crc32(value)
+    }
+}
+
+/// `FCrc::StrCrc32`: CRC32 over each UTF-16 unit widened to four bytes
+///
+/// This is also the hash stored in [`LocresEntry::source_hash`].
+pub fn str_crc32(value: &str) -> u32 {
+    let bytes: Vec<u8> = value.encode_utf16().flat_map(|unit| u32::from(unit).to_le_bytes()).collect();
+    crc32fast::hash(&bytes)
+}
+
+const K0: u64 = 0xc3a5c85c97cb3127;
+const K1: u64 = 0xb492b66fbe98f273;
+const K2: u64 = 0x9ae16a3b2f90404f;
+
+fn fetch64(s: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(s[at..at + 8].try_into().unwrap())
+}
+
+fn fetch32(s: &[u8], at: usize) -> u64 {
+    u64::from(u32::from_le_bytes(s[at..at + 4].try_into().unwrap()))
+}
+
+fn shift_mix(v: u64) -> u64 {
+    v ^ (v >> 47)
+}
+
+fn hash_len16(u: u64, v: u64, mul: u64) -> u64 {
+    let a = shift_mix((u ^ v).wrapping_mul(mul));
+    let b = shift_mix((v ^ a).wrapping_mul(mul));
+    b.wrapping_mul(mul)
+}
+
+fn weak_hash_len32_with_seeds(s: &[u8], at: usize, a: u64, b: u64) -> (u64, u64) {
+    let (w, x, y, z) = (fetch64(s, at), fetch64(s, at + 8), fetch64(s, at + 16), fetch64(s, at + 24));
+    let a = a.wrapping_add(w);
+    let b = b.wrapping_add(a).wrapping_add(z).rotate_right(21);
+    let c = a;
+    let a = a.wrapping_add(x).wrapping_add(y);
+    let b = b.wrapping_add(a.rotate_right(44));
+    (a.wrapping_add(z), b.wrapping_add(c))
+}
+
+/// CityHash64 (v1.1), used by UE for text keys
+fn cityhash64(s: &[u8]) -> u64 {
+    let len = s.len();
+    let mul = K2.wrapping_add(len as u64 * 2);
+    match len {
+        0 => K2,
+        1..=3 => {
+            let y = u32::from(s[0]).wrapping_add(u32::from(s[len >> 1]) << 8);
+            let z = (len as u32).wrapping_add(u32::from(s[len - 1]) << 2);
+            shift_mix(u64::from(y).wrapping_mul(K2) ^ u64::from(z).wrapping_mul(K0)).wrapping_mul(K2)
+        }
+        4..=7 => hash_len16((len as u64).wrapping_add(fetch32(s, 0) << 3), fetch32(s, len - 4), mul),
+        8..=16 => {
+            let a = fetch64(s, 0).wrapping_add(K2);
+            let b = fetch64(s, len - 8);
+            let c = b.rotate_right(37).wrapping_mul(mul).wrapping_add(a);
+            let d = a.rotate_right(25).wrapping_add(b).wrapping_mul(mul);
+            hash_len16(c, d, mul)
+        }
+        17..=32 => {
+            let a = fetch64(s, 0).wrapping_mul(K1);
+            let b = fetch64(s, 8);
+            let c = fetch64(s, len - 8).wrapping_mul(mul);
+            let d = fetch64(s, len - 16).wrapping_mul(K2);
+            hash_len16(
+                a.wrapping_add(b).rotate_right(43).wrapping_add(c.rotate_right(30)).wrapping_add(d),
+                a.wrapping_add(b.wrapping_add(K2).rotate_right(18)).wrapping_add(c),
+                mul,
+            )
+        }
+        33..=64 => {
+            let a = fetch64(s, 0).wrapping_mul(K2);
+            let b = fetch64(s, 8);
+            let c = fetch64(s, len - 24);
+            let d = fetch64(s, len - 32);
+            let e = fetch64(s, 16).wrapping_mul(K2);
+            let f = fetch64(s, 24).wrapping_mul(9);
+            let g = fetch64(s, len - 8);
+            let h = fetch64(s, len - 16).wrapping_mul(mul);
+            let u = a.wrapping_add(g).rotate_right(43).wrapping_add(b.rotate_right(30).wrapping_add(c).wrapping_mul(9));
+            let v = (a.wrapping_add(g) ^ d).wrapping_add(f).wrapping_add(1);
+            let w = u.wrapping_add(v).wrapping_mul(mul).swap_bytes().wrapping_add(h);
+            let x = e.wrapping_add(f).rotate_right(42).wrapping_add(c);
+            let y = v.wrapping_add(w).wrapping_mul(mul).swap_bytes().wrapping_add(g).wrapping_mul(mul);
+            let z = e.wrapping_add(f).wrapping_add(c);
+            let a = x.wrapping_add(z).wrapping_mul(mul).wrapping_add(y).swap_bytes().wrapping_add(b);
+            let b = shift_mix(z.wrapping_add(a).wrapping_mul(mul).wrapping_add(d).wrapping_add(h)).wrapping_mul(mul);
+            b.wrapping_add(x)
+        }
+        _ => {
+            const MUL: u64 = 0x9ddfea08eb382d69;
+            let mut x = fetch64(s, len - 40);
+            let mut y = fetch64(s, len - 16).wrapping_add(fetch64(s, len - 56));
+            let mut z = hash_len16(fetch64(s, len - 48).wrapping_add(len as u64), fetch64(s, len - 24), MUL);
+            let mut v = weak_hash_len32_with_seeds(s, len - 64, len as u64, z);
+            let mut w = weak_hash_len32_with_seeds(s, len - 32, y.wrapping_add(K1), x);
+            x = x.wrapping_mul(K1).wrapping_add(fetch64(s, 0));
+
+            for at in (0..(len - 1) & !63).step_by(64) {
+                x = x.wrapping_add(y).wrapping_add(v.0).wrapping_add(fetch64(s, at + 8)).rotate_right(37).wrapping_mul(K1);
+                y = y.wrapping_add(v.1).wrapping_add(fetch64(s, at + 48)).rotate_right(42).wrapping_mul(K1);
+                x ^= w.1;
+                y = y.wrapping_add(v.0).wrapping_add(fetch64(s, at + 40));
+                z = z.wrapping_add(w.0).rotate_right(33).wrapping_mul(K1);
+                v = weak_hash_len32_with_seeds(s, at, v.1.wrapping_mul(K1), x.wrapping_add(w.0));
+                w = weak_hash_len32_with_seeds(s, at + 32, z.wrapping_add(w.1), y.wrapping_add(fetch64(s, at + 16)));
+                std::mem::swap(&mut z, &mut x);
+            }
+            hash_len16(
+                hash_len16(v.0, w.0, MUL).wrapping_add(shift_mix(y).wrapping_mul(K1)).wrapping_add(z),
+                hash_len16(v.1, w.1, MUL).wrapping_add(x),
+                MUL,
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(version: LocresVersion) -> Locres {
+        let entry = |key: &str, source: &str, string: &str| LocresEntry {
+            key: key.to_string(),
+            source_hash: str_crc32(source),
+            string: string.to_string(),
+        };
+        Locres {
+            version,
+            namespaces: vec![
+                LocresNamespace {
+                    name: String::new(),
+                    entries: vec![entry("Play", "Play", "Jouer"), entry("Start", "Start", "Jouer")],
+                },
+                LocresNamespace {
+                    name: "Heroes".to_string(),
+                    entries: vec![entry("Name", "Thor", "Þórr, \"god\"")],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_locres_roundtrip() {
+        for version in [LocresVersion::Legacy, LocresVersion::Compact, LocresVersion::Optimized, LocresVersion::CityHash] {
+            let locres = sample(version);
+            let parsed = Locres::parse(&locres.to_bytes()).unwrap();
+            assert_eq!(parsed, locres);
+            assert_eq!(parsed.get("Heroes", "Name"), Some("Þórr, \"god\""));
+        }
+
+        let json = sample(LocresVersion::CityHash).render(TableFormat::Json).unwrap();
+        assert_eq!(Locres::from_json(&json).unwrap(), sample(LocresVersion::CityHash));
+        let csv = sample(LocresVersion::Legacy).render(TableFormat::Csv).unwrap();
+        assert!(csv.ends_with(&format!("Heroes,Name,{},\"Þórr, \"\"god\"\"\"\n", str_crc32("Thor"))));
+    }
+
+    #[test]
+    fn test_key_hashes() {
+        assert_eq!(cityhash64(b""), K2);
+        // First entries of CityHash's own test vectors
+        let mut data = vec![0u8; 128 * 128 + 128];
+        let (mut a, mut b) = (9u64, 777u64);
+        for (i, byte) in data.iter_mut().enumerate() {
+            a = a.wrapping_add(b);
+            b = b.wrapping_add(a);
+            a = (a ^ (a >> 41)).wrapping_mul(K0);
+            b = (b ^ (b >> 41)).wrapping_mul(K0).wrapping_add(i as u64);
+            *byte = (b >> 37) as u8;
+        }
+        assert_eq!(cityhash64(&data[1..2]), 0x541150e87f415e96);
+        assert_eq!(str_crc32("a"), crc32fast::hash(&[b'a', 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_locmeta() {
+        let mut data = Vec::new();
+        for part in LOCMETA_MAGIC {
+            data.extend_from_slice(&part.to_le_bytes());
+        }
+        data.push(1);
+        write_fstring(&mut data, "en");
+        write_fstring(&mut data, "en/Game.locres");
+        data.extend_from_slice(&2i32.to_le_bytes());
+        write_fstring(&mut data, "en");
+        write_fstring(&mut data, "fr");
+        let meta = LocMeta::parse(&data).unwrap();
+        assert_eq!(meta.native_culture, "en");
+        assert_eq!(meta.compiled_cultures, vec!["en", "fr"]);
+    }
+}
@@ -0,0 +1,62 @@
+//! Diagnostics setup for the command-line tools
+//!
+//! The library reports diagnostics only through `tracing` spans and events and
+//! never writes to stdout or stderr itself. Binaries call [`init_cli_logging`]
+//! to print those events to stderr, keeping stdout clean for listings and JSON.
+
+use clap::ValueEnum;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::EnvFilter;
+
+/// Output format for log lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum LogFormat {
+    /// Human readable lines
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// Map a `-v` count to the most verbose level that is shown
+pub fn verbosity_level(verbose: u8) -> LevelFilter {
+    match verbose {
+        0 => LevelFilter::WARN,
+        1 => LevelFilter::INFO,
+        2 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    }
+}
+
+/// Install a stderr subscriber for the CLIs
+///
+/// `RUST_LOG` takes precedence over the `-v` count when it is set. Calling
+/// this more than once keeps the first subscriber.
+pub fn init_cli_logging(verbose: u8, format: LogFormat) {
+    let filter = EnvFilter::builder()
+        .with_default_directive(verbosity_level(verbose).into())
+        .from_env_lossy();
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_target(verbose >= 2);
+
+    let _ = match format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder.json().flatten_event(true).try_init(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verbosity_level() {
+        assert_eq!(verbosity_level(0), LevelFilter::WARN);
+        assert_eq!(verbosity_level(1), LevelFilter::INFO);
+        assert_eq!(verbosity_level(2), LevelFilter::DEBUG);
+        assert_eq!(verbosity_level(5), LevelFilter::TRACE);
+    }
+}
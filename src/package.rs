@@ -0,0 +1,245 @@
+//! Grouping of raw container entries into logical packages
+//!
+//! A cooked package is split across several files: the `.uasset`/`.umap`
+//! header, the `.uexp` export data and optional `.ubulk`, `.m.ubulk` and
+//! `.uptnl` bulk data. Listings report these separately; this module folds
+//! them back into one row per package and flags incomplete packages.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::{AssetPath, FileEntry};
+
+/// Role a file plays within its package
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum PackageFileKind {
+    /// `.uasset` package header
+    Asset,
+    /// `.umap` package header
+    Map,
+    /// `.uexp` export data
+    Exports,
+    /// `.ubulk` bulk data
+    Bulk,
+    /// `.m.ubulk` memory mapped bulk data
+    MemoryMappedBulk,
+    /// `.uptnl` optional bulk data
+    OptionalBulk,
+    /// Any file that is not part of a cooked package (ini, bnk, json...)
+    Other,
+}
+
+impl PackageFileKind {
+    /// Classify a path and return the package name it belongs to
+    pub fn classify(path: &str) -> (Self, &str) {
+        let lower = path.to_ascii_lowercase();
+        let suffixes = [
+            (".m.ubulk", PackageFileKind::MemoryMappedBulk),
+            (".uasset", PackageFileKind::Asset),
+            (".umap", PackageFileKind::Map),
+            (".uexp", PackageFileKind::Exports),
+            (".ubulk", PackageFileKind::Bulk),
+            (".uptnl", PackageFileKind::OptionalBulk),
+        ];
+
+        for (suffix, kind) in suffixes {
+            if lower.ends_with(suffix) {
+                return (kind, &path[..path.len() - suffix.len()]);
+            }
+        }
+
+        (PackageFileKind::Other, path)
+    }
+
+    /// Whether this file is a package header (`.uasset` or `.umap`)
+    pub fn is_header(self) -> bool {
+        matches!(self, PackageFileKind::Asset | PackageFileKind::Map)
+    }
+}
+
+/// Which kind of container a listing came from
+///
+/// Legacy paks store export data in a separate `.uexp`, while IoStore
+/// containers merge it into the package chunk, so a missing `.uexp` is only
+/// an error for paks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContainerKind {
+    Pak,
+    IoStore,
+}
+
+impl ContainerKind {
+    /// Kind of a container file from its `.pak` or `.utoc` extension
+    pub fn from_path(path: &std::path::Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "pak" => Some(ContainerKind::Pak),
+            "utoc" => Some(ContainerKind::IoStore),
+            _ => None,
+        }
+    }
+}
+
+/// Problems detected while grouping a package
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PackageIssue {
+    /// Companion files are present but the `.uasset`/`.umap` header is not
+    MissingHeader,
+    /// A pak package header has no matching `.uexp`
+    MissingExports,
+    /// Both a `.uasset` and a `.umap` exist for the same package name
+    DuplicateHeader,
+}
+
+impl std::fmt::Display for PackageIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PackageIssue::MissingHeader => write!(f, "missing .uasset/.umap"),
+            PackageIssue::MissingExports => write!(f, "missing .uexp"),
+            PackageIssue::DuplicateHeader => write!(f, "both .uasset and .umap"),
+        }
+    }
+}
+
+/// A single file belonging to a package
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageFile {
+    pub path: AssetPath,
+    pub kind: PackageFileKind,
+    pub size: u64,
+}
+
+/// One logical package with its companion files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Package {
+    /// Package path without extension (e.g. `Game/Content/Hero/Hero`)
+    pub name: String,
+    pub files: Vec<PackageFile>,
+    /// Combined size of all files in the package
+    pub total_size: u64,
+    /// Problems found with the package's file set, empty when complete
+    pub issues: Vec<PackageIssue>,
+    /// Class of the package's asset export (e.g. `Texture2D`), once classified
+    /// with [`crate::Unpacker::classify_packages`]
+    #[serde(default)]
+    pub class: Option<String>,
+}
+
+impl Package {
+    /// Whether the package is missing one of its required files
+    pub fn is_orphan(&self) -> bool {
+        self.issues.iter().any(|i| matches!(i, PackageIssue::MissingHeader | PackageIssue::MissingExports))
+    }
+
+    /// The package header file, if present
+    pub fn header(&self) -> Option<&PackageFile> {
+        self.files.iter().find(|f| f.kind.is_header())
+    }
+
+    /// Whether the package contains a file of the given kind
+    pub fn has(&self, kind: PackageFileKind) -> bool {
+        self.files.iter().any(|f| f.kind == kind)
+    }
+
+    /// Whether the package was classified as `class`, ignoring case
+    pub fn is_class(&self, class: &str) -> bool {
+        self.class.as_deref().is_some_and(|c| c.eq_ignore_ascii_case(class))
+    }
+}
+
+/// Group flat container entries into packages, sorted by package name
+pub fn group_packages(entries: &[FileEntry], container: ContainerKind) -> Vec<Package> {
+    let mut grouped: BTreeMap<String, Vec<PackageFile>> = BTreeMap::new();
+
+    for entry in entries {
+        let (kind, name) = PackageFileKind::classify(entry.path.as_str());
+        grouped.entry(name.to_string()).or_default().push(PackageFile {
+            path: entry.path.clone(),
+            kind,
+            size: entry.size,
+        });
+    }
+
+    grouped
+        .into_iter()
+        .map(|(name, mut files)| {
+            files.sort_by(|a, b| a.kind.cmp(&b.kind).then_with(|| a.path.cmp(&b.path)));
+            let total_size = files.iter().map(|f| f.size).sum();
+            let issues = detect_issues(&files, container);
+            Package { name, files, total_size, issues, class: None }
+        })
+        .collect()
+}
+
+fn detect_issues(files: &[PackageFile], container: ContainerKind) -> Vec<PackageIssue> {
+    let mut issues = Vec::new();
+    let has = |kind: PackageFileKind| files.iter().any(|f| f.kind == kind);
+
+    if files.iter().all(|f| f.kind == PackageFileKind::Other) {
+        return issues;
+    }
+
+    let has_asset = has(PackageFileKind::Asset);
+    let has_map = has(PackageFileKind::Map);
+
+    if !has_asset && !has_map {
+        issues.push(PackageIssue::MissingHeader);
+    } else if container == ContainerKind::Pak && !has(PackageFileKind::Exports) {
+        issues.push(PackageIssue::MissingExports);
+    }
+
+    if has_asset && has_map {
+        issues.push(PackageIssue::DuplicateHeader);
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, size: u64) -> FileEntry {
+        FileEntry {
+            path: AssetPath::new(path),
+            size,
+            is_compressed: false,
+            compression: None,
+        }
+    }
+
+    #[test]
+    fn test_group_packages_with_companions_and_orphans() {
+        let entries = vec![
+            entry("Game/Content/Hero.uasset", 10),
+            entry("Game/Content/Hero.uexp", 20),
+            entry("Game/Content/Hero.ubulk", 30),
+            entry("Game/Content/Hero.m.ubulk", 5),
+            entry("Game/Content/Lonely.uexp", 7),
+            entry("Game/Content/NoExports.uasset", 3),
+            entry("Game/Config/DefaultGame.ini", 1),
+        ];
+
+        let packages = group_packages(&entries, ContainerKind::Pak);
+        assert_eq!(packages.len(), 4);
+
+        let hero = packages.iter().find(|p| p.name == "Game/Content/Hero").unwrap();
+        assert_eq!(hero.files.len(), 4);
+        assert_eq!(hero.total_size, 65);
+        assert!(hero.issues.is_empty());
+
+        let lonely = packages.iter().find(|p| p.name == "Game/Content/Lonely").unwrap();
+        assert_eq!(lonely.issues, vec![PackageIssue::MissingHeader]);
+
+        let no_exports = packages.iter().find(|p| p.name == "Game/Content/NoExports").unwrap();
+        assert_eq!(no_exports.issues, vec![PackageIssue::MissingExports]);
+
+        let ini = packages.iter().find(|p| p.name == "Game/Config/DefaultGame.ini").unwrap();
+        assert!(!ini.is_orphan());
+
+        // IoStore packages have no .uexp, so a lone .uasset is complete
+        let iostore = group_packages(&entries, ContainerKind::IoStore);
+        let no_exports = iostore.iter().find(|p| p.name == "Game/Content/NoExports").unwrap();
+        assert!(no_exports.issues.is_empty());
+    }
+}
@@ -0,0 +1,670 @@
+//! Low-level PAK footer and index parsing
+//!
+//! `repak` exposes file names and decompressed data, but not the per-entry
+//! records (sizes, offsets, compression blocks, SHA-1 hashes) stored in the
+//! index. This module reads those records directly so listings can report
+//! sizes and other tools can inspect entries without touching their data.
+
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use aes::cipher::{generic_array::GenericArray, BlockDecrypt};
+use serde::{Deserialize, Serialize};
+
+use crate::binary::ReadExt;
+use crate::error::{Result, UeToolError};
+
+/// Magic number found in every pak footer
+pub const PAK_MAGIC: u32 = 0x5A6F_12E1;
+
+/// Pak file versions, mirroring the variants understood by repak
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum PakVersion {
+    V1,
+    V2,
+    V3,
+    V4,
+    V5,
+    V6,
+    V7,
+    V8A,
+    V8B,
+    V9,
+    V10,
+    V11,
+}
+
+impl PakVersion {
+    /// Candidate versions, newest first, used when probing the footer
+    const PROBE_ORDER: [PakVersion; 12] = [
+        PakVersion::V11,
+        PakVersion::V10,
+        PakVersion::V9,
+        PakVersion::V8B,
+        PakVersion::V8A,
+        PakVersion::V7,
+        PakVersion::V6,
+        PakVersion::V5,
+        PakVersion::V4,
+        PakVersion::V3,
+        PakVersion::V2,
+        PakVersion::V1,
+    ];
+
+    /// Version number as written in the footer
+    pub fn number(self) -> u32 {
+        match self {
+            PakVersion::V1 => 1,
+            PakVersion::V2 => 2,
+            PakVersion::V3 => 3,
+            PakVersion::V4 => 4,
+            PakVersion::V5 => 5,
+            PakVersion::V6 => 6,
+            PakVersion::V7 => 7,
+            PakVersion::V8A | PakVersion::V8B => 8,
+            PakVersion::V9 => 9,
+            PakVersion::V10 => 10,
+            PakVersion::V11 => 11,
+        }
+    }
+
+    /// Size in bytes of the footer for this version
+    pub fn footer_size(self) -> u64 {
+        // magic + version + index offset + index size + index hash
+        let mut size = 4 + 4 + 8 + 8 + 20;
+        if self >= PakVersion::V4 {
+            size += 1; // encrypted index flag
+        }
+        if self >= PakVersion::V7 {
+            size += 16; // encryption key guid
+        }
+        if self == PakVersion::V9 {
+            size += 1; // frozen index flag
+        }
+        if self == PakVersion::V8A {
+            size += 32 * 4;
+        } else if self >= PakVersion::V8B {
+            size += 32 * 5;
+        }
+        size
+    }
+}
+
+impl std::fmt::Display for PakVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Location of an index section inside the pak file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexRegion {
+    pub offset: u64,
+    pub size: u64,
+    #[serde(with = "hex_hash")]
+    pub hash: [u8; 20],
+}
+
+/// A single entry record from the pak index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PakEntry {
+    /// Path relative to the mount point
+    pub path: String,
+    /// Offset of the entry header within the pak file
+    pub offset: u64,
+    /// Size of the stored (possibly compressed) data
+    pub compressed_size: u64,
+    /// Size of the data once decompressed
+    pub uncompressed_size: u64,
+    /// Compression method name, `None` for stored entries
+    pub compression: Option<String>,
+    /// SHA-1 recorded in the index (all zeroes for encoded v10+ entries)
+    #[serde(with = "hex_hash")]
+    pub hash: [u8; 20],
+    /// Absolute `[start, end)` file offsets of each compression block
+    pub blocks: Vec<(u64, u64)>,
+    /// Whether the entry data is AES encrypted
+    pub encrypted: bool,
+    /// Uncompressed size of each compression block
+    pub compression_block_size: u32,
+}
+
+impl PakEntry {
+    /// Size of the serialized entry header that precedes the data
+    pub fn header_size(&self, version: PakVersion) -> u64 {
+        let mut size = 8 + 8 + 8 + 20;
+        size += if version == PakVersion::V8A { 1 } else { 4 };
+        if version == PakVersion::V1 {
+            size += 8;
+        }
+        if version >= PakVersion::V3 {
+            if self.compression.is_some() {
+                size += 4 + 16 * self.blocks.len() as u64;
+            }
+            size += 1 + 4;
+        }
+        size
+    }
+
+    /// Offset of the first byte of entry data, `None` if a corrupt offset overflows
+    pub fn data_offset(&self, version: PakVersion) -> Option<u64> {
+        self.offset.checked_add(self.header_size(version))
+    }
+
+    /// Whether the entry is stored compressed
+    pub fn is_compressed(&self) -> bool {
+        self.compression.is_some()
+    }
+}
+
+/// Parsed pak footer and index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PakIndex {
+    pub version: PakVersion,
+    pub mount_point: String,
+    pub encrypted_index: bool,
+    #[serde(with = "hex_guid")]
+    pub encryption_guid: [u8; 16],
+    pub index: IndexRegion,
+    pub path_hash_seed: Option<u64>,
+    pub path_hash_index: Option<IndexRegion>,
+    pub full_directory_index: Option<IndexRegion>,
+    pub compression_methods: Vec<String>,
+    pub entries: Vec<PakEntry>,
+}
+
+impl PakIndex {
+    /// Open a pak file and read its index
+    pub fn open<P: AsRef<Path>>(pak_path: P, aes_key: Option<&str>) -> Result<Self> {
+        let pak_path = pak_path.as_ref();
+        if !pak_path.exists() {
+            return Err(UeToolError::file_not_found(pak_path));
+        }
+
+        let key = aes_key.map(parse_aes_key).transpose()?;
+        let file = File::open(pak_path)
+            .map_err(|e| UeToolError::IoError(format!("Failed to open PAK file: {}", e)))?;
+        let mut reader = BufReader::new(file);
+        Self::read(&mut reader, key.as_ref())
+    }
+
+    /// Read the footer and index from a pak stream
+    pub fn read<R: Read + Seek>(reader: &mut R, key: Option<&aes::Aes256>) -> Result<Self> {
+        let file_size = reader.seek(SeekFrom::End(0))?;
+        let footer = Footer::probe(reader, file_size)?;
+
+        let index_data = read_region(reader, footer.index_offset, footer.index_size, footer.encrypted_index, key)?;
+        let mut index = Cursor::new(index_data);
+
+        let mount_point = index.read_fstring()?;
+        let entry_count = index.read_u32()? as usize;
+
+        let mut path_hash_seed = None;
+        let mut path_hash_index = None;
+        let mut full_directory_index = None;
+
+        let entries = if footer.version >= PakVersion::V10 {
+            path_hash_seed = Some(index.read_u64()?);
+            if index.read_bool32()? {
+                path_hash_index = Some(read_index_region(&mut index)?);
+            }
+            if index.read_bool32()? {
+                full_directory_index = Some(read_index_region(&mut index)?);
+            }
+
+            let encoded_size = index.read_u32()? as usize;
+            let encoded = index.read_bytes(encoded_size)?;
+
+            let plain_count = index.read_u32()? as usize;
+            let mut plain = Vec::with_capacity(plain_count.min(4096));
+            for _ in 0..plain_count {
+                plain.push(read_entry(&mut index, footer.version, &footer.compression_methods)?);
+            }
+
+            let fdi = full_directory_index.as_ref().ok_or_else(|| {
+                UeToolError::PakError("PAK index has no full directory index; file names are unavailable".to_string())
+            })?;
+            let fdi_data = read_region(reader, fdi.offset, fdi.size, footer.encrypted_index, key)?;
+            read_directory_index(&fdi_data, &encoded, &plain, footer.version, &footer.compression_methods, entry_count)?
+        } else {
+            let mut entries = Vec::with_capacity(entry_count.min(4096));
+            for _ in 0..entry_count {
+                let path = index.read_fstring()?;
+                let mut entry = read_entry(&mut index, footer.version, &footer.compression_methods)?;
+                entry.path = path;
+                entries.push(entry);
+            }
+            entries
+        };
+
+        Ok(PakIndex {
+            version: footer.version,
+            mount_point,
+            encrypted_index: footer.encrypted_index,
+            encryption_guid: footer.encryption_guid,
+            index: IndexRegion {
+                offset: footer.index_offset,
+                size: footer.index_size,
+                hash: footer.index_hash,
+            },
+            path_hash_seed,
+            path_hash_index,
+            full_directory_index,
+            compression_methods: footer.compression_methods,
+            entries,
+        })
+    }
+
+    /// Look up an entry by its path relative to the mount point
+    pub fn entry(&self, path: &str) -> Option<&PakEntry> {
+        self.entries.iter().find(|e| e.path == path)
+    }
+
+    /// Total uncompressed size of all entries
+    pub fn total_uncompressed_size(&self) -> u64 {
+        self.entries.iter().map(|e| e.uncompressed_size).sum()
+    }
+}
+
+/// Parse an AES key string (hex or base64) into a cipher instance
+pub(crate) fn parse_aes_key(key: &str) -> Result<aes::Aes256> {
+    key.parse::<repak::utils::AesKey>()
+        .map(|k| k.0)
+        .map_err(|_| UeToolError::InvalidAesKey(format!("Invalid AES key format: {}", key)))
+}
+
+/// Decrypt a buffer in place using UE's AES-256 ECB scheme
+pub(crate) fn decrypt_in_place(key: &aes::Aes256, data: &mut [u8]) {
+    for block in data.chunks_exact_mut(16) {
+        key.decrypt_block(GenericArray::from_mut_slice(block));
+    }
+}
+
+/// Decoded pak footer
+struct Footer {
+    version: PakVersion,
+    encryption_guid: [u8; 16],
+    encrypted_index: bool,
+    index_offset: u64,
+    index_size: u64,
+    index_hash: [u8; 20],
+    compression_methods: Vec<String>,
+}
+
+impl Footer {
+    /// Try each known footer layout until magic and version line up
+    fn probe<R: Read + Seek>(reader: &mut R, file_size: u64) -> Result<Self> {
+        for version in PakVersion::PROBE_ORDER {
+            let footer_size = version.footer_size();
+            if footer_size > file_size {
+                continue;
+            }
+            reader.seek(SeekFrom::Start(file_size - footer_size))?;
+            let mut raw = vec![0u8; footer_size as usize];
+            reader.read_exact(&mut raw)?;
+
+            if let Some(footer) = Self::parse(&raw, version)? {
+                return Ok(footer);
+            }
+        }
+
+        Err(UeToolError::PakError("Not a PAK file: footer magic not found".to_string()))
+    }
+
+    fn parse(raw: &[u8], version: PakVersion) -> Result<Option<Self>> {
+        let mut cursor = Cursor::new(raw);
+
+        let encryption_guid = if version >= PakVersion::V7 { cursor.read_guid()? } else { [0; 16] };
+        let encrypted_index = if version >= PakVersion::V4 { cursor.read_bool()? } else { false };
+
+        if cursor.read_u32()? != PAK_MAGIC || cursor.read_u32()? != version.number() {
+            return Ok(None);
+        }
+
+        let index_offset = cursor.read_u64()?;
+        let index_size = cursor.read_u64()?;
+        let index_hash = cursor.read_fixed::<20>()?;
+
+        if version == PakVersion::V9 && cursor.read_bool()? {
+            return Err(UeToolError::PakError("Frozen PAK indices are not supported".to_string()));
+        }
+
+        let slots = match version {
+            PakVersion::V8A => 4,
+            v if v >= PakVersion::V8B => 5,
+            _ => 0,
+        };
+        let mut compression_methods = Vec::new();
+        for _ in 0..slots {
+            let name = cursor.read_fixed::<32>()?;
+            let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+            if end > 0 {
+                compression_methods.push(String::from_utf8_lossy(&name[..end]).to_string());
+            }
+        }
+        if version < PakVersion::V8A {
+            compression_methods = vec!["Zlib".to_string(), "Gzip".to_string(), "Oodle".to_string()];
+        }
+
+        Ok(Some(Footer {
+            version,
+            encryption_guid,
+            encrypted_index,
+            index_offset,
+            index_size,
+            index_hash,
+            compression_methods,
+        }))
+    }
+}
+
+/// Read (and decrypt if needed) a region of the pak file
+pub(crate) fn read_region<R: Read + Seek>(
+    reader: &mut R,
+    offset: u64,
+    size: u64,
+    encrypted: bool,
+    key: Option<&aes::Aes256>,
+) -> Result<Vec<u8>> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut data = reader.read_bytes(size as usize)?;
+
+    if encrypted {
+        let key = key.ok_or_else(|| {
+            UeToolError::EncryptionError("PAK index is encrypted and no AES key was supplied".to_string())
+        })?;
+        decrypt_in_place(key, &mut data);
+    }
+
+    Ok(data)
+}
+
+fn read_index_region<R: Read>(reader: &mut R) -> Result<IndexRegion> {
+    Ok(IndexRegion {
+        offset: reader.read_u64()?,
+        size: reader.read_u64()?,
+        hash: reader.read_fixed()?,
+    })
+}
+
+/// Map a raw compression slot to a method name
+fn compression_name(version: PakVersion, raw: u32, methods: &[String]) -> Option<String> {
+    if raw == 0 {
+        return None;
+    }
+
+    if version < PakVersion::V8A {
+        // Pre-v8 paks store a flag set rather than a slot index
+        let name = match raw {
+            0x01 => "Zlib",
+            0x02 => "Gzip",
+            _ => "Oodle",
+        };
+        return Some(name.to_string());
+    }
+
+    Some(
+        methods
+            .get(raw as usize - 1)
+            .cloned()
+            .unwrap_or_else(|| format!("Unknown({})", raw)),
+    )
+}
+
+/// Read a fully serialized `FPakEntry`
+pub(crate) fn read_entry<R: Read>(reader: &mut R, version: PakVersion, methods: &[String]) -> Result<PakEntry> {
+    let offset = reader.read_u64()?;
+    let compressed_size = reader.read_u64()?;
+    let uncompressed_size = reader.read_u64()?;
+    let raw_compression = if version == PakVersion::V8A {
+        reader.read_u8()? as u32
+    } else {
+        reader.read_u32()?
+    };
+    if version == PakVersion::V1 {
+        let _timestamp = reader.read_u64()?;
+    }
+    let hash = reader.read_fixed::<20>()?;
+
+    let compression = compression_name(version, raw_compression, methods);
+
+    let mut blocks = Vec::new();
+    let mut encrypted = false;
+    let mut compression_block_size = 0;
+    if version >= PakVersion::V3 {
+        if compression.is_some() {
+            let count = reader.read_u32()? as usize;
+            for _ in 0..count {
+                let start = reader.read_u64()?;
+                let end = reader.read_u64()?;
+                // From v5 onwards block offsets are relative to the entry
+                let base = if version >= PakVersion::V5 { offset } else { 0 };
+                match (base.checked_add(start), base.checked_add(end)) {
+                    (Some(start), Some(end)) => blocks.push((start, end)),
+                    _ => return Err(UeToolError::PakError(format!("Compression block of the entry at offset {} overflows", offset))),
+                }
+            }
+        }
+        encrypted = reader.read_u8()? & 0x01 != 0;
+        compression_block_size = reader.read_u32()?;
+    }
+
+    Ok(PakEntry {
+        path: String::new(),
+        offset,
+        compressed_size,
+        uncompressed_size,
+        compression,
+        hash,
+        blocks,
+        encrypted,
+        compression_block_size,
+    })
+}
+
+/// Decode a bit-packed v10+ entry from the encoded entries blob
+fn decode_entry(encoded: &[u8], location: usize, version: PakVersion, methods: &[String]) -> Result<PakEntry> {
+    let mut reader = Cursor::new(encoded.get(location..).ok_or_else(|| {
+        UeToolError::PakError(format!("Encoded entry offset {} out of range", location))
+    })?);
+
+    let bits = reader.read_u32()?;
+
+    let compression_block_size = if bits & 0x3f == 0x3f {
+        reader.read_u32()?
+    } else {
+        (bits & 0x3f) << 11
+    };
+    let raw_compression = (bits >> 23) & 0x3f;
+    let encrypted = bits & (1 << 22) != 0;
+    let block_count = ((bits >> 6) & 0xffff) as usize;
+
+    let offset = if bits & (1 << 31) != 0 { reader.read_u32()? as u64 } else { reader.read_u64()? };
+    let uncompressed_size = if bits & (1 << 30) != 0 { reader.read_u32()? as u64 } else { reader.read_u64()? };
+    let compressed_size = if raw_compression != 0 {
+        if bits & (1 << 29) != 0 { reader.read_u32()? as u64 } else { reader.read_u64()? }
+    } else {
+        uncompressed_size
+    };
+
+    let mut entry = PakEntry {
+        path: String::new(),
+        offset,
+        compressed_size,
+        uncompressed_size,
+        compression: compression_name(version, raw_compression, methods),
+        hash: [0; 20],
+        blocks: Vec::with_capacity(block_count),
+        encrypted,
+        compression_block_size,
+    };
+
+    // Block offsets are implied: they start right after the entry header
+    let header_size = {
+        let mut probe = entry.clone();
+        probe.blocks = vec![(0, 0); block_count];
+        probe.header_size(version)
+    };
+
+    if entry.compression.is_some() {
+        let overflow = || UeToolError::PakError(format!("Compression block of the entry at offset {} overflows", offset));
+        let mut cursor = offset.checked_add(header_size).ok_or_else(overflow)?;
+        if block_count == 1 && !encrypted {
+            entry.blocks.push((cursor, cursor.checked_add(compressed_size).ok_or_else(overflow)?));
+        } else {
+            for _ in 0..block_count {
+                let size = reader.read_u32()? as u64;
+                entry.blocks.push((cursor, cursor.checked_add(size).ok_or_else(overflow)?));
+                let stored = if encrypted { align16(size).ok_or_else(overflow)? } else { size };
+                cursor = cursor.checked_add(stored).ok_or_else(overflow)?;
+            }
+        }
+    }
+
+    Ok(entry)
+}
+
+/// Walk the full directory index, resolving each file to its entry
+fn read_directory_index(
+    data: &[u8],
+    encoded: &[u8],
+    plain: &[PakEntry],
+    version: PakVersion,
+    methods: &[String],
+    expected: usize,
+) -> Result<Vec<PakEntry>> {
+    let mut reader = Cursor::new(data);
+    let mut entries = Vec::with_capacity(expected.min(65536));
+
+    let dir_count = reader.read_u32()?;
+    for _ in 0..dir_count {
+        let dir_name = reader.read_fstring()?;
+        let file_count = reader.read_u32()?;
+        for _ in 0..file_count {
+            let file_name = reader.read_fstring()?;
+            let location = reader.read_i32()?;
+
+            let mut entry = if location >= 0 {
+                decode_entry(encoded, location as usize, version, methods)?
+            } else {
+                let index = (-(location as i64) - 1) as usize;
+                plain.get(index).cloned().ok_or_else(|| {
+                    UeToolError::PakError(format!("Entry index {} out of range", index))
+                })?
+            };
+
+            entry.path = if dir_name == "/" {
+                file_name
+            } else {
+                format!("{}{}", dir_name.strip_prefix('/').unwrap_or(&dir_name), file_name)
+            };
+            entries.push(entry);
+        }
+    }
+
+    Ok(entries)
+}
+
+/// `value` rounded up to the AES block size, `None` if that overflows
+pub(crate) fn align16(value: u64) -> Option<u64> {
+    value.checked_add(15).map(|v| v & !15)
+}
+
+/// Serialize fixed-size hashes as hex strings
+mod hex_hash {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(hash: &[u8; 20], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(hash))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<[u8; 20], D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let bytes = hex::decode(&s).map_err(serde::de::Error::custom)?;
+        bytes.try_into().map_err(|_| serde::de::Error::custom("expected 20 byte hash"))
+    }
+}
+
+mod hex_guid {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(guid: &[u8; 16], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(guid))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<[u8; 16], D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let bytes = hex::decode(&s).map_err(serde::de::Error::custom)?;
+        bytes.try_into().map_err(|_| serde::de::Error::custom("expected 16 byte guid"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::write_fstring;
+
+    /// Build a minimal uncompressed v8B pak with a single entry
+    fn build_v8b_pak(path: &str, data: &[u8]) -> Vec<u8> {
+        let mut pak = Vec::new();
+
+        // Entry header followed by data
+        let mut header = Vec::new();
+        header.extend_from_slice(&0u64.to_le_bytes());
+        header.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        header.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&[0xAB; 20]);
+        header.push(0);
+        header.extend_from_slice(&0u32.to_le_bytes());
+        pak.extend_from_slice(&header);
+        pak.extend_from_slice(data);
+
+        // Index
+        let index_offset = pak.len() as u64;
+        let mut index = Vec::new();
+        write_fstring(&mut index, "../../../");
+        index.extend_from_slice(&1u32.to_le_bytes());
+        write_fstring(&mut index, path);
+        index.extend_from_slice(&0u64.to_le_bytes());
+        index.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        index.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        index.extend_from_slice(&0u32.to_le_bytes());
+        index.extend_from_slice(&[0xAB; 20]);
+        index.push(0);
+        index.extend_from_slice(&0u32.to_le_bytes());
+        pak.extend_from_slice(&index);
+
+        // Footer
+        pak.extend_from_slice(&[0; 16]);
+        pak.push(0);
+        pak.extend_from_slice(&PAK_MAGIC.to_le_bytes());
+        pak.extend_from_slice(&8u32.to_le_bytes());
+        pak.extend_from_slice(&index_offset.to_le_bytes());
+        pak.extend_from_slice(&(index.len() as u64).to_le_bytes());
+        pak.extend_from_slice(&[0; 20]);
+        let mut zlib = [0u8; 32];
+        zlib[..4].copy_from_slice(b"Zlib");
+        pak.extend_from_slice(&zlib);
+        pak.extend_from_slice(&[0; 32 * 4]);
+        pak
+    }
+
+    #[test]
+    fn test_read_v8b_index() {
+        let pak = build_v8b_pak("Game/Content/Hero.uasset", b"hello");
+        let index = PakIndex::read(&mut Cursor::new(pak), None).unwrap();
+
+        assert_eq!(index.version, PakVersion::V8B);
+        assert_eq!(index.mount_point, "../../../");
+        assert_eq!(index.compression_methods, vec!["Zlib".to_string()]);
+        assert_eq!(index.entries.len(), 1);
+
+        let entry = &index.entries[0];
+        assert_eq!(entry.path, "Game/Content/Hero.uasset");
+        assert_eq!(entry.uncompressed_size, 5);
+        assert!(!entry.is_compressed());
+        assert_eq!(entry.data_offset(index.version), Some(53));
+    }
+}
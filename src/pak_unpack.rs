@@ -3,44 +3,67 @@
 //! This module provides programmatic access to unpacking Unreal Engine .pak files
 //! using the repak library.
 
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::io::BufReader;
 use std::fs::File;
 
 use crate::error::{Result, UeToolError};
-use crate::types::{AssetPath, PakUnpackOptions, UnpackedFile, ProgressInfo, ProgressCallback};
+use crate::extract::join_entry_path;
+use crate::types::{AssetPath, PakUnpackOptions, UnpackedFile, FileEntry};
+use crate::pak_index::PakIndex;
+use crate::signature::signature_path;
+use crate::cancel::{self, CancellationToken, Interrupted, PartialResult};
+use crate::progress::{Phase, ProgressCallback, ProgressSink};
 
 /// Main struct for unpacking pak files
 pub struct PakUnpacker {
-    progress_callback: Option<ProgressCallback>,
+    progress: ProgressSink,
+    cancellation: Option<CancellationToken>,
 }
 
 impl PakUnpacker {
     /// Create a new pak unpacker
     pub fn new() -> Self {
         Self {
-            progress_callback: None,
+            progress: ProgressSink::disabled(),
+            cancellation: None,
         }
     }
 
     /// Set progress callback for long operations
     pub fn with_progress_callback(mut self, callback: ProgressCallback) -> Self {
-        self.progress_callback = Some(callback);
+        self.progress = ProgressSink::new(callback);
+        self
+    }
+
+    /// Report progress to an existing sink, shared with other components
+    pub fn with_progress_sink(mut self, sink: ProgressSink) -> Self {
+        self.progress = sink;
+        self
+    }
+
+    /// Set a cancellation token that is checked between entries
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
         self
     }
 
     /// Unpack a pak file to the specified output directory
+    ///
+    /// If the operation fails or is cancelled part way through, the files
+    /// already written are returned inside the [`Interrupted`] error.
     pub fn unpack<P: AsRef<Path>>(
         &mut self,
         pak_path: P,
         output_dir: P,
         options: &PakUnpackOptions,
-    ) -> Result<Vec<UnpackedFile>> {
+    ) -> PartialResult<Vec<UnpackedFile>> {
         let pak_path = pak_path.as_ref();
         let output_dir = output_dir.as_ref();
 
         if !pak_path.exists() {
-            return Err(UeToolError::file_not_found(pak_path));
+            return Err(UeToolError::file_not_found(pak_path).into());
         }
 
         if !output_dir.exists() {
@@ -48,161 +71,165 @@ impl PakUnpacker {
                 .map_err(|e| UeToolError::IoError(format!("Failed to create output directory: {}", e)))?;
         }
 
-        self.report_progress(ProgressInfo {
-            percentage: 0,
-            message: "Opening PAK file".to_string(),
-            processed: 0,
-            total: 1,
-        });
-
-        // Create PakBuilder and open the pak file
-        let mut builder = repak::PakBuilder::new();
-        if let Some(ref aes_key) = options.aes_key {
-            if let Ok(key) = aes_key.parse::<repak::utils::AesKey>() {
-                builder = builder.key(key.0);
-            } else {
-                return Err(UeToolError::InvalidAesKey(format!("Invalid AES key format: {}", aes_key)));
-            }
-        }
-
-        let pak_file = File::open(pak_path)
-            .map_err(|e| UeToolError::IoError(format!("Failed to open PAK file: {}", e)))?;
-        let mut reader = BufReader::new(pak_file);
-        let pak = builder.reader(&mut reader)
-            .map_err(|e| UeToolError::PakError(format!("Failed to read PAK file: {}", e)))?;
-
-        self.report_progress(ProgressInfo {
-            percentage: 20,
-            message: "Reading file list".to_string(),
-            processed: 0,
-            total: 1,
-        });
+        let _span = tracing::info_span!("unpack_pak", container = %pak_path.display()).entered();
+        let opening = self.progress.phase(Phase::OpenContainer, 0, 0);
+        let (pak, mut reader) = open_pak(pak_path, options.aes_key.as_deref())?;
 
         let files = pak.files();
-        let total_files = files.len();
 
-        self.report_progress(ProgressInfo {
-            percentage: 30,
-            message: format!("Found {} files to unpack", total_files),
-            processed: 0,
-            total: total_files as u64,
-        });
+        // Entry sizes come from the pak index so byte totals are known up front;
+        // repak does not expose them, and without them progress only counts entries
+        let sizes: HashMap<String, u64> = if self.progress.is_enabled() {
+            match PakIndex::open(pak_path, options.aes_key.as_deref()) {
+                Ok(index) => index.entries.into_iter().map(|e| (e.path, e.uncompressed_size)).collect(),
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to read entry sizes; progress will not report bytes");
+                    HashMap::new()
+                }
+            }
+        } else {
+            HashMap::new()
+        };
+        opening.finish();
+
+        // Destinations are checked up front so a hostile path fails the unpack before anything is written
+        let mut destinations = Vec::with_capacity(files.len());
+        for file_path in &files {
+            match join_entry_path(output_dir, strip_path_prefix(file_path, &options.strip_prefix)) {
+                Ok(dest) => destinations.push(dest),
+                Err(e) => {
+                    tracing::error!(entry = %file_path, error = %e, "Refused to unpack entry");
+                    return Err(Interrupted::new(Vec::new(), e));
+                }
+            }
+        }
 
+        tracing::debug!(entries = files.len(), "Opened PAK file");
+        let mut progress = self.progress.phase(Phase::Unpack, files.len() as u64, sizes.values().sum());
         let mut unpacked_files = Vec::new();
-        let mut processed = 0;
 
         // Process each file
-        for file_path in files {
-            processed += 1;
-            let progress_percentage = 30 + ((processed as f64 / total_files as f64) * 70.0) as u8;
-
-            self.report_progress(ProgressInfo {
-                percentage: progress_percentage,
-                message: format!("Unpacking: {}", file_path),
-                processed: processed as u64,
-                total: total_files as u64,
-            });
-
-            // Apply strip prefix if specified
-            let stripped_path = if !options.strip_prefix.is_empty() {
-                file_path.strip_prefix(&options.strip_prefix).unwrap_or(&file_path)
-            } else {
-                &file_path
-            };
-
-            // Create output path
-            let output_path = output_dir.join(stripped_path);
-
-            // Create parent directories
-            if let Some(parent) = output_path.parent() {
-                std::fs::create_dir_all(parent)
-                    .map_err(|e| UeToolError::IoError(format!("Failed to create directory: {}", e)))?;
+        for (file_path, output_path) in files.into_iter().zip(destinations) {
+            if let Err(e) = cancel::check(self.cancellation.as_ref()) {
+                return Err(Interrupted::new(unpacked_files, e));
             }
 
-            // Read file data
-            let data = pak.get(&file_path, &mut reader)
-                .map_err(|e| UeToolError::PakError(format!("Failed to read file {}: {}", file_path, e)))?;
-
-            // Write file
-            std::fs::write(&output_path, &data)
-                .map_err(|e| UeToolError::IoError(format!("Failed to write file {}: {}", output_path.display(), e)))?;
-
-            // Create UnpackedFile info
-            let unpacked_file = UnpackedFile {
-                original_path: AssetPath::new(file_path.clone()),
-                output_path: output_path.clone(),
-                size: data.len() as u64,
-                error: None,
-            };
-
-            unpacked_files.push(unpacked_file);
+            progress.entry_started(&file_path, sizes.get(&file_path).copied().unwrap_or(0));
+
+            match Self::unpack_entry(&pak, &mut reader, &file_path, output_path) {
+                Ok(unpacked_file) => {
+                    tracing::trace!(entry = %file_path, bytes = unpacked_file.size, "Unpacked entry");
+                    progress.entry_finished(&file_path, unpacked_file.size);
+                    unpacked_files.push(unpacked_file);
+                }
+                Err(e) => {
+                    tracing::error!(entry = %file_path, error = %e, "Failed to unpack entry");
+                    return Err(Interrupted::new(unpacked_files, e));
+                }
+            }
         }
 
-        self.report_progress(ProgressInfo {
-            percentage: 100,
-            message: format!("Completed - unpacked {} files", unpacked_files.len()),
-            processed: processed as u64,
-            total: processed as u64,
-        });
-
+        progress.finish();
         Ok(unpacked_files)
     }
 
-    /// Report progress to callback if set
-    fn report_progress(&mut self, progress: ProgressInfo) {
-        if let Some(ref mut callback) = self.progress_callback {
-            callback(progress);
+    /// Extract a single entry from an open pak to `output_path`
+    fn unpack_entry<R: std::io::Read + std::io::Seek>(
+        pak: &repak::PakReader,
+        reader: &mut R,
+        file_path: &str,
+        output_path: PathBuf,
+    ) -> Result<UnpackedFile> {
+        // Create parent directories
+        if let Some(parent) = output_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| UeToolError::IoError(format!("Failed to create directory: {}", e)))?;
         }
+
+        // Read file data
+        let data = pak.get(file_path, reader)
+            .map_err(|e| UeToolError::PakError(format!("Failed to read file {}: {}", file_path, e)))?;
+
+        // Write file
+        std::fs::write(&output_path, &data)
+            .map_err(|e| UeToolError::IoError(format!("Failed to write file {}: {}", output_path.display(), e)))?;
+
+        Ok(UnpackedFile {
+            original_path: AssetPath::new(file_path),
+            output_path,
+            size: data.len() as u64,
+            error: None,
+        })
     }
 
     /// List files in a pak file without extracting them
+    ///
+    /// Paths are relative to the pak's mount point, as stored in the index.
     pub fn list_files<P: AsRef<Path>>(
         &mut self,
         pak_path: P,
-        _options: &PakUnpackOptions,
+        options: &PakUnpackOptions,
     ) -> Result<Vec<AssetPath>> {
-        let pak_path = pak_path.as_ref();
-
-        if !pak_path.exists() {
-            return Err(UeToolError::file_not_found(pak_path));
-        }
-
-        // Create PakBuilder and open the pak file
-        let mut builder = repak::PakBuilder::new();
-
-        let pak_file = File::open(pak_path)
-            .map_err(|e| UeToolError::IoError(format!("Failed to open PAK file: {}", e)))?;
-        let mut reader = BufReader::new(pak_file);
-        
-        let pak = builder.reader(&mut reader)
-            .map_err(|e| UeToolError::PakError(format!("Failed to read PAK file: {}", e)))?;
+        let (pak, _) = open_pak(pak_path.as_ref(), options.aes_key.as_deref())?;
 
         // Just return the file list without reading content
         let files = pak.files();
-        Ok(files.into_iter().map(|path| AssetPath::new(path)).collect())
+        Ok(files.into_iter().map(AssetPath::new).collect())
+    }
+
+    /// List files in a pak file as full paths, with the strip prefix removed
+    ///
+    /// Each entry is joined to the mount point (typically `../../../`) before
+    /// `options.strip_prefix` is removed, so paths line up with the ones
+    /// listed from IoStore containers. Only the index is read.
+    pub fn list_mounted_files<P: AsRef<Path>>(
+        &mut self,
+        pak_path: P,
+        options: &PakUnpackOptions,
+    ) -> Result<Vec<AssetPath>> {
+        let (pak, _) = open_pak(pak_path.as_ref(), options.aes_key.as_deref())?;
+        let mount_point = pak.mount_point();
+
+        Ok(pak
+            .files()
+            .into_iter()
+            .map(|file| {
+                let full = format!("{}{}", mount_point, file);
+                AssetPath::new(strip_path_prefix(&full, &options.strip_prefix))
+            })
+            .collect())
+    }
+
+    /// List file entries in a pak file, with sizes, without extracting them
+    ///
+    /// Sizes come straight from the pak index, so no entry data is read.
+    pub fn list_entries<P: AsRef<Path>>(
+        &mut self,
+        pak_path: P,
+        options: &PakUnpackOptions,
+    ) -> Result<Vec<FileEntry>> {
+        let index = PakIndex::open(pak_path, options.aes_key.as_deref())?;
+
+        Ok(index
+            .entries
+            .into_iter()
+            .map(|entry| FileEntry {
+                is_compressed: entry.is_compressed(),
+                compression: entry.compression.as_deref().and_then(|c| c.parse().ok()),
+                size: entry.uncompressed_size,
+                path: AssetPath::new(entry.path),
+            })
+            .collect())
     }
 
     /// Get information about a pak file
     pub fn get_info<P: AsRef<Path>>(
         &mut self,
         pak_path: P,
-        _options: &PakUnpackOptions,
+        options: &PakUnpackOptions,
     ) -> Result<serde_json::Value> {
         let pak_path = pak_path.as_ref();
-
-        if !pak_path.exists() {
-            return Err(UeToolError::file_not_found(pak_path));
-        }
-
-        // Create PakBuilder and open the pak file
-        let mut builder = repak::PakBuilder::new();
-
-        let pak_file = File::open(pak_path)
-            .map_err(|e| UeToolError::IoError(format!("Failed to open PAK file: {}", e)))?;
-        let mut reader = BufReader::new(pak_file);
-        
-        let pak = builder.reader(&mut reader)
-            .map_err(|e| UeToolError::PakError(format!("Failed to read PAK file: {}", e)))?;
+        let (pak, _) = open_pak(pak_path, options.aes_key.as_deref())?;
 
         let files = pak.files();
         let file_count = files.len();
@@ -210,6 +237,7 @@ impl PakUnpacker {
         let info = serde_json::json!({
             "file_path": pak_path.to_string_lossy(),
             "file_count": file_count,
+            "signed": signature_path(pak_path).exists(),
             "file_names": files
         });
 
@@ -221,4 +249,73 @@ impl Default for PakUnpacker {
     fn default() -> Self {
         Self::new()
     }
+}
+
+/// Open a pak and read its index, decrypting it with the AES key if given
+///
+/// Only the footer and index are read; the returned reader is positioned
+/// wherever repak left it and can be used to read entries.
+pub(crate) fn open_pak(pak_path: &Path, aes_key: Option<&str>) -> Result<(repak::PakReader, BufReader<File>)> {
+    if !pak_path.exists() {
+        return Err(UeToolError::file_not_found(pak_path));
+    }
+
+    let mut builder = repak::PakBuilder::new();
+    if let Some(aes_key) = aes_key {
+        match aes_key.parse::<repak::utils::AesKey>() {
+            Ok(key) => builder = builder.key(key.0),
+            Err(_) => return Err(UeToolError::InvalidAesKey(format!("Invalid AES key format: {}", aes_key))),
+        }
+    }
+
+    let pak_file = File::open(pak_path)
+        .map_err(|e| UeToolError::IoError(format!("Failed to open PAK file: {}", e)))?;
+    let mut reader = BufReader::new(pak_file);
+    let pak = builder.reader(&mut reader)
+        .map_err(|e| UeToolError::PakError(format!("Failed to read PAK file: {}", e)))?;
+
+    Ok((pak, reader))
+}
+
+/// Remove `prefix` from the start of a container path, if present
+pub(crate) fn strip_path_prefix<'a>(path: &'a str, prefix: &str) -> &'a str {
+    if prefix.is_empty() {
+        path
+    } else {
+        path.strip_prefix(prefix).unwrap_or(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_path_prefix() {
+        assert_eq!(strip_path_prefix("../../../Game/A.uasset", "../../../"), "Game/A.uasset");
+        assert_eq!(strip_path_prefix("Game/A.uasset", "../../../"), "Game/A.uasset");
+        assert_eq!(strip_path_prefix("../../../Game/A.uasset", ""), "../../../Game/A.uasset");
+    }
+
+    #[test]
+    fn test_unpack_refuses_traversal() {
+        let dir = tempfile::tempdir().unwrap();
+        let pak_path = dir.path().join("Evil.pak");
+        let mut writer = repak::PakBuilder::new().writer(
+            std::io::BufWriter::new(File::create(&pak_path).unwrap()),
+            repak::Version::V8B,
+            "../../../".to_string(),
+            None,
+        );
+        writer.write_file("Game/A.uasset", false, b"a").unwrap();
+        writer.write_file("../../escaped.txt", false, b"x").unwrap();
+        writer.write_index().unwrap();
+
+        let out = dir.path().join("out");
+        let error = PakUnpacker::new().unpack(&pak_path, &out, &PakUnpackOptions::new()).unwrap_err();
+        assert!(error.partial.is_empty());
+        assert!(matches!(error.error, UeToolError::InvalidFormat(_)));
+        assert!(!dir.path().join("escaped.txt").exists());
+        assert!(!out.join("Game/A.uasset").exists());
+    }
 }
\ No newline at end of file
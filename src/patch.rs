@@ -0,0 +1,253 @@
+//! Generation of `_P` patch paks from an edited tree
+//!
+//! Mod authors unpack a pak, edit a few files and want a pak holding only
+//! what changed. [`Unpacker::make_patch`] diffs the base pak against the
+//! edited directory and writes the added and modified entries to a new pak
+//! with the base's version, mount point, path hash seed and compression, named
+//! so that it loads after the base.
+//!
+//! Removed files cannot be expressed in a patch pak and are only reported.
+
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::cancel;
+use crate::diff::ChangeKind;
+use crate::error::{Result, UeToolError};
+use crate::pak_index::{PakIndex, PakVersion};
+use crate::progress::Phase;
+use crate::types::{AssetPath, CompressionMethod, DiffOptions, PatchOptions};
+use crate::vfs::normalize_path;
+use crate::Unpacker;
+
+/// Result of writing a patch pak
+#[derive(Debug, Clone, Serialize)]
+pub struct PatchResult {
+    /// The pak that was written
+    pub path: PathBuf,
+    pub mount_point: String,
+    pub compression: Vec<CompressionMethod>,
+    /// Entries written to the patch, relative to the mount point
+    pub added: Vec<AssetPath>,
+    pub modified: Vec<AssetPath>,
+    /// Entries missing from the edited tree, which a patch cannot remove
+    pub removed: Vec<AssetPath>,
+}
+
+impl Unpacker {
+    /// Write a `_P` pak holding the entries of `edited` that differ from `base`
+    ///
+    /// `edited` is a directory laid out either relative to the base's mount
+    /// point, as [`crate::PakUnpacker::unpack`] writes it, or with the mount
+    /// point kept and the `../../../` prefix stripped, e.g.
+    /// `Marvel/Content/...`, as [`Unpacker::extract_many`] writes it. The
+    /// second is assumed when `edited` has the mount point's directory. Every
+    /// changed file must lie under the base's mount point. The patch is
+    /// written next to the base unless [`PatchOptions::output_dir`] is set,
+    /// and is named by [`patch_file_name`]; an existing file is only
+    /// overwritten with [`PatchOptions::force`].
+    pub fn make_patch<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, base: P, edited: Q, options: &PatchOptions) -> Result<PatchResult> {
+        let (base, edited) = (base.as_ref(), edited.as_ref());
+        let _span = tracing::info_span!("make_patch", base = %base.display(), edited = %edited.display()).entered();
+
+        if base.extension().is_none_or(|ext| !ext.eq_ignore_ascii_case("pak")) {
+            return Err(UeToolError::invalid_argument("Patches can only be built against a .pak base"));
+        }
+        if !edited.is_dir() {
+            return Err(UeToolError::invalid_argument(format!("{} is not a directory", edited.display())));
+        }
+
+        let index = PakIndex::open(base, options.aes_key.as_deref())?;
+        let mount = normalize_path(&index.mount_point);
+        let prefix = if mount.is_empty() || edited.join(&mount).is_dir() { "" } else { mount.as_str() };
+        let mut diff_options = DiffOptions::new();
+        diff_options.aes_key = options.aes_key.clone();
+        let report = self.diff_tree(base, edited, prefix, &diff_options)?;
+
+        let relative = |path: &str| -> Result<String> {
+            path.get(..mount.len())
+                .filter(|prefix| prefix.eq_ignore_ascii_case(&mount))
+                .map(|_| path[mount.len()..].to_string())
+                .ok_or_else(|| {
+                    UeToolError::invalid_argument(format!("{} is outside the base mount point {}", path, index.mount_point))
+                })
+        };
+
+        let mut result = PatchResult {
+            path: options
+                .output_dir
+                .as_deref()
+                .or_else(|| base.parent())
+                .unwrap_or(Path::new("."))
+                .join(patch_file_name(base)),
+            mount_point: index.mount_point.clone(),
+            compression: index
+                .compression_methods
+                .iter()
+                .filter_map(|name| CompressionMethod::from_str(name).ok())
+                .filter(|method| *method != CompressionMethod::None)
+                .collect(),
+            added: Vec::new(),
+            modified: Vec::new(),
+            removed: Vec::new(),
+        };
+
+        let mut writes = Vec::new();
+        for entry in &report.entries {
+            let path = AssetPath::new(relative(&entry.path)?);
+            match entry.change {
+                ChangeKind::Added => result.added.push(path.clone()),
+                ChangeKind::Modified => result.modified.push(path.clone()),
+                ChangeKind::Removed => {
+                    tracing::warn!(path = %entry.path, "Removed file cannot be expressed in a patch pak");
+                    result.removed.push(path);
+                    continue;
+                }
+            }
+            writes.push((edited.join(&entry.path[prefix.len()..]), path));
+        }
+        if writes.is_empty() {
+            return Err(UeToolError::invalid_argument(format!(
+                "{} has no added or modified files compared to {}",
+                edited.display(),
+                base.display()
+            )));
+        }
+
+        // New files are compressed whenever the base uses compression
+        let compress_new = !result.compression.is_empty();
+        if result.path.exists() && !options.force {
+            return Err(UeToolError::invalid_argument(format!(
+                "{} already exists; use force to overwrite",
+                result.path.display()
+            )));
+        }
+        let builder = repak::PakBuilder::new().compression(result.compression.iter().filter_map(repak_compression));
+        let file = File::create(&result.path)
+            .map_err(|e| UeToolError::IoError(format!("Failed to create {}: {}", result.path.display(), e)))?;
+        let mut writer = builder.writer(
+            BufWriter::new(file),
+            repak_version(index.version),
+            index.mount_point.clone(),
+            index.path_hash_seed,
+        );
+
+        let mut progress = self.progress.phase(Phase::Pack, writes.len() as u64, 0);
+        for (source, path) in &writes {
+            cancel::check(self.cancellation.as_ref())?;
+            let data = fs::read(source)
+                .map_err(|e| UeToolError::IoError(format!("Failed to read {}: {}", source.display(), e)))?;
+            let compress = index.entry(path.as_str()).map_or(compress_new, |e| e.is_compressed());
+            progress.entry_started(path.as_str(), data.len() as u64);
+            writer
+                .write_file(path.as_str(), compress, &data)
+                .map_err(|e| UeToolError::PakError(format!("Failed to write {}: {}", path, e)))?;
+            progress.entry_finished(path.as_str(), data.len() as u64);
+        }
+        writer
+            .write_index()
+            .map_err(|e| UeToolError::PakError(format!("Failed to write PAK index: {}", e)))?;
+        progress.finish();
+
+        tracing::info!(
+            patch = %result.path.display(),
+            added = result.added.len(),
+            modified = result.modified.len(),
+            "Wrote patch pak"
+        );
+        Ok(result)
+    }
+}
+
+/// File name of a patch that loads after `base`
+///
+/// `Mod.pak` becomes `Mod_P.pak`, and an existing patch `Mod_P.pak` or
+/// `Mod_<N>_P.pak` becomes `Mod_<N+1>_P.pak`, which has a higher chunk version.
+pub fn patch_file_name(base: &Path) -> String {
+    let stem = base.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let Some(unpatched) = stem.strip_suffix("_P").or_else(|| stem.strip_suffix("_p")) else {
+        return format!("{}_P.pak", stem);
+    };
+
+    match unpatched.rsplit_once('_').and_then(|(name, n)| Some((name, n.parse::<u32>().ok()?.checked_add(1)?))) {
+        Some((name, version)) => format!("{}_{}_P.pak", name, version),
+        None => format!("{}_1_P.pak", unpatched),
+    }
+}
+
+fn repak_version(version: PakVersion) -> repak::Version {
+    match version {
+        PakVersion::V1 => repak::Version::V1,
+        PakVersion::V2 => repak::Version::V2,
+        PakVersion::V3 => repak::Version::V3,
+        PakVersion::V4 => repak::Version::V4,
+        PakVersion::V5 => repak::Version::V5,
+        PakVersion::V6 => repak::Version::V6,
+        PakVersion::V7 => repak::Version::V7,
+        PakVersion::V8A => repak::Version::V8A,
+        PakVersion::V8B => repak::Version::V8B,
+        PakVersion::V9 => repak::Version::V9,
+        PakVersion::V10 => repak::Version::V10,
+        PakVersion::V11 => repak::Version::V11,
+    }
+}
+
+fn repak_compression(method: &CompressionMethod) -> Option<repak::Compression> {
+    match method {
+        CompressionMethod::None => None,
+        CompressionMethod::Zlib => Some(repak::Compression::Zlib),
+        CompressionMethod::Gzip => Some(repak::Compression::Gzip),
+        CompressionMethod::Oodle => Some(repak::Compression::Oodle),
+        CompressionMethod::Zstd => Some(repak::Compression::Zstd),
+        CompressionMethod::Lz4 => Some(repak::Compression::LZ4),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patch_file_name() {
+        assert_eq!(patch_file_name(Path::new("Paks/pakchunk0-Windows.pak")), "pakchunk0-Windows_P.pak");
+        assert_eq!(patch_file_name(Path::new("Mod_P.pak")), "Mod_1_P.pak");
+        assert_eq!(patch_file_name(Path::new("Mod_2_P.pak")), "Mod_3_P.pak");
+        assert_eq!(patch_file_name(Path::new("Mod_4294967295_P.pak")), "Mod_4294967295_1_P.pak");
+    }
+
+    #[test]
+    fn test_patch_from_unpacked_pak() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("Mod.pak");
+        let mut writer = repak::PakBuilder::new().writer(
+            BufWriter::new(File::create(&base).unwrap()),
+            repak::Version::V8B,
+            "../../../Marvel/Content/".to_string(),
+            None,
+        );
+        writer.write_file("UI/Icon.uasset", false, b"icon").unwrap();
+        writer.write_file("UI/Menu.uasset", false, b"menu").unwrap();
+        writer.write_index().unwrap();
+
+        // Unpacked relative to the mount point, then edited
+        let edited = dir.path().join("Mod");
+        crate::PakUnpacker::new().unpack(&base, &edited, &Default::default()).unwrap();
+        fs::write(edited.join("UI/Menu.uasset"), b"new menu").unwrap();
+        fs::write(edited.join("UI/Added.uasset"), b"added").unwrap();
+
+        let options = PatchOptions::new();
+        let result = Unpacker::new().make_patch(&base, &edited, &options).unwrap();
+        assert_eq!(result.added, vec![AssetPath::new("UI/Added.uasset")]);
+        assert_eq!(result.modified, vec![AssetPath::new("UI/Menu.uasset")]);
+
+        let patch = PakIndex::open(&result.path, None).unwrap();
+        assert_eq!(patch.mount_point, "../../../Marvel/Content/");
+        assert_eq!(patch.entries.len(), 2);
+        assert!(Unpacker::new().make_patch(&base, &edited, &options).is_err());
+        assert!(Unpacker::new().make_patch(&base, &edited, &options.with_force(true)).is_ok());
+    }
+}
@@ -0,0 +1,376 @@
+//! Typed progress events for long running operations
+//!
+//! Operations report a [`ProgressEvent`] stream: a phase starts with its
+//! known totals, entries start and finish with their byte counts, and the
+//! running byte/item counters are sent as [`ProgressEvent::Bytes`]. Entry and
+//! byte events are throttled so a pak with 100k small files does not call the
+//! callback 100k times; phase events are always delivered.
+
+#[cfg(feature = "archive")]
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "progress")]
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde::{Deserialize, Serialize};
+
+/// Minimum time between throttled events from one phase
+pub const DEFAULT_PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Stage of a long running operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Phase {
+    /// Opening a container and reading its index
+    OpenContainer,
+    /// Writing pak entries to disk
+    Unpack,
+    /// Walking the chunks of an IoStore container
+    List,
+    /// Extracting a ZIP, RAR or 7z archive to a temporary directory
+    ExtractArchive,
+    /// Reading the containers found inside an extracted archive
+    ScanContainers,
+    /// Reading and hashing container entries
+    Hash,
+    /// Writing entries to a new pak
+    Pack,
+    /// Checking entries against their recorded hashes
+    Verify,
+    /// Parsing package headers to find their dependencies
+    Analyze,
+}
+
+impl std::fmt::Display for Phase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Phase::OpenContainer => write!(f, "Opening"),
+            Phase::Unpack => write!(f, "Unpacking"),
+            Phase::List => write!(f, "Listing"),
+            Phase::ExtractArchive => write!(f, "Extracting archive"),
+            Phase::ScanContainers => write!(f, "Scanning containers"),
+            Phase::Hash => write!(f, "Hashing"),
+            Phase::Pack => write!(f, "Packing"),
+            Phase::Verify => write!(f, "Verifying"),
+            Phase::Analyze => write!(f, "Analyzing"),
+        }
+    }
+}
+
+/// A progress update from a long running operation
+///
+/// Totals of `0` mean the total is not known.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProgressEvent {
+    PhaseStarted {
+        phase: Phase,
+        items_total: u64,
+        bytes_total: u64,
+    },
+    PhaseFinished {
+        phase: Phase,
+        items_processed: u64,
+        bytes_processed: u64,
+        elapsed: Duration,
+    },
+    EntryStarted {
+        path: String,
+        bytes: u64,
+    },
+    EntryFinished {
+        path: String,
+        bytes: u64,
+    },
+    Bytes {
+        processed: u64,
+        total: u64,
+        items_processed: u64,
+        items_total: u64,
+    },
+}
+
+/// Callback for progress updates
+pub type ProgressCallback = Box<dyn FnMut(ProgressEvent) + Send + Sync>;
+
+/// Shared destination for progress events
+///
+/// Cloning the sink shares the callback, so an [`crate::Unpacker`] and the
+/// pak and utoc components it drives report to the same place.
+#[derive(Clone)]
+pub struct ProgressSink {
+    callback: Option<Arc<Mutex<ProgressCallback>>>,
+    interval: Duration,
+}
+
+impl ProgressSink {
+    /// Create a sink that forwards events to the callback
+    pub fn new(callback: ProgressCallback) -> Self {
+        Self {
+            callback: Some(Arc::new(Mutex::new(callback))),
+            interval: DEFAULT_PROGRESS_INTERVAL,
+        }
+    }
+
+    /// Create a sink that drops every event
+    pub fn disabled() -> Self {
+        Self {
+            callback: None,
+            interval: DEFAULT_PROGRESS_INTERVAL,
+        }
+    }
+
+    /// Set the minimum time between throttled events
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Whether a callback is attached
+    pub fn is_enabled(&self) -> bool {
+        self.callback.is_some()
+    }
+
+    /// Send an event to the callback, bypassing throttling
+    pub fn emit(&self, event: ProgressEvent) {
+        if let Some(ref callback) = self.callback {
+            if let Ok(mut callback) = callback.lock() {
+                callback(event);
+            }
+        }
+    }
+
+    /// Start a phase; it is reported as finished when the returned tracker is dropped
+    pub fn phase(&self, phase: Phase, items_total: u64, bytes_total: u64) -> PhaseProgress {
+        self.emit(ProgressEvent::PhaseStarted { phase, items_total, bytes_total });
+        PhaseProgress {
+            sink: self.clone(),
+            phase,
+            started: Instant::now(),
+            last_emit: None,
+            items_total,
+            bytes_total,
+            items_processed: 0,
+            bytes_processed: 0,
+            entry_bytes: 0,
+        }
+    }
+}
+
+impl Default for ProgressSink {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+impl std::fmt::Debug for ProgressSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgressSink")
+            .field("enabled", &self.is_enabled())
+            .field("interval", &self.interval)
+            .finish()
+    }
+}
+
+/// Counters and throttling for one running phase
+pub struct PhaseProgress {
+    sink: ProgressSink,
+    phase: Phase,
+    started: Instant,
+    last_emit: Option<Instant>,
+    items_total: u64,
+    bytes_total: u64,
+    items_processed: u64,
+    bytes_processed: u64,
+    /// Bytes written so far for the entry in flight
+    entry_bytes: u64,
+}
+
+impl PhaseProgress {
+    /// Report that work on an entry has begun
+    pub fn entry_started(&mut self, path: &str, bytes: u64) {
+        if self.due() {
+            self.sink.emit(ProgressEvent::EntryStarted { path: path.to_string(), bytes });
+        }
+    }
+
+    /// Report bytes done so far within the current entry
+    pub fn entry_progress(&mut self, bytes_in_entry: u64) {
+        self.entry_bytes = bytes_in_entry;
+        if self.due() {
+            self.emit_bytes();
+        }
+    }
+
+    /// Report that an entry is complete
+    pub fn entry_finished(&mut self, path: &str, bytes: u64) {
+        self.items_processed += 1;
+        self.bytes_processed += bytes;
+        self.entry_bytes = 0;
+        if self.due() {
+            self.sink.emit(ProgressEvent::EntryFinished { path: path.to_string(), bytes });
+            self.emit_bytes();
+        }
+    }
+
+    /// Finish the phase, sending the final counters
+    pub fn finish(self) {}
+
+    fn due(&self) -> bool {
+        self.sink.is_enabled() && self.last_emit.is_none_or(|t| t.elapsed() >= self.sink.interval)
+    }
+
+    fn emit_bytes(&mut self) {
+        self.last_emit = Some(Instant::now());
+        self.sink.emit(ProgressEvent::Bytes {
+            processed: self.bytes_processed + self.entry_bytes,
+            total: self.bytes_total,
+            items_processed: self.items_processed,
+            items_total: self.items_total,
+        });
+    }
+}
+
+impl Drop for PhaseProgress {
+    fn drop(&mut self) {
+        if !self.sink.is_enabled() {
+            return;
+        }
+        self.emit_bytes();
+        self.sink.emit(ProgressEvent::PhaseFinished {
+            phase: self.phase,
+            items_processed: self.items_processed,
+            bytes_processed: self.bytes_processed,
+            elapsed: self.started.elapsed(),
+        });
+    }
+}
+
+/// Build a callback that renders progress events as terminal progress bars
+///
+/// Each phase gets its own bar on stderr, showing bytes or items with
+/// throughput and ETA when the totals are known, and a spinner otherwise.
+#[cfg(feature = "progress")]
+pub fn progress_bar_callback() -> ProgressCallback {
+    let multi = MultiProgress::with_draw_target(ProgressDrawTarget::stderr());
+    let mut bars: Vec<(ProgressBar, bool)> = Vec::new();
+
+    Box::new(move |event| match event {
+        ProgressEvent::PhaseStarted { phase, items_total, bytes_total } => {
+            let (bar, by_bytes) = if bytes_total > 0 {
+                (ProgressBar::new(bytes_total).with_style(bar_style(
+                    "{spinner} {msg} [{bar:30}] {bytes}/{total_bytes} ({binary_bytes_per_sec}, ETA {eta})",
+                )), true)
+            } else if items_total > 0 {
+                (ProgressBar::new(items_total).with_style(bar_style(
+                    "{spinner} {msg} [{bar:30}] {pos}/{len} ({per_sec}, ETA {eta})",
+                )), false)
+            } else {
+                (ProgressBar::new_spinner().with_style(bar_style("{spinner} {msg} {elapsed}")), false)
+            };
+            bar.set_message(phase.to_string());
+            bar.enable_steady_tick(Duration::from_millis(120));
+            bars.push((multi.add(bar), by_bytes));
+        }
+        ProgressEvent::EntryStarted { path, .. } | ProgressEvent::EntryFinished { path, .. } => {
+            if let (Some((bar, _)), false) = (bars.last(), path.is_empty()) {
+                bar.set_message(path);
+            }
+        }
+        ProgressEvent::Bytes { processed, items_processed, .. } => {
+            if let Some((bar, by_bytes)) = bars.last() {
+                bar.set_position(if *by_bytes { processed } else { items_processed });
+            }
+        }
+        ProgressEvent::PhaseFinished { .. } => {
+            if let Some((bar, _)) = bars.pop() {
+                bar.finish_and_clear();
+                multi.remove(&bar);
+            }
+        }
+    })
+}
+
+/// Copy a reader to a writer, reporting bytes within the current entry
+#[cfg(feature = "archive")]
+pub(crate) fn copy_with_progress<R: Read + ?Sized, W: Write + ?Sized>(
+    reader: &mut R,
+    writer: &mut W,
+    progress: &mut PhaseProgress,
+) -> std::io::Result<u64> {
+    let mut buf = [0u8; 64 * 1024];
+    let mut copied = 0u64;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok(copied),
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buf[..n])?;
+        copied += n as u64;
+        progress.entry_progress(copied);
+    }
+}
+
+#[cfg(feature = "progress")]
+fn bar_style(template: &str) -> ProgressStyle {
+    ProgressStyle::with_template(template)
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+        .progress_chars("=> ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording_sink(interval: Duration) -> (ProgressSink, Arc<Mutex<Vec<ProgressEvent>>>) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&events);
+        let sink = ProgressSink::new(Box::new(move |event| recorded.lock().unwrap().push(event)))
+            .with_interval(interval);
+        (sink, events)
+    }
+
+    #[test]
+    fn test_entry_events_are_throttled() {
+        let (sink, events) = recording_sink(Duration::from_secs(3600));
+
+        let mut progress = sink.phase(Phase::Unpack, 100, 1000);
+        for i in 0..100 {
+            progress.entry_started(&format!("file{}", i), 10);
+            progress.entry_finished(&format!("file{}", i), 10);
+        }
+        progress.finish();
+
+        let events = events.lock().unwrap();
+        // Phase start, the first entry's events, then the final counters and phase end
+        assert_eq!(events.len(), 6);
+        assert!(matches!(events[0], ProgressEvent::PhaseStarted { phase: Phase::Unpack, .. }));
+        assert_eq!(
+            events[4],
+            ProgressEvent::Bytes { processed: 1000, total: 1000, items_processed: 100, items_total: 100 }
+        );
+        assert!(matches!(
+            events[5],
+            ProgressEvent::PhaseFinished { items_processed: 100, bytes_processed: 1000, .. }
+        ));
+    }
+
+    #[test]
+    fn test_disabled_sink_emits_nothing() {
+        let (sink, events) = recording_sink(Duration::ZERO);
+
+        // A component left with the default sink runs beside one that records
+        let disabled = ProgressSink::default();
+        assert!(!disabled.is_enabled());
+        let mut progress = disabled.phase(Phase::List, 1, 10);
+        progress.entry_started("file", 10);
+        progress.entry_progress(5);
+        progress.entry_finished("file", 10);
+        progress.finish();
+        assert!(events.lock().unwrap().is_empty());
+
+        sink.phase(Phase::List, 1, 10).finish();
+        assert_eq!(events.lock().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,617 @@
+//! Decoding of unversioned properties
+//!
+//! UE5 games cook exports with unversioned property serialization: each
+//! struct starts with an `FUnversionedHeader` saying which slots of the
+//! struct's schema are serialized, and the values follow without names or
+//! types. [`PropertyReader`] walks that header with the layouts from a
+//! [`Mappings`] file and turns the values into a JSON tree: structs become
+//! objects, arrays and sets become arrays, maps become arrays of
+//! `{"key", "value"}` objects.
+//!
+//! Common native structs (vectors, rotators, colors, GUIDs, soft object
+//! paths, gameplay tag containers, curve keys) are decoded from their binary
+//! layout. Other structs with custom serialization fail with an error naming
+//! them.
+
+use std::io::Cursor;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::{json, Map, Number, Value};
+
+use crate::asset::{format_guid, name_with_number, soft_object_path, ObjectRef, PackageSummary, ScriptObjects};
+use crate::binary::ReadExt;
+use crate::error::{Result, UeToolError};
+use crate::types::{ExtractOptions, InspectOptions};
+use crate::usmap::{MappedProperty, MappedStruct, Mappings, PropertyType};
+use crate::Unpacker;
+
+// Object versions (EUnrealEngineObjectUE5Version) that changed property layouts
+const VER_UE5_LARGE_WORLD_COORDINATES: i32 = 1004;
+const VER_UE5_FSOFTOBJECTPATH_REMOVE_ASSET_PATH_FNAMES: i32 = 1007;
+
+/// Decoded properties of one export
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportProperties {
+    pub export: String,
+    pub class: Option<String>,
+    /// The decoded properties; `None` if decoding failed
+    pub properties: Option<Value>,
+    pub error: Option<String>,
+}
+
+/// Decodes unversioned properties of a package's exports
+pub struct PropertyReader<'a> {
+    summary: &'a PackageSummary,
+    mappings: &'a Mappings,
+    script_objects: Option<&'a ScriptObjects>,
+    large_world_coordinates: bool,
+    soft_path_has_package: bool,
+}
+
+impl<'a> PropertyReader<'a> {
+    /// Create a reader for the exports of `summary`
+    ///
+    /// Unversioned zen packages parsed without an engine version take the
+    /// object version from the mappings, or assume the latest UE5.
+    pub fn new(summary: &'a PackageSummary, mappings: &'a Mappings) -> Self {
+        let versions = &summary.versions;
+        let ue5 = match (versions.file_version_ue4, versions.file_version_ue5) {
+            (0, 0) => mappings.file_version_ue5.unwrap_or(i32::MAX),
+            (_, ue5) => ue5,
+        };
+        Self {
+            summary,
+            mappings,
+            script_objects: None,
+            large_world_coordinates: ue5 >= VER_UE5_LARGE_WORLD_COORDINATES,
+            soft_path_has_package: ue5 >= VER_UE5_FSOFTOBJECTPATH_REMOVE_ASSET_PATH_FNAMES,
+        }
+    }
+
+    /// Resolve the zen package's references to native classes and objects
+    /// through the game's [`ScriptObjects`]
+    pub fn with_script_objects(mut self, script_objects: &'a ScriptObjects) -> Self {
+        self.script_objects = Some(script_objects);
+        self
+    }
+
+    /// Decode the properties of `struct_name` at the start of `data`
+    ///
+    /// Returns the properties and the number of bytes they took; data the
+    /// class serializes after its properties follows.
+    pub fn read_struct(&self, data: &[u8], struct_name: &str) -> Result<(Value, usize)> {
+        let mut r = Cursor::new(data);
+        let value = self.struct_properties(&mut r, struct_name)?;
+        Ok((value, r.position() as usize))
+    }
+
+    /// Decode the properties of export `index`
+    ///
+    /// `package` is the whole package as taken by [`PackageSummary::export_data`].
+    /// Classes of zen packages are only known with [`PropertyReader::with_script_objects`].
+    pub fn read_export(&self, package: &[u8], index: usize) -> Result<Value> {
+        self.read_export_with_tail(package, index).map(|(value, _)| value)
+    }
+
+    /// Decode the properties of export `index`, also returning the data that follows them
+    pub fn read_export_with_tail<'p>(&self, package: &'p [u8], index: usize) -> Result<(Value, &'p [u8])> {
+        let data = self.summary.export_data(package, index)?;
+        let export = &self.summary.exports[index];
+        let class = self
+            .summary
+            .export_class(export, self.script_objects)
+            .ok_or_else(|| UeToolError::DeserializationError(format!("Class of export {} is unknown", export.object_name)))?;
+        let (value, read) = self.read_struct(data, &class)?;
+        Ok((value, &data[read..]))
+    }
+
+    /// Decode every export of the package, keeping going past exports that fail
+    pub fn read_exports(&self, package: &[u8]) -> Vec<ExportProperties> {
+        self.summary
+            .exports
+            .iter()
+            .enumerate()
+            .map(|(index, export)| {
+                let result = self.read_export(package, index);
+                ExportProperties {
+                    export: export.object_name.clone(),
+                    class: self.summary.export_class(export, self.script_objects),
+                    error: result.as_ref().err().map(ToString::to_string),
+                    properties: result.ok(),
+                }
+            })
+            .collect()
+    }
+
+    /// Schema slots of a struct including its super structs: (property, static array element)
+    fn schema(&self, struct_name: &str) -> Result<Vec<Option<(&'a MappedProperty, usize)>>> {
+        let mut chain = Vec::new();
+        let mut next = Some(struct_name);
+        while let Some(name) = next {
+            let mapped = self
+                .mappings
+                .get_struct(name)
+                .ok_or_else(|| UeToolError::DeserializationError(format!("{} is not in the mappings", name)))?;
+            // Malformed mappings can make a struct its own ancestor
+            if chain.iter().any(|s: &&MappedStruct| s.name == mapped.name) {
+                return Err(UeToolError::DeserializationError(format!("{} is its own super struct", struct_name)));
+            }
+            chain.push(mapped);
+            next = mapped.super_struct.as_deref();
+        }
+
+        let mut slots = Vec::new();
+        for mapped in chain.into_iter().rev() {
+            let offset = slots.len();
+            slots.resize(offset + mapped.property_count as usize, None);
+            for property in &mapped.properties {
+                for element in 0..property.array_size as usize {
+                    let slot = offset + property.schema_index as usize + element;
+                    if slot >= slots.len() {
+                        slots.resize(slot + 1, None);
+                    }
+                    slots[slot] = Some((property, element));
+                }
+            }
+        }
+        Ok(slots)
+    }
+
+    fn struct_properties(&self, r: &mut Cursor<&[u8]>, struct_name: &str) -> Result<Value> {
+        let schema = self.schema(struct_name)?;
+        let mut out = Map::new();
+        for (index, serialized) in read_unversioned_header(r)? {
+            let (property, element) = schema.get(index).copied().flatten().ok_or_else(|| {
+                UeToolError::DeserializationError(format!("{} has no property in schema slot {}", struct_name, index))
+            })?;
+            let value = if serialized {
+                self.value(r, &property.kind).map_err(|e| {
+                    let message = match e {
+                        UeToolError::DeserializationError(message) => message,
+                        other => other.to_string(),
+                    };
+                    UeToolError::DeserializationError(format!("{}.{}: {}", struct_name, property.name, message))
+                })?
+            } else {
+                self.zero(&property.kind)
+            };
+            if property.array_size > 1 {
+                let elements = out
+                    .entry(property.name.clone())
+                    .or_insert_with(|| Value::Array(vec![Value::Null; property.array_size as usize]));
+                if let Some(slot) = elements.as_array_mut().and_then(|a| a.get_mut(element)) {
+                    *slot = value;
+                }
+            } else {
+                out.insert(property.name.clone(), value);
+            }
+        }
+        Ok(Value::Object(out))
+    }
+
+    fn value(&self, r: &mut Cursor<&[u8]>, kind: &PropertyType) -> Result<Value> {
+        Ok(match kind {
+            PropertyType::Bool => Value::Bool(r.read_u8()? != 0),
+            PropertyType::Byte => json!(r.read_u8()?),
+            PropertyType::Int8 => json!(r.read_u8()? as i8),
+            PropertyType::Int16 => json!(r.read_u16()? as i16),
+            PropertyType::Int => json!(r.read_i32()?),
+            PropertyType::Int64 => json!(r.read_u64()? as i64),
+            PropertyType::UInt16 => json!(r.read_u16()?),
+            PropertyType::UInt32 => json!(r.read_u32()?),
+            PropertyType::UInt64 => json!(r.read_u64()?),
+            PropertyType::Float => float(f32::from_bits(r.read_u32()?)),
+            PropertyType::Double => double(f64::from_bits(r.read_u64()?)),
+            PropertyType::Name => Value::String(self.name(r)?),
+            PropertyType::Str | PropertyType::Utf8Str | PropertyType::AnsiStr => Value::String(r.read_fstring()?),
+            PropertyType::Text => self.text(r)?,
+            PropertyType::Object | PropertyType::WeakObject | PropertyType::Interface => self.object(r.read_i32()?),
+            PropertyType::LazyObject => Value::String(format_guid(&r.read_guid()?)),
+            PropertyType::SoftObject | PropertyType::AssetObject => Value::String(self.soft_object_path(r)?),
+            PropertyType::Delegate => self.delegate(r)?,
+            PropertyType::MulticastDelegate => {
+                Value::Array(r.read_array_of(|r| self.delegate(r))?)
+            }
+            PropertyType::FieldPath => {
+                let path = r.read_array_of(|r| self.name(r))?;
+                json!({ "path": path.join("."), "owner": self.object(r.read_i32()?) })
+            }
+            PropertyType::Enum(inner, enum_name) => {
+                let raw = match inner.as_ref() {
+                    PropertyType::Byte => r.read_u8()? as i64,
+                    PropertyType::UInt16 => r.read_u16()? as i64,
+                    PropertyType::Int8 => r.read_u8()? as i8 as i64,
+                    PropertyType::Int16 => r.read_u16()? as i16 as i64,
+                    PropertyType::Int => r.read_i32()? as i64,
+                    PropertyType::UInt32 => r.read_u32()? as i64,
+                    PropertyType::Int64 | PropertyType::UInt64 => r.read_u64()? as i64,
+                    other => return Err(UeToolError::DeserializationError(format!("Enum {} stored as {:?}", enum_name, other))),
+                };
+                self.enum_value(enum_name, raw)
+            }
+            PropertyType::Array(inner) => Value::Array(r.read_array_of(|r| self.value(r, inner))?),
+            PropertyType::Set(inner) => {
+                r.read_array_of(|r| self.value(r, inner))?; // Elements to remove
+                Value::Array(r.read_array_of(|r| self.value(r, inner))?)
+            }
+            PropertyType::Map(key, value) => {
+                r.read_array_of(|r| self.value(r, key))?; // Keys to remove
+                Value::Array(r.read_array_of(|r| {
+                    let key = self.value(r, key)?;
+                    Ok(json!({ "key": key, "value": self.value(r, value)? }))
+                })?)
+            }
+            PropertyType::Struct(name) => self.struct_value(r, name)?,
+            PropertyType::Optional(inner) => {
+                if r.read_bool32()? {
+                    self.value(r, inner)?
+                } else {
+                    Value::Null
+                }
+            }
+            PropertyType::Unknown(id) => {
+                return Err(UeToolError::DeserializationError(format!("Unknown property type {}", id)));
+            }
+        })
+    }
+
+    /// Value of a property the header marks as zero, i.e. not serialized
+    fn zero(&self, kind: &PropertyType) -> Value {
+        match kind {
+            PropertyType::Bool => Value::Bool(false),
+            PropertyType::Byte
+            | PropertyType::Int8
+            | PropertyType::Int16
+            | PropertyType::Int
+            | PropertyType::Int64
+            | PropertyType::UInt16
+            | PropertyType::UInt32
+            | PropertyType::UInt64
+            | PropertyType::Float
+            | PropertyType::Double => json!(0),
+            PropertyType::Name => Value::String("None".to_string()),
+            PropertyType::Str
+            | PropertyType::Utf8Str
+            | PropertyType::AnsiStr
+            | PropertyType::Text
+            | PropertyType::SoftObject
+            | PropertyType::AssetObject => Value::String(String::new()),
+            PropertyType::Enum(_, enum_name) => self.enum_value(enum_name, 0),
+            PropertyType::Array(_) | PropertyType::Set(_) | PropertyType::Map(..) | PropertyType::MulticastDelegate => {
+                Value::Array(Vec::new())
+            }
+            PropertyType::Struct(_) => Value::Object(Map::new()),
+            _ => Value::Null,
+        }
+    }
+
+    /// Decode a struct, by its native layout for the structs that have one
+    fn struct_value(&self, r: &mut Cursor<&[u8]>, name: &str) -> Result<Value> {
+        let lwc = self.large_world_coordinates;
+        let reals = |r: &mut Cursor<&[u8]>, keys: &[&str], doubles: bool| -> Result<Value> {
+            let mut out = Map::new();
+            for key in keys {
+                let value = if doubles { double(f64::from_bits(r.read_u64()?)) } else { float(f32::from_bits(r.read_u32()?)) };
+                out.insert(key.to_string(), value);
+            }
+            Ok(Value::Object(out))
+        };
+        Ok(match name {
+            "Vector" | "Vector3d" => reals(r, &["x", "y", "z"], lwc || name == "Vector3d")?,
+            "Vector3f" => reals(r, &["x", "y", "z"], false)?,
+            "Vector2D" => reals(r, &["x", "y"], lwc)?,
+            "Vector2f" => reals(r, &["x", "y"], false)?,
+            "Vector4" | "Quat" | "Plane" => reals(r, &["x", "y", "z", "w"], lwc)?,
+            "Vector4f" | "Quat4f" => reals(r, &["x", "y", "z", "w"], false)?,
+            "Rotator" => reals(r, &["pitch", "yaw", "roll"], lwc)?,
+            "LinearColor" => reals(r, &["r", "g", "b", "a"], false)?,
+            "Box" | "Box2D" => {
+                let keys: &[&str] = if name == "Box" { &["x", "y", "z"] } else { &["x", "y"] };
+                let min = reals(r, keys, lwc)?;
+                let max = reals(r, keys, lwc)?;
+                json!({ "min": min, "max": max, "is_valid": r.read_u8()? != 0 })
+            }
+            "Color" => {
+                let [b, g, red, a] = r.read_fixed::<4>()?;
+                json!({ "r": red, "g": g, "b": b, "a": a })
+            }
+            "IntPoint" => json!({ "x": r.read_i32()?, "y": r.read_i32()? }),
+            "IntVector" => json!({ "x": r.read_i32()?, "y": r.read_i32()?, "z": r.read_i32()? }),
+            "Guid" => Value::String(format_guid(&r.read_guid()?)),
+            "DateTime" | "Timespan" => json!(r.read_u64()? as i64),
+            "FrameNumber" => json!(r.read_i32()?),
+            "SoftObjectPath" | "SoftClassPath" => Value::String(self.soft_object_path(r)?),
+            "GameplayTagContainer" => Value::Array(r.read_array_of(|r| self.name(r).map(Value::String))?),
+            "SimpleCurveKey" => {
+                let time = float(f32::from_bits(r.read_u32()?));
+                json!({ "Time": time, "Value": float(f32::from_bits(r.read_u32()?)) })
+            }
+            "RichCurveKey" => {
+                let [interp, tangent, weight] = r.read_fixed::<3>()?;
+                let mut key = Map::new();
+                key.insert("InterpMode".to_string(), self.enum_value("ERichCurveInterpMode", interp as i64));
+                key.insert("TangentMode".to_string(), self.enum_value("ERichCurveTangentMode", tangent as i64));
+                key.insert("TangentWeightMode".to_string(), self.enum_value("ERichCurveTangentWeightMode", weight as i64));
+                for name in ["Time", "Value", "ArriveTangent", "ArriveTangentWeight", "LeaveTangent", "LeaveTangentWeight"] {
+                    key.insert(name.to_string(), float(f32::from_bits(r.read_u32()?)));
+                }
+                Value::Object(key)
+            }
+            _ => self.struct_properties(r, name)?,
+        })
+    }
+
+    /// Read an `FName` as (name map index, number)
+    fn name(&self, r: &mut Cursor<&[u8]>) -> Result<String> {
+        let index = r.read_u32()?;
+        let number = r.read_u32()?;
+        self.summary
+            .names
+            .get(index as usize)
+            .map(|base| name_with_number(base, number))
+            .ok_or_else(|| UeToolError::DeserializationError(format!("Name index {} is out of range", index)))
+    }
+
+    /// An object reference: the object's name, or the reference itself when it has none
+    fn object(&self, index: i32) -> Value {
+        let object = ObjectRef::from_package_index(index);
+        if object == ObjectRef::Null {
+            return Value::Null;
+        }
+        if let Some(name) = self.summary.object_name(object) {
+            return Value::String(name.to_string());
+        }
+        let global = match object {
+            ObjectRef::Import { index } => self.summary.imports.get(index).and_then(|import| import.global_ref),
+            _ => None,
+        };
+        if let (Some(ObjectRef::ScriptImport { hash }), Some(script_objects)) = (global, self.script_objects) {
+            if let Some(name) = script_objects.name(hash) {
+                return Value::String(name.to_string());
+            }
+        }
+        serde_json::to_value(global.unwrap_or(object)).unwrap_or(Value::Null)
+    }
+
+    fn delegate(&self, r: &mut Cursor<&[u8]>) -> Result<Value> {
+        let object = self.object(r.read_i32()?);
+        Ok(json!({ "object": object, "function": self.name(r)? }))
+    }
+
+    fn soft_object_path(&self, r: &mut Cursor<&[u8]>) -> Result<String> {
+        if !self.summary.soft_object_paths.is_empty() {
+            let index = r.read_i32()?;
+            return usize::try_from(index)
+                .ok()
+                .and_then(|i| self.summary.soft_object_paths.get(i))
+                .cloned()
+                .ok_or_else(|| UeToolError::DeserializationError(format!("Soft object path {} is out of range", index)));
+        }
+        if self.soft_path_has_package {
+            let package = self.name(r)?;
+            let asset = self.name(r)?;
+            Ok(soft_object_path(&package, &asset, &r.read_fstring()?))
+        } else {
+            let path = self.name(r)?;
+            Ok(soft_object_path(&path, "None", &r.read_fstring()?))
+        }
+    }
+
+    /// Read an `FText`; only the histories cooked games commonly use are supported
+    fn text(&self, r: &mut Cursor<&[u8]>) -> Result<Value> {
+        r.read_u32()?; // Flags
+        Ok(match r.read_u8()? as i8 {
+            -1 => Value::String(if r.read_bool32()? { r.read_fstring()? } else { String::new() }),
+            0 => {
+                let namespace = r.read_fstring()?;
+                let key = r.read_fstring()?;
+                json!({ "namespace": namespace, "key": key, "source": r.read_fstring()? })
+            }
+            11 => {
+                let table = self.name(r)?;
+                json!({ "table": table, "key": r.read_fstring()? })
+            }
+            history => return Err(UeToolError::DeserializationError(format!("Unsupported text history type {}", history))),
+        })
+    }
+
+    fn enum_value(&self, enum_name: &str, raw: i64) -> Value {
+        match self.mappings.get_enum(enum_name).and_then(|e| e.name_of(raw)) {
+            Some(name) => Value::String(name.to_string()),
+            None => json!(raw),
+        }
+    }
+}
+
+impl Unpacker {
+    /// Decode the properties of every export of a package in a `.pak` or `.utoc`
+    ///
+    /// Native class names of IoStore packages come from [`ScriptObjects`].
+    pub fn read_properties<P: AsRef<Path>>(
+        &mut self,
+        container: P,
+        path: &str,
+        mappings: &Mappings,
+        options: &InspectOptions,
+    ) -> Result<Vec<ExportProperties>> {
+        let container = container.as_ref();
+        let _span = tracing::info_span!("read_properties", container = %container.display(), path).entered();
+
+        let (package, summary, script_objects) = self.load_cooked_package(container, path, options)?;
+        let mut reader = PropertyReader::new(&summary, mappings);
+        if let Some(script_objects) = &script_objects {
+            reader = reader.with_script_objects(script_objects);
+        }
+        Ok(reader.read_exports(&package))
+    }
+
+    /// Read and parse a package, with the game's script objects for IoStore containers
+    ///
+    /// For IoStore containers, native class names come from the `global.utoc`
+    /// next to the container.
+    pub(crate) fn load_cooked_package(
+        &mut self,
+        container: &Path,
+        path: &str,
+        options: &InspectOptions,
+    ) -> Result<(Vec<u8>, PackageSummary, Option<ScriptObjects>)> {
+        let mut extract = ExtractOptions::new();
+        extract.aes_key = options.aes_key.clone();
+        let package = self.read_package(container, path, &extract)?;
+        let summary = PackageSummary::parse(&package, options.engine_version)?;
+        let script_objects = ScriptObjects::for_container(container, options.aes_key.as_deref());
+        Ok((package, summary, script_objects))
+    }
+}
+
+/// Read an `FUnversionedHeader` into (schema slot, serialized) pairs
+///
+/// Slots that are present but not serialized hold their zero value.
+fn read_unversioned_header(r: &mut Cursor<&[u8]>) -> Result<Vec<(usize, bool)>> {
+    let mut fragments = Vec::new();
+    loop {
+        let packed = r.read_u16()?;
+        let skip = (packed & 0x7F) as usize;
+        let has_zeroes = packed & 0x80 != 0;
+        let values = (packed >> 9) as usize;
+        fragments.push((skip, has_zeroes, values));
+        if packed & 0x100 != 0 {
+            break;
+        }
+    }
+
+    let zero_bits: usize = fragments.iter().filter(|f| f.1).map(|f| f.2).sum();
+    let zero_mask: Vec<u8> = match zero_bits {
+        0 => Vec::new(),
+        1..=8 => r.read_bytes(1)?,
+        9..=16 => r.read_bytes(2)?,
+        n => r.read_bytes(n.div_ceil(32) * 4)?,
+    };
+    let is_zero = |bit: usize| zero_mask[bit / 8] & (1 << (bit % 8)) != 0;
+
+    let mut slots = Vec::new();
+    let (mut index, mut zero_index) = (0, 0);
+    for (skip, has_zeroes, values) in fragments {
+        index += skip;
+        for _ in 0..values {
+            let zero = has_zeroes && {
+                zero_index += 1;
+                is_zero(zero_index - 1)
+            };
+            slots.push((index, !zero));
+            index += 1;
+        }
+    }
+    Ok(slots)
+}
+
+/// A float as JSON, using its shortest decimal form rather than the widened double
+fn float(value: f32) -> Value {
+    value.to_string().parse::<f64>().ok().and_then(Number::from_f64).map_or(Value::Null, Value::Number)
+}
+
+fn double(value: f64) -> Value {
+    Number::from_f64(value).map_or(Value::Null, Value::Number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::{ExportEntry, PackageFormat, PackageVersions};
+    use crate::binary::write_fstring;
+    use crate::usmap::tests::usmap;
+
+    fn summary(names: &[&str], export_size: usize) -> PackageSummary {
+        PackageSummary {
+            versions: PackageVersions { file_version_ue4: 522, file_version_ue5: 1009, ..Default::default() },
+            ..PackageSummary::for_test(PackageFormat::Zen, names, vec![ExportEntry::for_test("Paint", "Paint", export_size)])
+        }
+    }
+
+    #[test]
+    fn test_read_unversioned_header() {
+        // Skip 1, 3 values with zeroes; last fragment skips 2, 1 value
+        let mut data = Vec::new();
+        data.extend_from_slice(&(1u16 | 0x80 | (3 << 9)).to_le_bytes());
+        data.extend_from_slice(&(2u16 | 0x100 | (1 << 9)).to_le_bytes());
+        data.push(0b010);
+        let slots = read_unversioned_header(&mut Cursor::new(&data[..])).unwrap();
+        assert_eq!(slots, vec![(1, true), (2, false), (3, true), (6, true)]);
+
+        // As FUnversionedHeader::Save writes it: 20 values with zeroes, so a
+        // u32 zero mask (slots 0 and 19 zero), then skip 5 and 2 values
+        let data = [0x80, 0x28, 0x05, 0x05, 0x01, 0x00, 0x08, 0x00];
+        let slots = read_unversioned_header(&mut Cursor::new(&data[..])).unwrap();
+        let mut expected: Vec<(usize, bool)> = (0..20).map(|i| (i, i != 0 && i != 19)).collect();
+        expected.extend([(25, true), (26, true)]);
+        assert_eq!(slots, expected);
+    }
+
+    #[test]
+    fn test_read_struct() {
+        // Base: Title (str); Paint : Base: Color (enum EColor), Tags (array of name), Offset (Vector), Width (int, zero)
+        let names = ["EColor", "Red", "Green", "Paint", "Color", "Tags", "Base", "Title", "Offset", "Vector", "Width"];
+        let mut structs = Vec::new();
+        let property = |out: &mut Vec<u8>, index: u16, name: u32, kind: &[u8]| {
+            out.extend_from_slice(&index.to_le_bytes());
+            out.push(1);
+            out.extend_from_slice(&name.to_le_bytes());
+            out.extend_from_slice(kind);
+        };
+        structs.extend_from_slice(&6u32.to_le_bytes());
+        structs.extend_from_slice(&(-1i32).to_le_bytes());
+        structs.extend_from_slice(&[1, 0, 1, 0]);
+        property(&mut structs, 0, 7, &[10]);
+        structs.extend_from_slice(&3u32.to_le_bytes());
+        structs.extend_from_slice(&6u32.to_le_bytes());
+        structs.extend_from_slice(&[4, 0, 4, 0]);
+        property(&mut structs, 0, 4, &[26, 0, 0, 0, 0, 0]);
+        property(&mut structs, 1, 5, &[8, 5]);
+        property(&mut structs, 2, 8, &[9, 9, 0, 0, 0]);
+        property(&mut structs, 3, 10, &[2]);
+        let mappings = Mappings::parse(&usmap(&names, &structs, 2, false)).unwrap();
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&(0x80u16 | 0x100 | (5 << 9)).to_le_bytes());
+        data.push(0b10000);
+        write_fstring(&mut data, "Hello");
+        data.push(1); // Color = Green
+        data.extend_from_slice(&1i32.to_le_bytes());
+        data.extend_from_slice(&[1, 0, 0, 0, 2, 0, 0, 0]); // Tags = [Red_1]
+        for v in [1.0f64, 2.5, -3.0] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        data.extend_from_slice(b"tail");
+
+        let summary = summary(&names, data.len());
+        let reader = PropertyReader::new(&summary, &mappings);
+        let (value, read) = reader.read_struct(&data, "Paint").unwrap();
+        assert_eq!(read, data.len() - 4);
+        assert_eq!(
+            value,
+            json!({
+                "Title": "Hello",
+                "Color": "Green",
+                "Tags": ["Red_1"],
+                "Offset": { "x": 1.0, "y": 2.5, "z": -3.0 },
+                "Width": 0,
+            })
+        );
+
+        let exports = reader.read_exports(&data);
+        assert!(exports[0].error.is_none());
+        assert!(reader.read_struct(&data, "Missing").is_err());
+
+        // Paint : Base : Paint
+        let mut cycle = Vec::new();
+        for (name, super_struct) in [(3u32, 6i32), (6, 3)] {
+            cycle.extend_from_slice(&name.to_le_bytes());
+            cycle.extend_from_slice(&super_struct.to_le_bytes());
+            cycle.extend_from_slice(&[0, 0, 0, 0]);
+        }
+        let mappings = Mappings::parse(&usmap(&names, &cycle, 2, false)).unwrap();
+        let reader = PropertyReader::new(&summary, &mappings);
+        assert!(matches!(reader.read_struct(&[0, 1], "Paint"), Err(UeToolError::DeserializationError(_))));
+    }
+}
@@ -3,11 +3,162 @@
 //! This module provides PyO3 bindings for native Python integration.
 //! PyO3 is now the only supported binding method.
 
+// pyo3 0.22's #[pymethods] expansion trips this lint on `PyResult` returns
+#![allow(clippy::useless_conversion)]
+
 use pyo3::prelude::*;
+use pyo3::create_exception;
 use std::collections::HashMap;
-use tempfile;
-use crate::{Unpacker, AssetPath};
-use crate::error::{UeToolError, Result};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::Duration;
+use tracing::field::{Field, Visit};
+use tracing::subscriber::Interest;
+use tracing::{Event, Level, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use crate::{Unpacker, AssetPath, CancellationToken, ContainerMap, Interrupted, ListingCache, ScanOptions};
+
+create_exception!(
+    rust_ue_tools,
+    OperationCancelled,
+    pyo3::exceptions::PyException,
+    "Raised when an operation is cancelled or times out; args[1] holds the partial results."
+);
+
+/// Convert an interrupted operation into a Python exception, keeping partial results
+fn interrupted_to_pyerr(interrupted: Interrupted<Vec<AssetPath>>) -> PyErr {
+    if interrupted.is_cancelled() {
+        let partial: Vec<String> = interrupted.partial.into_iter().map(|a| a.0).collect();
+        OperationCancelled::new_err((interrupted.error.to_string(), partial))
+    } else {
+        PyErr::new::<pyo3::exceptions::PyIOError, String>(interrupted.error.to_string())
+    }
+}
+
+/// Lowest Python `logging` level forwarded from Rust, WARNING by default
+static PY_LOG_LEVEL: AtomicI32 = AtomicI32::new(30);
+
+fn python_level(level: &Level) -> i32 {
+    match *level {
+        Level::ERROR => 40,
+        Level::WARN => 30,
+        Level::INFO => 20,
+        Level::DEBUG => 10,
+        Level::TRACE => 5,
+    }
+}
+
+/// Formats event and span fields as `message key=value ...`
+#[derive(Default)]
+struct FieldFormatter(String);
+
+impl Visit for FieldFormatter {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{:?}", value);
+        } else {
+            let _ = write!(self.0, " {}={:?}", field.name(), value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.0.push_str(value);
+        } else {
+            let _ = write!(self.0, " {}={}", field.name(), value);
+        }
+    }
+}
+
+/// Fields recorded when a span is created, kept for events inside it
+struct SpanFields(String);
+
+/// Forwards `tracing` events to the Python `logging` module
+///
+/// Each event goes to the logger named after its target (e.g.
+/// `rust_ue_tools.pak_unpack`), with the fields of enclosing spans appended.
+struct PythonLoggingLayer;
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for PythonLoggingLayer {
+    fn register_callsite(&self, _metadata: &'static Metadata<'static>) -> Interest {
+        // The level can change at runtime, so never cache the decision
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, _ctx: Context<'_, S>) -> bool {
+        python_level(metadata.level()) >= PY_LOG_LEVEL.load(Ordering::Relaxed)
+    }
+
+    fn on_new_span(&self, attrs: &tracing::span::Attributes<'_>, id: &tracing::span::Id, ctx: Context<'_, S>) {
+        let mut fields = FieldFormatter::default();
+        attrs.record(&mut fields);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanFields(fields.0));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut message = FieldFormatter::default();
+        event.record(&mut message);
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(fields) = span.extensions().get::<SpanFields>() {
+                    message.0.push_str(&fields.0);
+                }
+            }
+        }
+
+        let level = python_level(event.metadata().level());
+        let target = event.metadata().target().replace("::", ".");
+        Python::with_gil(|py| {
+            let logger = py
+                .import_bound("logging")
+                .and_then(|logging| logging.call_method1("getLogger", (target,)));
+            if let Ok(logger) = logger {
+                let _ = logger.call_method1("log", (level, message.0));
+            }
+        });
+    }
+}
+
+/// Set the lowest Python logging level forwarded from the Rust side
+///
+/// Takes a `logging` level such as `logging.DEBUG`; use `5` to include trace
+/// events. Python's own logger configuration still applies on top.
+#[pyfunction]
+fn set_log_level(level: i32) {
+    PY_LOG_LEVEL.store(level, Ordering::Relaxed);
+}
+
+#[pyclass(name = "CancellationToken")]
+#[derive(Clone)]
+pub struct PyCancellationToken {
+    token: CancellationToken,
+}
+
+#[pymethods]
+impl PyCancellationToken {
+    #[new]
+    #[pyo3(signature = (timeout_secs = None))]
+    fn new(timeout_secs: Option<f64>) -> Self {
+        let token = match timeout_secs {
+            Some(secs) => CancellationToken::with_timeout(Duration::from_secs_f64(secs.max(0.0))),
+            None => CancellationToken::new(),
+        };
+        Self { token }
+    }
+
+    fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    #[getter]
+    fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+}
 
 #[pyclass]
 pub struct PyAssetPath {
@@ -57,176 +208,100 @@ pub struct PyUnpacker {
 #[pymethods]
 impl PyUnpacker {
     #[new]
-    fn new() -> Self {
-        Self {
-            unpacker: Unpacker::new(),
+    #[pyo3(signature = (cancel_token = None, archive_tool_timeout_secs = None, use_cache = false, cache_path = None))]
+    fn new(
+        cancel_token: Option<PyCancellationToken>,
+        archive_tool_timeout_secs: Option<f64>,
+        use_cache: bool,
+        cache_path: Option<String>,
+    ) -> PyResult<Self> {
+        let mut unpacker = Unpacker::new();
+        if let Some(path) = cache_path {
+            unpacker = unpacker.with_listing_cache(ListingCache::open(path));
+        } else if use_cache {
+            let cache = ListingCache::open_default()
+                .map_err(|e| PyErr::new::<pyo3::exceptions::PyIOError, String>(e.to_string()))?;
+            unpacker = unpacker.with_listing_cache(cache);
+        }
+        if let Some(token) = cancel_token {
+            unpacker = unpacker.with_cancellation_token(token.token);
+        }
+        if let Some(secs) = archive_tool_timeout_secs {
+            unpacker = unpacker.with_archive_tool_timeout(Duration::from_secs_f64(secs.max(0.0)));
         }
+        Ok(Self { unpacker })
+    }
+
+    /// Write pending listing cache changes to disk
+    fn save_cache(&self) -> PyResult<()> {
+        self.unpacker
+            .listing_cache()
+            .save()
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyIOError, String>(e.to_string()))
+    }
+
+    /// Forget cached listings read from the given file
+    fn invalidate_cache(&self, path: &str) {
+        self.unpacker.listing_cache().invalidate(path);
     }
 
     #[pyo3(signature = (zip_path, aes_key = None, keep_temp = false))]
     fn extract_asset_paths_from_zip(
         &mut self,
+        py: Python<'_>,
         zip_path: &str,
         aes_key: Option<&str>,
         keep_temp: bool,
     ) -> PyResult<Vec<PyAssetPath>> {
-        match self.unpacker.extract_asset_paths_from_archive(zip_path, aes_key, keep_temp) {
+        // Release the GIL so another Python thread can cancel the token
+        let unpacker = &mut self.unpacker;
+        match py.allow_threads(|| unpacker.extract_asset_paths_from_archive(zip_path, aes_key, keep_temp)) {
             Ok(assets) => Ok(assets.into_iter().map(|a| PyAssetPath { asset_path: a }).collect()),
-            Err(e) => Err(PyErr::new::<pyo3::exceptions::PyIOError, String>(e.to_string())),
+            Err(e) => Err(interrupted_to_pyerr(e)),
         }
     }
 
-    #[pyo3(signature = (folder_path, aes_key = None))]
+    /// Map each container in a folder to its asset paths
+    ///
+    /// Keys are container file names, or paths relative to the folder with
+    /// `relative_keys=True` so same-named containers in different subfolders
+    /// stay apart. Containers that fail to list are left out with a
+    /// `RuntimeWarning` naming them.
+    #[pyo3(signature = (folder_path, aes_key = None, relative_keys = false))]
     fn extract_pak_asset_map_from_folder(
         &mut self,
+        py: Python<'_>,
         folder_path: &str,
         aes_key: Option<&str>,
+        relative_keys: bool,
     ) -> PyResult<HashMap<String, Vec<String>>> {
-        // Implement folder scanning logic
-        use walkdir::WalkDir;
-
-        let mut result_map: HashMap<String, Vec<String>> = HashMap::new();
-
-        // Find all pak and utoc files
-        eprintln!("[DEBUG] Scanning folder: {}", folder_path);
-        let mut pak_files = Vec::new();
-        let mut utoc_files = Vec::new();
-        
-        for entry in WalkDir::new(folder_path) {
-            match entry {
-                Ok(entry) if entry.file_type().is_file() => {
-                    eprintln!("[DEBUG] Found file: {}", entry.path().display());
-                    if let Some(ext) = entry.path().extension().and_then(|e| e.to_str()) {
-                        eprintln!("[DEBUG]   Extension: {}", ext);
-                        if ext.eq_ignore_ascii_case("pak") {
-                            eprintln!("[DEBUG]   -> Adding to pak_files");
-                            pak_files.push(entry.path().to_path_buf());
-                        } else if ext.eq_ignore_ascii_case("utoc") {
-                            eprintln!("[DEBUG]   -> Adding to utoc_files");
-                            utoc_files.push(entry.path().to_path_buf());
-                        }
-                    }
-                }
-                _ => {}
-            }
+        let mut options = ScanOptions::new();
+        if let Some(key) = aes_key {
+            options = options.with_aes_key(key);
         }
 
-        eprintln!("[DEBUG] Found {} pak files and {} utoc files", pak_files.len(), utoc_files.len());
-        // Process pak files
-        for pak_path in &pak_files {
-            let pak_name = pak_path.file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("unknown");
-
-            // Check if this pak has a corresponding utoc file (bundle scenario)
-            let has_utoc = utoc_files.iter().any(|utoc| {
-                utoc.file_stem()
-                    .and_then(|s| s.to_str())
-                    .map(|utoc_name| utoc_name == pak_name)
-                    .unwrap_or(false)
-            });
-
-            if has_utoc {
-                // Bundle scenario: will be handled by utoc processing
-                continue;
-            }
-
-            // Solo pak scenario: unpack to get file list (like basic_usage.rs example)
-            let temp_dir = tempfile::tempdir()
-                .map_err(|e| PyErr::new::<pyo3::exceptions::PyIOError, String>(
-                    format!("Failed to create temp directory: {}", e)
-                ))?;
-            let temp_path = temp_dir.path().to_path_buf();
-
-            let options = crate::PakUnpackOptions {
-                aes_key: aes_key.map(|s| s.to_string()),
-                strip_prefix: "../../../".to_string(),
-                force: true,
-                quiet: true,
-                include_patterns: Vec::new(),
-            };
-
-            match self.unpacker.unpack_pak(pak_path, &temp_path, &options) {
-                Ok(assets) => {
-                    let asset_paths: Vec<String> = assets.into_iter().map(|a| a.0).collect();
-                    if !asset_paths.is_empty() {
-                        result_map.insert(format!("{}.pak", pak_name), asset_paths);
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Warning: Failed to read pak file {}: {}", pak_path.display(), e);
-                }
-            }
-        }
-
-        // Process utoc files
-        let utoc_options = crate::UtocListOptions {
-            aes_key: aes_key.map(|s| s.to_string()),
-            json_format: false,
+        let unpacker = &mut self.unpacker;
+        let containers = match py.allow_threads(|| unpacker.scan_folder(folder_path, &options)) {
+            Ok(containers) => containers,
+            Err(e) => return Err(interrupted_to_pyerr(e.map(ContainerMap::into_assets))),
         };
-
-        eprintln!("[DEBUG] Processing {} utoc files", utoc_files.len());
-        for utoc_path in &utoc_files {
-            let utoc_name = utoc_path.file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("unknown");
-
-            eprintln!("[DEBUG] Processing UTOC: {}", utoc_path.display());
-            eprintln!("[DEBUG]   File exists: {}", utoc_path.exists());
-            eprintln!("[DEBUG]   File size: {:?}", utoc_path.metadata().map(|m| m.len()));
-            eprintln!("[DEBUG]   AES key available: {}", aes_key.is_some());
-
-            match self.unpacker.list_utoc(utoc_path, &utoc_options) {
-                Ok(assets) => {
-                    eprintln!("[DEBUG]   UTOC parse SUCCESS: Found {} assets", assets.len());
-                    let asset_paths: Vec<String> = assets.into_iter().map(|a| a.0).collect();
-                    
-                    if !asset_paths.is_empty() {
-                        eprintln!("[DEBUG]   Inserting {}.utoc with {} assets", utoc_name, asset_paths.len());
-                        result_map.insert(format!("{}.utoc", utoc_name), asset_paths);
-                    } else {
-                        // FALLBACK: If UTOC returns 0 assets, try reading from the PAK file instead
-                        eprintln!("[DEBUG]   WARNING: UTOC returned 0 assets, falling back to PAK file");
-                        
-                        // Find corresponding PAK file
-                        let pak_path = utoc_path.with_extension("pak");
-                        if pak_path.exists() {
-                            eprintln!("[DEBUG]   Found corresponding PAK file: {}", pak_path.display());
-                            
-                            match self.unpacker.get_pak_file_list(&pak_path, aes_key) {
-                                Ok(pak_assets) => {
-                                    eprintln!("[DEBUG]   PAK fallback SUCCESS: Found {} assets", pak_assets.len());
-                                    let pak_asset_paths: Vec<String> = pak_assets.into_iter().map(|a| a.0).collect();
-                                    
-                                    if !pak_asset_paths.is_empty() {
-                                        eprintln!("[DEBUG]   Inserting {}.utoc (from PAK) with {} assets", utoc_name, pak_asset_paths.len());
-                                        result_map.insert(format!("{}.utoc", utoc_name), pak_asset_paths);
-                                    } else {
-                                        eprintln!("[DEBUG]   WARNING: PAK file also returned 0 assets");
-                                    }
-                                }
-                                Err(e) => {
-                                    eprintln!("[ERROR] PAK fallback failed for {}: {}", pak_path.display(), e);
-                                }
-                            }
-                        } else {
-                            eprintln!("[DEBUG]   No corresponding PAK file found at: {}", pak_path.display());
-                        }
-                    }
-                }
-                Err(e) => {
-                    eprintln!("[ERROR] Failed to list utoc file {}: {}", utoc_path.display(), e);
-                    eprintln!("[ERROR]   Error details: {:?}", e);
-                }
+        let warning = py.get_type_bound::<pyo3::exceptions::PyRuntimeWarning>();
+        let mut map = HashMap::new();
+        for (relative, container) in containers.containers {
+            if let Some(ref error) = container.error {
+                PyErr::warn_bound(py, warning.as_any(), &format!("Failed to list {}: {}", relative, error), 1)?;
+                continue;
             }
+            let key = if relative_keys { relative } else { container.name };
+            map.insert(key, container.assets.into_iter().map(String::from).collect());
         }
-
-        Ok(result_map)
+        Ok(map)
     }
 
     #[pyo3(signature = (pak_path, output_dir, aes_key = None, force = false, quiet = true))]
     fn unpack_pak(
         &mut self,
+        py: Python<'_>,
         pak_path: &str,
         output_dir: &str,
         aes_key: Option<&str>,
@@ -241,22 +316,24 @@ impl PyUnpacker {
             include_patterns: Vec::new(),
         };
 
-        match self.unpacker.unpack_pak(pak_path, output_dir, &options) {
+        let unpacker = &mut self.unpacker;
+        match py.allow_threads(|| unpacker.unpack_pak(pak_path, output_dir, &options)) {
             Ok(assets) => Ok(assets.into_iter().map(|a| PyAssetPath { asset_path: a }).collect()),
-            Err(e) => Err(PyErr::new::<pyo3::exceptions::PyIOError, String>(e.to_string())),
+            Err(e) => Err(interrupted_to_pyerr(e)),
         }
     }
 
     #[pyo3(signature = (utoc_path, aes_key = None, json_format = false))]
-    fn list_utoc(&mut self, utoc_path: &str, aes_key: Option<&str>, json_format: bool) -> PyResult<Vec<PyAssetPath>> {
+    fn list_utoc(&mut self, py: Python<'_>, utoc_path: &str, aes_key: Option<&str>, json_format: bool) -> PyResult<Vec<PyAssetPath>> {
         let options = crate::UtocListOptions {
             aes_key: aes_key.map(|s| s.to_string()),
             json_format,
         };
 
-        match self.unpacker.list_utoc(utoc_path, &options) {
+        let unpacker = &mut self.unpacker;
+        match py.allow_threads(|| unpacker.list_utoc(utoc_path, &options)) {
             Ok(assets) => Ok(assets.into_iter().map(|a| PyAssetPath { asset_path: a }).collect()),
-            Err(e) => Err(PyErr::new::<pyo3::exceptions::PyIOError, String>(e.to_string())),
+            Err(e) => Err(interrupted_to_pyerr(e)),
         }
     }
 
@@ -267,11 +344,80 @@ impl PyUnpacker {
             Err(e) => Err(PyErr::new::<pyo3::exceptions::PyIOError, String>(e.to_string())),
         }
     }
+
+    /// Export a DataTable, CurveTable or StringTable as a list of row dicts
+    #[pyo3(signature = (container, asset, mappings, aes_key = None, engine_version = None))]
+    fn export_table(
+        &mut self,
+        py: Python<'_>,
+        container: &str,
+        asset: &str,
+        mappings: &str,
+        aes_key: Option<&str>,
+        engine_version: Option<&str>,
+    ) -> PyResult<Vec<PyObject>> {
+        let mut options = crate::InspectOptions::new();
+        if let Some(key) = aes_key {
+            options = options.with_aes_key(key);
+        }
+        if let Some(version) = engine_version {
+            let version = version.parse().map_err(PyErr::new::<pyo3::exceptions::PyValueError, String>)?;
+            options = options.with_engine_version(version);
+        }
+
+        let unpacker = &mut self.unpacker;
+        let table = py.allow_threads(|| {
+            let mappings = crate::Mappings::open(mappings)?;
+            unpacker.export_table(container, asset, &mappings, &options)
+        });
+        match table {
+            Ok(table) => table
+                .records()
+                .into_iter()
+                .map(|record| json_to_py(py, &serde_json::Value::Object(record)))
+                .collect(),
+            Err(e) => Err(PyErr::new::<pyo3::exceptions::PyIOError, String>(e.to_string())),
+        }
+    }
+}
+
+/// Convert a decoded JSON value to the matching Python object
+fn json_to_py(py: Python<'_>, value: &serde_json::Value) -> PyResult<PyObject> {
+    use pyo3::types::{PyDict, PyList};
+    use serde_json::Value;
+
+    Ok(match value {
+        Value::Null => py.None(),
+        Value::Bool(b) => b.into_py(py),
+        Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => i.into_py(py),
+            (None, Some(u)) => u.into_py(py),
+            _ => n.as_f64().unwrap_or(f64::NAN).into_py(py),
+        },
+        Value::String(s) => s.into_py(py),
+        Value::Array(items) => {
+            let items = items.iter().map(|item| json_to_py(py, item)).collect::<PyResult<Vec<_>>>()?;
+            PyList::new_bound(py, items).into_py(py)
+        }
+        Value::Object(fields) => {
+            let dict = PyDict::new_bound(py);
+            for (key, item) in fields {
+                dict.set_item(key, json_to_py(py, item)?)?;
+            }
+            dict.into_py(py)
+        }
+    })
 }
 
 #[pymodule]
 fn rust_ue_tools(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<PyAssetPath>()?;
     m.add_class::<PyUnpacker>()?;
+    m.add_class::<PyCancellationToken>()?;
+    m.add("OperationCancelled", m.py().get_type_bound::<OperationCancelled>())?;
+    m.add_function(wrap_pyfunction!(set_log_level, m)?)?;
+
+    // Route library diagnostics into Python's logging; keep any subscriber the host already set
+    let _ = tracing_subscriber::registry().with(PythonLoggingLayer).try_init();
     Ok(())
 }
@@ -0,0 +1,384 @@
+//! Scanning a folder of pak and IoStore containers
+//!
+//! A Paks or mods folder mixes solo `.pak` files with IoStore bundles made of
+//! a `.utoc`, a `.ucas` and a small companion `.pak`. This module pairs those
+//! files by name, lists every container and collects the results into a
+//! [`ContainerMap`], recording failures per container instead of giving up on
+//! the whole folder.
+//!
+//! Only container indexes are read: solo paks are listed from the pak index
+//! and IoStore containers from the `.utoc`, so no entry data is decompressed.
+//! Paths from both are reported as mount point plus entry path with
+//! [`ScanOptions::strip_prefix`] removed.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use crate::cache::{self, ListingCache, ListingKind};
+use crate::cancel::{self, CancellationToken, Interrupted, PartialResult};
+use crate::error::UeToolError;
+use crate::package::ContainerKind;
+use crate::pak_unpack::strip_path_prefix;
+use crate::progress::{Phase, PhaseProgress};
+use crate::types::{AssetPath, PakUnpackOptions, ScanOptions, UtocListOptions};
+use crate::Unpacker;
+
+/// The files on disk that make up one container
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ContainerFiles {
+    pub pak: Option<PathBuf>,
+    pub utoc: Option<PathBuf>,
+    pub ucas: Option<PathBuf>,
+}
+
+impl ContainerFiles {
+    /// IoStore if any IoStore file is present, otherwise a solo pak
+    pub fn kind(&self) -> ContainerKind {
+        if self.utoc.is_some() || self.ucas.is_some() {
+            ContainerKind::IoStore
+        } else {
+            ContainerKind::Pak
+        }
+    }
+
+    /// The file the container is listed from: the `.utoc` for IoStore, else the `.pak`
+    pub fn primary(&self) -> Option<&Path> {
+        self.utoc
+            .as_deref()
+            .or(self.pak.as_deref())
+            .or(self.ucas.as_deref())
+    }
+
+    /// Every file present, primary first
+    pub fn paths(&self) -> Vec<&Path> {
+        [&self.utoc, &self.pak, &self.ucas]
+            .into_iter()
+            .filter_map(|p| p.as_deref())
+            .collect()
+    }
+}
+
+/// Listing of one container found by [`Unpacker::scan_folder`]
+#[derive(Debug, Clone, Serialize)]
+pub struct ScannedContainer {
+    /// File name of the primary file, e.g. `MyMod_P.utoc`
+    pub name: String,
+    pub kind: ContainerKind,
+    pub files: ContainerFiles,
+    /// Asset paths in the container; empty when `error` is set, except for
+    /// the paths listed before a cancellation
+    pub assets: Vec<AssetPath>,
+    /// Why the container could not be listed
+    pub error: Option<String>,
+}
+
+impl ScannedContainer {
+    /// Whether the container was listed successfully
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Containers found in a folder, keyed by the primary file's path relative to the folder
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ContainerMap {
+    pub containers: BTreeMap<String, ScannedContainer>,
+}
+
+impl ContainerMap {
+    /// Number of containers found
+    pub fn len(&self) -> usize {
+        self.containers.len()
+    }
+
+    /// Whether no containers were found
+    pub fn is_empty(&self) -> bool {
+        self.containers.is_empty()
+    }
+
+    /// Look up a container by its relative path
+    pub fn get(&self, key: &str) -> Option<&ScannedContainer> {
+        self.containers.get(key)
+    }
+
+    /// Iterate containers in path order
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &ScannedContainer)> {
+        self.containers.iter()
+    }
+
+    /// Containers that could not be listed
+    pub fn errors(&self) -> impl Iterator<Item = &ScannedContainer> {
+        self.containers.values().filter(|c| !c.is_ok())
+    }
+
+    /// Total number of assets across all containers
+    pub fn asset_count(&self) -> usize {
+        self.containers.values().map(|c| c.assets.len()).sum()
+    }
+
+    /// All asset paths, in container order
+    pub fn into_assets(self) -> Vec<AssetPath> {
+        self.containers.into_values().flat_map(|c| c.assets).collect()
+    }
+}
+
+impl Unpacker {
+    /// List every container in a folder and its subfolders
+    ///
+    /// `.pak`, `.utoc` and `.ucas` files with the same name in the same
+    /// directory are treated as one container. IoStore containers are listed
+    /// from their `.utoc`, falling back to the companion `.pak` when the
+    /// `.utoc` lists no assets. Only indexes are read, never entry data.
+    /// Containers that fail to list are kept in the map with their error set.
+    ///
+    /// With the `parallel` feature and [`ScanOptions::parallel`] set,
+    /// containers are listed on the rayon thread pool. If the cancellation
+    /// token fires, the containers finished so far, and those stopped part way
+    /// with the paths they listed, are returned inside the [`Interrupted`]
+    /// error.
+    pub fn scan_folder<P: AsRef<Path>>(
+        &mut self,
+        folder: P,
+        options: &ScanOptions,
+    ) -> PartialResult<ContainerMap> {
+        let folder = folder.as_ref();
+        if !folder.is_dir() {
+            return Err(UeToolError::file_not_found(folder).into());
+        }
+
+        let _span = tracing::info_span!("scan_folder", folder = %folder.display()).entered();
+
+        let files = WalkDir::new(folder)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| entry.into_path());
+        let pending = pair_containers(folder, files);
+        tracing::debug!(containers = pending.len(), "Found containers");
+
+        let token = self.cancellation.clone();
+        let cache = &self.cache;
+        let progress = Mutex::new(self.progress.phase(Phase::ScanContainers, pending.len() as u64, 0));
+        let scan = |(key, files): (String, ContainerFiles)| {
+            scan_container(key, files, options, token.as_ref(), cache, &progress)
+        };
+
+        #[cfg(feature = "parallel")]
+        let results: Vec<_> = if options.parallel {
+            use rayon::prelude::*;
+            pending.into_par_iter().map(scan).collect()
+        } else {
+            pending.into_iter().map(scan).collect()
+        };
+        #[cfg(not(feature = "parallel"))]
+        let results: Vec<_> = pending.into_iter().map(scan).collect();
+
+        drop(progress);
+
+        let mut map = ContainerMap::default();
+        let mut stopped = None;
+        for (scanned, cancelled) in results {
+            if let Some((key, container)) = scanned {
+                map.containers.insert(key, container);
+            }
+            if let Some(e) = cancelled {
+                stopped.get_or_insert(e);
+            }
+        }
+
+        match stopped {
+            Some(e) => Err(Interrupted::new(map, e)),
+            None => Ok(map),
+        }
+    }
+}
+
+/// Group container files by directory and case-insensitive stem
+///
+/// Returns the containers keyed by their primary file's path relative to `root`.
+pub(crate) fn pair_containers<I>(root: &Path, files: I) -> Vec<(String, ContainerFiles)>
+where
+    I: IntoIterator<Item = PathBuf>,
+{
+    let mut grouped: BTreeMap<(PathBuf, String), ContainerFiles> = BTreeMap::new();
+
+    for path in files {
+        let ext = match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) => ext.to_ascii_lowercase(),
+            None => continue,
+        };
+        let (Some(parent), Some(stem)) = (path.parent(), path.file_stem()) else {
+            continue;
+        };
+        let key = (parent.to_path_buf(), stem.to_string_lossy().to_ascii_lowercase());
+
+        match ext.as_str() {
+            "pak" => grouped.entry(key).or_default().pak = Some(path),
+            "utoc" => grouped.entry(key).or_default().utoc = Some(path),
+            "ucas" => grouped.entry(key).or_default().ucas = Some(path),
+            _ => {}
+        }
+    }
+
+    grouped
+        .into_values()
+        .filter_map(|files| {
+            let primary = files.primary()?;
+            let relative = primary.strip_prefix(root).unwrap_or(primary);
+            let key = relative.to_string_lossy().replace('\\', "/");
+            Some((key, files))
+        })
+        .collect()
+}
+
+/// List one container, recording ordinary failures on the container itself
+///
+/// Returns the container, unless it was not started, and the cancellation
+/// that stopped the scan, if any. A container stopped part way keeps the
+/// paths listed so far.
+fn scan_container(
+    key: String,
+    files: ContainerFiles,
+    options: &ScanOptions,
+    token: Option<&CancellationToken>,
+    cache: &ListingCache,
+    progress: &Mutex<PhaseProgress>,
+) -> (Option<(String, ScannedContainer)>, Option<UeToolError>) {
+    if let Err(e) = cancel::check(token) {
+        return (None, Some(e));
+    }
+
+    if let Ok(mut progress) = progress.lock() {
+        progress.entry_started(&key, 0);
+    }
+
+    let kind = files.kind();
+    let paths = files.paths();
+    let digest = cache::options_digest(&[options.aes_key.as_deref(), Some(&options.strip_prefix)]);
+
+    let listed = match cache.get(ListingKind::Container, &digest, &paths) {
+        Some(assets) => Ok(assets),
+        None => {
+            let mut unpacker = Unpacker::new();
+            if let Some(token) = token {
+                unpacker = unpacker.with_cancellation_token(token.clone());
+            }
+
+            let listed = match kind {
+                ContainerKind::IoStore => list_iostore(&mut unpacker, &files, options),
+                ContainerKind::Pak => list_solo_pak(&mut unpacker, &files, options),
+            };
+            if let Ok(ref assets) = listed {
+                cache.insert(ListingKind::Container, &digest, &paths, assets);
+            }
+            listed
+        }
+    };
+
+    if let Ok(mut progress) = progress.lock() {
+        progress.entry_finished(&key, 0);
+    }
+
+    let (assets, error, stopped) = match listed {
+        Ok(assets) => {
+            tracing::debug!(container = %key, assets = assets.len(), "Listed container");
+            (assets, None, None)
+        }
+        Err(interrupted) if interrupted.is_cancelled() => {
+            (interrupted.partial, Some(interrupted.error.to_string()), Some(interrupted.error))
+        }
+        Err(Interrupted { error, .. }) => {
+            tracing::warn!(container = %key, error = %error, "Failed to list container");
+            (Vec::new(), Some(error.to_string()), None)
+        }
+    };
+
+    let name = files
+        .primary()
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    (Some((key, ScannedContainer { name, kind, files, assets, error })), stopped)
+}
+
+/// List an IoStore container from its `.utoc`, falling back to the companion `.pak`
+fn list_iostore(unpacker: &mut Unpacker, files: &ContainerFiles, options: &ScanOptions) -> PartialResult<Vec<AssetPath>> {
+    let (utoc, _ucas) = match (&files.utoc, &files.ucas) {
+        (Some(utoc), Some(ucas)) => (utoc, ucas),
+        (Some(utoc), None) => return Err(UeToolError::MissingFile(utoc.with_extension("ucas")).into()),
+        (None, Some(ucas)) => return Err(UeToolError::MissingFile(ucas.with_extension("utoc")).into()),
+        (None, None) => unreachable!("IoStore containers have a .utoc or .ucas"),
+    };
+
+    let utoc_options = UtocListOptions {
+        aes_key: options.aes_key.clone(),
+        json_format: false,
+    };
+    let strip = |assets: Vec<AssetPath>| -> Vec<AssetPath> {
+        assets
+            .into_iter()
+            .map(|asset| AssetPath::new(strip_path_prefix(asset.as_str(), &options.strip_prefix)))
+            .collect()
+    };
+    let assets = strip(unpacker.list_utoc(utoc, &utoc_options).map_err(|interrupted| interrupted.map(strip))?);
+    if !assets.is_empty() {
+        return Ok(assets);
+    }
+
+    match files.pak {
+        Some(ref pak) => {
+            tracing::warn!(container = %utoc.display(), "UTOC returned no assets, falling back to PAK file");
+            Ok(unpacker.pak_unpacker.list_mounted_files(pak, &pak_options(options))?)
+        }
+        None => Ok(assets),
+    }
+}
+
+/// List a pak that has no IoStore companion files from its index alone
+fn list_solo_pak(unpacker: &mut Unpacker, files: &ContainerFiles, options: &ScanOptions) -> PartialResult<Vec<AssetPath>> {
+    let pak = files.pak.as_deref().ok_or_else(|| UeToolError::Internal("solo pak without a .pak file".to_string()))?;
+    Ok(unpacker.pak_unpacker.list_mounted_files(pak, &pak_options(options))?)
+}
+
+fn pak_options(options: &ScanOptions) -> PakUnpackOptions {
+    PakUnpackOptions {
+        aes_key: options.aes_key.clone(),
+        strip_prefix: options.strip_prefix.clone(),
+        ..PakUnpackOptions::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pair_containers() {
+        let root = Path::new("/mods");
+        let files = [
+            "/mods/Solo_P.pak",
+            "/mods/~mods/Bundle_P.utoc",
+            "/mods/~mods/Bundle_P.ucas",
+            "/mods/~mods/bundle_p.pak",
+            "/mods/Orphan.ucas",
+            "/mods/readme.txt",
+        ]
+        .into_iter()
+        .map(PathBuf::from);
+
+        let containers = pair_containers(root, files);
+        let keys: Vec<_> = containers.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, ["Orphan.ucas", "Solo_P.pak", "~mods/Bundle_P.utoc"]);
+
+        let (_, bundle) = &containers[2];
+        assert_eq!(bundle.kind(), ContainerKind::IoStore);
+        assert_eq!(bundle.pak.as_deref(), Some(Path::new("/mods/~mods/bundle_p.pak")));
+        assert_eq!(containers[1].1.kind(), ContainerKind::Pak);
+        assert_eq!(containers[0].1.kind(), ContainerKind::IoStore);
+    }
+}
@@ -0,0 +1,303 @@
+//! Container signature (`.sig`) files
+//!
+//! A signed build ships `<Container>.sig` next to each pak. It holds a CRC-32
+//! of every 64 KiB chunk of the container and an RSA signature over the SHA-1
+//! of that chunk table, made with the private half of the project's signing
+//! key (PKCS#1 v1.5 padding, without a digest prefix).
+//!
+//! Checking the chunk table against the container tells which chunks were
+//! modified and needs no key. Checking the table itself needs the public key
+//! and the `signing` feature, as does signing a container with a private key.
+//!
+//! For a `.utoc` the chunks cover its `.ucas`. Containers signed by the engine
+//! embed their IoStore signatures in the `.utoc` instead; those are not read here.
+
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+
+use crate::binary::ReadExt;
+use crate::error::{Result, UeToolError};
+use crate::Unpacker;
+
+/// Magic number at the start of every `.sig` file
+pub const SIGNATURE_MAGIC: u32 = 0x7383_2DAA;
+/// Version of the `.sig` layout written by [`SignatureFile::write`]
+pub const SIGNATURE_VERSION: u32 = 1;
+/// Size of each signed chunk of the container
+pub const SIGNATURE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Parsed `.sig` file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureFile {
+    /// RSA signature over [`SignatureFile::master_hash`]
+    pub encrypted_hash: Vec<u8>,
+    /// CRC-32 of each chunk of the container
+    pub chunk_hashes: Vec<u32>,
+}
+
+impl SignatureFile {
+    /// Read a `.sig` file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(UeToolError::file_not_found(path));
+        }
+        let file = File::open(path).map_err(|e| UeToolError::IoError(format!("Failed to open {}: {}", path.display(), e)))?;
+        Self::read(&mut BufReader::new(file))
+    }
+
+    /// Read a signature file from a stream
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self> {
+        if reader.read_u32()? != SIGNATURE_MAGIC {
+            return Err(UeToolError::invalid_format("Not a signature file: magic not found"));
+        }
+        let version = reader.read_u32()?;
+        if version != SIGNATURE_VERSION {
+            return Err(UeToolError::invalid_format(format!("Unsupported signature file version {}", version)));
+        }
+
+        let hash_len = reader.read_u32()? as usize;
+        let encrypted_hash = reader.read_bytes(hash_len)?;
+        let chunk_count = reader.read_u32()? as usize;
+        let mut chunk_hashes = Vec::with_capacity(chunk_count.min(1 << 20));
+        for _ in 0..chunk_count {
+            chunk_hashes.push(reader.read_u32()?);
+        }
+
+        Ok(Self { encrypted_hash, chunk_hashes })
+    }
+
+    /// Serialize the signature file
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&SIGNATURE_MAGIC.to_le_bytes())?;
+        writer.write_all(&SIGNATURE_VERSION.to_le_bytes())?;
+        writer.write_all(&(self.encrypted_hash.len() as u32).to_le_bytes())?;
+        writer.write_all(&self.encrypted_hash)?;
+        writer.write_all(&(self.chunk_hashes.len() as u32).to_le_bytes())?;
+        for hash in &self.chunk_hashes {
+            writer.write_all(&hash.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// SHA-1 of the chunk table, which is what the RSA signature covers
+    pub fn master_hash(&self) -> [u8; 20] {
+        master_hash(&self.chunk_hashes)
+    }
+}
+
+/// A chunk whose contents no longer match the signature
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TamperedChunk {
+    pub index: usize,
+    /// Offset of the chunk in the signed data file
+    pub offset: u64,
+    pub expected: Option<u32>,
+    pub actual: Option<u32>,
+}
+
+/// Result of checking a container against its `.sig`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureReport {
+    pub container: PathBuf,
+    pub signature: PathBuf,
+    /// Whether the RSA signature matches the chunk table; `None` when no public key was given
+    pub signature_valid: Option<bool>,
+    pub chunk_count: usize,
+    /// Chunks that differ from the table, including chunks present on only one side
+    pub tampered_chunks: Vec<TamperedChunk>,
+}
+
+impl SignatureReport {
+    /// Whether the container is intact and, if checked, the signature is valid
+    pub fn is_ok(&self) -> bool {
+        self.tampered_chunks.is_empty() && self.signature_valid != Some(false)
+    }
+}
+
+/// `.sig` file that belongs to a `.pak` or `.utoc`
+pub fn signature_path(container: &Path) -> PathBuf {
+    container.with_extension("sig")
+}
+
+/// File whose chunks the signature covers: the pak itself, or the `.ucas` of a `.utoc`
+pub fn signed_data_path(container: &Path) -> PathBuf {
+    if container.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("utoc")) {
+        container.with_extension("ucas")
+    } else {
+        container.to_path_buf()
+    }
+}
+
+/// CRC-32 of each 64 KiB chunk of a file
+pub fn chunk_hashes<P: AsRef<Path>>(path: P) -> Result<Vec<u32>> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|e| UeToolError::IoError(format!("Failed to open {}: {}", path.display(), e)))?;
+    let mut reader = BufReader::new(file);
+    let mut buffer = vec![0u8; SIGNATURE_CHUNK_SIZE];
+    let mut hashes = Vec::new();
+    loop {
+        let mut filled = 0;
+        while filled < buffer.len() {
+            match reader.read(&mut buffer[filled..])? {
+                0 => break,
+                n => filled += n,
+            }
+        }
+        if filled == 0 {
+            return Ok(hashes);
+        }
+        hashes.push(crc32fast::hash(&buffer[..filled]));
+    }
+}
+
+fn master_hash(chunk_hashes: &[u32]) -> [u8; 20] {
+    let mut hasher = Sha1::new();
+    for hash in chunk_hashes {
+        hasher.update(hash.to_le_bytes());
+    }
+    hasher.finalize().into()
+}
+
+/// Compare the chunk table of a signature with the chunks of the container
+fn compare_chunks(expected: &[u32], actual: &[u32]) -> Vec<TamperedChunk> {
+    (0..expected.len().max(actual.len()))
+        .filter_map(|index| {
+            let (expected, actual) = (expected.get(index).copied(), actual.get(index).copied());
+            (expected != actual).then_some(TamperedChunk {
+                index,
+                offset: (index * SIGNATURE_CHUNK_SIZE) as u64,
+                expected,
+                actual,
+            })
+        })
+        .collect()
+}
+
+impl Unpacker {
+    /// Check a `.pak` or `.utoc` against the chunk table of its `.sig`
+    ///
+    /// This detects modified chunks but not a forged table; use
+    /// [`Unpacker::verify_signature_with_key`] to also check the RSA signature.
+    pub fn verify_signature<P: AsRef<Path>>(&mut self, container: P) -> Result<SignatureReport> {
+        let container = container.as_ref();
+        let _span = tracing::info_span!("verify_signature", container = %container.display()).entered();
+
+        let signature = signature_path(container);
+        let sig = SignatureFile::open(&signature)?;
+        let actual = chunk_hashes(signed_data_path(container))?;
+        crate::cancel::check(self.cancellation.as_ref())?;
+
+        let tampered_chunks = compare_chunks(&sig.chunk_hashes, &actual);
+        tracing::debug!(chunks = actual.len(), tampered = tampered_chunks.len(), "Checked signature chunks");
+
+        Ok(SignatureReport {
+            container: container.to_path_buf(),
+            signature,
+            signature_valid: None,
+            chunk_count: sig.chunk_hashes.len(),
+            tampered_chunks,
+        })
+    }
+
+    /// Check a `.pak` or `.utoc` against its `.sig`, including the RSA signature over the chunk table
+    #[cfg(feature = "signing")]
+    pub fn verify_signature_with_key<P: AsRef<Path>>(
+        &mut self,
+        container: P,
+        public_key: &rsa::RsaPublicKey,
+    ) -> Result<SignatureReport> {
+        let mut report = self.verify_signature(container)?;
+        let sig = SignatureFile::open(&report.signature)?;
+        report.signature_valid = Some(keys::verify(public_key, &sig));
+        Ok(report)
+    }
+
+    /// Hash a `.pak` or `.utoc` and write its signed `.sig` next to it
+    #[cfg(feature = "signing")]
+    pub fn sign_container<P: AsRef<Path>>(&mut self, container: P, private_key: &rsa::RsaPrivateKey) -> Result<PathBuf> {
+        let container = container.as_ref();
+        let _span = tracing::info_span!("sign_container", container = %container.display()).entered();
+
+        let chunk_hashes = chunk_hashes(signed_data_path(container))?;
+        crate::cancel::check(self.cancellation.as_ref())?;
+        let sig = SignatureFile {
+            encrypted_hash: keys::sign(private_key, &master_hash(&chunk_hashes))?,
+            chunk_hashes,
+        };
+
+        let path = signature_path(container);
+        let file = File::create(&path).map_err(|e| UeToolError::IoError(format!("Failed to create {}: {}", path.display(), e)))?;
+        let mut writer = std::io::BufWriter::new(file);
+        sig.write(&mut writer)?;
+        writer.flush()?;
+        Ok(path)
+    }
+}
+
+/// Loading RSA keys and signing chunk tables
+#[cfg(feature = "signing")]
+pub mod keys {
+    use std::path::Path;
+
+    use rsa::pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey};
+    use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey};
+    use rsa::{Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
+
+    use super::SignatureFile;
+    use crate::error::{Result, UeToolError};
+
+    /// Load a public key from a PEM file (SubjectPublicKeyInfo or PKCS#1)
+    pub fn load_public_key<P: AsRef<Path>>(path: P) -> Result<RsaPublicKey> {
+        let pem = read_pem(path.as_ref())?;
+        RsaPublicKey::from_public_key_pem(&pem)
+            .or_else(|_| RsaPublicKey::from_pkcs1_pem(&pem))
+            .map_err(|e| UeToolError::InvalidArgument(format!("Invalid RSA public key: {}", e)))
+    }
+
+    /// Load a private key from a PEM file (PKCS#8 or PKCS#1)
+    pub fn load_private_key<P: AsRef<Path>>(path: P) -> Result<RsaPrivateKey> {
+        let pem = read_pem(path.as_ref())?;
+        RsaPrivateKey::from_pkcs8_pem(&pem)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_pem(&pem))
+            .map_err(|e| UeToolError::InvalidArgument(format!("Invalid RSA private key: {}", e)))
+    }
+
+    fn read_pem(path: &Path) -> Result<String> {
+        std::fs::read_to_string(path).map_err(|e| UeToolError::IoError(format!("Failed to read {}: {}", path.display(), e)))
+    }
+
+    pub(super) fn sign(key: &RsaPrivateKey, master_hash: &[u8; 20]) -> Result<Vec<u8>> {
+        key.sign(Pkcs1v15Sign::new_unprefixed(), master_hash)
+            .map_err(|e| UeToolError::EncryptionError(format!("Failed to sign chunk table: {}", e)))
+    }
+
+    pub(super) fn verify(key: &RsaPublicKey, sig: &SignatureFile) -> bool {
+        key.verify(Pkcs1v15Sign::new_unprefixed(), &sig.master_hash(), &sig.encrypted_hash).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_file_roundtrip() {
+        let sig = SignatureFile { encrypted_hash: vec![1, 2, 3], chunk_hashes: vec![0xDEADBEEF, 7] };
+        let mut data = Vec::new();
+        sig.write(&mut data).unwrap();
+        assert_eq!(SignatureFile::read(&mut data.as_slice()).unwrap(), sig);
+    }
+
+    #[test]
+    fn test_compare_chunks() {
+        let tampered = compare_chunks(&[1, 2, 3], &[1, 9]);
+        assert_eq!(tampered.iter().map(|c| c.index).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(tampered[0].offset, SIGNATURE_CHUNK_SIZE as u64);
+        assert_eq!(tampered[1].actual, None);
+    }
+}
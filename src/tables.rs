@@ -0,0 +1,417 @@
+//! Export of DataTable, CurveTable and StringTable assets
+//!
+//! Tables are read from the table export of a cooked package. Their own
+//! properties are unversioned, so a [`Mappings`] file is needed, and the
+//! rows follow the properties:
+//!
+//! - `DataTable`: each row is a name followed by the properties of the
+//!   struct named by the table's `RowStruct`.
+//! - `CurveTable`: each row is a name followed by a `RichCurve` or
+//!   `SimpleCurve`.
+//! - `StringTable`: a namespace followed by key and source string pairs.
+//!
+//! [`Table::records`] gives one JSON object per row, with the row name under
+//! `Name` (`Key` and `SourceString` for string tables), the layout UE uses
+//! when exporting tables to JSON.
+
+use std::fmt;
+use std::io::Cursor;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::asset::{read_name, PackageSummary, ScriptObjects};
+use crate::binary::ReadExt;
+use crate::error::{Result, UeToolError};
+use crate::properties::PropertyReader;
+use crate::types::InspectOptions;
+use crate::usmap::Mappings;
+use crate::Unpacker;
+
+/// Kind of table asset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum TableKind {
+    DataTable,
+    CurveTable,
+    StringTable,
+}
+
+impl TableKind {
+    /// Table kind of an export class
+    pub fn from_class(class: &str) -> Option<Self> {
+        match class {
+            "DataTable" | "CompositeDataTable" => Some(TableKind::DataTable),
+            "CurveTable" | "CompositeCurveTable" => Some(TableKind::CurveTable),
+            "StringTable" => Some(TableKind::StringTable),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TableKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableKind::DataTable => write!(f, "DataTable"),
+            TableKind::CurveTable => write!(f, "CurveTable"),
+            TableKind::StringTable => write!(f, "StringTable"),
+        }
+    }
+}
+
+/// Output format for [`Table::render`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableFormat {
+    Json,
+    Csv,
+}
+
+impl FromStr for TableFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(TableFormat::Json),
+            "csv" => Ok(TableFormat::Csv),
+            _ => Err(format!("Unknown table format: {} (expected json or csv)", s)),
+        }
+    }
+}
+
+/// The rows of a table asset
+#[derive(Debug, Clone, Serialize)]
+pub struct Table {
+    pub kind: TableKind,
+    /// Name of the table export
+    pub name: String,
+    /// Row struct of a data table, curve type of a curve table
+    pub row_struct: Option<String>,
+    /// Namespace of a string table
+    pub namespace: Option<String>,
+    /// Rows in table order: the row name (string table key) and its value
+    pub rows: Vec<(String, Value)>,
+}
+
+impl Table {
+    /// Read the table export of a package
+    ///
+    /// `package` is the whole package as taken by [`PackageSummary::export_data`].
+    /// Zen packages need the game's [`ScriptObjects`] to know their classes.
+    pub fn from_package(
+        summary: &PackageSummary,
+        package: &[u8],
+        mappings: &Mappings,
+        script_objects: Option<&ScriptObjects>,
+    ) -> Result<Self> {
+        let (index, kind) = summary
+            .exports
+            .iter()
+            .enumerate()
+            .find_map(|(i, export)| {
+                let class = summary.export_class(export, script_objects)?;
+                TableKind::from_class(&class).map(|kind| (i, kind))
+            })
+            .ok_or_else(|| UeToolError::invalid_argument("Package has no DataTable, CurveTable or StringTable export"))?;
+
+        let mut reader = PropertyReader::new(summary, mappings);
+        if let Some(script_objects) = script_objects {
+            reader = reader.with_script_objects(script_objects);
+        }
+        let (properties, tail) = reader.read_export_with_tail(package, index)?;
+        let mut r = Cursor::new(tail);
+        if r.read_bool32()? {
+            r.read_guid()?; // Lazy object pointer GUID
+        }
+
+        let mut table = Table {
+            kind,
+            name: summary.exports[index].object_name.clone(),
+            row_struct: None,
+            namespace: None,
+            rows: Vec::new(),
+        };
+        match kind {
+            TableKind::DataTable => {
+                let row_struct = properties.get("RowStruct").and_then(Value::as_str).ok_or_else(|| {
+                    UeToolError::DeserializationError(format!("Row struct of {} is unknown", table.name))
+                })?;
+                let count = r.read_count()?;
+                table.rows = read_rows(&mut r, summary, &reader, row_struct, count)?;
+                table.row_struct = Some(row_struct.to_string());
+            }
+            TableKind::CurveTable => {
+                let count = r.read_count()?;
+                let curve = match r.read_u8()? {
+                    0 => None,
+                    1 => Some("SimpleCurve"),
+                    _ => Some("RichCurve"),
+                };
+                if let Some(curve) = curve {
+                    table.rows = read_rows(&mut r, summary, &reader, curve, count)?;
+                }
+                table.row_struct = curve.map(str::to_string);
+            }
+            TableKind::StringTable => {
+                table.namespace = Some(r.read_fstring()?);
+                table.rows = r.read_array_of(|r| Ok((r.read_fstring()?, Value::String(r.read_fstring()?))))?;
+            }
+        }
+        Ok(table)
+    }
+
+    /// One object per row: the row name under `Name` followed by its fields
+    ///
+    /// String tables give `Key` and `SourceString` instead.
+    pub fn records(&self) -> Vec<Map<String, Value>> {
+        self.rows
+            .iter()
+            .map(|(name, value)| {
+                let mut record = Map::new();
+                if self.kind == TableKind::StringTable {
+                    record.insert("Key".to_string(), Value::String(name.clone()));
+                    record.insert("SourceString".to_string(), value.clone());
+                    return record;
+                }
+                record.insert("Name".to_string(), Value::String(name.clone()));
+                match value {
+                    Value::Object(fields) => record.extend(fields.iter().map(|(k, v)| (k.clone(), v.clone()))),
+                    other => {
+                        record.insert("Value".to_string(), other.clone());
+                    }
+                }
+                record
+            })
+            .collect()
+    }
+
+    /// The table as a JSON array of [`Table::records`]
+    pub fn to_json(&self) -> Value {
+        Value::Array(self.records().into_iter().map(Value::Object).collect())
+    }
+
+    /// The table as CSV
+    ///
+    /// Data and string tables get one column per field, nested values as
+    /// compact JSON. Curve tables get one column per key time, as UE exports them.
+    pub fn to_csv(&self) -> String {
+        let (header, rows) = match self.kind {
+            TableKind::CurveTable => self.curve_columns(),
+            _ => {
+                let records = self.records();
+                let key = if self.kind == TableKind::StringTable { "Key" } else { "Name" };
+                let mut header = vec![key.to_string()];
+                for record in &records {
+                    for key in record.keys() {
+                        if !header.contains(key) {
+                            header.push(key.clone());
+                        }
+                    }
+                }
+                let rows = records
+                    .iter()
+                    .map(|record| header.iter().map(|key| record.get(key).map(csv_value).unwrap_or_default()).collect())
+                    .collect();
+                (header, rows)
+            }
+        };
+
+        let mut out = String::new();
+        for row in std::iter::once(&header).chain(&rows) {
+            let cells: Vec<String> = row.iter().map(|cell| csv_escape(cell)).collect();
+            out.push_str(&cells.join(","));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Render the table in `format`
+    pub fn render(&self, format: TableFormat) -> Result<String> {
+        match format {
+            TableFormat::Json => serde_json::to_string_pretty(&self.to_json())
+                .map_err(|e| UeToolError::SerializationError(e.to_string())),
+            TableFormat::Csv => Ok(self.to_csv()),
+        }
+    }
+
+    /// Curve table columns: `Name` then every key time, and each row's value at those times
+    fn curve_columns(&self) -> (Vec<String>, Vec<Vec<String>>) {
+        let keys = |curve: &Value| -> Vec<(f64, Value)> {
+            curve
+                .get("Keys")
+                .and_then(Value::as_array)
+                .map(|keys| {
+                    keys.iter()
+                        .filter_map(|key| Some((key.get("Time")?.as_f64()?, key.get("Value")?.clone())))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let mut times: Vec<f64> = self.rows.iter().flat_map(|(_, curve)| keys(curve)).map(|(time, _)| time).collect();
+        times.sort_by(f64::total_cmp);
+        times.dedup();
+
+        let header = std::iter::once("Name".to_string())
+            .chain(times.iter().map(|time| time.to_string()))
+            .collect();
+        let rows = self
+            .rows
+            .iter()
+            .map(|(name, curve)| {
+                let keys = keys(curve);
+                std::iter::once(name.clone())
+                    .chain(times.iter().map(|time| {
+                        keys.iter().find(|(t, _)| t == time).map(|(_, value)| csv_value(value)).unwrap_or_default()
+                    }))
+                    .collect()
+            })
+            .collect();
+        (header, rows)
+    }
+}
+
+impl Unpacker {
+    /// Read a DataTable, CurveTable or StringTable from a `.pak` or `.utoc`
+    ///
+    /// Native class names are resolved as in [`Unpacker::read_properties`].
+    pub fn export_table<P: AsRef<Path>>(&mut self, container: P, path: &str, mappings: &Mappings, options: &InspectOptions) -> Result<Table> {
+        let container = container.as_ref();
+        let _span = tracing::info_span!("export_table", container = %container.display(), path).entered();
+
+        let (package, summary, script_objects) = self.load_cooked_package(container, path, options)?;
+        Table::from_package(&summary, &package, mappings, script_objects.as_ref())
+    }
+}
+
+/// Read `count` rows of a row name followed by a `row_struct`
+fn read_rows(
+    r: &mut Cursor<&[u8]>,
+    summary: &PackageSummary,
+    reader: &PropertyReader<'_>,
+    row_struct: &str,
+    count: usize,
+) -> Result<Vec<(String, Value)>> {
+    let mut rows = Vec::with_capacity(count.min(4096));
+    for _ in 0..count {
+        let name = read_name(r, &summary.names)?;
+        let start = r.position() as usize;
+        let (value, read) = reader
+            .read_struct(&r.get_ref()[start..], row_struct)
+            .map_err(|e| UeToolError::DeserializationError(format!("Row {}: {}", name, e)))?;
+        r.set_position((start + read) as u64);
+        rows.push((name, value));
+    }
+    Ok(rows)
+}
+
+/// A JSON value as a CSV cell: strings as they are, everything else as compact JSON
+fn csv_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Quote a CSV cell when it contains a separator, quote or line break
+pub(crate) fn csv_escape(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::{ExportEntry, ImportEntry, ObjectRef, PackageFormat, PackageVersions};
+    use crate::binary::write_fstring;
+    use crate::usmap::tests::usmap;
+    use serde_json::json;
+
+    const NAMES: [&str; 8] = ["EUnused", "A", "B", "DataTable", "RowStruct", "HeroRow", "Damage", "StringTable"];
+
+    fn mappings() -> Mappings {
+        let mut structs = Vec::new();
+        let mut add = |name: u32, properties: &[(u32, u8)]| {
+            structs.extend_from_slice(&name.to_le_bytes());
+            structs.extend_from_slice(&(-1i32).to_le_bytes());
+            structs.extend_from_slice(&(properties.len() as u16).to_le_bytes());
+            structs.extend_from_slice(&(properties.len() as u16).to_le_bytes());
+            for (i, (property, kind)) in properties.iter().enumerate() {
+                structs.extend_from_slice(&(i as u16).to_le_bytes());
+                structs.push(1);
+                structs.extend_from_slice(&property.to_le_bytes());
+                structs.push(*kind);
+            }
+        };
+        add(3, &[(4, 4)]); // DataTable: RowStruct (object)
+        add(5, &[(6, 2)]); // HeroRow: Damage (int)
+        add(7, &[]); // StringTable
+        Mappings::parse(&usmap(&NAMES, &structs, 3, false)).unwrap()
+    }
+
+    fn summary(class: &str, export_size: usize) -> PackageSummary {
+        PackageSummary {
+            versions: PackageVersions { file_version_ue4: 522, file_version_ue5: 1009, ..Default::default() },
+            imports: vec![ImportEntry {
+                object_name: Some("HeroRow".to_string()),
+                class_package: None,
+                class_name: None,
+                outer: ObjectRef::Null,
+                package_name: None,
+                global_ref: None,
+            }],
+            ..PackageSummary::for_test(PackageFormat::Legacy, &NAMES, vec![ExportEntry::for_test("DT_Heroes", class, export_size)])
+        }
+    }
+
+    #[test]
+    fn test_data_table() {
+        let data = [
+            0x00, 0x03, // Header: 1 value, last fragment
+            0xFF, 0xFF, 0xFF, 0xFF, // RowStruct = import 0
+            0x00, 0x00, 0x00, 0x00, // No GUID
+            0x02, 0x00, 0x00, 0x00, // 2 rows
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // A
+            0x00, 0x03, 0x19, 0x00, 0x00, 0x00, // Damage = 25
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // B
+            0x00, 0x03, 0xFD, 0xFF, 0xFF, 0xFF, // Damage = -3
+        ];
+
+        let table = Table::from_package(&summary("DataTable", data.len()), &data, &mappings(), None).unwrap();
+        assert_eq!(table.kind, TableKind::DataTable);
+        assert_eq!(table.row_struct.as_deref(), Some("HeroRow"));
+        assert_eq!(table.to_json(), json!([{ "Name": "A", "Damage": 25 }, { "Name": "B", "Damage": -3 }]));
+        assert_eq!(table.to_csv(), "Name,Damage\nA,25\nB,-3\n");
+    }
+
+    #[test]
+    fn test_string_table() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&0x100u16.to_le_bytes()); // No values, last fragment
+        data.extend_from_slice(&0u32.to_le_bytes());
+        write_fstring(&mut data, "UI");
+        data.extend_from_slice(&1i32.to_le_bytes());
+        write_fstring(&mut data, "Greeting");
+        write_fstring(&mut data, "Hello, \"hero\"");
+
+        let table = Table::from_package(&summary("StringTable", data.len()), &data, &mappings(), None).unwrap();
+        assert_eq!(table.namespace.as_deref(), Some("UI"));
+        assert_eq!(table.to_csv(), "Key,SourceString\nGreeting,\"Hello, \"\"hero\"\"\"\n");
+    }
+
+    #[test]
+    fn test_curve_table_csv() {
+        let curve = |keys: &[(f64, f64)]| json!({ "Keys": keys.iter().map(|(t, v)| json!({ "Time": t, "Value": v })).collect::<Vec<_>>() });
+        let table = Table {
+            kind: TableKind::CurveTable,
+            name: "CT_Scaling".to_string(),
+            row_struct: Some("SimpleCurve".to_string()),
+            namespace: None,
+            rows: vec![("Health".to_string(), curve(&[(1.0, 100.0), (2.0, 150.0)])), ("Armor".to_string(), curve(&[(2.0, 5.5)]))],
+        };
+        assert_eq!(table.to_csv(), "Name,1,2\nHealth,100.0,150.0\nArmor,,5.5\n");
+    }
+}
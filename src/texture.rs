@@ -0,0 +1,900 @@
+//! Texture export to PNG and DDS
+//!
+//! Cooked textures keep their mips in the platform data serialized after the
+//! export's properties. Large mips are bulk data in the package's `.ubulk`
+//! (`.uptnl` for optional mips, or an IoStore bulk data chunk); small ones
+//! are stored inline. A mip is decoded on the CPU to RGBA8 for PNG, or
+//! written as it is into a DDS.
+//!
+//! Decoded pixel formats: DXT1/3/5, BC4, BC5, BC6H, BC7, B8G8R8A8,
+//! R8G8B8A8, G8 and FloatRGBA (RGBA16F).
+
+use std::collections::{hash_map, BTreeMap, HashMap};
+use std::fmt;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::asset::{PackageFormat, PackageSummary, ScriptObjects, VER_UE5_DATA_RESOURCES};
+use crate::bcn;
+use crate::binary::ReadExt;
+use crate::cancel::{self, Interrupted, PartialResult};
+use crate::error::{Result, UeToolError};
+use crate::extract::{join_entry_path, open_container, select, write_output, Entry};
+use crate::package::PackageFileKind;
+use crate::progress::Phase;
+use crate::types::{AssetPath, ExtractOptions, TextureOptions, UnpackedFile};
+use crate::vfs::ContainerReader;
+use crate::Unpacker;
+
+/// Export classes whose platform data is read as a texture
+const TEXTURE_CLASSES: [&str; 7] = [
+    "Texture2D",
+    "Texture2DArray",
+    "TextureCube",
+    "TextureCubeArray",
+    "VolumeTexture",
+    "LightMapTexture2D",
+    "ShadowMapTexture2D",
+];
+
+// EBulkDataFlags
+const BULKDATA_SERIALIZE_COMPRESSED_ZLIB: u32 = 0x2;
+const BULKDATA_UNUSED: u32 = 0x20;
+const BULKDATA_FORCE_INLINE_PAYLOAD: u32 = 0x40;
+const BULKDATA_PAYLOAD_IN_SEPARATE_FILE: u32 = 0x100;
+const BULKDATA_OPTIONAL_PAYLOAD: u32 = 0x800;
+const BULKDATA_MEMORY_MAPPED_PAYLOAD: u32 = 0x1000;
+const BULKDATA_SIZE_64BIT: u32 = 0x2000;
+const BULKDATA_BAD_DATA_VERSION: u32 = 0x8000;
+const BULKDATA_NO_OFFSET_FIX_UP: u32 = 0x10000;
+
+// FTexturePlatformData::PackedData
+const PACKED_CUBEMAP: u32 = 1 << 31;
+const PACKED_OPT_DATA: u32 = 1 << 30;
+const PACKED_CPU_COPY: u32 = 1 << 29;
+const PACKED_SLICES: u32 = PACKED_CPU_COPY - 1;
+
+/// Output format of an exported texture
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub enum TextureFormat {
+    /// The mip decoded to 8-bit RGBA
+    #[default]
+    Png,
+    /// The mip's data as stored, behind a DX10 DDS header
+    Dds,
+}
+
+impl TextureFormat {
+    /// File extension without the dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            TextureFormat::Png => "png",
+            TextureFormat::Dds => "dds",
+        }
+    }
+}
+
+impl FromStr for TextureFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "png" => Ok(TextureFormat::Png),
+            "dds" => Ok(TextureFormat::Dds),
+            _ => Err(format!("Unknown texture format: {} (expected png or dds)", s)),
+        }
+    }
+}
+
+/// `EPixelFormat` of a texture's platform data
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum PixelFormat {
+    Dxt1,
+    Dxt3,
+    Dxt5,
+    Bc4,
+    Bc5,
+    Bc6h,
+    Bc7,
+    B8G8R8A8,
+    R8G8B8A8,
+    G8,
+    FloatRgba,
+    /// A format that is neither decoded nor written to DDS, e.g. `PF_ASTC_4x4`
+    Other(String),
+}
+
+impl PixelFormat {
+    /// Parse a `PF_` name as stored in the platform data
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "PF_DXT1" => PixelFormat::Dxt1,
+            "PF_DXT3" => PixelFormat::Dxt3,
+            "PF_DXT5" => PixelFormat::Dxt5,
+            "PF_BC4" => PixelFormat::Bc4,
+            "PF_BC5" => PixelFormat::Bc5,
+            "PF_BC6H" => PixelFormat::Bc6h,
+            "PF_BC7" => PixelFormat::Bc7,
+            "PF_B8G8R8A8" => PixelFormat::B8G8R8A8,
+            "PF_R8G8B8A8" => PixelFormat::R8G8B8A8,
+            "PF_G8" => PixelFormat::G8,
+            "PF_FloatRGBA" => PixelFormat::FloatRgba,
+            other => PixelFormat::Other(other.to_string()),
+        }
+    }
+
+    /// Whether the format is stored in 4x4 blocks
+    pub fn is_block_compressed(&self) -> bool {
+        matches!(
+            self,
+            PixelFormat::Dxt1 | PixelFormat::Dxt3 | PixelFormat::Dxt5 | PixelFormat::Bc4 | PixelFormat::Bc5 | PixelFormat::Bc6h | PixelFormat::Bc7
+        )
+    }
+
+    /// Bytes per 4x4 block, or per pixel for uncompressed formats
+    fn unit_size(&self) -> Option<usize> {
+        match self {
+            PixelFormat::Dxt1 | PixelFormat::Bc4 => Some(8),
+            PixelFormat::Dxt3 | PixelFormat::Dxt5 | PixelFormat::Bc5 | PixelFormat::Bc6h | PixelFormat::Bc7 => Some(16),
+            PixelFormat::B8G8R8A8 | PixelFormat::R8G8B8A8 => Some(4),
+            PixelFormat::G8 => Some(1),
+            PixelFormat::FloatRgba => Some(8),
+            PixelFormat::Other(_) => None,
+        }
+    }
+
+    /// Size of one `width` x `height` slice
+    fn slice_size(&self, width: u32, height: u32) -> Result<usize> {
+        let unit = self
+            .unit_size()
+            .ok_or_else(|| UeToolError::invalid_format(format!("Pixel format {} has no known size", self)))?;
+        let (width, height) = (width as usize, height as usize);
+        let size = if self.is_block_compressed() {
+            width.div_ceil(4).checked_mul(height.div_ceil(4))
+        } else {
+            width.checked_mul(height)
+        };
+        size.and_then(|units| units.checked_mul(unit))
+            .ok_or_else(|| UeToolError::invalid_format(format!("A {}x{} {} slice is too large", width, height, self)))
+    }
+
+    fn dxgi_format(&self) -> Option<u32> {
+        match self {
+            PixelFormat::Dxt1 => Some(71),
+            PixelFormat::Dxt3 => Some(74),
+            PixelFormat::Dxt5 => Some(77),
+            PixelFormat::Bc4 => Some(80),
+            PixelFormat::Bc5 => Some(83),
+            PixelFormat::Bc6h => Some(95),
+            PixelFormat::Bc7 => Some(98),
+            PixelFormat::B8G8R8A8 => Some(87),
+            PixelFormat::R8G8B8A8 => Some(28),
+            PixelFormat::G8 => Some(61),
+            PixelFormat::FloatRgba => Some(10),
+            PixelFormat::Other(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for PixelFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PixelFormat::Dxt1 => "PF_DXT1",
+            PixelFormat::Dxt3 => "PF_DXT3",
+            PixelFormat::Dxt5 => "PF_DXT5",
+            PixelFormat::Bc4 => "PF_BC4",
+            PixelFormat::Bc5 => "PF_BC5",
+            PixelFormat::Bc6h => "PF_BC6H",
+            PixelFormat::Bc7 => "PF_BC7",
+            PixelFormat::B8G8R8A8 => "PF_B8G8R8A8",
+            PixelFormat::R8G8B8A8 => "PF_R8G8B8A8",
+            PixelFormat::G8 => "PF_G8",
+            PixelFormat::FloatRgba => "PF_FloatRGBA",
+            PixelFormat::Other(name) => name,
+        };
+        f.write_str(name)
+    }
+}
+
+/// One mip level of a texture
+#[derive(Debug, Clone, Serialize)]
+pub struct TextureMip {
+    pub width: u32,
+    pub height: u32,
+    /// Depth of a volume texture; 1 otherwise
+    pub depth: u32,
+    /// Every slice of the mip; `None` when its optional `.uptnl` payload is
+    /// not in the container
+    #[serde(skip)]
+    pub data: Option<Vec<u8>>,
+}
+
+/// The cooked platform data of a texture export
+#[derive(Debug, Clone, Serialize)]
+pub struct Texture2D {
+    /// Name of the texture export
+    pub name: String,
+    pub class: Option<String>,
+    pub pixel_format: PixelFormat,
+    pub width: u32,
+    pub height: u32,
+    /// Array slices, or the six faces of a cube
+    pub slices: u32,
+    pub cube: bool,
+    /// Mips from the largest down
+    pub mips: Vec<TextureMip>,
+}
+
+impl Texture2D {
+    /// Read the texture export of a cooked package
+    ///
+    /// `package` is the whole package, as for [`PackageSummary::export_data`].
+    /// `read_bulk` loads the package's `.ubulk`, `.m.ubulk` or `.uptnl`,
+    /// returning `None` if the package has no such file.
+    pub fn from_package<F>(summary: &PackageSummary, package: &[u8], script_objects: Option<&ScriptObjects>, read_bulk: F) -> Result<Self>
+    where
+        F: FnMut(PackageFileKind) -> Result<Option<Vec<u8>>>,
+    {
+        read_texture(summary, package, script_objects, read_bulk)?
+            .ok_or_else(|| UeToolError::invalid_argument("Package has no texture export"))
+    }
+
+    /// Index of mip `mip`, or of the largest mip with data
+    pub fn mip_index(&self, mip: Option<usize>) -> Result<usize> {
+        match mip {
+            Some(index) => match self.mips.get(index) {
+                Some(TextureMip { data: Some(_), .. }) => Ok(index),
+                Some(_) => Err(UeToolError::invalid_argument(format!("Mip {} of {} has no data in this container", index, self.name))),
+                None => Err(UeToolError::invalid_argument(format!("{} has {} mips; mip {} does not exist", self.name, self.mips.len(), index))),
+            },
+            None => self
+                .mips
+                .iter()
+                .position(|m| m.data.is_some())
+                .ok_or_else(|| UeToolError::invalid_format(format!("No mip of {} has data", self.name))),
+        }
+    }
+
+    /// Decode the first slice of mip `mip` to 8-bit RGBA
+    ///
+    /// HDR formats are clamped to 0..1.
+    pub fn decode(&self, mip: usize) -> Result<Vec<u8>> {
+        let (level, data) = self.mip_data(mip)?;
+        let (width, height) = (level.width as usize, level.height as usize);
+        let format = &self.pixel_format;
+        let unsupported = || UeToolError::invalid_format(format!("Cannot decode pixel format {}", format));
+        let size = format.slice_size(level.width, level.height)?;
+        let data = data.get(..size).ok_or_else(|| {
+            UeToolError::invalid_format(format!("Mip {} of {} has {} bytes, expected {}", mip, self.name, data.len(), size))
+        })?;
+
+        let rgba = match format {
+            PixelFormat::Dxt1 => decode_blocks(data, width, height, 8, bcn::bc1),
+            PixelFormat::Dxt3 => decode_blocks(data, width, height, 16, bcn::bc2),
+            PixelFormat::Dxt5 => decode_blocks(data, width, height, 16, bcn::bc3),
+            PixelFormat::Bc4 => decode_blocks(data, width, height, 8, bcn::bc4),
+            PixelFormat::Bc5 => decode_blocks(data, width, height, 16, bcn::bc5),
+            PixelFormat::Bc7 => decode_blocks(data, width, height, 16, bcn::bc7),
+            PixelFormat::Bc6h => decode_blocks(data, width, height, 16, |block| {
+                bcn::bc6h(block).map(|[r, g, b]| [half_to_u8(r), half_to_u8(g), half_to_u8(b), 255])
+            }),
+            PixelFormat::B8G8R8A8 => data.chunks_exact(4).flat_map(|p| [p[2], p[1], p[0], p[3]]).collect(),
+            PixelFormat::R8G8B8A8 => data.to_vec(),
+            PixelFormat::G8 => data.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+            PixelFormat::FloatRgba => data
+                .chunks_exact(2)
+                .map(|half| half_to_u8(u16::from_le_bytes([half[0], half[1]])))
+                .collect(),
+            PixelFormat::Other(_) => return Err(unsupported()),
+        };
+        Ok(rgba)
+    }
+
+    /// Encode the first slice of mip `mip` as a PNG
+    pub fn to_png(&self, mip: usize) -> Result<Vec<u8>> {
+        let rgba = self.decode(mip)?;
+        let level = &self.mips[mip];
+        let png_error = |e: png::EncodingError| UeToolError::SerializationError(format!("Failed to encode PNG: {}", e));
+
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, level.width, level.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(png_error)?;
+        writer.write_image_data(&rgba).map_err(png_error)?;
+        writer.finish().map_err(png_error)?;
+        Ok(out)
+    }
+
+    /// Write every slice of mip `mip` to a DDS with a DX10 header
+    pub fn to_dds(&self, mip: usize) -> Result<Vec<u8>> {
+        let (level, data) = self.mip_data(mip)?;
+        let format = &self.pixel_format;
+        let Some(dxgi_format) = format.dxgi_format() else {
+            return Err(UeToolError::invalid_format(format!("Pixel format {} has no DDS equivalent", format)));
+        };
+        let slice_size = format.slice_size(level.width, level.height)?;
+        let volume = level.depth > 1;
+
+        // DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT, with a linear size or a pitch
+        let mut flags = 0x1 | 0x2 | 0x4 | 0x1000;
+        let pitch = if format.is_block_compressed() {
+            flags |= 0x80000;
+            slice_size
+        } else {
+            flags |= 0x8;
+            slice_size / level.height.max(1) as usize
+        };
+        let pitch = u32::try_from(pitch)
+            .map_err(|_| UeToolError::invalid_format(format!("Mip {} of {} is too large for a DDS", mip, self.name)))?;
+        if volume {
+            flags |= 0x800000;
+        }
+        let (caps, caps2) = match (self.cube, volume) {
+            (true, _) => (0x1000 | 0x8, 0xFE00),
+            (_, true) => (0x1000 | 0x8, 0x200000),
+            _ => (0x1000, 0),
+        };
+
+        let mut out = Vec::with_capacity(148 + data.len());
+        out.extend_from_slice(b"DDS ");
+        let mut u32s = vec![124, flags, level.height, level.width, pitch, level.depth, 1];
+        u32s.extend([0; 11]);
+        u32s.extend([32, 0x4, u32::from_le_bytes(*b"DX10"), 0, 0, 0, 0, 0]);
+        u32s.extend([caps, caps2, 0, 0, 0]);
+        // DDS_HEADER_DXT10: format, dimension, misc flags, array size, misc flags 2
+        let array_size = match (self.cube, volume) {
+            (true, _) => (self.slices / 6).max(1),
+            (_, true) => 1,
+            _ => self.slices.max(1),
+        };
+        u32s.extend([dxgi_format, if volume { 4 } else { 3 }, if self.cube { 0x4 } else { 0 }, array_size, 0]);
+        for value in u32s {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out.extend_from_slice(data);
+        Ok(out)
+    }
+
+    /// Mip `mip` (the largest with data if `None`) in `format`
+    pub fn render(&self, format: TextureFormat, mip: Option<usize>) -> Result<Vec<u8>> {
+        let mip = self.mip_index(mip)?;
+        match format {
+            TextureFormat::Png => self.to_png(mip),
+            TextureFormat::Dds => self.to_dds(mip),
+        }
+    }
+
+    fn mip_data(&self, mip: usize) -> Result<(&TextureMip, &[u8])> {
+        let index = self.mip_index(Some(mip))?;
+        let level = &self.mips[index];
+        Ok((level, level.data.as_deref().unwrap_or_default()))
+    }
+}
+
+impl Unpacker {
+    /// Read a texture from a `.pak` or `.utoc`, with the mips in its bulk data
+    ///
+    /// Native class names are resolved as in [`Unpacker::read_properties`].
+    pub fn read_texture<P: AsRef<Path>>(&mut self, container: P, path: &str, options: &TextureOptions) -> Result<Texture2D> {
+        let container = container.as_ref();
+        let _span = tracing::info_span!("read_texture", container = %container.display(), path).entered();
+
+        let (mut reader, entries) = open_container(container, &extract_options(options))?;
+        let script_objects = ScriptObjects::for_container(container, options.aes_key.as_deref());
+        let package = match PackageFileKind::classify(path) {
+            (PackageFileKind::Other, _) => path,
+            (_, name) => name,
+        };
+        let files = select(&entries, package);
+        if files.is_empty() {
+            return Err(UeToolError::file_not_found(path));
+        }
+        let (package, summary) = read_header(&mut reader, &files, options)?;
+        load_texture(&mut reader, &files, package, &summary, script_objects.as_ref())?
+            .ok_or_else(|| UeToolError::invalid_argument(format!("{} is not a texture", path)))
+    }
+
+    /// Export a texture to the file `dest` as PNG or DDS
+    pub fn export_texture<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        container: P,
+        path: &str,
+        dest: Q,
+        options: &TextureOptions,
+    ) -> Result<UnpackedFile> {
+        let texture = self.read_texture(container, path, options)?;
+        write_texture(&texture, path, dest.as_ref(), options)
+    }
+
+    /// Export every texture in a container under `dest_dir`
+    ///
+    /// Textures are written to `dest_dir/<package path>.png` (or `.dds`).
+    /// Only packages matching `options.include_patterns` are read, when any
+    /// are given. A texture that fails to export is reported with its error
+    /// rather than stopping the batch.
+    pub fn export_textures<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        container: P,
+        dest_dir: Q,
+        options: &TextureOptions,
+    ) -> PartialResult<Vec<UnpackedFile>> {
+        let (container, dest_dir) = (container.as_ref(), dest_dir.as_ref());
+        let _span = tracing::info_span!("export_textures", container = %container.display()).entered();
+
+        let (mut reader, entries) = open_container(container, &extract_options(options))?;
+        let script_objects = ScriptObjects::for_container(container, options.aes_key.as_deref());
+        let mut packages: BTreeMap<&str, Vec<&Entry>> = BTreeMap::new();
+        for entry in &entries {
+            match PackageFileKind::classify(&entry.0) {
+                (PackageFileKind::Other | PackageFileKind::Map, _) => {}
+                (_, name) => packages.entry(name).or_default().push(entry),
+            }
+        }
+        packages.retain(|name, files| {
+            files.iter().any(|(full, _)| PackageFileKind::classify(full).0 == PackageFileKind::Asset)
+                && (options.include_patterns.is_empty() || options.include_patterns.iter().any(|p| p.matches(name)))
+        });
+
+        let mut progress = self.progress.phase(Phase::Unpack, packages.len() as u64, 0);
+        let mut exported = Vec::new();
+        for (name, files) in packages {
+            if let Err(e) = cancel::check(self.cancellation.as_ref()) {
+                return Err(Interrupted::new(exported, e));
+            }
+            progress.entry_started(name, 0);
+            let (dest, header) = match join_entry_path(dest_dir, &format!("{}.{}", name, options.format.extension())) {
+                Ok(dest) => (dest, read_header(&mut reader, &files, options)),
+                Err(e) => (PathBuf::new(), Err(e)),
+            };
+            let result = match header {
+                Ok((package, summary)) => load_texture(&mut reader, &files, package, &summary, script_objects.as_ref())
+                    .and_then(|texture| texture.map(|texture| write_texture(&texture, name, &dest, options)).transpose()),
+                // Unversioned and no engine version given: every package would fail the same way
+                Err(e @ UeToolError::InvalidArgument(_)) => return Err(Interrupted::new(exported, e)),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(Some(file)) => {
+                    progress.entry_finished(name, file.size);
+                    exported.push(file);
+                }
+                Ok(None) => progress.entry_finished(name, 0),
+                Err(e) => {
+                    tracing::warn!(package = %name, error = %e, "Failed to export texture");
+                    progress.entry_finished(name, 0);
+                    exported.push(UnpackedFile {
+                        original_path: AssetPath::new(name),
+                        output_path: dest,
+                        size: 0,
+                        error: Some(e.to_string()),
+                    });
+                }
+            }
+        }
+        progress.finish();
+        Ok(exported)
+    }
+}
+
+fn extract_options(options: &TextureOptions) -> ExtractOptions {
+    let mut extract = ExtractOptions::new();
+    extract.aes_key = options.aes_key.clone();
+    extract
+}
+
+/// Read and parse the header of the package made of `files`
+fn read_header(reader: &mut ContainerReader, files: &[&Entry], options: &TextureOptions) -> Result<(Vec<u8>, PackageSummary)> {
+    let header = files
+        .iter()
+        .find(|(full, _)| PackageFileKind::classify(full).0 == PackageFileKind::Asset)
+        .ok_or_else(|| UeToolError::invalid_format("Package has no .uasset header"))?;
+    let package = reader.read(&header.1)?;
+    let summary = PackageSummary::parse(&package, options.engine_version)?;
+    Ok((package, summary))
+}
+
+/// Read the texture of a package from its header and the rest of `files`;
+/// `None` if it is not a texture
+///
+/// The `.uexp` and bulk data are only read for textures.
+fn load_texture(
+    reader: &mut ContainerReader,
+    files: &[&Entry],
+    mut package: Vec<u8>,
+    summary: &PackageSummary,
+    script_objects: Option<&ScriptObjects>,
+) -> Result<Option<Texture2D>> {
+    if texture_exports(summary, script_objects).is_empty() {
+        return Ok(None);
+    }
+    let file = |kind: PackageFileKind| files.iter().find(|(full, _)| PackageFileKind::classify(full).0 == kind);
+    if let Some(exports) = file(PackageFileKind::Exports) {
+        package.extend_from_slice(&reader.read(&exports.1)?);
+    }
+
+    read_texture(summary, &package, script_objects, |kind| match file(kind) {
+        Some(entry) => reader.read(&entry.1).map(Some),
+        None => Ok(None),
+    })
+}
+
+fn write_texture(texture: &Texture2D, path: &str, dest: &Path, options: &TextureOptions) -> Result<UnpackedFile> {
+    let data = texture.render(options.format, options.mip)?;
+    write_output(dest, &data, options.force)?;
+    tracing::trace!(texture = %texture.name, bytes = data.len(), "Exported texture");
+
+    Ok(UnpackedFile {
+        original_path: AssetPath::new(path),
+        output_path: dest.to_path_buf(),
+        size: data.len() as u64,
+        error: None,
+    })
+}
+
+/// Exports that may be textures: those of a texture class, or of an unknown
+/// class when the package has no known texture export
+fn texture_exports(summary: &PackageSummary, script_objects: Option<&ScriptObjects>) -> Vec<(usize, Option<String>)> {
+    let classes: Vec<_> = summary.exports.iter().map(|e| summary.export_class(e, script_objects)).collect();
+    let known: Vec<_> = classes
+        .iter()
+        .enumerate()
+        .filter(|(_, class)| class.as_deref().is_some_and(|c| TEXTURE_CLASSES.contains(&c)))
+        .map(|(i, class)| (i, class.clone()))
+        .collect();
+    if !known.is_empty() {
+        return known;
+    }
+    classes.iter().enumerate().filter(|(_, class)| class.is_none()).map(|(i, _)| (i, None)).collect()
+}
+
+fn read_texture<F>(
+    summary: &PackageSummary,
+    package: &[u8],
+    script_objects: Option<&ScriptObjects>,
+    mut read_bulk: F,
+) -> Result<Option<Texture2D>>
+where
+    F: FnMut(PackageFileKind) -> Result<Option<Vec<u8>>>,
+{
+    let mut bulk_files: HashMap<PackageFileKind, Option<Vec<u8>>> = HashMap::new();
+    for (index, class) in texture_exports(summary, script_objects) {
+        let data = summary.export_data(package, index)?;
+        let Some((pixel_format, mut r)) = find_platform_data(summary, data) else {
+            if class.is_some() {
+                return Err(UeToolError::invalid_format(format!(
+                    "No cooked platform data found in {}",
+                    summary.exports[index].object_name
+                )));
+            }
+            continue;
+        };
+
+        let ue5 = has_ue5_layout(summary);
+        let width = r.read_u32()?;
+        let height = r.read_u32()?;
+        let packed = r.read_u32()?;
+        r.read_fstring()?; // pixel format, checked by find_platform_data
+        if packed & PACKED_OPT_DATA != 0 {
+            r.read_fixed::<8>()?; // ExtData, NumMipsInTail
+        }
+        if packed & PACKED_CPU_COPY != 0 {
+            return Err(UeToolError::invalid_format("Textures with a CPU copy are not supported"));
+        }
+        r.read_i32()?; // FirstMipToSerialize
+
+        let count = r.read_count()?;
+        let mut mips = Vec::with_capacity(count.min(32));
+        for _ in 0..count {
+            let cooked = if ue5 { true } else { r.read_bool32()? };
+            let payload = read_bulk_data(&mut r, summary)?;
+            let mip_width = r.read_u32()?;
+            let mip_height = r.read_u32()?;
+            let depth = r.read_u32()?;
+            if !cooked {
+                r.read_fstring()?; // DerivedDataKey
+            }
+
+            let data = match payload {
+                Payload::None => None,
+                Payload::Inline(data) => Some(data),
+                Payload::Package { offset, size } => Some(slice(package, offset, size)?.to_vec()),
+                Payload::File { kind, offset, size } => {
+                    if let hash_map::Entry::Vacant(slot) = bulk_files.entry(kind) {
+                        slot.insert(read_bulk(kind)?);
+                    }
+                    match &bulk_files[&kind] {
+                        Some(file) => Some(slice(file, offset, size)?.to_vec()),
+                        // Optional mips are shipped separately and may be missing
+                        None if kind == PackageFileKind::OptionalBulk => None,
+                        None => {
+                            let extension = if kind == PackageFileKind::MemoryMappedBulk { "m.ubulk" } else { "ubulk" };
+                            let name = summary.package_name.as_deref().unwrap_or(&summary.exports[index].object_name);
+                            return Err(UeToolError::MissingFile(format!("{}.{}", name, extension).into()));
+                        }
+                    }
+                }
+            };
+            mips.push(TextureMip { width: mip_width, height: mip_height, depth: depth.max(1), data });
+        }
+
+        return Ok(Some(Texture2D {
+            name: summary.exports[index].object_name.clone(),
+            class,
+            pixel_format,
+            width,
+            height,
+            slices: (packed & PACKED_SLICES).max(1),
+            cube: packed & PACKED_CUBEMAP != 0,
+            mips,
+        }));
+    }
+    Ok(None)
+}
+
+/// Whether the platform data has the UE5 layout: a placeholder for derived
+/// data before the size, and no `bCooked` before each mip
+///
+/// UE 4.26/4.27 IoStore packages have the UE4 layout, so this follows the
+/// object versions rather than the container. Zen packages without any were
+/// read without an engine version, which only works for UE5 summaries.
+fn has_ue5_layout(summary: &PackageSummary) -> bool {
+    let versions = &summary.versions;
+    if versions.file_version_ue4 == 0 && versions.file_version_ue5 == 0 {
+        return summary.format == PackageFormat::Zen;
+    }
+    versions.file_version_ue5 > 0
+}
+
+/// Find the platform data in a texture export and position a reader at its size
+///
+/// The platform data follows the export's properties, so it is found by
+/// looking for its pixel format name, then checking that the layout after
+/// it repeats that name as a string.
+fn find_platform_data<'a>(summary: &PackageSummary, data: &'a [u8]) -> Option<(PixelFormat, Cursor<&'a [u8]>)> {
+    let ue5 = has_ue5_layout(summary);
+    let formats: Vec<(i32, &str)> = summary
+        .names
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| name.starts_with("PF_"))
+        .map(|(i, name)| (i as i32, name.as_str()))
+        .collect();
+
+    for start in 0..data.len().saturating_sub(8) {
+        let index = i32::from_le_bytes(data[start..start + 4].try_into().unwrap());
+        let Some(&(_, name)) = formats.iter().find(|(i, _)| *i == index) else {
+            continue;
+        };
+        if data[start + 4..start + 8] != [0; 4] {
+            continue;
+        }
+
+        let mut r = Cursor::new(data);
+        r.set_position(start as u64 + 8);
+        let layout = (|| -> Result<bool> {
+            r.read_u64()?; // SkipOffset
+            if ue5 {
+                r.read_fixed::<16>()?; // placeholder for derived data
+            }
+            r.read_fixed::<12>()?; // SizeX, SizeY, PackedData
+            Ok(r.read_fstring()? == name)
+        })();
+        if layout.unwrap_or(false) {
+            r.set_position(start as u64 + 16 + if ue5 { 16 } else { 0 });
+            return Some((PixelFormat::from_name(name), r));
+        }
+    }
+    None
+}
+
+/// Where the payload of a bulk data header is stored
+enum Payload {
+    None,
+    Inline(Vec<u8>),
+    /// In the package itself, past the exports
+    Package { offset: i64, size: i64 },
+    /// In the package's `.ubulk`, `.m.ubulk` or `.uptnl`
+    File { kind: PackageFileKind, offset: i64, size: i64 },
+}
+
+/// Read an `FByteBulkData` header, and its payload when stored inline
+fn read_bulk_data(r: &mut Cursor<&[u8]>, summary: &PackageSummary) -> Result<Payload> {
+    let legacy = summary.format == PackageFormat::Legacy;
+    let uses_data_resources = if legacy {
+        summary.versions.file_version_ue5 >= VER_UE5_DATA_RESOURCES
+    } else {
+        !summary.data_resources.is_empty()
+    };
+
+    let (flags, size, mut offset) = if uses_data_resources {
+        let index = r.read_i32()?;
+        let resource = usize::try_from(index)
+            .ok()
+            .and_then(|i| summary.data_resources.get(i))
+            .ok_or_else(|| UeToolError::invalid_format(format!("Data resource {} is out of range", index)))?;
+        (resource.flags, resource.serial_size, resource.serial_offset)
+    } else {
+        let flags = r.read_u32()?;
+        let size = if flags & BULKDATA_SIZE_64BIT != 0 {
+            r.read_u64()?; // ElementCount
+            r.read_u64()? as i64
+        } else {
+            r.read_i32()?;
+            r.read_i32()? as i64
+        };
+        let offset = r.read_u64()? as i64;
+        if flags & BULKDATA_BAD_DATA_VERSION != 0 {
+            r.read_u16()?;
+        }
+        (flags, size, offset)
+    };
+
+    if flags & BULKDATA_UNUSED != 0 || size == 0 {
+        return Ok(Payload::None);
+    }
+    if flags & BULKDATA_SERIALIZE_COMPRESSED_ZLIB != 0 {
+        return Err(UeToolError::invalid_format("Compressed bulk data is not supported"));
+    }
+    if flags & BULKDATA_FORCE_INLINE_PAYLOAD != 0 {
+        let size = usize::try_from(size).map_err(|_| UeToolError::invalid_format(format!("Invalid bulk data size {}", size)))?;
+        return Ok(Payload::Inline(r.read_bytes(size)?));
+    }
+    if legacy && flags & BULKDATA_NO_OFFSET_FIX_UP == 0 {
+        offset += summary.bulk_data_start_offset;
+    }
+    if flags & BULKDATA_PAYLOAD_IN_SEPARATE_FILE != 0 {
+        let kind = if flags & BULKDATA_OPTIONAL_PAYLOAD != 0 {
+            PackageFileKind::OptionalBulk
+        } else if flags & BULKDATA_MEMORY_MAPPED_PAYLOAD != 0 {
+            PackageFileKind::MemoryMappedBulk
+        } else {
+            PackageFileKind::Bulk
+        };
+        return Ok(Payload::File { kind, offset, size });
+    }
+    Ok(Payload::Package { offset: offset - summary.cooked_header_size as i64 + summary.header_size as i64, size })
+}
+
+fn slice(data: &[u8], offset: i64, size: i64) -> Result<&[u8]> {
+    usize::try_from(offset)
+        .ok()
+        .zip(usize::try_from(size).ok())
+        .and_then(|(start, size)| data.get(start..start.checked_add(size)?))
+        .ok_or_else(|| UeToolError::invalid_format(format!("Bulk data at {} ({} bytes) runs past the end of its file", offset, size)))
+}
+
+/// Decode a block compressed slice; blocks past the image edge are cropped
+fn decode_blocks<F>(data: &[u8], width: usize, height: usize, block_size: usize, decode: F) -> Vec<u8>
+where
+    F: Fn(&[u8]) -> [[u8; 4]; 16],
+{
+    let blocks_wide = width.div_ceil(4);
+    let mut rgba = vec![0u8; width * height * 4];
+    for (i, block) in data.chunks_exact(block_size).enumerate().take(blocks_wide * height.div_ceil(4)) {
+        let (bx, by) = (i % blocks_wide * 4, i / blocks_wide * 4);
+        for (p, pixel) in decode(block).iter().enumerate() {
+            let (x, y) = (bx + p % 4, by + p / 4);
+            if x < width && y < height {
+                rgba[(y * width + x) * 4..][..4].copy_from_slice(pixel);
+            }
+        }
+    }
+    rgba
+}
+
+fn half_to_u8(half: u16) -> u8 {
+    (bcn::half_to_f32(half).clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::{ExportEntry, PackageVersions};
+    use crate::binary::write_fstring;
+
+    const NAMES: [&str; 3] = ["None", "PF_B8G8R8A8", "T_Icon"];
+
+    fn summary(export_size: usize) -> PackageSummary {
+        PackageSummary {
+            versions: PackageVersions { file_version_ue4: 522, file_version_ue5: 1008, ..Default::default() },
+            ..PackageSummary::for_test(PackageFormat::Legacy, &NAMES, vec![ExportEntry::for_test("T_Icon", "Texture2D", export_size)])
+        }
+    }
+
+    /// A 4x2 B8G8R8A8 texture: mip 0 in the `.ubulk`, the 2x1 mip 1 inline
+    fn texture_export(ue5: bool) -> Vec<u8> {
+        let mut data = vec![0u8; 6]; // properties and strip flags
+        data.extend_from_slice(&1i32.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&0i64.to_le_bytes()); // SkipOffset
+        if ue5 {
+            data.extend_from_slice(&[0; 16]);
+        }
+        for value in [4u32, 2, 1] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        write_fstring(&mut data, "PF_B8G8R8A8");
+        data.extend_from_slice(&0i32.to_le_bytes());
+        data.extend_from_slice(&2i32.to_le_bytes());
+
+        let separate = BULKDATA_PAYLOAD_IN_SEPARATE_FILE | BULKDATA_NO_OFFSET_FIX_UP;
+        for (flags, size, width, height) in [(separate, 32i32, 4u32, 2u32), (BULKDATA_FORCE_INLINE_PAYLOAD, 8, 2, 1)] {
+            if !ue5 {
+                data.extend_from_slice(&1u32.to_le_bytes()); // bCooked
+            }
+            data.extend_from_slice(&flags.to_le_bytes());
+            data.extend_from_slice(&size.to_le_bytes());
+            data.extend_from_slice(&size.to_le_bytes());
+            data.extend_from_slice(&4i64.to_le_bytes());
+            if flags == BULKDATA_FORCE_INLINE_PAYLOAD {
+                data.extend_from_slice(&[0x10, 0x20, 0x30, 0xFF, 0x40, 0x50, 0x60, 0x80]);
+            }
+            for value in [width, height, 1] {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        data.extend_from_slice(&0i32.to_le_bytes()); // next pixel format: None
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data
+    }
+
+    fn read(bulk: Option<Vec<u8>>) -> Result<Texture2D> {
+        let data = texture_export(true);
+        let mut requested = Vec::new();
+        let texture = Texture2D::from_package(&summary(data.len()), &data, None, |kind| {
+            requested.push(kind);
+            Ok(bulk.clone())
+        });
+        assert!(requested.iter().all(|&kind| kind == PackageFileKind::Bulk));
+        texture
+    }
+
+    #[test]
+    fn test_read_texture() {
+        let ubulk: Vec<u8> = [0u8; 4].into_iter().chain(0..32).collect();
+        let texture = read(Some(ubulk)).unwrap();
+        assert_eq!(texture.name, "T_Icon");
+        assert_eq!(texture.pixel_format, PixelFormat::B8G8R8A8);
+        assert_eq!((texture.width, texture.height, texture.slices, texture.cube), (4, 2, 1, false));
+        assert_eq!(texture.mips.len(), 2);
+        assert_eq!(texture.mips[0].data.as_deref(), Some(&(0..32).collect::<Vec<u8>>()[..]));
+        assert_eq!(texture.mip_index(None).unwrap(), 0);
+
+        // BGRA to RGBA
+        assert_eq!(texture.decode(1).unwrap(), [0x30, 0x20, 0x10, 0xFF, 0x60, 0x50, 0x40, 0x80]);
+        assert!(texture.mip_index(Some(2)).is_err());
+
+        let png = texture.render(TextureFormat::Png, Some(1)).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        let dds = texture.render(TextureFormat::Dds, None).unwrap();
+        assert_eq!(dds.len(), 148 + 32);
+        assert_eq!(&dds[84..88], b"DX10");
+        assert_eq!(u32::from_le_bytes(dds[128..132].try_into().unwrap()), 87);
+    }
+
+    #[test]
+    fn test_missing_bulk_file() {
+        assert!(matches!(read(None), Err(UeToolError::MissingFile(_))));
+        assert_eq!(TextureFormat::from_str("DDS"), Ok(TextureFormat::Dds));
+    }
+
+    #[test]
+    fn test_ue4_iostore_layout() {
+        // A UE 4.27 IoStore package: zen summary, UE4 platform data
+        let data = texture_export(false);
+        let mut summary = summary(data.len());
+        summary.format = PackageFormat::Zen;
+        summary.versions = PackageVersions { file_version_ue4: 522, file_version_ue5: 0, ..Default::default() };
+        let texture = Texture2D::from_package(&summary, &data, None, |_| Ok(Some(vec![0; 36]))).unwrap();
+        assert_eq!((texture.width, texture.height, texture.mips.len()), (4, 2, 2));
+        assert_eq!(texture.mips[1].data.as_deref(), Some(&[0x10, 0x20, 0x30, 0xFF, 0x40, 0x50, 0x60, 0x80][..]));
+
+        assert!(PixelFormat::Bc7.slice_size(u32::MAX, u32::MAX).is_err());
+        assert!(PixelFormat::FloatRgba.slice_size(u32::MAX, u32::MAX).is_err());
+        assert!(PixelFormat::Other("PF_ASTC_4x4".to_string()).slice_size(4, 4).is_err());
+    }
+}
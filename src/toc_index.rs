@@ -0,0 +1,382 @@
+//! Low-level IoStore table of contents (.utoc) parsing
+//!
+//! retoc resolves chunk paths for listings, but the per-chunk sizes, the
+//! compression block table and the container flags are only available by
+//! reading the TOC itself. This module parses those tables so listings can
+//! report real sizes and compression details.
+
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::binary::ReadExt;
+use crate::error::{Result, UeToolError};
+use crate::pak_index::{decrypt_in_place, parse_aes_key};
+
+/// Magic string at the start of every .utoc file
+pub const TOC_MAGIC: &[u8; 16] = b"-==--==--==--==-";
+
+/// TOC version that introduced the directory index
+const VERSION_DIRECTORY_INDEX: u8 = 2;
+/// TOC version that introduced perfect hash seeds
+const VERSION_PERFECT_HASH: u8 = 4;
+/// TOC version that added the list of chunks without a perfect hash
+const VERSION_PERFECT_HASH_WITH_OVERFLOW: u8 = 5;
+
+/// Container flags stored in the TOC header
+pub mod container_flags {
+    pub const COMPRESSED: u8 = 1 << 0;
+    pub const ENCRYPTED: u8 = 1 << 1;
+    pub const SIGNED: u8 = 1 << 2;
+    pub const INDEXED: u8 = 1 << 3;
+    pub const ON_DEMAND: u8 = 1 << 4;
+}
+
+/// One entry of the compression block table
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TocBlock {
+    /// Offset of the compressed block inside the .ucas partition space
+    pub offset: u64,
+    pub compressed_size: u32,
+    pub uncompressed_size: u32,
+    /// Index into `TocIndex::compression_methods`, 0 meaning stored
+    pub compression_method: u8,
+}
+
+/// A chunk listed in the TOC
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TocChunk {
+    /// Raw 12 byte `FIoChunkId`
+    #[serde(with = "hex_chunk_id")]
+    pub id: [u8; 12],
+    /// Chunk type byte from the id
+    pub chunk_type: u8,
+    /// Path from the directory index (mount point included), if indexed
+    pub path: Option<String>,
+    /// Offset of the chunk in the uncompressed container space
+    pub offset: u64,
+    /// Uncompressed size of the chunk
+    pub size: u64,
+    /// Sum of the compressed sizes of the blocks covering the chunk
+    pub compressed_size: u64,
+    /// Compression method of the first block, if compressed
+    pub compression: Option<String>,
+}
+
+/// Parsed .utoc header and tables
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TocIndex {
+    pub version: u8,
+    pub container_id: u64,
+    pub container_flags: u8,
+    pub compression_block_size: u32,
+    pub partition_count: u32,
+    pub partition_size: u64,
+    pub compression_methods: Vec<String>,
+    pub mount_point: String,
+    pub chunks: Vec<TocChunk>,
+    pub blocks: Vec<TocBlock>,
+    /// Raw TOC and block signatures, present for signed containers
+    pub toc_signature: Option<Vec<u8>>,
+    pub block_signature: Option<Vec<u8>>,
+    /// SHA-1 of each compression block, present for signed containers
+    pub block_hashes: Vec<[u8; 20]>,
+}
+
+impl TocIndex {
+    /// Read a .utoc file from disk
+    pub fn open<P: AsRef<Path>>(utoc_path: P, aes_key: Option<&str>) -> Result<Self> {
+        let utoc_path = utoc_path.as_ref();
+        if !utoc_path.exists() {
+            return Err(UeToolError::file_not_found(utoc_path));
+        }
+
+        let data = std::fs::read(utoc_path)
+            .map_err(|e| UeToolError::IoError(format!("Failed to read UTOC file: {}", e)))?;
+        let key = aes_key.map(parse_aes_key).transpose()?;
+        Self::parse(&data, key.as_ref())
+    }
+
+    /// Parse a .utoc file already loaded into memory
+    pub fn parse(data: &[u8], key: Option<&aes::Aes256>) -> Result<Self> {
+        let mut reader = Cursor::new(data);
+
+        let magic = reader.read_fixed::<16>()?;
+        if &magic != TOC_MAGIC {
+            return Err(UeToolError::UtocError("Not a UTOC file: bad magic".to_string()));
+        }
+
+        let version = reader.read_u8()?;
+        let _reserved0 = reader.read_u8()?;
+        let _reserved1 = reader.read_u16()?;
+        let header_size = reader.read_u32()?;
+        let entry_count = reader.read_u32()? as usize;
+        let block_count = reader.read_u32()? as usize;
+        let _block_entry_size = reader.read_u32()?;
+        let method_count = reader.read_u32()? as usize;
+        let method_length = reader.read_u32()? as usize;
+        let compression_block_size = reader.read_u32()?;
+        let directory_index_size = reader.read_u32()? as usize;
+        let partition_count = reader.read_u32()?;
+        let container_id = reader.read_u64()?;
+        let _encryption_guid = reader.read_guid()?;
+        let container_flags = reader.read_u8()?;
+        let _reserved3 = reader.read_u8()?;
+        let _reserved4 = reader.read_u16()?;
+        let perfect_hash_seed_count = reader.read_u32()? as usize;
+        let partition_size = reader.read_u64()?;
+        let chunks_without_perfect_hash_count = reader.read_u32()? as usize;
+
+        reader.set_position(header_size as u64);
+
+        let mut ids = Vec::with_capacity(entry_count.min(4096));
+        for _ in 0..entry_count {
+            ids.push(reader.read_fixed::<12>()?);
+        }
+
+        let mut offset_lengths = Vec::with_capacity(entry_count.min(4096));
+        for _ in 0..entry_count {
+            let raw = reader.read_fixed::<10>()?;
+            offset_lengths.push((read_u40_be(&raw[0..5]), read_u40_be(&raw[5..10])));
+        }
+
+        if version >= VERSION_PERFECT_HASH {
+            skip(&mut reader, perfect_hash_seed_count * 4)?;
+        }
+        if version >= VERSION_PERFECT_HASH_WITH_OVERFLOW {
+            skip(&mut reader, chunks_without_perfect_hash_count * 4)?;
+        }
+
+        let mut blocks = Vec::with_capacity(block_count.min(4096));
+        for _ in 0..block_count {
+            let raw = reader.read_fixed::<12>()?;
+            blocks.push(TocBlock {
+                offset: read_u40_le(&raw[0..5]),
+                compressed_size: u32::from_le_bytes([raw[5], raw[6], raw[7], 0]),
+                uncompressed_size: u32::from_le_bytes([raw[8], raw[9], raw[10], 0]),
+                compression_method: raw[11],
+            });
+        }
+
+        let mut compression_methods = Vec::with_capacity(method_count.min(4096));
+        for _ in 0..method_count {
+            let raw = reader.read_bytes(method_length)?;
+            let end = raw.iter().position(|&b| b == 0).unwrap_or(raw.len());
+            compression_methods.push(String::from_utf8_lossy(&raw[..end]).to_string());
+        }
+
+        let mut toc_signature = None;
+        let mut block_signature = None;
+        let mut block_hashes = Vec::new();
+        if container_flags & container_flags::SIGNED != 0 {
+            let hash_size = reader.read_count()?;
+            toc_signature = Some(reader.read_bytes(hash_size)?);
+            block_signature = Some(reader.read_bytes(hash_size)?);
+            for _ in 0..block_count {
+                block_hashes.push(reader.read_fixed::<20>()?);
+            }
+        }
+
+        let mut mount_point = String::new();
+        let mut paths: HashMap<u32, String> = HashMap::new();
+        if version >= VERSION_DIRECTORY_INDEX
+            && container_flags & container_flags::INDEXED != 0
+            && directory_index_size > 0
+        {
+            let mut index = reader.read_bytes(directory_index_size)?;
+            if container_flags & container_flags::ENCRYPTED != 0 {
+                let key = key.ok_or_else(|| {
+                    UeToolError::EncryptionError("UTOC directory index is encrypted and no AES key was supplied".to_string())
+                })?;
+                decrypt_in_place(key, &mut index);
+            }
+            let directory = DirectoryIndex::parse(&index)?;
+            mount_point = directory.mount_point.clone();
+            paths = directory.file_paths();
+        }
+
+        let chunks = ids
+            .iter()
+            .zip(offset_lengths.iter())
+            .enumerate()
+            .map(|(i, (id, &(offset, size)))| {
+                let (compressed_size, compression) =
+                    chunk_compression(&blocks, &compression_methods, compression_block_size, offset, size);
+                TocChunk {
+                    id: *id,
+                    chunk_type: id[11],
+                    path: paths.get(&(i as u32)).map(|p| format!("{}{}", mount_point, p)),
+                    offset,
+                    size,
+                    compressed_size,
+                    compression,
+                }
+            })
+            .collect();
+
+        Ok(TocIndex {
+            version,
+            container_id,
+            container_flags,
+            compression_block_size,
+            partition_count,
+            partition_size,
+            compression_methods,
+            mount_point,
+            chunks,
+            blocks,
+            toc_signature,
+            block_signature,
+            block_hashes,
+        })
+    }
+
+    /// Whether the container data is encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.container_flags & container_flags::ENCRYPTED != 0
+    }
+
+    /// Whether the container carries block signatures
+    pub fn is_signed(&self) -> bool {
+        self.container_flags & container_flags::SIGNED != 0
+    }
+
+    /// Map of chunk path to chunk, for chunks present in the directory index
+    pub fn chunks_by_path(&self) -> HashMap<&str, &TocChunk> {
+        self.chunks
+            .iter()
+            .filter_map(|c| c.path.as_deref().map(|p| (p, c)))
+            .collect()
+    }
+}
+
+/// The `FIoDirectoryIndexResource` tree
+struct DirectoryIndex {
+    mount_point: String,
+    directories: Vec<(u32, u32, u32, u32)>,
+    files: Vec<(u32, u32, u32)>,
+    strings: Vec<String>,
+}
+
+impl DirectoryIndex {
+    const NONE: u32 = u32::MAX;
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        let mut reader = Cursor::new(data);
+        let mount_point = reader.read_fstring()?;
+        let directories = reader.read_array_of(|r| Ok((r.read_u32()?, r.read_u32()?, r.read_u32()?, r.read_u32()?)))?;
+        let files = reader.read_array_of(|r| Ok((r.read_u32()?, r.read_u32()?, r.read_u32()?)))?;
+        let strings = reader.read_array_of(|r| r.read_fstring())?;
+        Ok(Self { mount_point, directories, files, strings })
+    }
+
+    fn name(&self, index: u32) -> &str {
+        self.strings.get(index as usize).map(String::as_str).unwrap_or("")
+    }
+
+    /// Resolve every file to its path relative to the mount point, keyed by TOC entry index
+    fn file_paths(&self) -> HashMap<u32, String> {
+        let mut paths = HashMap::new();
+        if self.directories.is_empty() {
+            return paths;
+        }
+
+        let mut stack = vec![(0u32, String::new())];
+        while let Some((dir_index, prefix)) = stack.pop() {
+            let Some(&(_, first_child, _, first_file)) = self.directories.get(dir_index as usize) else {
+                continue;
+            };
+
+            let mut file = first_file;
+            while file != Self::NONE {
+                let Some(&(name, next, user_data)) = self.files.get(file as usize) else { break };
+                paths.insert(user_data, format!("{}{}", prefix, self.name(name)));
+                file = next;
+            }
+
+            let mut child = first_child;
+            while child != Self::NONE {
+                let Some(&(name, _, next_sibling, _)) = self.directories.get(child as usize) else { break };
+                stack.push((child, format!("{}{}/", prefix, self.name(name))));
+                child = next_sibling;
+            }
+        }
+
+        paths
+    }
+}
+
+/// Total compressed size and first compression method for a chunk range
+fn chunk_compression(
+    blocks: &[TocBlock],
+    methods: &[String],
+    block_size: u32,
+    offset: u64,
+    size: u64,
+) -> (u64, Option<String>) {
+    if block_size == 0 || size == 0 {
+        return (size, None);
+    }
+
+    let first = (offset / block_size as u64) as usize;
+    let last = ((offset + size - 1) / block_size as u64) as usize;
+    let covered = blocks.get(first..=last.min(blocks.len().saturating_sub(1))).unwrap_or(&[]);
+
+    let compressed = covered.iter().map(|b| b.compressed_size as u64).sum();
+    let method = covered
+        .first()
+        .filter(|b| b.compression_method != 0)
+        .and_then(|b| methods.get(b.compression_method as usize - 1).cloned());
+    (compressed, method)
+}
+
+fn read_u40_be(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64)
+}
+
+fn read_u40_le(bytes: &[u8]) -> u64 {
+    bytes.iter().rev().fold(0u64, |acc, &b| (acc << 8) | b as u64)
+}
+
+fn skip<R: Read>(reader: &mut R, len: usize) -> Result<()> {
+    reader.read_bytes(len).map(|_| ())
+}
+
+mod hex_chunk_id {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(id: &[u8; 12], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(id))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<[u8; 12], D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let bytes = hex::decode(&s).map_err(serde::de::Error::custom)?;
+        bytes.try_into().map_err(|_| serde::de::Error::custom("expected 12 byte chunk id"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_compression_spans_blocks() {
+        let blocks = vec![
+            TocBlock { offset: 0, compressed_size: 100, uncompressed_size: 65536, compression_method: 1 },
+            TocBlock { offset: 100, compressed_size: 50, uncompressed_size: 65536, compression_method: 1 },
+            TocBlock { offset: 150, compressed_size: 10, uncompressed_size: 1000, compression_method: 0 },
+        ];
+        let methods = vec!["Oodle".to_string()];
+
+        let (compressed, method) = chunk_compression(&blocks, &methods, 65536, 0, 65536 + 10);
+        assert_eq!(compressed, 150);
+        assert_eq!(method.as_deref(), Some("Oodle"));
+
+        let (compressed, method) = chunk_compression(&blocks, &methods, 65536, 131072, 1000);
+        assert_eq!(compressed, 10);
+        assert_eq!(method, None);
+    }
+}
@@ -5,6 +5,9 @@ use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use glob::Pattern;
 
+use crate::asset::EngineVersion;
+use crate::texture::TextureFormat;
+
 /// Represents a UE asset path (like "/Game/Characters/Hero/Hero.uasset")
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, PartialOrd, Ord)]
 pub struct AssetPath(pub String);
@@ -77,7 +80,7 @@ impl std::fmt::Display for AssetPath {
 }
 
 /// Supported compression methods
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum CompressionMethod {
     /// No compression
     None,
@@ -86,6 +89,7 @@ pub enum CompressionMethod {
     /// Gzip compression
     Gzip,
     /// Oodle compression (default)
+    #[default]
     Oodle,
     /// Zstd compression
     Zstd,
@@ -93,12 +97,6 @@ pub enum CompressionMethod {
     Lz4,
 }
 
-impl Default for CompressionMethod {
-    fn default() -> Self {
-        CompressionMethod::Oodle
-    }
-}
-
 impl FromStr for CompressionMethod {
     type Err = String;
 
@@ -188,22 +186,50 @@ impl PakUnpackOptions {
 }
 
 /// Options for listing.utoc files
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct UtocListOptions {
     pub aes_key: Option<String>,
     pub json_format: bool,
 }
 
-impl Default for UtocListOptions {
+impl UtocListOptions {
+    /// Create new options with default values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the AES key for encrypted files
+    pub fn with_aes_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.aes_key = Some(key.into());
+        self
+    }
+
+    /// Enable JSON output format
+    pub fn with_json_format(mut self, json: bool) -> Self {
+        self.json_format = json;
+        self
+    }
+}
+
+/// Options for scanning a folder of containers
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    pub aes_key: Option<String>,
+    pub strip_prefix: String,
+    pub parallel: bool,
+}
+
+impl Default for ScanOptions {
     fn default() -> Self {
         Self {
             aes_key: None,
-            json_format: false,
+            strip_prefix: "../../../".to_string(),
+            parallel: true,
         }
     }
 }
 
-impl UtocListOptions {
+impl ScanOptions {
     /// Create new options with default values
     pub fn new() -> Self {
         Self::default()
@@ -215,9 +241,319 @@ impl UtocListOptions {
         self
     }
 
-    /// Enable JSON output format
-    pub fn with_json_format(mut self, json: bool) -> Self {
-        self.json_format = json;
+    /// Set the strip prefix for pak entry paths
+    pub fn with_strip_prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.strip_prefix = prefix.into();
+        self
+    }
+
+    /// Scan containers in parallel (needs the `parallel` feature)
+    pub fn with_parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+}
+
+/// Options for finding conflicts between containers
+#[derive(Debug, Clone)]
+pub struct ConflictOptions {
+    pub aes_key: Option<String>,
+    pub hash_contents: bool,
+}
+
+impl Default for ConflictOptions {
+    fn default() -> Self {
+        Self {
+            aes_key: None,
+            hash_contents: true,
+        }
+    }
+}
+
+impl ConflictOptions {
+    /// Create new options with default values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the AES key for encrypted files
+    pub fn with_aes_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.aes_key = Some(key.into());
+        self
+    }
+
+    /// Read and hash every conflicting copy to tell whether contents differ
+    pub fn with_hash_contents(mut self, hash_contents: bool) -> Self {
+        self.hash_contents = hash_contents;
+        self
+    }
+}
+
+/// Options for reading and merging config files
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOptions {
+    pub aes_key: Option<String>,
+    /// Platform whose inis are merged, e.g. `Windows`; platform inis are skipped when `None`
+    pub platform: Option<String>,
+}
+
+impl ConfigOptions {
+    /// Create new options with default values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the AES key for encrypted files
+    pub fn with_aes_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.aes_key = Some(key.into());
+        self
+    }
+
+    /// Merge the platform inis of `platform` on top of the defaults
+    pub fn with_platform<S: Into<String>>(mut self, platform: S) -> Self {
+        self.platform = Some(platform.into());
+        self
+    }
+}
+
+/// Options for comparing two containers
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    pub aes_key: Option<String>,
+    pub package_level: bool,
+}
+
+impl DiffOptions {
+    /// Create new options with default values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the AES key for encrypted files, used for both sides
+    pub fn with_aes_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.aes_key = Some(key.into());
+        self
+    }
+
+    /// Compare packages instead of files, even when both sides have the same kind
+    pub fn with_package_level(mut self, package_level: bool) -> Self {
+        self.package_level = package_level;
+        self
+    }
+}
+
+/// Options for building a patch pak
+#[derive(Debug, Clone, Default)]
+pub struct PatchOptions {
+    pub aes_key: Option<String>,
+    /// Directory to write the patch to, next to the base pak when unset
+    pub output_dir: Option<PathBuf>,
+    /// Overwrite a patch pak that already exists
+    pub force: bool,
+}
+
+impl PatchOptions {
+    /// Create new options with default values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the AES key used to read the base pak
+    pub fn with_aes_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.aes_key = Some(key.into());
+        self
+    }
+
+    /// Write the patch to this directory instead of next to the base pak
+    pub fn with_output_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.output_dir = Some(dir.into());
+        self
+    }
+
+    /// Overwrite a patch pak that already exists
+    pub fn with_force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+}
+
+/// Options for verifying a pak
+#[derive(Debug, Clone)]
+pub struct VerifyOptions {
+    pub aes_key: Option<String>,
+    pub parallel: bool,
+}
+
+impl Default for VerifyOptions {
+    fn default() -> Self {
+        Self {
+            aes_key: None,
+            parallel: true,
+        }
+    }
+}
+
+impl VerifyOptions {
+    /// Create new options with default values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the AES key for encrypted files
+    pub fn with_aes_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.aes_key = Some(key.into());
+        self
+    }
+
+    /// Check entries in parallel (needs the `parallel` feature)
+    pub fn with_parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+}
+
+/// Options for extracting selected entries
+#[derive(Debug, Clone, Default)]
+pub struct ExtractOptions {
+    pub aes_key: Option<String>,
+    pub force: bool,
+}
+
+impl ExtractOptions {
+    /// Create new options with default values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the AES key for encrypted files
+    pub fn with_aes_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.aes_key = Some(key.into());
+        self
+    }
+
+    /// Overwrite files that already exist
+    pub fn with_force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+}
+
+/// Options for parsing package summaries
+#[derive(Debug, Clone, Default)]
+pub struct InspectOptions {
+    pub aes_key: Option<String>,
+    /// Engine the game was built with; required for unversioned legacy packages
+    pub engine_version: Option<EngineVersion>,
+}
+
+impl InspectOptions {
+    /// Create new options with default values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the AES key for encrypted files
+    pub fn with_aes_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.aes_key = Some(key.into());
+        self
+    }
+
+    /// Set the engine version used to parse unversioned packages
+    pub fn with_engine_version(mut self, version: EngineVersion) -> Self {
+        self.engine_version = Some(version);
+        self
+    }
+}
+
+/// Options for exporting textures
+#[derive(Debug, Clone, Default)]
+pub struct TextureOptions {
+    pub aes_key: Option<String>,
+    /// Engine the game was built with; required for unversioned legacy packages
+    pub engine_version: Option<EngineVersion>,
+    /// Mip to export, 0 being the largest; the largest mip with data by default
+    pub mip: Option<usize>,
+    pub format: TextureFormat,
+    pub force: bool,
+    /// Textures to export from a whole container, matched against package
+    /// paths; every texture when empty
+    pub include_patterns: Vec<Pattern>,
+}
+
+impl TextureOptions {
+    /// Create new options with default values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the AES key for encrypted files
+    pub fn with_aes_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.aes_key = Some(key.into());
+        self
+    }
+
+    /// Set the engine version used to parse unversioned packages
+    pub fn with_engine_version(mut self, version: EngineVersion) -> Self {
+        self.engine_version = Some(version);
+        self
+    }
+
+    /// Export mip `mip` instead of the largest one
+    pub fn with_mip(mut self, mip: usize) -> Self {
+        self.mip = Some(mip);
+        self
+    }
+
+    /// Set the output format
+    pub fn with_format(mut self, format: TextureFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Overwrite files that already exist
+    pub fn with_force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Only export textures matching these patterns
+    pub fn with_include_patterns(mut self, patterns: Vec<Pattern>) -> Self {
+        self.include_patterns = patterns;
+        self
+    }
+}
+
+/// Options for building package dependency graphs
+#[derive(Debug, Clone, Default)]
+pub struct DependencyOptions {
+    pub aes_key: Option<String>,
+    /// Engine the game was built with; required for unversioned paks
+    pub engine_version: Option<EngineVersion>,
+    /// Keep `/Script/` packages (native modules) as dependencies
+    pub include_script: bool,
+}
+
+impl DependencyOptions {
+    /// Create new options with default values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the AES key for encrypted files
+    pub fn with_aes_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.aes_key = Some(key.into());
+        self
+    }
+
+    /// Set the engine version used to parse unversioned packages
+    pub fn with_engine_version(mut self, version: EngineVersion) -> Self {
+        self.engine_version = Some(version);
+        self
+    }
+
+    /// Keep dependencies on `/Script/` packages
+    pub fn with_include_script(mut self, include_script: bool) -> Self {
+        self.include_script = include_script;
         self
     }
 }
@@ -248,22 +584,6 @@ pub struct UnpackedFile {
     pub error: Option<String>,
 }
 
-/// Progress information for long operations
-#[derive(Debug, Clone)]
-pub struct ProgressInfo {
-    /// Current progress (0-100)
-    pub percentage: u8,
-    /// Current operation description
-    pub message: String,
-    /// Number of items processed
-    pub processed: u64,
-    /// Total number of items to process
-    pub total: u64,
-}
-
-/// Callback for progress updates
-pub type ProgressCallback = Box<dyn FnMut(ProgressInfo) + Send + Sync>;
-
 /// Information about a PAK file
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PakFileInfo {
@@ -0,0 +1,431 @@
+//! Loading of `.usmap` mappings files
+//!
+//! Games cooked with unversioned property serialization store properties
+//! without names or types, so the layout of every class and struct has to
+//! come from mappings dumped from the running game. A `.usmap` holds the
+//! game's names, enums and structs (with their properties in schema order).
+//!
+//! Versions 0 through 4 (`ExplicitEnumValues`) are read, uncompressed or
+//! compressed with Oodle, Brotli or Zstandard. Oodle goes through the same
+//! loader repak uses for Oodle compressed paks.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+use crate::binary::ReadExt;
+use crate::error::{Result, UeToolError};
+
+/// Magic at the start of every `.usmap`
+pub const USMAP_MAGIC: u16 = 0x30C4;
+
+// EUsmapVersion
+const VER_PACKAGE_VERSIONING: u8 = 1;
+const VER_LONG_FNAME: u8 = 2;
+const VER_LARGE_ENUMS: u8 = 3;
+const VER_EXPLICIT_ENUM_VALUES: u8 = 4;
+
+/// Largest decompressed size accepted from a `.usmap` header
+const MAX_DECOMPRESSED_SIZE: usize = 1 << 28;
+
+/// Type of a mapped property, with the types it contains
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropertyType {
+    Byte,
+    Bool,
+    Int,
+    Float,
+    Object,
+    Name,
+    Delegate,
+    Double,
+    Array(Box<PropertyType>),
+    Struct(String),
+    Str,
+    Text,
+    Interface,
+    MulticastDelegate,
+    WeakObject,
+    LazyObject,
+    AssetObject,
+    SoftObject,
+    UInt64,
+    UInt32,
+    UInt16,
+    Int64,
+    Int16,
+    Int8,
+    Map(Box<PropertyType>, Box<PropertyType>),
+    Set(Box<PropertyType>),
+    /// Enum stored as its underlying integer type
+    Enum(Box<PropertyType>, String),
+    FieldPath,
+    Optional(Box<PropertyType>),
+    Utf8Str,
+    AnsiStr,
+    /// A type id this version of the format does not know
+    Unknown(u8),
+}
+
+/// A property of a mapped struct
+#[derive(Debug, Clone)]
+pub struct MappedProperty {
+    pub name: String,
+    /// Index of the first element in the struct's schema
+    pub schema_index: u16,
+    /// Number of elements of a fixed-size array; 1 for plain properties
+    pub array_size: u8,
+    pub kind: PropertyType,
+}
+
+/// A class or struct with its serializable properties
+#[derive(Debug, Clone)]
+pub struct MappedStruct {
+    pub name: String,
+    pub super_struct: Option<String>,
+    /// Schema slots the struct adds on top of its super struct
+    pub property_count: u16,
+    pub properties: Vec<MappedProperty>,
+}
+
+/// An enum with its (value, name) pairs
+#[derive(Debug, Clone)]
+pub struct MappedEnum {
+    pub name: String,
+    pub values: Vec<(i64, String)>,
+}
+
+impl MappedEnum {
+    /// Name of the enumerator with this value
+    pub fn name_of(&self, value: i64) -> Option<&str> {
+        self.values.iter().find(|(v, _)| *v == value).map(|(_, name)| name.as_str())
+    }
+}
+
+/// Class, struct and enum layouts loaded from a `.usmap`
+#[derive(Debug, Clone, Default)]
+pub struct Mappings {
+    /// Object versions the mappings were dumped with, if recorded
+    pub file_version_ue4: Option<i32>,
+    pub file_version_ue5: Option<i32>,
+    structs: HashMap<String, MappedStruct>,
+    enums: HashMap<String, MappedEnum>,
+}
+
+impl Mappings {
+    /// Parse a `.usmap` file
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let mut r = Cursor::new(data);
+        let magic = r.read_u16()?;
+        if magic != USMAP_MAGIC {
+            return Err(UeToolError::invalid_format(format!("Not a .usmap file: bad magic {:#06x}", magic)));
+        }
+        let version = r.read_u8()?;
+        if version > VER_EXPLICIT_ENUM_VALUES {
+            return Err(UeToolError::invalid_format(format!("Unsupported .usmap version {}", version)));
+        }
+
+        let mut mappings = Self::default();
+        if version >= VER_PACKAGE_VERSIONING && r.read_bool32()? {
+            mappings.file_version_ue4 = Some(r.read_i32()?);
+            mappings.file_version_ue5 = Some(r.read_i32()?);
+            r.read_array_of(|r| {
+                r.read_guid()?;
+                r.read_i32()
+            })?; // CustomVersions
+            r.read_u32()?; // NetCL
+        }
+
+        let method = r.read_u8()?;
+        let compressed_size = r.read_u32()? as usize;
+        let decompressed_size = r.read_u32()? as usize;
+        let payload = decompress(method, r.read_bytes(compressed_size)?, decompressed_size)?;
+        if payload.len() != decompressed_size {
+            return Err(UeToolError::invalid_format(format!(
+                ".usmap payload is {} bytes, expected {}",
+                payload.len(),
+                decompressed_size
+            )));
+        }
+
+        let mut r = Cursor::new(payload.as_slice());
+        let name_count = r.read_u32()? as usize;
+        let mut names = Vec::with_capacity(name_count.min(1 << 20));
+        for _ in 0..name_count {
+            let len = if version >= VER_LONG_FNAME { r.read_u16()? as usize } else { r.read_u8()? as usize };
+            let bytes = r.read_bytes(len)?;
+            names.push(String::from_utf8_lossy(&bytes).into_owned());
+        }
+        let name = |r: &mut Cursor<&[u8]>| -> Result<String> {
+            let index = r.read_u32()?;
+            names
+                .get(index as usize)
+                .cloned()
+                .ok_or_else(|| UeToolError::invalid_format(format!("Name index {} is out of range", index)))
+        };
+
+        for _ in 0..r.read_u32()? {
+            let enum_name = name(&mut r)?;
+            let count = if version >= VER_LARGE_ENUMS { r.read_u16()? as usize } else { r.read_u8()? as usize };
+            let mut values = Vec::with_capacity(count);
+            for i in 0..count {
+                let value = if version >= VER_EXPLICIT_ENUM_VALUES { r.read_u64()? as i64 } else { i as i64 };
+                values.push((value, name(&mut r)?));
+            }
+            mappings.enums.insert(enum_name.clone(), MappedEnum { name: enum_name, values });
+        }
+
+        for _ in 0..r.read_u32()? {
+            let struct_name = name(&mut r)?;
+            let super_struct = match r.read_i32()? {
+                -1 => None,
+                index => Some(
+                    names
+                        .get(index as usize)
+                        .cloned()
+                        .ok_or_else(|| UeToolError::invalid_format(format!("Name index {} is out of range", index)))?,
+                ),
+            };
+            let property_count = r.read_u16()?;
+            let serializable_count = r.read_u16()?;
+            let mut properties = Vec::with_capacity(serializable_count as usize);
+            for _ in 0..serializable_count {
+                let schema_index = r.read_u16()?;
+                let array_size = r.read_u8()?;
+                let property_name = name(&mut r)?;
+                let kind = read_property_type(&mut r, &name)?;
+                properties.push(MappedProperty { name: property_name, schema_index, array_size, kind });
+            }
+            mappings
+                .structs
+                .insert(struct_name.clone(), MappedStruct { name: struct_name, super_struct, property_count, properties });
+        }
+        Ok(mappings)
+    }
+
+    /// Read and parse a `.usmap` file on disk
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(|e| UeToolError::IoError(format!("Failed to read {}: {}", path.display(), e)))?;
+        Self::parse(&data)
+    }
+
+    /// A class or struct by name, without its `U`/`F` prefix
+    pub fn get_struct(&self, name: &str) -> Option<&MappedStruct> {
+        self.structs.get(name)
+    }
+
+    /// An enum by name
+    pub fn get_enum(&self, name: &str) -> Option<&MappedEnum> {
+        self.enums.get(name)
+    }
+
+    /// Number of mapped classes and structs
+    pub fn struct_count(&self) -> usize {
+        self.structs.len()
+    }
+
+    /// Number of mapped enums
+    pub fn enum_count(&self) -> usize {
+        self.enums.len()
+    }
+}
+
+fn read_property_type<F>(r: &mut Cursor<&[u8]>, name: &F) -> Result<PropertyType>
+where
+    F: Fn(&mut Cursor<&[u8]>) -> Result<String>,
+{
+    let inner = |r: &mut Cursor<&[u8]>| read_property_type(r, name).map(Box::new);
+    Ok(match r.read_u8()? {
+        0 => PropertyType::Byte,
+        1 => PropertyType::Bool,
+        2 => PropertyType::Int,
+        3 => PropertyType::Float,
+        4 => PropertyType::Object,
+        5 => PropertyType::Name,
+        6 => PropertyType::Delegate,
+        7 => PropertyType::Double,
+        8 => PropertyType::Array(inner(r)?),
+        9 => PropertyType::Struct(name(r)?),
+        10 => PropertyType::Str,
+        11 => PropertyType::Text,
+        12 => PropertyType::Interface,
+        13 => PropertyType::MulticastDelegate,
+        14 => PropertyType::WeakObject,
+        15 => PropertyType::LazyObject,
+        16 => PropertyType::AssetObject,
+        17 => PropertyType::SoftObject,
+        18 => PropertyType::UInt64,
+        19 => PropertyType::UInt32,
+        20 => PropertyType::UInt16,
+        21 => PropertyType::Int64,
+        22 => PropertyType::Int16,
+        23 => PropertyType::Int8,
+        24 => {
+            let key = inner(r)?;
+            PropertyType::Map(key, inner(r)?)
+        }
+        25 => PropertyType::Set(inner(r)?),
+        26 => {
+            let underlying = inner(r)?;
+            PropertyType::Enum(underlying, name(r)?)
+        }
+        27 => PropertyType::FieldPath,
+        28 => PropertyType::Optional(inner(r)?),
+        29 => PropertyType::Utf8Str,
+        30 => PropertyType::AnsiStr,
+        other => PropertyType::Unknown(other),
+    })
+}
+
+/// Decompress the payload of a `.usmap`
+fn decompress(method: u8, payload: Vec<u8>, decompressed_size: usize) -> Result<Vec<u8>> {
+    let failed = |e: &dyn std::fmt::Display| UeToolError::invalid_format(format!("Failed to decompress .usmap: {}", e));
+    if method != 0 && decompressed_size > MAX_DECOMPRESSED_SIZE {
+        return Err(UeToolError::invalid_format(format!(
+            ".usmap claims {} decompressed bytes, more than the {} allowed",
+            decompressed_size, MAX_DECOMPRESSED_SIZE
+        )));
+    }
+    match method {
+        0 => Ok(payload),
+        1 => {
+            let oodle = oodle_loader::oodle().map_err(|e| UeToolError::CompressionError(format!("Failed to load Oodle: {}", e)))?;
+            let mut out = vec![0u8; decompressed_size];
+            let written = oodle.decompress(&payload, &mut out);
+            if written <= 0 && decompressed_size > 0 {
+                return Err(failed(&"Oodle reported corrupt data"));
+            }
+            out.truncate(written.max(0) as usize);
+            Ok(out)
+        }
+        2 => {
+            let mut out = Vec::with_capacity(decompressed_size);
+            brotli_decompressor::Decompressor::new(payload.as_slice(), 4096)
+                .read_to_end(&mut out)
+                .map_err(|e| failed(&e))?;
+            Ok(out)
+        }
+        3 => zstd::bulk::decompress(&payload, decompressed_size).map_err(|e| failed(&e)),
+        other => Err(UeToolError::invalid_format(format!("Unknown .usmap compression method {}", other))),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Build a version 3 `.usmap` with one enum and the given structs
+    pub(crate) fn usmap(names: &[&str], structs: &[u8], struct_count: u32, compress: bool) -> Vec<u8> {
+        let payload = payload(names, structs, struct_count);
+        if compress {
+            with_payload(3, &zstd::bulk::compress(&payload, 3).unwrap(), payload.len())
+        } else {
+            with_payload(0, &payload, payload.len())
+        }
+    }
+
+    fn payload(names: &[&str], structs: &[u8], struct_count: u32) -> Vec<u8> {
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&(names.len() as u32).to_le_bytes());
+        for name in names {
+            payload.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            payload.extend_from_slice(name.as_bytes());
+        }
+        // enum names[0] with enumerators names[1], names[2]
+        payload.extend_from_slice(&1u32.to_le_bytes());
+        payload.extend_from_slice(&0u32.to_le_bytes());
+        payload.extend_from_slice(&2u16.to_le_bytes());
+        payload.extend_from_slice(&1u32.to_le_bytes());
+        payload.extend_from_slice(&2u32.to_le_bytes());
+        payload.extend_from_slice(&struct_count.to_le_bytes());
+        payload.extend_from_slice(structs);
+        payload
+    }
+
+    fn with_payload(method: u8, stored: &[u8], size: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&USMAP_MAGIC.to_le_bytes());
+        out.push(VER_LARGE_ENUMS);
+        out.extend_from_slice(&0u32.to_le_bytes()); // no versioning info
+        out.push(method);
+        out.extend_from_slice(&(stored.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(size as u32).to_le_bytes());
+        out.extend_from_slice(stored);
+        out
+    }
+
+    #[test]
+    fn test_parse_usmap() {
+        let names = ["EColor", "Red", "Green", "Paint", "Color", "Tags", "Base"];
+        let mut structs = Vec::new();
+        // Paint : Base, 2 schema slots: Color (enum EColor as byte), Tags (array of names)
+        for v in [3u32, 6] {
+            structs.extend_from_slice(&v.to_le_bytes());
+        }
+        structs.extend_from_slice(&2u16.to_le_bytes());
+        structs.extend_from_slice(&2u16.to_le_bytes());
+        structs.extend_from_slice(&0u16.to_le_bytes());
+        structs.push(1);
+        structs.extend_from_slice(&4u32.to_le_bytes());
+        structs.extend_from_slice(&[26, 0]);
+        structs.extend_from_slice(&0u32.to_le_bytes());
+        structs.extend_from_slice(&1u16.to_le_bytes());
+        structs.push(1);
+        structs.extend_from_slice(&5u32.to_le_bytes());
+        structs.extend_from_slice(&[8, 5]);
+
+        for compress in [false, true] {
+            let mappings = Mappings::parse(&usmap(&names, &structs, 1, compress)).unwrap();
+            let paint = mappings.get_struct("Paint").unwrap();
+            assert_eq!(paint.super_struct.as_deref(), Some("Base"));
+            assert_eq!(paint.properties[0].kind, PropertyType::Enum(Box::new(PropertyType::Byte), "EColor".to_string()));
+            assert_eq!(paint.properties[1].kind, PropertyType::Array(Box::new(PropertyType::Name)));
+            assert_eq!(mappings.get_enum("EColor").unwrap().name_of(1), Some("Green"));
+        }
+    }
+
+    /// The names-only payload of an empty mappings file
+    fn empty_payload() -> Vec<u8> {
+        payload(&["EUnused", "A", "B"], &[], 0)
+    }
+
+    #[test]
+    fn test_parse_brotli() {
+        // One uncompressed meta-block (window bits 0, MNIBBLES 4, MLEN - 1,
+        // ISUNCOMPRESSED) followed by an empty last meta-block
+        let payload = empty_payload();
+        let header = (((payload.len() - 1) as u32) << 4) | (1 << 20);
+        let mut stored = header.to_le_bytes()[..3].to_vec();
+        stored.extend_from_slice(&payload);
+        stored.push(0b11);
+
+        let mappings = Mappings::parse(&with_payload(2, &stored, payload.len())).unwrap();
+        assert_eq!(mappings.get_enum("EUnused").unwrap().name_of(1), Some("B"));
+    }
+
+    #[test]
+    #[ignore = "loads the Oodle library"]
+    fn test_parse_oodle() {
+        // A stored (uncompressed) Kraken block
+        let payload = empty_payload();
+        let mut stored = vec![0xCC, 0x06];
+        stored.extend_from_slice(&payload);
+
+        let mappings = Mappings::parse(&with_payload(1, &stored, payload.len())).unwrap();
+        assert_eq!(mappings.get_enum("EUnused").unwrap().name_of(1), Some("B"));
+    }
+
+    #[test]
+    fn test_rejects_bad_magic() {
+        assert!(Mappings::parse(&[0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_oversized_payload() {
+        for method in [1, 2, 3] {
+            assert!(Mappings::parse(&with_payload(method, &[0; 4], u32::MAX as usize)).is_err());
+        }
+    }
+}
@@ -5,15 +5,15 @@
 
 use std::path::Path;
 use std::sync::Arc;
-use std::io::{Read, BufReader, Write};
-use std::fs::File;
 
 use crate::error::{Result, UeToolError};
-use crate::types::{AssetPath, UtocListOptions, FileEntry, CompressionMethod, ProgressInfo, ProgressCallback};
+use crate::types::{AssetPath, UtocListOptions, FileEntry, CompressionMethod};
+use crate::toc_index::{TocChunk, TocIndex};
+use crate::signature::signature_path;
+use crate::cancel::{self, CancellationToken, Interrupted, PartialResult};
+use crate::progress::{Phase, ProgressCallback, ProgressSink};
 
 use serde::{Deserialize, Serialize};
-use fs_err as fs;
-use indicatif::{ProgressBar, ProgressStyle};
 use std::collections::HashMap;
 
 /// UTOC file chunk information
@@ -38,103 +38,84 @@ pub struct UtocMetadata {
     pub chunk_count: u32,
     pub compression_methods: Vec<String>,
     pub container_flags: u32,
+    /// Whether the container carries embedded signatures or ships a `.sig`
+    #[serde(default)]
+    pub signed: bool,
 }
 
 /// Main struct for listing .utoc file contents
 pub struct UtocLister {
-    progress_callback: Option<ProgressCallback>,
+    progress: ProgressSink,
+    cancellation: Option<CancellationToken>,
 }
 
 impl UtocLister {
     /// Create a new .utoc lister
     pub fn new() -> Self {
         Self {
-            progress_callback: None,
+            progress: ProgressSink::disabled(),
+            cancellation: None,
         }
     }
 
     /// Set progress callback for long operations
     pub fn with_progress_callback(mut self, callback: ProgressCallback) -> Self {
-        self.progress_callback = Some(callback);
+        self.progress = ProgressSink::new(callback);
+        self
+    }
+
+    /// Report progress to an existing sink, shared with other components
+    pub fn with_progress_sink(mut self, sink: ProgressSink) -> Self {
+        self.progress = sink;
+        self
+    }
+
+    /// Set a cancellation token that is checked between chunks
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
         self
     }
 
     /// List contents of a .utoc file
+    ///
+    /// If the listing is cancelled part way through, the assets found so far
+    /// are returned inside the [`Interrupted`] error.
     pub fn list<P: AsRef<Path>>(
         &mut self,
         utoc_path: P,
         options: &UtocListOptions,
-    ) -> Result<Vec<AssetPath>> {
+    ) -> PartialResult<Vec<AssetPath>> {
         let utoc_path = utoc_path.as_ref();
 
         if !utoc_path.exists() {
-            return Err(UeToolError::file_not_found(utoc_path));
+            return Err(UeToolError::file_not_found(utoc_path).into());
         }
 
-        self.report_progress(ProgressInfo {
-            percentage: 0,
-            message: "Opening UTOC file".to_string(),
-            processed: 0,
-            total: 1,
-        });
+        let _span = tracing::info_span!("list_utoc", container = %utoc_path.display()).entered();
+        cancel::check(self.cancellation.as_ref())?;
+        let opening = self.progress.phase(Phase::OpenContainer, 0, 0);
 
         // Use retoc library for proper UTOC parsing
-        let config = retoc::Config {
-            aes_keys: if let Some(ref aes_key) = options.aes_key {
-                let mut keys = HashMap::new();
-                keys.insert(retoc::FGuid::default(), aes_key.parse().map_err(|e| UeToolError::InvalidAesKey(format!("Invalid AES key: {}", e)))?);
-                keys
-            } else {
-                HashMap::new()
-            },
-            container_header_version_override: None,
-        };
-
-        let config = Arc::new(config);
-
-        self.report_progress(ProgressInfo {
-            percentage: 10,
-            message: "Parsing UTOC structure".to_string(),
-            processed: 0,
-            total: 1,
-        });
+        let config = Self::retoc_config(options)?;
 
         // Open UTOC file using retoc
         let iostore = retoc::open_iostore(utoc_path, config.clone())
             .map_err(|e| UeToolError::UtocError(format!("Failed to open UTOC file: {}", e)))?;
 
-        self.report_progress(ProgressInfo {
-            percentage: 30,
-            message: "Extracting file listing".to_string(),
-            processed: 0,
-            total: 1,
-        });
-
         let chunks: Vec<_> = iostore.chunks().collect();
+        opening.finish();
 
-        self.report_progress(ProgressInfo {
-            percentage: 60,
-            message: format!("Found {} chunks", chunks.len()),
-            processed: 0,
-            total: chunks.len() as u64,
-        });
-
+        let mut progress = self.progress.phase(Phase::List, chunks.len() as u64, 0);
         let mut asset_paths = Vec::new();
-        let mut processed = 0;
 
         for chunk in &chunks {
-            processed += 1;
-            let progress_percentage = 60 + ((processed as f64 / chunks.len() as f64) * 35.0) as u8;
-            
-            self.report_progress(ProgressInfo {
-                percentage: progress_percentage,
-                message: format!("Processing chunk {}", processed),
-                processed: processed as u64,
-                total: chunks.len() as u64,
-            });
+            if let Err(e) = cancel::check(self.cancellation.as_ref()) {
+                asset_paths.sort();
+                asset_paths.dedup();
+                return Err(Interrupted::new(asset_paths, e));
+            }
 
             // Get chunk information
-            let chunk_id = chunk.id();
             let chunk_path = chunk.path();
             
             // Only include chunks that have file paths and are asset files
@@ -143,25 +124,14 @@ impl UtocLister {
                     asset_paths.push(AssetPath::new(path.to_string()));
                 }
             }
+            progress.entry_finished(chunk_path.as_deref().unwrap_or_default(), 0);
         }
 
         asset_paths.sort();
         asset_paths.dedup();
+        progress.finish();
 
-        self.report_progress(ProgressInfo {
-            percentage: 100,
-            message: format!("Completed - found {} assets", asset_paths.len()),
-            processed: processed as u64,
-            total: processed as u64,
-        });
-
-        if !options.json_format && !options.aes_key.is_some() {
-            // Simple console output for non-JSON mode
-            println!("Found {} assets in {}", asset_paths.len(), utoc_path.display());
-            for asset in &asset_paths {
-                println!("  {}", asset.as_str());
-            }
-        }
+        tracing::debug!(chunks = chunks.len(), assets = asset_paths.len(), "Listed UTOC file");
 
         Ok(asset_paths)
     }
@@ -179,51 +149,38 @@ impl UtocLister {
         }
 
         // Use retoc library for proper UTOC parsing
-        let config = retoc::Config {
-            aes_keys: if let Some(ref aes_key) = options.aes_key {
-                let mut keys = HashMap::new();
-                keys.insert(retoc::FGuid::default(), aes_key.parse().map_err(|e| UeToolError::InvalidAesKey(format!("Invalid AES key: {}", e)))?);
-                keys
-            } else {
-                HashMap::new()
-            },
-            container_header_version_override: None,
-        };
-
-        let config = Arc::new(config);
+        let config = Self::retoc_config(options)?;
 
         // Open UTOC file using retoc
         let iostore = retoc::open_iostore(utoc_path, config)
             .map_err(|e| UeToolError::UtocError(format!("Failed to open UTOC file: {}", e)))?;
 
         let chunks: Vec<_> = iostore.chunks().collect();
+
+        // The TOC tables carry sizes, flags and compression methods that retoc does not expose
+        let toc = TocIndex::open(utoc_path, options.aes_key.as_deref())?;
+        let toc_chunks = Self::chunks_by_relative_path(&toc);
+
         let metadata = UtocMetadata {
             version: iostore.container_file_version().unwrap_or_default() as u32,
             container_id: format!("{:?}", iostore.container_name()),
             file_count: chunks.len() as u32,
             chunk_count: chunks.len() as u32,
-            compression_methods: vec![], // TODO: extract compression methods
-            container_flags: 0, // TODO: extract container flags
+            compression_methods: toc.compression_methods.clone(),
+            container_flags: toc.container_flags as u32,
+            signed: toc.is_signed() || signature_path(utoc_path).exists(),
         };
 
         let mut file_entries = Vec::new();
         let mut asset_paths = Vec::new();
 
         for chunk in chunks {
-            let chunk_id = chunk.id();
             let chunk_path = chunk.path();
-            let chunk_type = chunk.id().get_chunk_type();
-            
+
             if let Some(ref path) = chunk_path {
                 if self.is_asset_file(path) {
                     asset_paths.push(AssetPath::new(path.to_string()));
-                    
-                    file_entries.push(FileEntry {
-                        path: AssetPath::new(path.to_string()),
-                        size: 0, // TODO: get actual size
-                        is_compressed: false, // TODO: determine compression
-                        compression: None, // TODO: extract compression method
-                    });
+                    file_entries.push(Self::file_entry(path, &toc.mount_point, &toc_chunks));
                 }
             }
         }
@@ -238,6 +195,82 @@ impl UtocLister {
         })
     }
 
+    /// List every chunk that has a path, with sizes, including bulk data companions
+    ///
+    /// Unlike [`UtocLister::list`], this does not filter by asset extension, so
+    /// `.ubulk` and `.uptnl` chunks are included for package grouping.
+    pub fn list_entries<P: AsRef<Path>>(
+        &mut self,
+        utoc_path: P,
+        options: &UtocListOptions,
+    ) -> Result<Vec<FileEntry>> {
+        let utoc_path = utoc_path.as_ref();
+
+        if !utoc_path.exists() {
+            return Err(UeToolError::file_not_found(utoc_path));
+        }
+
+        let config = Self::retoc_config(options)?;
+        let iostore = retoc::open_iostore(utoc_path, config)
+            .map_err(|e| UeToolError::UtocError(format!("Failed to open UTOC file: {}", e)))?;
+        let toc = TocIndex::open(utoc_path, options.aes_key.as_deref())?;
+        let toc_chunks = Self::chunks_by_relative_path(&toc);
+
+        let mut entries = Vec::new();
+        for chunk in iostore.chunks() {
+            cancel::check(self.cancellation.as_ref())?;
+            if let Some(path) = chunk.path() {
+                entries.push(Self::file_entry(&path, &toc.mount_point, &toc_chunks));
+            }
+        }
+
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        entries.dedup_by(|a, b| a.path == b.path);
+        Ok(entries)
+    }
+
+    /// Build the retoc configuration for the given options
+    pub(crate) fn retoc_config(options: &UtocListOptions) -> Result<Arc<retoc::Config>> {
+        let config = retoc::Config {
+            aes_keys: if let Some(ref aes_key) = options.aes_key {
+                let mut keys = HashMap::new();
+                keys.insert(retoc::FGuid::default(), aes_key.parse().map_err(|e| UeToolError::InvalidAesKey(format!("Invalid AES key: {}", e)))?);
+                keys
+            } else {
+                HashMap::new()
+            },
+            container_header_version_override: None,
+        };
+
+        Ok(Arc::new(config))
+    }
+
+    /// Index TOC chunks by their path relative to the container mount point
+    fn chunks_by_relative_path(toc: &TocIndex) -> HashMap<&str, &TocChunk> {
+        toc.chunks
+            .iter()
+            .filter_map(|c| {
+                let path = c.path.as_deref()?;
+                Some((path.strip_prefix(toc.mount_point.as_str()).unwrap_or(path), c))
+            })
+            .collect()
+    }
+
+    /// Build a file entry for a chunk path, taking sizes from the TOC when available
+    ///
+    /// retoc may report paths with or without the mount point, so it is
+    /// stripped the same way as in [`UtocLister::chunks_by_relative_path`].
+    fn file_entry(path: &str, mount_point: &str, chunks: &HashMap<&str, &TocChunk>) -> FileEntry {
+        let chunk = chunks.get(path.strip_prefix(mount_point).unwrap_or(path));
+
+        FileEntry {
+            path: AssetPath::new(path.to_string()),
+            size: chunk.map(|c| c.size).unwrap_or(0),
+            is_compressed: chunk.is_some_and(|c| c.compression.is_some()),
+            compression: chunk.and_then(|c| c.compression.as_deref()).and_then(|m| m.parse().ok()),
+        }
+    }
+
     /// Check if a path represents an asset file
     fn is_asset_file(&self, path: &str) -> bool {
         let ext = std::path::Path::new(path)
@@ -248,16 +281,10 @@ impl UtocLister {
 
         matches!(ext.as_str(),
             "uasset" | "umap" | "bnk" | "json" | "wem" | "fbx" | "obj" | "glb" | "gltf" |
-            "ini" | "wav" | "mp3" | "ogg" | "uplugin" | "usf"
+            "ini" | "wav" | "mp3" | "ogg" | "uplugin" | "usf" | "locres" | "locmeta"
         )
     }
 
-    /// Report progress to callback if set
-    fn report_progress(&mut self, progress: ProgressInfo) {
-        if let Some(ref mut callback) = self.progress_callback {
-            callback(progress);
-        }
-    }
 }
 
 /// Complete UTOC file information for JSON output
@@ -281,13 +308,31 @@ mod tests {
     #[test]
     fn test_utoc_lister_creation() {
         let lister = UtocLister::new();
-        assert!(lister.progress_callback.is_none());
+        assert!(!lister.progress.is_enabled());
+    }
+
+    #[test]
+    fn test_file_entry_strips_mount_point() {
+        let chunk = |path: &str, size| TocChunk {
+            id: [0; 12],
+            chunk_type: 1,
+            path: Some(path.to_string()),
+            offset: 0,
+            size,
+            compressed_size: size,
+            compression: None,
+        };
+        let (nested, flat) = (chunk("Game/Sub/../X.uasset", 5), chunk("X.uasset", 9));
+        let chunks: HashMap<&str, &TocChunk> = [("Game/Sub/../X.uasset", &nested), ("X.uasset", &flat)].into();
+        assert_eq!(UtocLister::file_entry("../../../Game/Sub/../X.uasset", "../../../", &chunks).size, 5);
+        assert_eq!(UtocLister::file_entry("Game/Sub/../X.uasset", "../../../", &chunks).size, 5);
+        assert_eq!(UtocLister::file_entry("Marvel/Content/X.uasset", "Marvel/Content/", &chunks).size, 9);
     }
 
     #[test]
     fn test_progress_callback() {
-        let callback = Box::new(|_: ProgressInfo| {});
+        let callback = Box::new(|_: crate::progress::ProgressEvent| {});
         let lister = UtocLister::new().with_progress_callback(callback);
-        assert!(lister.progress_callback.is_some());
+        assert!(lister.progress.is_enabled());
     }
 }
\ No newline at end of file
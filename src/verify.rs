@@ -0,0 +1,373 @@
+//! Integrity verification of pak files
+//!
+//! Every pak records a SHA-1 of its index in the footer and a SHA-1 of each
+//! entry's stored data in the entry header written before the data. Like the
+//! engine's `-checkpak`, [`Unpacker::verify_pak`] hashes the stored bytes
+//! and compares them with the recorded hashes, checks that every compression
+//! block lies inside the file, and additionally decompresses each entry.
+
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+
+use crate::cancel;
+use crate::error::{Result, UeToolError};
+use crate::pak_index::{align16, decrypt_in_place, parse_aes_key, read_entry, read_region, IndexRegion, PakEntry, PakIndex, PakVersion};
+use crate::pak_unpack::open_pak;
+use crate::progress::{Phase, PhaseProgress};
+use crate::types::VerifyOptions;
+use crate::Unpacker;
+
+/// What is wrong with an index region or entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VerifyIssueKind {
+    /// An index region does not match the hash that references it
+    IndexHash,
+    /// Entry data or a compression block lies outside the file
+    OutOfBounds,
+    /// The header before the entry data disagrees with the index
+    HeaderMismatch,
+    /// The stored data does not match the recorded SHA-1
+    HashMismatch,
+    /// The entry could not be read or decompressed
+    Decompression,
+    /// The decompressed size differs from the recorded size
+    SizeMismatch,
+}
+
+/// One problem found while verifying
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyIssue {
+    pub kind: VerifyIssueKind,
+    pub detail: String,
+}
+
+impl VerifyIssue {
+    fn new(kind: VerifyIssueKind, detail: impl Into<String>) -> Self {
+        Self { kind, detail: detail.into() }
+    }
+}
+
+/// An entry with at least one problem
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorruptEntry {
+    pub path: String,
+    pub issues: Vec<VerifyIssue>,
+}
+
+/// Result of verifying a pak
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyReport {
+    pub path: PathBuf,
+    pub entries_checked: usize,
+    /// Problems with the main index, path hash index or directory index
+    pub index_issues: Vec<VerifyIssue>,
+    /// Corrupt entries sorted by path
+    pub corrupt: Vec<CorruptEntry>,
+}
+
+impl VerifyReport {
+    /// Whether no problems were found
+    pub fn is_ok(&self) -> bool {
+        self.index_issues.is_empty() && self.corrupt.is_empty()
+    }
+}
+
+impl Unpacker {
+    /// Verify the index and every entry of a pak
+    ///
+    /// Problems are reported in the returned [`VerifyReport`]; an error is
+    /// only returned when the pak cannot be opened at all or the operation is
+    /// cancelled. Entries are checked in parallel when
+    /// [`VerifyOptions::parallel`] is set and the `parallel` feature is enabled.
+    pub fn verify_pak<P: AsRef<Path>>(&mut self, pak_path: P, options: &VerifyOptions) -> Result<VerifyReport> {
+        let pak_path = pak_path.as_ref();
+        let _span = tracing::info_span!("verify_pak", pak = %pak_path.display()).entered();
+
+        let aes_key = options.aes_key.as_deref();
+        let key = aes_key.map(parse_aes_key).transpose()?;
+        let index = PakIndex::open(pak_path, aes_key)?;
+        let index_issues = verify_index(pak_path, &index, key.as_ref())?;
+        // repak's index is parsed once and shared; each worker only opens the file
+        let (pak, _) = open_pak(pak_path, aes_key)?;
+
+        let token = self.cancellation.clone();
+        let progress = Mutex::new(self.progress.phase(Phase::Verify, index.entries.len() as u64, 0));
+        let open = || EntryChecker::open(pak_path, &index, &pak, key.clone());
+        let check = |checker: &mut io::Result<EntryChecker>, entry: &PakEntry| -> Result<Option<CorruptEntry>> {
+            cancel::check(token.as_ref())?;
+            let checker = checker
+                .as_mut()
+                .map_err(|e| UeToolError::IoError(format!("Failed to open PAK file: {}", e)))?;
+            let issues = checker.check(entry, &progress);
+            Ok((!issues.is_empty()).then(|| CorruptEntry { path: entry.path.clone(), issues }))
+        };
+
+        #[cfg(feature = "parallel")]
+        let results: Vec<_> = if options.parallel {
+            use rayon::prelude::*;
+            index.entries.par_iter().map_init(open, check).collect()
+        } else {
+            let mut checker = open();
+            index.entries.iter().map(|entry| check(&mut checker, entry)).collect()
+        };
+        #[cfg(not(feature = "parallel"))]
+        let results: Vec<_> = {
+            let mut checker = open();
+            index.entries.iter().map(|entry| check(&mut checker, entry)).collect()
+        };
+
+        if let Ok(progress) = progress.into_inner() {
+            progress.finish();
+        }
+
+        let mut corrupt = results.into_iter().filter_map(Result::transpose).collect::<Result<Vec<_>>>()?;
+        corrupt.sort_by(|a, b| a.path.cmp(&b.path));
+        tracing::debug!(entries = index.entries.len(), corrupt = corrupt.len(), "Verified pak");
+
+        Ok(VerifyReport {
+            path: pak_path.to_path_buf(),
+            entries_checked: index.entries.len(),
+            index_issues,
+            corrupt,
+        })
+    }
+}
+
+/// Check the main index against the footer hash, and the v10+ secondary indices against the main index
+fn verify_index(pak_path: &Path, index: &PakIndex, key: Option<&aes::Aes256>) -> Result<Vec<VerifyIssue>> {
+    let file = File::open(pak_path).map_err(|e| UeToolError::IoError(format!("Failed to open PAK file: {}", e)))?;
+    let mut reader = BufReader::new(file);
+
+    let regions = [
+        ("index", Some(&index.index)),
+        ("path hash index", index.path_hash_index.as_ref()),
+        ("full directory index", index.full_directory_index.as_ref()),
+    ];
+
+    let mut issues = Vec::new();
+    for (name, region) in regions {
+        let Some(IndexRegion { offset, size, hash }) = region else {
+            continue;
+        };
+        if *hash == [0; 20] {
+            continue;
+        }
+        let data = read_region(&mut reader, *offset, *size, index.encrypted_index, key)?;
+        let actual: [u8; 20] = Sha1::digest(&data).into();
+        if actual != *hash {
+            issues.push(VerifyIssue::new(
+                VerifyIssueKind::IndexHash,
+                format!("{} hash is {}, expected {}", name, hex::encode(actual), hex::encode(hash)),
+            ));
+        }
+    }
+    Ok(issues)
+}
+
+/// Per-thread file handle for checking entries, with the shared indexes
+struct EntryChecker<'a> {
+    file: BufReader<File>,
+    file_size: u64,
+    pak: &'a repak::PakReader,
+    version: PakVersion,
+    compression_methods: &'a [String],
+    key: Option<aes::Aes256>,
+}
+
+impl<'a> EntryChecker<'a> {
+    fn open(pak_path: &Path, index: &'a PakIndex, pak: &'a repak::PakReader, key: Option<aes::Aes256>) -> io::Result<Self> {
+        let file = File::open(pak_path)?;
+        let file_size = file.metadata()?.len();
+        Ok(Self {
+            file: BufReader::new(file),
+            file_size,
+            pak,
+            version: index.version,
+            compression_methods: &index.compression_methods,
+            key,
+        })
+    }
+
+    fn check(&mut self, entry: &PakEntry, progress: &Mutex<PhaseProgress>) -> Vec<VerifyIssue> {
+        if let Ok(mut progress) = progress.lock() {
+            progress.entry_started(&entry.path, entry.uncompressed_size);
+        }
+
+        let mut issues = bounds_issues(entry, self.version, self.file_size);
+        if issues.is_empty() {
+            if let Err(e) = self.check_hash(entry, &mut issues) {
+                issues.push(VerifyIssue::new(VerifyIssueKind::HashMismatch, format!("Failed to read entry data: {}", e)));
+            }
+
+            // Only read entries whose data is known to be inside the file
+            match self.pak.get(&entry.path, &mut self.file) {
+                Ok(data) if data.len() as u64 != entry.uncompressed_size => issues.push(VerifyIssue::new(
+                    VerifyIssueKind::SizeMismatch,
+                    format!("decompressed to {} bytes, expected {}", data.len(), entry.uncompressed_size),
+                )),
+                Ok(_) => {}
+                Err(e) => issues.push(VerifyIssue::new(VerifyIssueKind::Decompression, e.to_string())),
+            }
+        }
+
+        if let Ok(mut progress) = progress.lock() {
+            progress.entry_finished(&entry.path, entry.uncompressed_size);
+        }
+        issues
+    }
+
+    /// Compare the stored bytes with the hash in the entry header
+    ///
+    /// v10+ indices do not record entry hashes, so the header written before
+    /// the data is always the reference. Encrypted entries are hashed as
+    /// stored first, then decrypted, since packers differ in which they hash.
+    fn check_hash(&mut self, entry: &PakEntry, issues: &mut Vec<VerifyIssue>) -> Result<()> {
+        self.file.seek(SeekFrom::Start(entry.offset))?;
+        let header = read_entry(&mut self.file, self.version, self.compression_methods)?;
+        if header.compressed_size != entry.compressed_size || header.uncompressed_size != entry.uncompressed_size {
+            issues.push(VerifyIssue::new(
+                VerifyIssueKind::HeaderMismatch,
+                format!(
+                    "header records {}/{} bytes, index records {}/{}",
+                    header.compressed_size, header.uncompressed_size, entry.compressed_size, entry.uncompressed_size
+                ),
+            ));
+        }
+        if entry.hash != [0; 20] && entry.hash != header.hash {
+            issues.push(VerifyIssue::new(VerifyIssueKind::HeaderMismatch, "header and index record different hashes"));
+        }
+        if header.hash == [0; 20] {
+            return Ok(());
+        }
+
+        // Both were checked by bounds_issues before this runs
+        let (Some(data_start), Some(stored_size)) = (entry.data_offset(self.version), align16(entry.compressed_size)) else {
+            return Ok(());
+        };
+        self.file.seek(SeekFrom::Start(data_start))?;
+        let actual: [u8; 20] = if entry.encrypted {
+            let mut data = vec![0u8; stored_size as usize];
+            self.file.read_exact(&mut data)?;
+            let stored: [u8; 20] = Sha1::digest(&data[..entry.compressed_size as usize]).into();
+            match &self.key {
+                Some(key) if stored != header.hash => {
+                    decrypt_in_place(key, &mut data);
+                    Sha1::digest(&data[..entry.compressed_size as usize]).into()
+                }
+                _ => stored,
+            }
+        } else {
+            let mut hasher = Sha1::new();
+            io::copy(&mut (&mut self.file).take(entry.compressed_size), &mut hasher)?;
+            hasher.finalize().into()
+        };
+
+        if actual != header.hash {
+            issues.push(VerifyIssue::new(
+                VerifyIssueKind::HashMismatch,
+                format!("SHA-1 is {}, expected {}", hex::encode(actual), hex::encode(header.hash)),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The entry data and its compression blocks must all lie inside the file
+fn bounds_issues(entry: &PakEntry, version: PakVersion, file_size: u64) -> Vec<VerifyIssue> {
+    let stored_size = if entry.encrypted { align16(entry.compressed_size) } else { Some(entry.compressed_size) };
+    let data_start = entry.data_offset(version);
+    let data_end = data_start.zip(stored_size).and_then(|(start, size)| start.checked_add(size));
+    let (Some(data_start), Some(data_end)) = (data_start, data_end) else {
+        return vec![VerifyIssue::new(
+            VerifyIssueKind::OutOfBounds,
+            format!("data at offset {} with {} bytes overflows", entry.offset, entry.compressed_size),
+        )];
+    };
+
+    let mut issues = Vec::new();
+    if data_end > file_size {
+        issues.push(VerifyIssue::new(
+            VerifyIssueKind::OutOfBounds,
+            format!("data ends at {} past the end of the file ({} bytes)", data_end, file_size),
+        ));
+    }
+    for (i, &(start, end)) in entry.blocks.iter().enumerate() {
+        let end = if entry.encrypted {
+            align16(end.saturating_sub(start)).and_then(|size| start.checked_add(size)).unwrap_or(u64::MAX)
+        } else {
+            end
+        };
+        if start > end || start < data_start || end > file_size {
+            issues.push(VerifyIssue::new(
+                VerifyIssueKind::OutOfBounds,
+                format!("compression block {} spans {}..{} outside {}..{}", i, start, end, data_start, file_size),
+            ));
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(compressed_size: u64, blocks: Vec<(u64, u64)>) -> PakEntry {
+        PakEntry {
+            path: "A.uasset".to_string(),
+            offset: 0,
+            compressed_size,
+            uncompressed_size: compressed_size * 2,
+            compression: (!blocks.is_empty()).then(|| "Zlib".to_string()),
+            hash: [0; 20],
+            blocks,
+            encrypted: false,
+            compression_block_size: 0x10000,
+        }
+    }
+
+    #[test]
+    fn test_verify_written_pak() {
+        let dir = tempfile::tempdir().unwrap();
+        let pak_path = dir.path().join("Mod.pak");
+        let mut writer = repak::PakBuilder::new().writer(
+            io::BufWriter::new(File::create(&pak_path).unwrap()),
+            repak::Version::V8B,
+            "../../../".to_string(),
+            None,
+        );
+        for i in 0..8 {
+            writer.write_file(&format!("Game/{}.uasset", i), false, format!("asset {}", i).as_bytes()).unwrap();
+        }
+        writer.write_index().unwrap();
+
+        let report = Unpacker::new().verify_pak(&pak_path, &VerifyOptions::new()).unwrap();
+        assert_eq!(report.entries_checked, 8);
+        assert!(report.is_ok(), "{:?}", report);
+    }
+
+    #[test]
+    fn test_bounds_issues() {
+        let ok = entry(100, vec![]);
+        let start = ok.data_offset(PakVersion::V11).unwrap();
+        assert!(bounds_issues(&ok, PakVersion::V11, start + 100).is_empty());
+        assert_eq!(bounds_issues(&ok, PakVersion::V11, start + 99).len(), 1);
+
+        let mut blocks = entry(100, vec![(0, 0); 2]);
+        let start = blocks.data_offset(PakVersion::V11).unwrap();
+        blocks.blocks = vec![(start, start + 60), (start + 60, start + 200)];
+        let issues = bounds_issues(&blocks, PakVersion::V11, start + 100);
+        assert!(issues.iter().all(|i| i.kind == VerifyIssueKind::OutOfBounds));
+        assert_eq!(issues.len(), 1);
+
+        let mut overflowing = entry(100, vec![]);
+        overflowing.offset = u64::MAX - 10;
+        let issues = bounds_issues(&overflowing, PakVersion::V11, 1000);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, VerifyIssueKind::OutOfBounds);
+    }
+}
@@ -0,0 +1,499 @@
+//! Load-order-aware view of a game's mounted containers
+//!
+//! The game mounts every `.pak` and `.utoc` under `Content/Paks`, `~mods`
+//! included, and when several containers hold the same path only one of them
+//! is loaded. [`VirtualFileSystem`] applies the engine's priority rules to
+//! answer which container wins for each path, and reads the winning bytes:
+//!
+//! 1. Packages in IoStore containers are loaded before legacy paks.
+//! 2. Higher pak order wins. Paks start at order 3, or 4 for the base game's
+//!    `<Project>-*.pak`; a `_P` patch suffix adds 100 per chunk version
+//!    (`Mod_P.pak` is version 1, `Mod_2_P.pak` version 3).
+//! 3. Between containers of equal order, the later file name wins, since the
+//!    engine mounts in reverse alphabetical order.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use crate::error::{Result, UeToolError};
+use crate::package::ContainerKind;
+use crate::pak_unpack::open_pak;
+use crate::types::UtocListOptions;
+use crate::utoc_list::UtocLister;
+
+/// Pak order of content paks under `Content/Paks`
+const CONTENT_PAK_ORDER: i32 = 3;
+/// Pak order of the base game's `<Project>-<Platform>.pak`
+const PROJECT_PAK_ORDER: i32 = 4;
+
+/// A game installation, located by its `Content/Paks` directory
+#[derive(Debug, Clone)]
+pub struct GameInstall {
+    paks_dir: PathBuf,
+    project_name: Option<String>,
+}
+
+impl GameInstall {
+    /// Locate the Paks directory from a game root, a project directory or the Paks directory itself
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.is_dir() {
+            return Err(UeToolError::file_not_found(path));
+        }
+
+        let paks_dir = Self::find_paks_dir(path)
+            .ok_or_else(|| UeToolError::invalid_argument(format!("No Content/Paks directory under {}", path.display())))?;
+
+        // <Root>/<Project>/Content/Paks
+        let project_name = paks_dir
+            .parent()
+            .filter(|content| content.file_name().is_some_and(|n| n.eq_ignore_ascii_case("Content")))
+            .and_then(Path::parent)
+            .and_then(Path::file_name)
+            .map(|n| n.to_string_lossy().into_owned());
+
+        Ok(Self { paks_dir, project_name })
+    }
+
+    /// Treat `paks_dir` itself as the Paks directory, e.g. a bare mods folder
+    pub fn from_paks_dir<P: AsRef<Path>>(paks_dir: P) -> Result<Self> {
+        let paks_dir = paks_dir.as_ref();
+        if !paks_dir.is_dir() {
+            return Err(UeToolError::file_not_found(paks_dir));
+        }
+        Ok(Self { paks_dir: paks_dir.to_path_buf(), project_name: None })
+    }
+
+    /// Override the project name used to recognise the base game paks
+    pub fn with_project_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.project_name = Some(name.into());
+        self
+    }
+
+    /// The `Content/Paks` directory
+    pub fn paks_dir(&self) -> &Path {
+        &self.paks_dir
+    }
+
+    /// The project name, e.g. `Marvel`, if it could be determined
+    pub fn project_name(&self) -> Option<&str> {
+        self.project_name.as_deref()
+    }
+
+    /// Open every container under the Paks directory and resolve their files
+    ///
+    /// Containers that cannot be read are recorded in
+    /// [`VirtualFileSystem::errors`] and left out of the view.
+    pub fn mount(&self, aes_key: Option<&str>) -> Result<VirtualFileSystem> {
+        let _span = tracing::info_span!("mount", paks_dir = %self.paks_dir.display()).entered();
+        let mut vfs = VirtualFileSystem {
+            containers: Vec::new(),
+            entries: HashMap::new(),
+            errors: Vec::new(),
+            project_name: self.project_name.clone(),
+            aes_key: aes_key.map(str::to_string),
+        };
+
+        let mut files: Vec<PathBuf> = WalkDir::new(&self.paks_dir)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| entry.into_path())
+            .collect();
+        files.sort();
+
+        for path in files {
+            let Some(kind) = ContainerKind::from_path(&path) else {
+                continue;
+            };
+            if let Err(e) = vfs.mount_container(&path, kind, self.pak_order(&path)) {
+                tracing::warn!(container = %path.display(), error = %e, "Failed to mount container");
+                vfs.errors.push((path, e.to_string()));
+            }
+        }
+
+        for candidates in vfs.entries.values_mut() {
+            let containers = &vfs.containers;
+            candidates.sort_by(|a, b| containers[b.container].priority_cmp(&containers[a.container]));
+        }
+
+        tracing::debug!(containers = vfs.containers.len(), files = vfs.entries.len(), "Mounted containers");
+        Ok(vfs)
+    }
+
+    /// The pak order the engine would give a container at this path
+    pub fn pak_order(&self, path: &Path) -> i32 {
+        let file_name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        let is_project_pak = path.parent() == Some(self.paks_dir.as_path())
+            && self
+                .project_name
+                .as_deref()
+                .is_some_and(|project| file_name.starts_with(&format!("{}-", project)));
+
+        let base = if is_project_pak { PROJECT_PAK_ORDER } else { CONTENT_PAK_ORDER };
+        base + 100 * patch_chunk_version(&file_name) as i32
+    }
+
+    fn find_paks_dir(path: &Path) -> Option<PathBuf> {
+        if path.file_name().is_some_and(|n| n.eq_ignore_ascii_case("Paks")) {
+            return Some(path.to_path_buf());
+        }
+
+        // The root itself, a project directory or a game root containing <Project>/Content/Paks
+        WalkDir::new(path)
+            .max_depth(3)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_dir())
+            .map(|entry| entry.into_path())
+            .find(|dir| {
+                dir.file_name().is_some_and(|n| n.eq_ignore_ascii_case("Paks"))
+                    && dir
+                        .parent()
+                        .and_then(Path::file_name)
+                        .is_some_and(|n| n.eq_ignore_ascii_case("Content"))
+            })
+    }
+}
+
+/// Chunk version from a `_P` patch suffix, 0 for paks without one
+///
+/// Mirrors the engine: `Mod_P.pak` is version 1, and `Mod_<N>_P.pak` with
+/// N >= 1 is version N + 1 so the first numbered patch still beats the
+/// unnumbered one.
+fn patch_chunk_version(file_name: &str) -> u32 {
+    let stem = file_name.rsplit_once('.').map_or(file_name, |(stem, _)| stem);
+    let Some(base) = stem.strip_suffix("_P").or_else(|| stem.strip_suffix("_p")) else {
+        return 0;
+    };
+
+    base.rsplit_once('_')
+        .and_then(|(_, version)| version.parse::<u32>().ok())
+        .filter(|&version| version >= 1)
+        .map_or(1, |version| version + 1)
+}
+
+/// One mounted `.pak` or `.utoc`
+#[derive(Debug, Clone, Serialize)]
+pub struct MountedContainer {
+    pub path: PathBuf,
+    pub kind: ContainerKind,
+    /// Engine pak order; higher wins
+    pub order: i32,
+    pub mount_point: String,
+    /// Number of files the container provides, including shadowed ones
+    pub file_count: usize,
+}
+
+impl MountedContainer {
+    /// Whether this container beats `other` for a path both provide
+    fn priority_cmp(&self, other: &Self) -> Ordering {
+        let is_iostore = |c: &Self| c.kind == ContainerKind::IoStore;
+        is_iostore(self)
+            .cmp(&is_iostore(other))
+            .then(self.order.cmp(&other.order))
+            .then_with(|| {
+                let name = |c: &Self| c.path.file_name().map(|n| n.to_string_lossy().to_lowercase());
+                name(self).cmp(&name(other))
+            })
+    }
+}
+
+/// An open container that reads entries by the path stored in it
+pub(crate) enum ContainerReader {
+    Pak(repak::PakReader, BufReader<File>),
+    IoStore(Box<dyn retoc::IoStoreTrait>, HashMap<String, retoc::FIoChunkId>),
+}
+
+impl ContainerReader {
+    /// Open a `.pak` or `.utoc` container
+    pub(crate) fn open(path: &Path, kind: ContainerKind, aes_key: Option<&str>) -> Result<Self> {
+        match kind {
+            ContainerKind::Pak => {
+                let (pak, reader) = open_pak(path, aes_key)?;
+                Ok(ContainerReader::Pak(pak, reader))
+            }
+            ContainerKind::IoStore => {
+                if !path.with_extension("ucas").exists() {
+                    return Err(UeToolError::MissingFile(path.with_extension("ucas")));
+                }
+                let options = UtocListOptions { aes_key: aes_key.map(str::to_string), json_format: false };
+                let iostore = retoc::open_iostore(path, UtocLister::retoc_config(&options)?)
+                    .map_err(|e| UeToolError::UtocError(format!("Failed to open UTOC file: {}", e)))?;
+                let chunks = iostore.chunks().filter_map(|c| Some((c.path()?, c.id()))).collect();
+                Ok(ContainerReader::IoStore(iostore, chunks))
+            }
+        }
+    }
+
+    /// The pak mount point, empty for IoStore containers
+    pub(crate) fn mount_point(&self) -> &str {
+        match self {
+            ContainerReader::Pak(pak, _) => pak.mount_point(),
+            ContainerReader::IoStore(..) => "",
+        }
+    }
+
+    /// Every entry as (path with the mount point, path as stored)
+    pub(crate) fn entries(&self) -> Vec<(String, String)> {
+        match self {
+            ContainerReader::Pak(pak, _) => {
+                let mount_point = pak.mount_point();
+                pak.files().into_iter().map(|f| (format!("{}{}", mount_point, f), f)).collect()
+            }
+            ContainerReader::IoStore(_, chunks) => chunks.keys().map(|p| (p.clone(), p.clone())).collect(),
+        }
+    }
+
+    /// Read the first IoStore chunk of a type that has no path, such as the container header
+    pub(crate) fn read_chunk_of_type(&self, chunk_type: retoc::EIoChunkType) -> Result<Option<Vec<u8>>> {
+        let ContainerReader::IoStore(iostore, _) = self else {
+            return Ok(None);
+        };
+        let Some(id) = iostore.chunks().map(|c| c.id()).find(|id| id.get_chunk_type() == chunk_type) else {
+            return Ok(None);
+        };
+        iostore
+            .read(id)
+            .map(Some)
+            .map_err(|e| UeToolError::UtocError(format!("Failed to read {:?} chunk: {}", chunk_type, e)))
+    }
+
+    pub(crate) fn read(&mut self, raw: &str) -> Result<Vec<u8>> {
+        match self {
+            ContainerReader::Pak(pak, reader) => pak
+                .get(raw, reader)
+                .map_err(|e| UeToolError::PakError(format!("Failed to read {}: {}", raw, e))),
+            ContainerReader::IoStore(iostore, chunks) => {
+                let chunk = chunks
+                    .get(raw)
+                    .ok_or_else(|| UeToolError::UtocError(format!("Chunk not found: {}", raw)))?;
+                iostore
+                    .read(*chunk)
+                    .map_err(|e| UeToolError::UtocError(format!("Failed to read {}: {}", raw, e)))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct VfsEntry {
+    container: usize,
+    /// Normalized path, e.g. `Marvel/Content/UI/Icon.uasset`
+    path: String,
+    /// Path as stored in the container, used to read it
+    raw: String,
+}
+
+/// Which container a path resolves to, and which it overrides
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedFile<'a> {
+    pub path: &'a str,
+    pub container: &'a MountedContainer,
+    /// Lower priority containers holding the same path, highest first
+    pub shadowed: Vec<&'a MountedContainer>,
+}
+
+/// Every mounted container of a game, with each path resolved to its winning container
+#[derive(Debug)]
+pub struct VirtualFileSystem {
+    containers: Vec<MountedContainer>,
+    /// Candidates per lowercased path, highest priority first
+    entries: HashMap<String, Vec<VfsEntry>>,
+    errors: Vec<(PathBuf, String)>,
+    project_name: Option<String>,
+    aes_key: Option<String>,
+}
+
+impl VirtualFileSystem {
+    /// Mounted containers, in mount order
+    pub fn containers(&self) -> &[MountedContainer] {
+        &self.containers
+    }
+
+    /// Containers that could not be mounted, with the reason
+    pub fn errors(&self) -> &[(PathBuf, String)] {
+        &self.errors
+    }
+
+    /// Number of distinct paths
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no files are mounted
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Resolve a file path or a `/Game/...` package path to its winning container
+    ///
+    /// File paths may keep the `../../../` mount prefix and are matched
+    /// case-insensitively. Package paths try `.uasset` and then `.umap`.
+    pub fn resolve(&self, path: &str) -> Option<ResolvedFile<'_>> {
+        let candidates = self.lookup(path)?;
+        let (winner, rest) = candidates.split_first()?;
+        Some(ResolvedFile {
+            path: &winner.path,
+            container: &self.containers[winner.container],
+            shadowed: rest.iter().map(|e| &self.containers[e.container]).collect(),
+        })
+    }
+
+    /// Every path with its winning container, sorted by path
+    pub fn files(&self) -> Vec<ResolvedFile<'_>> {
+        let mut files: Vec<_> = self.entries.keys().filter_map(|key| self.resolve(key)).collect();
+        files.sort_by(|a, b| a.path.cmp(b.path));
+        files
+    }
+
+    /// Paths provided by more than one container
+    pub fn overridden(&self) -> Vec<ResolvedFile<'_>> {
+        let mut files = self.files();
+        files.retain(|f| !f.shadowed.is_empty());
+        files
+    }
+
+    /// Read the bytes the game would load for a path
+    ///
+    /// IoStore packages are returned as stored in the container, i.e. in the
+    /// zen package format rather than as a legacy `.uasset`.
+    pub fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let entry = self
+            .lookup(path)
+            .and_then(|candidates| candidates.first())
+            .ok_or_else(|| UeToolError::file_not_found(path))?;
+        self.open_container(entry.container)?.read(&entry.raw)
+    }
+
+    /// Open a mounted container for reading entries by their stored path
+    pub(crate) fn open_container(&self, index: usize) -> Result<ContainerReader> {
+        let container = &self.containers[index];
+        ContainerReader::open(&container.path, container.kind, self.aes_key.as_deref())
+    }
+
+    /// Candidates for a path as (container index, stored path), highest priority first
+    pub(crate) fn candidates(&self, path: &str) -> Vec<(usize, &str)> {
+        self.lookup(path)
+            .map(|entries| entries.iter().map(|e| (e.container, e.raw.as_str())).collect())
+            .unwrap_or_default()
+    }
+
+    fn lookup(&self, path: &str) -> Option<&[VfsEntry]> {
+        let is_package_path = path.starts_with('/') && Path::new(path).extension().is_none();
+        if !is_package_path {
+            return self.entries.get(&normalize_path(path).to_lowercase()).map(Vec::as_slice);
+        }
+
+        let file = self.package_file_path(path)?;
+        [".uasset", ".umap"]
+            .iter()
+            .find_map(|ext| self.entries.get(&format!("{}{}", file, ext).to_lowercase()))
+            .map(Vec::as_slice)
+    }
+
+    /// Map `/Game/X` to `<Project>/Content/X` and `/Engine/X` to `Engine/Content/X`
+    fn package_file_path(&self, package: &str) -> Option<String> {
+        let (root, rest) = package.trim_start_matches('/').split_once('/')?;
+        let mount = if root.eq_ignore_ascii_case("Game") {
+            self.project_name.clone()?
+        } else {
+            root.to_string()
+        };
+        Some(format!("{}/Content/{}", mount, rest))
+    }
+
+    fn mount_container(&mut self, path: &Path, kind: ContainerKind, order: i32) -> Result<()> {
+        let reader = ContainerReader::open(path, kind, self.aes_key.as_deref())?;
+        let mount_point = reader.mount_point().to_string();
+        let raw_paths = reader.entries();
+
+        let container = self.containers.len();
+        for (full, raw) in &raw_paths {
+            let path = normalize_path(full);
+            self.entries
+                .entry(path.to_lowercase())
+                .or_default()
+                .push(VfsEntry { container, path, raw: raw.clone() });
+        }
+
+        self.containers.push(MountedContainer {
+            path: path.to_path_buf(),
+            kind,
+            order,
+            mount_point,
+            file_count: raw_paths.len(),
+        });
+        Ok(())
+    }
+}
+
+/// Strip the `../../../` mount prefix and leading slashes, using `/` separators
+pub(crate) fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let mut rest = path.as_str();
+    loop {
+        if let Some(stripped) = rest.strip_prefix("../") {
+            rest = stripped;
+        } else if let Some(stripped) = rest.strip_prefix('/') {
+            rest = stripped;
+        } else {
+            return rest.to_string();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn container(name: &str, kind: ContainerKind, order: i32) -> MountedContainer {
+        MountedContainer {
+            path: PathBuf::from(name),
+            kind,
+            order,
+            mount_point: String::new(),
+            file_count: 0,
+        }
+    }
+
+    #[test]
+    fn test_patch_chunk_version() {
+        assert_eq!(patch_chunk_version("pakchunk0-Windows.pak"), 0);
+        assert_eq!(patch_chunk_version("Mod_P.pak"), 1);
+        assert_eq!(patch_chunk_version("pakchunk0-Windows_0_P.utoc"), 1);
+        assert_eq!(patch_chunk_version("Mod_2_P.pak"), 3);
+    }
+
+    #[test]
+    fn test_pak_order() {
+        let install = GameInstall { paks_dir: PathBuf::from("Marvel/Content/Paks"), project_name: Some("Marvel".into()) };
+        assert_eq!(install.pak_order(Path::new("Marvel/Content/Paks/Marvel-Windows.pak")), 4);
+        assert_eq!(install.pak_order(Path::new("Marvel/Content/Paks/pakchunk1-Windows.pak")), 3);
+        assert_eq!(install.pak_order(Path::new("Marvel/Content/Paks/~mods/Skin_P.pak")), 103);
+    }
+
+    #[test]
+    fn test_priority() {
+        let base = container("Marvel-Windows.pak", ContainerKind::Pak, 4);
+        let patch = container("aaa_P.pak", ContainerKind::Pak, 103);
+        let later = container("zzz_P.pak", ContainerKind::Pak, 103);
+        let iostore = container("pakchunk0-Windows.utoc", ContainerKind::IoStore, 3);
+
+        assert_eq!(patch.priority_cmp(&base), Ordering::Greater);
+        assert_eq!(later.priority_cmp(&patch), Ordering::Greater);
+        assert_eq!(iostore.priority_cmp(&later), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("../../../Marvel/Content/A.uasset"), "Marvel/Content/A.uasset");
+        assert_eq!(normalize_path("/Marvel\\Content\\A.uasset"), "Marvel/Content/A.uasset");
+    }
+}
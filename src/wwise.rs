@@ -0,0 +1,847 @@
+//! Wwise soundbanks: `.bnk` sections and the `.wem` media they play
+//!
+//! A bank is a sequence of chunks, each a four-byte tag and a u32 size:
+//!
+//! - `BKHD`: bank version and ID;
+//! - `DIDX`: ID, offset and size of every embedded `.wem`;
+//! - `DATA`: the embedded media `DIDX` offsets are relative to;
+//! - `HIRC`: the object hierarchy (events, actions, sounds, containers...).
+//!
+//! Sounds and music tracks name their media by ID. Media that is not
+//! embedded is streamed from `<id>.wem`, which the UE integration cooks into
+//! the same container as the bank. Events, actions, sounds, music tracks and
+//! the children of random/sequence, switch, layer and actor-mixer containers
+//! are decoded; other objects are listed by ID and kind. Container children
+//! are only read from banks newer than version 128 (Wwise 2019.2), whose
+//! node parameters have a known layout.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::binary::ReadExt;
+use crate::cancel::{self, Interrupted, PartialResult};
+use crate::error::{Result, UeToolError};
+use crate::extract::{open_container, select, write_output, Entry};
+use crate::progress::Phase;
+use crate::types::{AssetPath, ExtractOptions, UnpackedFile};
+use crate::vfs::ContainerReader;
+use crate::Unpacker;
+
+/// Last bank version with a u32 stream type and a file ID in source data
+const VER_OLD_SOURCE_DATA: u32 = 88;
+
+/// Last bank version with a u32 event action count rather than a var-int
+const VER_U32_EVENT_ACTIONS: u32 = 122;
+
+/// `AkPluginType` of a source plugin (tone, silence...), which has no media
+const PLUGIN_TYPE_SOURCE: u32 = 2;
+
+/// Last bank version whose node parameters are not decoded
+const VER_OLD_NODE_PARAMS: u32 = 128;
+
+/// Last bank version without metadata plugins in node parameters
+const VER_NO_METADATA_PARAMS: u32 = 136;
+
+/// Last bank version without a reflections aux bus in node parameters
+const VER_NO_REFLECTIONS_AUX: u32 = 134;
+
+/// Kind of a `HIRC` object
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum HircKind {
+    State,
+    Sound,
+    Action,
+    Event,
+    RandomSequence,
+    Switch,
+    ActorMixer,
+    Bus,
+    Layer,
+    MusicSegment,
+    MusicTrack,
+    MusicSwitch,
+    MusicRandomSequence,
+    Attenuation,
+    DialogueEvent,
+    FxShareSet,
+    FxCustom,
+    AuxBus,
+    Lfo,
+    Envelope,
+    AudioDevice,
+    TimeMod,
+    Other(u8),
+}
+
+impl HircKind {
+    fn from_u8(kind: u8) -> Self {
+        match kind {
+            1 => HircKind::State,
+            2 => HircKind::Sound,
+            3 => HircKind::Action,
+            4 => HircKind::Event,
+            5 => HircKind::RandomSequence,
+            6 => HircKind::Switch,
+            7 => HircKind::ActorMixer,
+            8 => HircKind::Bus,
+            9 => HircKind::Layer,
+            10 => HircKind::MusicSegment,
+            11 => HircKind::MusicTrack,
+            12 => HircKind::MusicSwitch,
+            13 => HircKind::MusicRandomSequence,
+            14 => HircKind::Attenuation,
+            15 => HircKind::DialogueEvent,
+            16 => HircKind::FxShareSet,
+            17 => HircKind::FxCustom,
+            18 => HircKind::AuxBus,
+            19 => HircKind::Lfo,
+            20 => HircKind::Envelope,
+            21 => HircKind::AudioDevice,
+            22 => HircKind::TimeMod,
+            other => HircKind::Other(other),
+        }
+    }
+}
+
+/// Where the media of a sound is read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum MediaSource {
+    /// Embedded in a bank's `DATA`
+    Embedded,
+    /// The start is embedded, the rest streamed from `<id>.wem`
+    PrefetchStreamed,
+    /// Streamed from `<id>.wem`
+    Streamed,
+}
+
+/// Media a sound or music track plays
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct MediaRef {
+    pub id: u32,
+    pub source: MediaSource,
+}
+
+/// A `.wem` embedded in a bank
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BankMedia {
+    pub id: u32,
+    /// Offset in the `DATA` section
+    pub offset: u32,
+    pub size: u32,
+}
+
+/// An object of the `HIRC` section
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HircObject {
+    pub id: u32,
+    pub kind: HircKind,
+    /// Media played by a sound or music track
+    pub media: Vec<MediaRef>,
+    /// Actions of an event, the target of an action, or the children of a container
+    pub references: Vec<u32>,
+}
+
+/// A parsed Wwise `.bnk`
+#[derive(Debug, Clone, Serialize)]
+pub struct SoundBank {
+    pub version: u32,
+    pub id: u32,
+    /// Embedded media, in `DIDX` order
+    pub media: Vec<BankMedia>,
+    pub objects: Vec<HircObject>,
+    /// The `DATA` section
+    #[serde(skip)]
+    data: Vec<u8>,
+}
+
+impl SoundBank {
+    /// Parse a `.bnk`
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let mut r = Cursor::new(data);
+        let mut bank: Option<SoundBank> = None;
+        while (r.position() as usize) < data.len() {
+            let tag = r.read_fixed::<4>()?;
+            let size = r.read_u32()? as usize;
+            let body = r.read_bytes(size).map_err(|_| {
+                UeToolError::invalid_format(format!("{} section runs past the end of the bank", String::from_utf8_lossy(&tag)))
+            })?;
+
+            if tag == *b"BKHD" {
+                let mut h = Cursor::new(body.as_slice());
+                let version = h.read_u32()?;
+                let id = h.read_u32()?;
+                bank = Some(SoundBank { version, id, media: Vec::new(), objects: Vec::new(), data: Vec::new() });
+                continue;
+            }
+            let bank = bank
+                .as_mut()
+                .ok_or_else(|| UeToolError::invalid_format("Not a Wwise soundbank: BKHD is not the first section"))?;
+            match &tag {
+                b"DIDX" => {
+                    bank.media = body
+                        .chunks_exact(12)
+                        .map(|entry| BankMedia {
+                            id: u32::from_le_bytes(entry[..4].try_into().unwrap()),
+                            offset: u32::from_le_bytes(entry[4..8].try_into().unwrap()),
+                            size: u32::from_le_bytes(entry[8..].try_into().unwrap()),
+                        })
+                        .collect();
+                }
+                b"DATA" => bank.data = body,
+                b"HIRC" => bank.objects = read_hierarchy(&body, bank.version)?,
+                _ => {}
+            }
+        }
+        bank.ok_or_else(|| UeToolError::invalid_format("Not a Wwise soundbank: no BKHD section"))
+    }
+
+    /// Read and parse a `.bnk` from disk
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(|e| UeToolError::io_error(format!("Failed to read {}: {}", path.display(), e)))?;
+        Self::parse(&data)
+    }
+
+    /// The bytes of the embedded `.wem` `id`
+    ///
+    /// For prefetched media this is only the start of the file.
+    pub fn media_data(&self, id: u32) -> Option<&[u8]> {
+        let media = self.media.iter().find(|m| m.id == id)?;
+        let start = media.offset as usize;
+        self.data.get(start..start.checked_add(media.size as usize)?)
+    }
+
+    /// The object with ID `id`
+    pub fn object(&self, id: u32) -> Option<&HircObject> {
+        self.objects.iter().find(|o| o.id == id)
+    }
+
+    /// Every event of the bank
+    pub fn events(&self) -> impl Iterator<Item = &HircObject> {
+        self.objects.iter().filter(|o| o.kind == HircKind::Event)
+    }
+
+    /// Media an event plays through its actions' sounds and music tracks
+    ///
+    /// Containers are walked down to the sounds they hold. Objects in other
+    /// banks, and the children of music containers, contribute nothing.
+    pub fn event_media(&self, event: u32) -> Vec<MediaRef> {
+        let objects: HashMap<u32, &HircObject> = self.objects.iter().map(|o| (o.id, o)).collect();
+        let mut media = Vec::new();
+        let mut visited = HashSet::new();
+        let mut pending = vec![event];
+        while let Some(id) = pending.pop() {
+            if !visited.insert(id) {
+                continue;
+            }
+            let Some(object) = objects.get(&id) else {
+                continue;
+            };
+            for item in &object.media {
+                if !media.contains(item) {
+                    media.push(*item);
+                }
+            }
+            // Reversed so that references are visited in bank order
+            pending.extend(object.references.iter().rev());
+        }
+        media
+    }
+
+    /// IDs of the media played from a loose `<id>.wem`, sorted
+    ///
+    /// That is media streamed or prefetched, or referenced without being
+    /// embedded.
+    pub fn streamed_media(&self) -> Vec<u32> {
+        let mut ids: Vec<u32> = self
+            .objects
+            .iter()
+            .flat_map(|o| &o.media)
+            .filter(|m| m.source != MediaSource::Embedded || !self.media.iter().any(|e| e.id == m.id))
+            .map(|m| m.id)
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+}
+
+/// A streamed `.wem` looked up in the bank's container
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StreamedWem {
+    pub id: u32,
+    /// Path of the `.wem` in the container; `None` if it is not there
+    pub path: Option<String>,
+}
+
+/// A bank read from a container, with its streamed media resolved
+#[derive(Debug, Clone, Serialize)]
+pub struct SoundBankIndex {
+    /// Path of the `.bnk` in the container
+    pub path: String,
+    pub bank: SoundBank,
+    pub streamed: Vec<StreamedWem>,
+}
+
+impl SoundBankIndex {
+    /// Streamed media the container does not have
+    pub fn missing(&self) -> impl Iterator<Item = u32> + '_ {
+        self.streamed.iter().filter(|w| w.path.is_none()).map(|w| w.id)
+    }
+}
+
+impl Unpacker {
+    /// Read a `.bnk` from a `.pak` or `.utoc`
+    pub fn read_soundbank<P: AsRef<Path>>(&mut self, container: P, path: &str, options: &ExtractOptions) -> Result<SoundBank> {
+        SoundBank::parse(&self.read_entry(container, path, options)?)
+    }
+
+    /// Read a `.bnk` and look up its streamed `.wem` files in the same container
+    pub fn index_soundbank<P: AsRef<Path>>(&mut self, container: P, path: &str, options: &ExtractOptions) -> Result<SoundBankIndex> {
+        let container = container.as_ref();
+        let _span = tracing::info_span!("index_soundbank", container = %container.display(), path).entered();
+
+        let (mut reader, entries) = open_container(container, options)?;
+        let entry = select_bank(&entries, path)?;
+        index_bank(&mut reader, entry, &wem_entries(&entries))
+    }
+
+    /// Read every `.bnk` of a container, with streamed media resolved
+    ///
+    /// A bank that fails to parse is logged and left out.
+    pub fn index_soundbanks<P: AsRef<Path>>(&mut self, container: P, options: &ExtractOptions) -> Result<Vec<SoundBankIndex>> {
+        let container = container.as_ref();
+        let _span = tracing::info_span!("index_soundbanks", container = %container.display()).entered();
+
+        let (mut reader, entries) = open_container(container, options)?;
+        let wems = wem_entries(&entries);
+        let banks: Vec<&Entry> = entries.iter().filter(|(full, _)| has_extension(full, "bnk")).collect();
+
+        let mut progress = self.progress.phase(Phase::Analyze, banks.len() as u64, 0);
+        let mut indexed = Vec::with_capacity(banks.len());
+        for entry in banks {
+            cancel::check(self.cancellation.as_ref())?;
+            progress.entry_started(&entry.0, 0);
+            match index_bank(&mut reader, entry, &wems) {
+                Ok(index) => indexed.push(index),
+                Err(e) => tracing::warn!(bank = %entry.0, error = %e, "Failed to read soundbank"),
+            }
+            progress.entry_finished(&entry.0, 0);
+        }
+        progress.finish();
+        Ok(indexed)
+    }
+
+    /// Extract the media of a bank to `dest_dir/<id>.wem`
+    ///
+    /// Streamed and prefetched media is copied from the container, and the
+    /// rest is cut out of the bank. Streamed media the container does not
+    /// have is reported with an error rather than failing the call.
+    pub fn extract_wems<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        container: P,
+        path: &str,
+        dest_dir: Q,
+        options: &ExtractOptions,
+    ) -> PartialResult<Vec<UnpackedFile>> {
+        let (container, dest_dir) = (container.as_ref(), dest_dir.as_ref());
+        let _span = tracing::info_span!("extract_wems", container = %container.display(), path).entered();
+
+        let (mut reader, entries) = open_container(container, options)?;
+        let entry = select_bank(&entries, path)?;
+        let index = index_bank(&mut reader, entry, &wem_entries(&entries))?;
+        let bank = &index.bank;
+        let embedded: Vec<&BankMedia> = bank.media.iter().filter(|m| !index.streamed.iter().any(|w| w.id == m.id)).collect();
+
+        let total = (embedded.len() + index.streamed.len()) as u64;
+        let mut progress = self.progress.phase(Phase::Unpack, total, 0);
+        let mut extracted = Vec::new();
+        for media in embedded {
+            if let Err(e) = cancel::check(self.cancellation.as_ref()) {
+                return Err(Interrupted::new(extracted, e));
+            }
+            let name = format!("{}.wem", media.id);
+            progress.entry_started(&name, media.size as u64);
+            let data = bank
+                .media_data(media.id)
+                .ok_or_else(|| UeToolError::invalid_format(format!("Media {} runs past the end of the DATA section", media.id)));
+            match data.and_then(|data| write_wem(data, &format!("{}/{}", index.path, name), &dest_dir.join(&name), options)) {
+                Ok(file) => {
+                    progress.entry_finished(&name, file.size);
+                    extracted.push(file);
+                }
+                Err(e) => return Err(Interrupted::new(extracted, e)),
+            }
+        }
+        for wem in &index.streamed {
+            if let Err(e) = cancel::check(self.cancellation.as_ref()) {
+                return Err(Interrupted::new(extracted, e));
+            }
+            let name = format!("{}.wem", wem.id);
+            let dest = dest_dir.join(&name);
+            let Some(source) = &wem.path else {
+                progress.entry_started(&name, 0);
+                progress.entry_finished(&name, 0);
+                extracted.push(UnpackedFile {
+                    original_path: AssetPath::new(name),
+                    output_path: dest,
+                    size: 0,
+                    error: Some("Streamed media is not in the container".to_string()),
+                });
+                continue;
+            };
+            progress.entry_started(source, 0);
+            let raw = entries
+                .iter()
+                .find(|(full, _)| full == source)
+                .ok_or_else(|| UeToolError::file_not_found(source.as_str()));
+            match raw.and_then(|(_, raw)| reader.read(raw)).and_then(|data| write_wem(&data, source, &dest, options)) {
+                Ok(file) => {
+                    progress.entry_finished(source, file.size);
+                    extracted.push(file);
+                }
+                Err(e) => return Err(Interrupted::new(extracted, e)),
+            }
+        }
+        progress.finish();
+        Ok(extracted)
+    }
+}
+
+fn has_extension(path: &str, extension: &str) -> bool {
+    Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+fn select_bank<'a>(entries: &'a [Entry], path: &str) -> Result<&'a Entry> {
+    select(entries, path)
+        .into_iter()
+        .find(|(full, _)| has_extension(full, "bnk"))
+        .ok_or_else(|| UeToolError::file_not_found(path))
+}
+
+/// Streamed media candidates: every `<id>.wem` of the container
+fn wem_entries(entries: &[Entry]) -> HashMap<u32, Vec<&str>> {
+    let mut wems: HashMap<u32, Vec<&str>> = HashMap::new();
+    for (full, _) in entries {
+        if !has_extension(full, "wem") {
+            continue;
+        }
+        if let Some(id) = Path::new(full.as_str()).file_stem().and_then(|stem| stem.to_str()).and_then(|stem| stem.parse().ok()) {
+            wems.entry(id).or_default().push(full);
+        }
+    }
+    wems
+}
+
+/// The `.wem` of `id` closest to the bank, for localized media that exists
+/// once per language folder
+fn resolve_wem(wems: &HashMap<u32, Vec<&str>>, bank_path: &str, id: u32) -> Option<String> {
+    let shared = |path: &str| path.bytes().zip(bank_path.bytes()).take_while(|(a, b)| a.eq_ignore_ascii_case(b)).count();
+    wems.get(&id)?.iter().max_by_key(|path| shared(path)).map(|path| path.to_string())
+}
+
+fn index_bank(reader: &mut ContainerReader, entry: &Entry, wems: &HashMap<u32, Vec<&str>>) -> Result<SoundBankIndex> {
+    let bank = SoundBank::parse(&reader.read(&entry.1)?)?;
+    let streamed = bank
+        .streamed_media()
+        .into_iter()
+        .map(|id| StreamedWem { id, path: resolve_wem(wems, &entry.0, id) })
+        .collect();
+    Ok(SoundBankIndex { path: entry.0.clone(), bank, streamed })
+}
+
+fn write_wem(data: &[u8], source: &str, dest: &Path, options: &ExtractOptions) -> Result<UnpackedFile> {
+    write_output(dest, data, options.force)?;
+    tracing::trace!(wem = %source, bytes = data.len(), "Extracted media");
+
+    Ok(UnpackedFile {
+        original_path: AssetPath::new(source),
+        output_path: dest.to_path_buf(),
+        size: data.len() as u64,
+        error: None,
+    })
+}
+
+/// Read the objects of a `HIRC` section
+fn read_hierarchy(body: &[u8], version: u32) -> Result<Vec<HircObject>> {
+    let mut r = Cursor::new(body);
+    let count = r.read_u32()? as usize;
+    let mut objects = Vec::with_capacity(count.min(65536));
+    for _ in 0..count {
+        let kind = HircKind::from_u8(r.read_u8()?);
+        let size = r.read_u32()? as usize;
+        let data = r.read_bytes(size)?;
+        let mut o = Cursor::new(data.as_slice());
+        let id = o.read_u32()?;
+
+        let mut object = HircObject { id, kind, media: Vec::new(), references: Vec::new() };
+        let decoded = match kind {
+            HircKind::Sound => read_source(&mut o, version).map(|media| object.media.extend(media)),
+            HircKind::MusicTrack => read_music_track(&mut o, version).map(|media| object.media = media),
+            HircKind::Event => read_event(&mut o, version).map(|actions| object.references = actions),
+            HircKind::Action => read_action(&mut o).map(|target| object.references.extend(target)),
+            HircKind::RandomSequence | HircKind::Switch | HircKind::Layer | HircKind::ActorMixer => {
+                read_container(&mut o, kind, version).map(|children| object.references = children)
+            }
+            _ => Ok(()),
+        };
+        // The size prefix lets the rest of the hierarchy be read past an object we misread
+        if let Err(e) = decoded {
+            tracing::debug!(id, kind = ?kind, error = %e, "Failed to decode HIRC object");
+        }
+        objects.push(object);
+    }
+    Ok(objects)
+}
+
+/// Read the sources of a `CAkMusicTrack`
+fn read_music_track(r: &mut Cursor<&[u8]>, version: u32) -> Result<Vec<MediaRef>> {
+    if version > VER_OLD_SOURCE_DATA {
+        r.read_u8()?; // uFlags
+    }
+    let mut media = Vec::new();
+    for _ in 0..r.read_u32()? {
+        media.extend(read_source(r, version)?);
+    }
+    Ok(media)
+}
+
+/// Read the action IDs of a `CAkEvent`
+fn read_event(r: &mut Cursor<&[u8]>, version: u32) -> Result<Vec<u32>> {
+    let count = if version <= VER_U32_EVENT_ACTIONS { r.read_u32()? } else { read_var_int(r)? };
+    (0..count).map(|_| r.read_u32()).collect()
+}
+
+/// Read the target of a `CAkAction`, if it has one
+fn read_action(r: &mut Cursor<&[u8]>) -> Result<Option<u32>> {
+    r.read_u16()?; // ulActionType
+    let target = r.read_u32()?;
+    Ok((target != 0).then_some(target))
+}
+
+/// Read the children of a random/sequence, switch, layer or actor-mixer container
+fn read_container(r: &mut Cursor<&[u8]>, kind: HircKind, version: u32) -> Result<Vec<u32>> {
+    if version <= VER_OLD_NODE_PARAMS {
+        return Err(UeToolError::invalid_format(format!("Node parameters of bank version {} are not supported", version)));
+    }
+    skip_node_base_params(r, version)?;
+    match kind {
+        // sLoopCount, sLoopModMin, sLoopModMax, fTransitionTime, fTransitionTimeModMin,
+        // fTransitionTimeModMax, wAvoidRepeatCount, eTransitionMode, eRandomMode, eMode, byBitVector
+        HircKind::RandomSequence => skip(r, 24)?,
+        // eGroupType, ulGroupID, ulDefaultSwitch, bIsContinuousValidation
+        HircKind::Switch => skip(r, 10)?,
+        _ => {}
+    }
+    let count = r.read_u32()? as usize;
+    if count > r.get_ref().len() / 4 {
+        return Err(UeToolError::invalid_format(format!("Container lists {} children", count)));
+    }
+    (0..count).map(|_| r.read_u32()).collect()
+}
+
+/// Skip the `NodeBaseParams` every sound and container starts with
+fn skip_node_base_params(r: &mut Cursor<&[u8]>, version: u32) -> Result<()> {
+    // NodeInitialFxParams
+    r.read_u8()?; // bIsOverrideParentFX
+    let fx = r.read_u8()? as u64;
+    if fx > 0 {
+        r.read_u8()?; // bitsFXBypass
+    }
+    skip(r, fx * 7)?; // uFXIndex, fxID, bIsShareSet, bIsRendered
+    if version > VER_NO_METADATA_PARAMS {
+        r.read_u8()?; // bIsOverrideParentMetadata
+        let metadata = r.read_u8()? as u64;
+        skip(r, metadata * 6)?; // uFXIndex, fxID, bIsShareSet
+    }
+    // bOverrideAttachmentParams, OverrideBusId, DirectParentID, byBitVector
+    skip(r, 10)?;
+
+    // NodeInitialParams: a property bundle of values then one of ranges
+    let props = r.read_u8()? as u64;
+    skip(r, props * 5)?;
+    let ranges = r.read_u8()? as u64;
+    skip(r, ranges * 9)?;
+
+    // PositioningParams
+    let positioning = r.read_u8()?;
+    let overrides = positioning & 1 != 0;
+    let has_3d = positioning & 2 != 0;
+    let automated = (positioning >> 5) & 3 != 0;
+    if overrides && has_3d {
+        r.read_u8()?; // uBits3d
+        if automated {
+            skip(r, 5)?; // ePathMode, TransitionTime
+            let vertices = r.read_u32()? as u64;
+            skip(r, vertices * 16)?;
+            let items = r.read_u32()? as u64;
+            skip(r, items * (8 + 12))?; // playlist items, then their automation ranges
+        }
+    }
+
+    // AuxParams
+    let aux = r.read_u8()?;
+    if aux & 8 != 0 {
+        skip(r, 16)?; // four aux bus IDs
+    }
+    if version > VER_NO_REFLECTIONS_AUX {
+        r.read_u32()?; // reflectionsAuxBus
+    }
+
+    // AdvSettingsParams: byBitVector, eVirtualQueueBehavior, u16MaxNumInstance,
+    // eBelowThresholdBehavior, byBitVector
+    skip(r, 6)?;
+
+    // StateChunk
+    for _ in 0..read_var_int(r)? {
+        read_var_int(r)?; // PropertyId
+        skip(r, 2)?; // accumType, inDb
+    }
+    for _ in 0..read_var_int(r)? {
+        skip(r, 5)?; // ulStateGroupID, eStateSyncType
+        let states = read_var_int(r)? as u64;
+        skip(r, states * 8)?;
+    }
+
+    // InitialRTPC
+    for _ in 0..r.read_u16()? {
+        skip(r, 6)?; // RTPCID, rtpcType, rtpcAccum
+        read_var_int(r)?; // ParamID
+        skip(r, 5)?; // rtpcCurveID, eScaling
+        let points = r.read_u16()? as u64;
+        skip(r, points * 12)?;
+    }
+    Ok(())
+}
+
+/// Advance past `len` bytes
+fn skip(r: &mut Cursor<&[u8]>, len: u64) -> Result<()> {
+    let position = r.position().saturating_add(len);
+    if position > r.get_ref().len() as u64 {
+        return Err(UeToolError::invalid_format("HIRC object is truncated"));
+    }
+    r.set_position(position);
+    Ok(())
+}
+
+/// Read an `AkBankSourceData`; `None` for source plugins, which play no media
+fn read_source(r: &mut Cursor<&[u8]>, version: u32) -> Result<Option<MediaRef>> {
+    let plugin = r.read_u32()?;
+    let (stream_type, id) = if version <= VER_OLD_SOURCE_DATA {
+        let stream_type = r.read_u32()?;
+        let id = r.read_u32()?;
+        r.read_u32()?; // uFileID
+        if stream_type != 1 {
+            r.read_fixed::<8>()?; // uFileOffset, uInMemoryMediaSize
+        }
+        // Old banks number prefetch and plain streaming the other way round
+        let stream_type = match stream_type {
+            1 => 2,
+            2 => 1,
+            other => other,
+        };
+        (stream_type, id)
+    } else {
+        let stream_type = r.read_u8()? as u32;
+        let id = r.read_u32()?;
+        r.read_u32()?; // uInMemoryMediaSize
+        (stream_type, id)
+    };
+    r.read_u8()?; // uSourceBits
+
+    if plugin & 0xF == PLUGIN_TYPE_SOURCE {
+        return Ok(None);
+    }
+    let source = match stream_type {
+        0 => MediaSource::Embedded,
+        1 => MediaSource::PrefetchStreamed,
+        _ => MediaSource::Streamed,
+    };
+    Ok(Some(MediaRef { id, source }))
+}
+
+/// Read a Wwise variable-size integer: 7 bits per byte, most significant first
+fn read_var_int(r: &mut Cursor<&[u8]>) -> Result<u32> {
+    let mut value = 0u32;
+    for _ in 0..5 {
+        let byte = r.read_u8()?;
+        value = (value << 7) | (byte & 0x7F) as u32;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(UeToolError::invalid_format("Variable-size integer is longer than 5 bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(out: &mut Vec<u8>, tag: &[u8; 4], body: &[u8]) {
+        out.extend_from_slice(tag);
+        out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        out.extend_from_slice(body);
+    }
+
+    fn object(out: &mut Vec<u8>, kind: u8, id: u32, body: &[u8]) {
+        out.push(kind);
+        out.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+        out.extend_from_slice(&id.to_le_bytes());
+        out.extend_from_slice(body);
+    }
+
+    fn sound(stream_type: u8, media: u32) -> Vec<u8> {
+        let mut body = 0x0004_0001u32.to_le_bytes().to_vec(); // Vorbis codec plugin
+        body.push(stream_type);
+        body.extend_from_slice(&media.to_le_bytes());
+        body.extend_from_slice(&16u32.to_le_bytes());
+        body.push(0);
+        body.extend_from_slice(&[0xAA; 8]); // node parameters
+        body
+    }
+
+    /// Version 145 node parameters with one of every optional part
+    fn node_base_params() -> Vec<u8> {
+        let mut body = vec![0, 1, 0]; // one effect, not bypassed
+        body.extend_from_slice(&[0, 0xEE, 0xEE, 0xEE, 0xEE, 0, 1]);
+        body.extend_from_slice(&[0, 1, 0, 0xDD, 0xDD, 0xDD, 0xDD, 0]); // one metadata plugin
+        body.push(0); // bOverrideAttachmentParams
+        body.extend_from_slice(&[0; 4]); // OverrideBusId
+        body.extend_from_slice(&0x1234u32.to_le_bytes()); // DirectParentID
+        body.push(0);
+        body.extend_from_slice(&[2, 0x00, 0x3A, 0, 0, 0x80, 0x3F, 0, 0, 0, 0]); // two properties
+        body.extend_from_slice(&[1, 0x00, 0, 0, 0x80, 0xBF, 0, 0, 0x80, 0x3F]); // one range
+        body.extend_from_slice(&[0x23, 0x08, 0, 0, 0, 0, 0]); // 3D, emitter with automation
+        body.extend_from_slice(&1u32.to_le_bytes());
+        body.extend_from_slice(&[0xCC; 16]); // vertex
+        body.extend_from_slice(&1u32.to_le_bytes());
+        body.extend_from_slice(&[0xCC; 20]); // playlist item and its ranges
+        body.push(0x08); // aux sends
+        body.extend_from_slice(&[0xBB; 16]);
+        body.extend_from_slice(&[0; 4]); // reflectionsAuxBus
+        body.extend_from_slice(&[0, 0, 0, 0, 0, 0]); // advanced settings
+        body.extend_from_slice(&[1, 0x81, 0x00, 0, 1]); // one state property
+        body.extend_from_slice(&[1, 0xAB, 0xAB, 0xAB, 0xAB, 0, 1]); // one state group of one state
+        body.extend_from_slice(&[0x99; 8]);
+        body.extend_from_slice(&1u16.to_le_bytes()); // one RTPC
+        body.extend_from_slice(&[0x77, 0x77, 0x77, 0x77, 0, 0, 0x05, 0x66, 0x66, 0x66, 0x66, 0x04]);
+        body.extend_from_slice(&2u16.to_le_bytes());
+        body.extend_from_slice(&[0x55; 24]);
+        body
+    }
+
+    fn container(extra: &[u8], children: &[u32]) -> Vec<u8> {
+        let mut body = node_base_params();
+        body.extend_from_slice(extra);
+        body.extend_from_slice(&(children.len() as u32).to_le_bytes());
+        for child in children {
+            body.extend_from_slice(&child.to_le_bytes());
+        }
+        body.extend_from_slice(&[0x11; 6]); // playlist or layers
+        body
+    }
+
+    /// A version 145 bank: one event playing an embedded and a streamed sound
+    fn bank() -> Vec<u8> {
+        let mut out = Vec::new();
+        let header: Vec<u8> = [145u32, 0xB0A4].iter().flat_map(|v| v.to_le_bytes()).collect();
+        section(&mut out, b"BKHD", &header);
+        let index: Vec<u8> = [100u32, 0, 4, 300, 4, 2].iter().flat_map(|v| v.to_le_bytes()).collect();
+        section(&mut out, b"DIDX", &index);
+        section(&mut out, b"DATA", b"RIFFxy");
+
+        let mut hierarchy = 5u32.to_le_bytes().to_vec();
+        object(&mut hierarchy, 2, 10, &sound(0, 100));
+        object(&mut hierarchy, 2, 11, &sound(2, 200));
+        for (id, target) in [(20u32, 10u32), (21, 11)] {
+            let mut action = 0x0403u16.to_le_bytes().to_vec();
+            action.extend_from_slice(&target.to_le_bytes());
+            action.push(0);
+            object(&mut hierarchy, 3, id, &action);
+        }
+        let mut event = vec![2u8];
+        event.extend_from_slice(&20u32.to_le_bytes());
+        event.extend_from_slice(&21u32.to_le_bytes());
+        object(&mut hierarchy, 4, 30, &event);
+        section(&mut out, b"HIRC", &hierarchy);
+        section(&mut out, b"STID", &[0; 8]);
+        out
+    }
+
+    #[test]
+    fn test_parse_bank() {
+        let bank = SoundBank::parse(&bank()).unwrap();
+        assert_eq!((bank.version, bank.id), (145, 0xB0A4));
+        assert_eq!(bank.media.len(), 2);
+        assert_eq!(bank.media_data(100), Some(&b"RIFF"[..]));
+        assert_eq!(bank.media_data(300), Some(&b"xy"[..]));
+        assert_eq!(bank.objects.len(), 5);
+        assert_eq!(bank.object(21).unwrap().references, [11]);
+
+        let events: Vec<u32> = bank.events().map(|e| e.id).collect();
+        assert_eq!(events, [30]);
+        assert_eq!(
+            bank.event_media(30),
+            [MediaRef { id: 100, source: MediaSource::Embedded }, MediaRef { id: 200, source: MediaSource::Streamed }]
+        );
+        assert_eq!(bank.streamed_media(), [200]);
+
+        assert!(SoundBank::parse(b"DATA\0\0\0\0").is_err());
+    }
+
+    #[test]
+    fn test_container_media() {
+        let mut hierarchy = 9u32.to_le_bytes().to_vec();
+        object(&mut hierarchy, 2, 10, &sound(0, 100));
+        object(&mut hierarchy, 2, 11, &sound(2, 200));
+        object(&mut hierarchy, 2, 12, &sound(1, 201));
+        // A random container and a switch container that hold each other
+        object(&mut hierarchy, 5, 40, &container(&[0x22; 24], &[10, 41]));
+        object(&mut hierarchy, 6, 41, &container(&[0x33; 10], &[11, 40]));
+        object(&mut hierarchy, 9, 42, &container(&[], &[12]));
+        object(&mut hierarchy, 7, 43, &container(&[], &[42]));
+        for (id, target) in [(20u32, 40u32), (21, 43)] {
+            let mut action = 0x0403u16.to_le_bytes().to_vec();
+            action.extend_from_slice(&target.to_le_bytes());
+            object(&mut hierarchy, 3, id, &action);
+        }
+        let mut out = Vec::new();
+        let header: Vec<u8> = [145u32, 1].iter().flat_map(|v| v.to_le_bytes()).collect();
+        section(&mut out, b"BKHD", &header);
+        section(&mut out, b"HIRC", &hierarchy);
+        let mut bank = SoundBank::parse(&out).unwrap();
+        assert_eq!(bank.object(41).unwrap().references, [11, 40]);
+        assert_eq!(bank.object(43).unwrap().references, [42]);
+
+        bank.objects.push(HircObject { id: 30, kind: HircKind::Event, media: Vec::new(), references: vec![20, 21] });
+        let media: Vec<u32> = bank.event_media(30).iter().map(|m| m.id).collect();
+        assert_eq!(media, [100, 200, 201]);
+    }
+
+    #[test]
+    fn test_resolve_wem() {
+        let entries: Vec<Entry> = [
+            "Game/Content/WwiseAudio/Media/English(US)/200.wem",
+            "Game/Content/WwiseAudio/Media/Chinese/200.wem",
+            "Game/Content/WwiseAudio/Media/Chinese/Voice.bnk",
+            "Game/Content/WwiseAudio/Media/notes.wem",
+        ]
+        .iter()
+        .map(|p| (p.to_string(), p.to_string()))
+        .collect();
+        let wems = wem_entries(&entries);
+        assert_eq!(wems.len(), 1);
+        assert_eq!(
+            resolve_wem(&wems, "Game/Content/WwiseAudio/Media/Chinese/Voice.bnk", 200).as_deref(),
+            Some("Game/Content/WwiseAudio/Media/Chinese/200.wem")
+        );
+        assert_eq!(resolve_wem(&wems, "Game/Content/WwiseAudio/Voice.bnk", 201), None);
+
+        let mut r = Cursor::new(&[0x81u8, 0x00][..]);
+        assert_eq!(read_var_int(&mut r).unwrap(), 128);
+    }
+}